@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// The parser only depends on anyhow/chrono, so it is compiled in directly
+#[path = "../../src/usbmon/parser.rs"]
+#[allow(dead_code)]
+mod parser;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = parser::parse_usbmon_binary_packet(data);
+});
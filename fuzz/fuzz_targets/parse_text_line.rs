@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// The parser only depends on anyhow/chrono, so it is compiled in directly
+#[path = "../../src/usbmon/parser.rs"]
+#[allow(dead_code)]
+mod parser;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(line) = std::str::from_utf8(data) {
+        let _ = parser::parse_usbmon_text_line(line);
+    }
+});
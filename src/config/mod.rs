@@ -1 +1,577 @@
-// Configuration module - stub for now
\ No newline at end of file
+use anyhow::{Result, anyhow};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+pub mod paths;
+
+use crate::device::{DEFAULT_MAX_DEVICES, DEFAULT_MERGE_WINDOW};
+use crate::usbmon::parser::DEFAULT_MAX_DATA_LENGTH;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub display: DisplayConfig,
+    pub monitoring: MonitoringConfig,
+    pub ui: UiConfig,
+    pub controllers: Vec<ControllerConfig>,
+    pub colors: ColorsConfig,
+    pub schedule: ScheduleConfig,
+    pub advanced: AdvancedConfig,
+    pub filter: FilterConfig,
+    pub hooks: Vec<HookConfig>,
+    pub trigger: TriggerConfig,
+    /// Action name -> key names, e.g. `quit = ["q", "Esc"]`
+    pub keybindings: HashMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DisplayConfig {
+    pub refresh_rate: u64,
+    pub show_disconnected_time: u64,
+    pub show_help_on_startup: bool,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            refresh_rate: 1000,
+            show_disconnected_time: 5,
+            show_help_on_startup: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MonitoringConfig {
+    pub history_window: u64,
+    pub binary_mode: bool,
+    pub max_devices: usize,
+    pub packet_buffer_size: usize,
+    pub exclude_control_endpoint: bool,
+    /// Seconds interrupt and isochronous bandwidth is averaged over; 0 uses
+    /// the same window as bulk and control traffic
+    pub periodic_window_secs: u64,
+    pub max_data_length: u32,
+    /// Payload bytes kept per binary packet; 0 skips payloads entirely
+    pub capture_len: u32,
+    /// Seconds within which a new device number with the same VID/PID/serial
+    /// is merged into the previous entry; 0 disables merging
+    pub reenumeration_merge_secs: u64,
+    /// Within that window, also merge a device without a serial into its
+    /// entry on the companion USB 2/USB 3 bus at the same port position
+    pub pair_companion_links: bool,
+    /// Grow usbmon's kernel ring on fast buses instead of only warning; only
+    /// the /dev/usbmonN character device has a ring, not the debugfs nodes
+    pub grow_ring_buffer: bool,
+}
+
+impl Default for MonitoringConfig {
+    fn default() -> Self {
+        Self {
+            history_window: 60,
+            binary_mode: true,
+            max_devices: DEFAULT_MAX_DEVICES,
+            packet_buffer_size: 4096,
+            exclude_control_endpoint: false,
+            periodic_window_secs: 30,
+            max_data_length: DEFAULT_MAX_DATA_LENGTH,
+            capture_len: 0,
+            reenumeration_merge_secs: DEFAULT_MERGE_WINDOW.as_secs(),
+            pair_companion_links: true,
+            grow_ring_buffer: false,
+        }
+    }
+}
+
+/// How the device table is ordered between refreshes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortStrategy {
+    /// Re-sort by bandwidth on every frame
+    #[default]
+    Immediate,
+    /// Keep the previous order until a device's rank moves far enough for long enough
+    Stable,
+}
+
+/// What the device table is ordered by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortKey {
+    /// Combined bandwidth, or share of capability in relative mode
+    #[default]
+    Bandwidth,
+    Rx,
+    Tx,
+    Device,
+    Vendor,
+    Product,
+    /// Time since the device connected
+    Connected,
+}
+
+impl SortKey {
+    /// Busiest or longest connected first, names alphabetically
+    pub fn default_order(&self) -> SortOrder {
+        match self {
+            SortKey::Device | SortKey::Vendor | SortKey::Product => SortOrder::Asc,
+            _ => SortOrder::Desc,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+/// A device table column; the URB counts column follows `show_packet_counts`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Column {
+    Device,
+    Speed,
+    Vendor,
+    Product,
+    Rx,
+    Tx,
+    Status,
+    Connected,
+}
+
+impl Column {
+    pub const DEFAULT: [Column; 8] = [
+        Column::Device,
+        Column::Speed,
+        Column::Vendor,
+        Column::Product,
+        Column::Rx,
+        Column::Tx,
+        Column::Status,
+        Column::Connected,
+    ];
+}
+
+/// What happens to a device once it disconnects
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InactiveDevices {
+    /// Drop it from the list after the disconnect grace period
+    #[default]
+    Remove,
+    /// Keep it listed, dimmed and below connected devices, until cleared
+    Dim,
+}
+
+/// Whether the bandwidth graph gets its own panel or a sparkline in the header
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GraphLayout {
+    /// Sparkline on terminals too short for the panel, full graph otherwise
+    #[default]
+    Auto,
+    Full,
+    Sparkline,
+}
+
+/// How packet and event times are shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeFormat {
+    /// Capture clock, for matching against other logs
+    #[default]
+    Absolute,
+    /// Time since, e.g. "2.3s ago"
+    Relative,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct UiConfig {
+    pub show_graphs: bool,
+    pub show_device_info: bool,
+    pub compact_mode: bool,
+    pub show_packet_counts: bool,
+    pub show_event_markers: bool,
+    /// Dashed line at the highest value in the visible graph window
+    pub show_peak_hold: bool,
+    /// Logarithmic Y axis for the bandwidth graph
+    pub graph_log_scale: bool,
+    pub history_retention_secs: u64,
+    pub sort_strategy: SortStrategy,
+    pub sort_hysteresis_ranks: usize,
+    pub sort_hysteresis_frames: u32,
+    pub sort_by: SortKey,
+    /// Unset follows `sort_by`: descending for rates and uptime, ascending for names
+    pub sort_order: Option<SortOrder>,
+    /// Device table columns, in order
+    pub columns: Vec<Column>,
+    pub heatmap_bucket_secs: u64,
+    pub heatmap_buckets: usize,
+    /// Decimals shown for bandwidth figures; unset picks them per value so
+    /// sub-KB/s traffic doesn't read as zero
+    pub rate_precision: Option<usize>,
+    /// Groups digits of exact byte and packet counts: one character, "" for
+    /// none, or "locale"
+    pub thousands_separator: String,
+    pub graph_layout: GraphLayout,
+    /// Open with the hex dump view instead of the device list
+    pub show_hexdump: bool,
+    /// Payload bytes shown per packet in the hex dump view
+    pub hexdump_bytes: usize,
+    /// Show device bandwidth as a share of what its negotiated speed can carry
+    pub show_relative_bandwidth: bool,
+    /// Start with the top row of the device list followed (toggle F)
+    pub follow_busiest: bool,
+    /// Seconds a device may go without a bound driver, or with nothing but
+    /// control traffic, before it's flagged as unused; 0 never flags
+    pub no_driver_secs: u64,
+    pub inactive_devices: InactiveDevices,
+    /// Seconds without traffic over which a row fades out when
+    /// `inactive_devices` is "dim"; 0 only dims disconnected devices
+    pub idle_fade_secs: u64,
+    pub time_format: TimeFormat,
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        Self {
+            show_graphs: true,
+            show_device_info: true,
+            compact_mode: false,
+            show_packet_counts: false,
+            show_event_markers: false,
+            show_peak_hold: true,
+            graph_log_scale: false,
+            history_retention_secs: 3600,
+            sort_strategy: SortStrategy::Immediate,
+            sort_hysteresis_ranks: 2,
+            sort_hysteresis_frames: 3,
+            sort_by: SortKey::Bandwidth,
+            sort_order: None,
+            columns: Column::DEFAULT.to_vec(),
+            heatmap_bucket_secs: 5,
+            heatmap_buckets: 120,
+            rate_precision: None,
+            thousands_separator: ",".to_string(),
+            graph_layout: GraphLayout::Auto,
+            show_hexdump: false,
+            hexdump_bytes: 256,
+            show_relative_bandwidth: false,
+            follow_busiest: false,
+            no_driver_secs: 10,
+            inactive_devices: InactiveDevices::Remove,
+            idle_fade_secs: 60,
+            time_format: TimeFormat::Absolute,
+        }
+    }
+}
+
+/// Daily capture windows such as "09:00-17:30"; empty means always capture
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ScheduleConfig {
+    pub windows: Vec<String>,
+}
+
+/// Filesystem location overrides for chroots, containers and test fixtures
+/// Unset paths fall back to the environment and then the usual locations
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AdvancedConfig {
+    pub debugfs_path: Option<String>,
+    pub usbmon_path: Option<String>,
+    pub sysfs_path: Option<String>,
+}
+
+/// Devices to record, as "VID:PID" in hex (`*` for either half) or
+/// "serial:<pattern>" with `*` wildcards. The blocklist takes precedence; an
+/// empty allowlist admits every device that isn't blocked
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct FilterConfig {
+    pub allowlist: Vec<String>,
+    pub blocklist: Vec<String>,
+    /// Capture only these buses; checked on each record's header before parsing
+    pub buses: Vec<u8>,
+    /// Capture only these "BUS:DEV" addresses
+    pub devices: Vec<String>,
+    /// Capture only these endpoint numbers (0-15, either direction)
+    pub endpoints: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ColorsConfig {
+    pub utilization: UtilizationColorsConfig,
+}
+
+/// Color scale for utilization displays (gauges, device rows, controller summary)
+/// `thresholds` are ascending percentages where the next color starts; `colors`
+/// has one more entry than `thresholds`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct UtilizationColorsConfig {
+    pub thresholds: Vec<f64>,
+    pub colors: Vec<String>,
+}
+
+impl Default for UtilizationColorsConfig {
+    fn default() -> Self {
+        Self {
+            thresholds: vec![25.0, 50.0, 75.0],
+            colors: vec![
+                "#00FF00".to_string(),
+                "#FFFF00".to_string(),
+                "#FFA500".to_string(),
+                "#FF0000".to_string(),
+            ],
+        }
+    }
+}
+
+/// A set of buses sharing one host controller's upstream bandwidth
+#[derive(Debug, Clone, Deserialize)]
+pub struct ControllerConfig {
+    pub name: String,
+    pub buses: Vec<u8>,
+    /// Aggregate limit for the whole controller in Mbps
+    pub limit_mbps: f64,
+    /// Utilization percentage at which the controller line turns into a warning
+    #[serde(default = "default_controller_warn_percentage")]
+    pub warn_percentage: f64,
+}
+
+fn default_controller_warn_percentage() -> f64 {
+    80.0
+}
+
+impl ControllerConfig {
+    /// Percentage of the controller limit used by `total_bps` (bytes per second)
+    pub fn get_utilization_percentage(&self, total_bps: f64) -> f64 {
+        let limit_bps = self.limit_mbps * 1_000_000.0 / 8.0;
+        if limit_bps > 0.0 {
+            total_bps / limit_bps * 100.0
+        } else {
+            0.0
+        }
+    }
+    
+    pub fn is_near_limit(&self, total_bps: f64) -> bool {
+        self.get_utilization_percentage(total_bps) >= self.warn_percentage
+    }
+}
+
+/// Device event a hook command runs on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookEvent {
+    DeviceConnected,
+    DeviceDisconnected,
+    ThresholdExceeded,
+}
+
+/// Command run on a device event, e.g. notify-send or curl to a chat webhook
+/// `command` may use `{vendor}`, `{product}`, `{bps}` and similar placeholders
+#[derive(Debug, Clone, Deserialize)]
+pub struct HookConfig {
+    pub event: HookEvent,
+    pub command: String,
+    /// Only fire for this device, as "VID:PID" in hex
+    #[serde(default)]
+    pub device: Option<String>,
+    /// Bandwidth in bytes per second a device must exceed (`threshold_exceeded` only)
+    #[serde(default)]
+    pub threshold_bps: f64,
+    /// Minimum time between two runs of this hook for the same device
+    #[serde(default = "default_hook_cooldown_secs")]
+    pub cooldown_secs: u64,
+}
+
+fn default_hook_cooldown_secs() -> u64 {
+    60
+}
+
+/// Condition that starts a triggered capture
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TriggerKind {
+    /// Data moves on `endpoint`
+    Endpoint,
+    /// A URB fails; cancelled URBs don't count
+    Error,
+    /// A device's bandwidth goes over `threshold_bps`
+    Bandwidth,
+}
+
+/// Payload capture around a condition, like an oscilloscope trigger
+/// While armed only the last `pre_packets` packets are held in memory; once
+/// the condition occurs `post_packets` more are taken and everything is
+/// written to `output` as usbmon text.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TriggerConfig {
+    /// What starts the capture; unset disables the trigger
+    pub on: Option<TriggerKind>,
+    /// Only watch this device, as "VID:PID" in hex
+    pub device: Option<String>,
+    /// Endpoint address with the direction bit, e.g. 0x81 for EP1 IN (`endpoint` only)
+    pub endpoint: Option<u8>,
+    /// Bytes per second a device must exceed (`bandwidth` only)
+    pub threshold_bps: f64,
+    pub pre_packets: usize,
+    pub post_packets: usize,
+    /// Dump file; strftime fields such as `%H%M%S` are filled in when it fires
+    pub output: String,
+    /// Arm again after each dump instead of stopping at the first
+    pub rearm: bool,
+}
+
+impl Default for TriggerConfig {
+    fn default() -> Self {
+        Self {
+            on: None,
+            device: None,
+            endpoint: None,
+            threshold_bps: 0.0,
+            pre_packets: 1000,
+            post_packets: 1000,
+            output: "usbtop-trigger-%Y%m%d-%H%M%S.txt".to_string(),
+            rearm: false,
+        }
+    }
+}
+
+impl Config {
+    /// Load configuration from `path`, or from the default location if it exists
+    /// Falls back to built-in defaults when no file is found
+    pub fn load(path: Option<&str>) -> Result<Self> {
+        let path = match path {
+            Some(path) => PathBuf::from(path),
+            None => match Self::default_path() {
+                Some(path) if path.exists() => path,
+                _ => return Ok(Self::default()),
+            },
+        };
+        
+        let contents = fs::read_to_string(&path)
+            .map_err(|e| anyhow!("Failed to read config {}: {}", path.display(), e))?;
+        
+        Self::from_toml(&contents)
+            .map_err(|e| anyhow!("Failed to parse config {}: {}", path.display(), e))
+    }
+    
+    pub fn from_toml(contents: &str) -> Result<Self> {
+        Ok(toml::from_str(contents)?)
+    }
+    
+    /// Parse a single setting value the way the config file would, e.g. "tx"
+    /// for `sort_by`, so runtime commands accept exactly what the file does
+    pub fn parse_value<T: DeserializeOwned>(value: &str) -> Result<T, String> {
+        T::deserialize(toml::Value::String(value.to_string())).map_err(|e| e.to_string())
+    }
+    
+    /// `$XDG_CONFIG_HOME/ng-usbtop/config.toml`, falling back to `~/.config`
+    pub fn default_path() -> Option<PathBuf> {
+        let config_dir = env::var("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|_| env::var("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .ok()?;
+        
+        Some(config_dir.join("ng-usbtop").join("config.toml"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_empty_config_uses_defaults() {
+        let config = Config::from_toml("").unwrap();
+        assert_eq!(config.display.refresh_rate, 1000);
+        assert_eq!(config.ui.sort_strategy, SortStrategy::Immediate);
+    }
+    
+    #[test]
+    fn test_parse_sort_and_columns() {
+        let config = Config::from_toml("[ui]\nsort_by = \"vendor\"\ncolumns = [\"device\", \"tx\", \"rx\"]\n").unwrap();
+        assert_eq!(config.ui.sort_by, SortKey::Vendor);
+        assert_eq!(config.ui.sort_order, None);
+        assert_eq!(config.ui.columns, vec![Column::Device, Column::Tx, Column::Rx]);
+        assert_eq!(Config::from_toml("").unwrap().ui.columns, Column::DEFAULT.to_vec());
+        
+        assert_eq!(Config::parse_value::<SortOrder>("desc"), Ok(SortOrder::Desc));
+        assert_eq!(Config::parse_value::<Column>("status"), Ok(Column::Status));
+        assert!(Config::parse_value::<SortKey>("speed").is_err());
+    }
+    
+    #[test]
+    fn test_parse_sort_strategy() {
+        let config = Config::from_toml("[ui]\nsort_strategy = \"stable\"\nsort_hysteresis_frames = 5\n").unwrap();
+        assert_eq!(config.ui.sort_strategy, SortStrategy::Stable);
+        assert_eq!(config.ui.sort_hysteresis_frames, 5);
+        assert_eq!(config.ui.sort_hysteresis_ranks, 2);
+    }
+    
+    #[test]
+    fn test_parse_rate_precision() {
+        assert_eq!(Config::from_toml("[ui]\n").unwrap().ui.rate_precision, None);
+        let config = Config::from_toml("[ui]\nrate_precision = 2\n").unwrap();
+        assert_eq!(config.ui.rate_precision, Some(2));
+    }
+    
+    #[test]
+    fn test_parse_graph_layout() {
+        assert_eq!(Config::from_toml("[ui]\n").unwrap().ui.graph_layout, GraphLayout::Auto);
+        let config = Config::from_toml("[ui]\ngraph_layout = \"sparkline\"\n").unwrap();
+        assert_eq!(config.ui.graph_layout, GraphLayout::Sparkline);
+    }
+    
+    #[test]
+    fn test_hooks() {
+        let config = Config::from_toml("[[hooks]]\nevent = \"threshold_exceeded\"\ncommand = \"notify-send {product}\"\nthreshold_bps = 5000000\n").unwrap();
+        let hook = &config.hooks[0];
+        assert_eq!(hook.event, HookEvent::ThresholdExceeded);
+        assert_eq!(hook.threshold_bps, 5_000_000.0);
+        assert_eq!(hook.cooldown_secs, 60);
+        assert!(Config::from_toml("[[hooks]]\nevent = \"unplugged\"\ncommand = \"true\"\n").is_err());
+    }
+    
+    #[test]
+    fn test_trigger() {
+        assert_eq!(Config::from_toml("").unwrap().trigger.on, None);
+        let config = Config::from_toml("[trigger]\non = \"endpoint\"\nendpoint = 0x81\npre_packets = 50\n").unwrap();
+        assert_eq!(config.trigger.on, Some(TriggerKind::Endpoint));
+        assert_eq!(config.trigger.endpoint, Some(0x81));
+        assert_eq!(config.trigger.pre_packets, 50);
+        assert_eq!(config.trigger.post_packets, 1000);
+    }
+    
+    #[test]
+    fn test_filter_lists() {
+        let config = Config::from_toml("[filter]\nblocklist = [\"046d:c52b\", \"serial:TEST*\"]\n").unwrap();
+        assert_eq!(config.filter.blocklist, vec!["046d:c52b", "serial:TEST*"]);
+        assert!(config.filter.allowlist.is_empty());
+    }
+    
+    #[test]
+    fn test_controller_groups() {
+        let config = Config::from_toml("[[controllers]]\nname = \"xhci\"\nbuses = [2, 3]\nlimit_mbps = 8000\n").unwrap();
+        let controller = &config.controllers[0];
+        assert_eq!(controller.buses, vec![2, 3]);
+        assert_eq!(controller.warn_percentage, 80.0);
+        
+        // 8000 Mbps = 1 GB/s
+        assert_eq!(controller.get_utilization_percentage(500_000_000.0), 50.0);
+        assert!(controller.is_near_limit(900_000_000.0));
+    }
+}
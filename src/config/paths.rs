@@ -0,0 +1,134 @@
+use anyhow::{anyhow, Result};
+use std::env;
+use std::path::PathBuf;
+
+use crate::config::AdvancedConfig;
+use crate::device::descriptors::SYSFS_USB_DEVICES;
+use crate::usbmon::{usbmon_dir, DEFAULT_DEBUGFS_PATH, USBMON_PATH_ENV};
+
+pub const DEBUGFS_PATH_ENV: &str = "USBTOP_DEBUGFS_PATH";
+pub const SYSFS_PATH_ENV: &str = "USBTOP_SYSFS_PATH";
+
+/// Explicitly requested locations, before defaults are applied
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PathOverrides {
+    pub debugfs: Option<PathBuf>,
+    pub usbmon: Option<PathBuf>,
+    pub sysfs: Option<PathBuf>,
+}
+
+/// Where usbmon nodes and device metadata are read from
+#[derive(Debug, Clone, PartialEq)]
+pub struct SystemPaths {
+    pub debugfs: PathBuf,
+    pub usbmon: PathBuf,
+    pub sysfs: PathBuf,
+}
+
+impl Default for SystemPaths {
+    fn default() -> Self {
+        PathOverrides::default().resolve()
+    }
+}
+
+impl PathOverrides {
+    /// Fill anything not given on the command line from the environment, then
+    /// from `[advanced]` in the config file
+    pub fn with_fallbacks(self, config: &AdvancedConfig) -> Self {
+        self.with_fallbacks_from(config, |name| env::var_os(name).map(PathBuf::from))
+    }
+    
+    fn with_fallbacks_from(self, config: &AdvancedConfig, env: impl Fn(&str) -> Option<PathBuf>) -> Self {
+        let pick = |cli: Option<PathBuf>, var: &str, configured: &Option<String>| {
+            cli.or_else(|| env(var)).or_else(|| configured.as_ref().map(PathBuf::from))
+        };
+        
+        Self {
+            debugfs: pick(self.debugfs, DEBUGFS_PATH_ENV, &config.debugfs_path),
+            usbmon: pick(self.usbmon, USBMON_PATH_ENV, &config.usbmon_path),
+            sysfs: pick(self.sysfs, SYSFS_PATH_ENV, &config.sysfs_path),
+        }
+    }
+    
+    /// An overridden path that doesn't exist is almost always a typo, which
+    /// would otherwise show up as "usbmon not available" or blank device names
+    pub fn validate(&self) -> Result<()> {
+        let overrides = [
+            ("debugfs", DEBUGFS_PATH_ENV, &self.debugfs),
+            ("usbmon", USBMON_PATH_ENV, &self.usbmon),
+            ("sysfs", SYSFS_PATH_ENV, &self.sysfs),
+        ];
+        
+        for (name, var, path) in overrides {
+            if let Some(path) = path {
+                if !path.is_dir() {
+                    return Err(anyhow!(
+                        "{} path {} does not exist or is not a directory (set by --{}-path, ${} or [advanced] {}_path)",
+                        name, path.display(), name, var, name,
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+    
+    /// Apply the defaults; without its own override the usbmon directory
+    /// follows debugfs
+    pub fn resolve(self) -> SystemPaths {
+        let debugfs = self.debugfs.unwrap_or_else(|| PathBuf::from(DEFAULT_DEBUGFS_PATH));
+        
+        SystemPaths {
+            usbmon: self.usbmon.unwrap_or_else(|| usbmon_dir(&debugfs)),
+            debugfs,
+            sysfs: self.sysfs.unwrap_or_else(|| PathBuf::from(SYSFS_USB_DEVICES)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_defaults() {
+        let paths = SystemPaths::default();
+        assert_eq!(paths.debugfs, PathBuf::from("/sys/kernel/debug"));
+        assert_eq!(paths.usbmon, PathBuf::from("/sys/kernel/debug/usb/usbmon"));
+        assert_eq!(paths.sysfs, PathBuf::from("/sys/bus/usb/devices"));
+    }
+    
+    #[test]
+    fn test_override_precedence() {
+        let config = AdvancedConfig {
+            debugfs_path: Some("/config/debug".to_string()),
+            usbmon_path: None,
+            sysfs_path: Some("/config/sys".to_string()),
+        };
+        let env = |name: &str| (name == SYSFS_PATH_ENV).then(|| PathBuf::from("/env/sys"));
+        let cli = PathOverrides {
+            sysfs: Some(PathBuf::from("/cli/sys")),
+            ..Default::default()
+        };
+        
+        let paths = cli.with_fallbacks_from(&config, env).resolve();
+        assert_eq!(paths.sysfs, PathBuf::from("/cli/sys"));
+        assert_eq!(paths.debugfs, PathBuf::from("/config/debug"));
+        assert_eq!(paths.usbmon, PathBuf::from("/config/debug/usb/usbmon"));
+        
+        let paths = PathOverrides::default().with_fallbacks_from(&config, env).resolve();
+        assert_eq!(paths.sysfs, PathBuf::from("/env/sys"));
+    }
+    
+    #[test]
+    fn test_missing_override_is_rejected() {
+        let overrides = PathOverrides {
+            sysfs: Some(PathBuf::from("/nonexistent/usbtop-ng/sysfs")),
+            ..Default::default()
+        };
+        let error = overrides.validate().unwrap_err().to_string();
+        assert!(error.contains("sysfs path /nonexistent/usbtop-ng/sysfs"));
+        assert!(error.contains("--sysfs-path"));
+        
+        assert!(PathOverrides::default().validate().is_ok());
+    }
+}
@@ -0,0 +1,279 @@
+use anyhow::{Result, anyhow};
+use std::path::Path;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::sync::oneshot;
+
+use crate::monitor::ReaderTasks;
+
+/// Longest command line accepted before the connection is dropped
+const MAX_COMMAND_LEN: usize = 4096;
+
+/// A line command read from the control socket
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ControlCommand {
+    /// Current device state as a JSON `snapshot` message, the same one
+    /// `--serve` streams and `--diff` reads; `snapshot vendors` adds the
+    /// per-vendor totals
+    Snapshot { vendors: bool },
+    /// Zero every device's counters, like the reset key
+    Reset,
+    /// Stop accounting packets until `resume`
+    Pause,
+    Resume,
+    /// Record only devices matching the patterns (`[filter]` allowlist
+    /// syntax) among those the config allows; no patterns goes back to the
+    /// config's `[filter]` alone
+    Filter(Vec<String>),
+    Quit,
+}
+
+impl ControlCommand {
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let mut words = line.split_whitespace();
+        let Some(name) = words.next() else {
+            return Err("empty command".to_string());
+        };
+        let args: Vec<String> = words.map(str::to_string).collect();
+        
+        let command = match name {
+            "snapshot" => return match args.as_slice() {
+                [] => Ok(ControlCommand::Snapshot { vendors: false }),
+                [section] if section == "vendors" => Ok(ControlCommand::Snapshot { vendors: true }),
+                _ => Err("'snapshot' takes only 'vendors'".to_string()),
+            },
+            "reset" => ControlCommand::Reset,
+            "pause" => ControlCommand::Pause,
+            "resume" => ControlCommand::Resume,
+            "filter" => return Ok(ControlCommand::Filter(args)),
+            "quit" => ControlCommand::Quit,
+            _ => return Err(format!("unknown command '{}'", name)),
+        };
+        if !args.is_empty() {
+            return Err(format!("'{}' takes no arguments", name));
+        }
+        Ok(command)
+    }
+}
+
+/// A command waiting for the UI loop, which answers on `reply`
+#[derive(Debug)]
+pub struct ControlRequest {
+    pub command: ControlCommand,
+    pub reply: oneshot::Sender<Result<String, String>>,
+}
+
+/// The status line sent back for a command: "OK" or "ERR" and the detail
+pub fn format_reply(reply: &Result<String, String>) -> String {
+    let (status, detail) = match reply {
+        Ok(detail) => ("OK", detail),
+        Err(detail) => ("ERR", detail),
+    };
+    if detail.is_empty() {
+        format!("{}\n", status)
+    } else {
+        format!("{} {}\n", status, detail.replace('\n', " "))
+    }
+}
+
+/// Listen for commands on a Unix socket at `path`
+/// A stale socket left by an earlier run is replaced, but any other file
+/// there is an error. The socket is only accessible to the current user and
+/// is removed again on shutdown.
+#[cfg(unix)]
+pub fn listen(path: &Path, tasks: &mut ReaderTasks) -> Result<UnboundedReceiver<ControlRequest>> {
+    use std::fs;
+    use std::os::unix::fs::FileTypeExt;
+    use log::{debug, info, warn};
+    
+    if let Ok(metadata) = fs::symlink_metadata(path) {
+        if !metadata.file_type().is_socket() {
+            return Err(anyhow!("{} exists and is not a socket", path.display()));
+        }
+        if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            return Err(anyhow!("{} is already in use by another instance", path.display()));
+        }
+        fs::remove_file(path)?;
+    }
+    
+    let listener = bind_private(path)
+        .map_err(|e| anyhow!("Failed to listen on {}: {}", path.display(), e))?;
+    info!("Accepting control commands on {}", path.display());
+    
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut shutdown = tasks.subscribe();
+    let path = path.to_path_buf();
+    tasks.push(tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = shutdown.changed() => break,
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => {
+                        debug!("Control client connected");
+                        tokio::spawn(handle_connection(stream, tx.clone()));
+                    }
+                    Err(e) => warn!("Failed to accept control client: {}", e),
+                },
+            }
+        }
+        let _ = fs::remove_file(&path);
+    }));
+    
+    Ok(rx)
+}
+
+/// Bind a socket at `path` that is never reachable by other users
+/// A socket bound in place would briefly have the umask's mode, so it is
+/// bound inside a new 0700 directory next to `path`, restricted to 0600 and
+/// only then moved into place.
+#[cfg(unix)]
+fn bind_private(path: &Path) -> std::io::Result<tokio::net::UnixListener> {
+    use std::fs;
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+    
+    let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    let staging = path.with_file_name(format!(".{}.{}", name, std::process::id()));
+    fs::DirBuilder::new().mode(0o700).create(&staging)?;
+    let staged = staging.join("socket");
+    
+    let bound = tokio::net::UnixListener::bind(&staged).and_then(|listener| {
+        fs::set_permissions(&staged, fs::Permissions::from_mode(0o600))?;
+        fs::rename(&staged, path)?;
+        Ok(listener)
+    });
+    let _ = fs::remove_file(&staged);
+    let _ = fs::remove_dir(&staging);
+    bound
+}
+
+#[cfg(not(unix))]
+pub fn listen(_path: &Path, _tasks: &mut ReaderTasks) -> Result<UnboundedReceiver<ControlRequest>> {
+    Err(anyhow!("--control-socket needs Unix domain sockets, which this platform lacks"))
+}
+
+/// Answer each line in turn until the client hangs up or the UI goes away
+#[cfg(unix)]
+async fn handle_connection(stream: tokio::net::UnixStream, requests: tokio::sync::mpsc::UnboundedSender<ControlRequest>) {
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+    let mut line = String::new();
+    loop {
+        line.clear();
+        match (&mut reader).take(MAX_COMMAND_LEN as u64 + 1).read_line(&mut line).await {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        if line.len() > MAX_COMMAND_LEN {
+            let _ = writer.write_all(format_reply(&Err("command too long".to_string())).as_bytes()).await;
+            break;
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        
+        let command = match ControlCommand::parse(&line) {
+            Ok(command) => command,
+            Err(e) => {
+                if writer.write_all(format_reply(&Err(e)).as_bytes()).await.is_err() {
+                    break;
+                }
+                continue;
+            }
+        };
+        let quit = command == ControlCommand::Quit;
+        let (reply_tx, reply_rx) = oneshot::channel();
+        if requests.send(ControlRequest { command, reply: reply_tx }).is_err() {
+            break;
+        }
+        let Ok(reply) = reply_rx.await else {
+            break;
+        };
+        if writer.write_all(format_reply(&reply).as_bytes()).await.is_err() || quit {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(ControlCommand::parse("snapshot"), Ok(ControlCommand::Snapshot { vendors: false }));
+        assert_eq!(ControlCommand::parse("snapshot vendors"), Ok(ControlCommand::Snapshot { vendors: true }));
+        assert!(ControlCommand::parse("snapshot buses").is_err());
+        assert_eq!(ControlCommand::parse("  reset \r"), Ok(ControlCommand::Reset));
+        assert_eq!(
+            ControlCommand::parse("filter 046d:* serial:AB*"),
+            Ok(ControlCommand::Filter(vec!["046d:*".to_string(), "serial:AB*".to_string()])),
+        );
+        assert_eq!(ControlCommand::parse("filter"), Ok(ControlCommand::Filter(Vec::new())));
+        assert!(ControlCommand::parse("pause now").is_err());
+        assert!(ControlCommand::parse("explode").is_err());
+        assert!(ControlCommand::parse("").is_err());
+    }
+    
+    #[test]
+    fn test_reply_is_one_line() {
+        assert_eq!(format_reply(&Ok(String::new())), "OK\n");
+        assert_eq!(format_reply(&Ok("paused".to_string())), "OK paused\n");
+        assert_eq!(format_reply(&Err("bad\npattern".to_string())), "ERR bad pattern\n");
+    }
+    
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_filter_round_trip_over_socket() {
+        use std::os::unix::fs::PermissionsExt;
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+        use crate::config::Config;
+        use crate::device::UsbDevice;
+        use crate::ui::UsbTopApp;
+        
+        let path = std::env::temp_dir().join(format!("usbtop-ng-control-{}.sock", std::process::id()));
+        let mut tasks = ReaderTasks::new();
+        let mut requests = listen(&path, &mut tasks).unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        
+        // The config only allows Logitech devices
+        let mut config = Config::default();
+        config.filter.allowlist = vec!["046d:*".to_string()];
+        let mut app = UsbTopApp::new(std::time::Duration::from_secs(1), std::time::Duration::from_secs(1));
+        app.apply_config(&config);
+        for (device_id, vendor_id) in [(2, 0x046d), (3, 0x046d)] {
+            let mut device = UsbDevice::new(1, device_id);
+            device.vendor_id = Some(vendor_id);
+            device.product_id = Some(0xc077 + device_id as u16);
+            app.update_device(device);
+        }
+        let ui = tokio::spawn(async move {
+            while let Some(request) = requests.recv().await {
+                let _ = request.reply.send(app.handle_control(&request.command));
+            }
+        });
+        
+        let stream = tokio::net::UnixStream::connect(&path).await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = BufReader::new(reader);
+        let mut replies = Vec::new();
+        for command in ["filter 046d:c079", "filter 0781:*", "filter", "filter zz"] {
+            writer.write_all(format!("{}\n", command).as_bytes()).await.unwrap();
+            let mut reply = String::new();
+            reader.read_line(&mut reply).await.unwrap();
+            replies.push(reply);
+        }
+        assert_eq!(replies, [
+            "OK 1 of 2 devices recorded\n",
+            // Outside the config allowlist, so nothing is recorded rather than more
+            "OK 0 of 2 devices recorded\n",
+            "OK 2 of 2 devices recorded\n",
+            "ERR invalid pattern 'zz': expected VID:PID in hex (or *) or serial:<pattern>\n",
+        ]);
+        
+        drop(writer);
+        tasks.shutdown().await;
+        ui.abort();
+        assert!(!path.exists());
+    }
+}
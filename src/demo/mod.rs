@@ -0,0 +1,307 @@
+use chrono::Utc;
+use std::time::Duration;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+use crate::monitor::ReaderTasks;
+
+use crate::device::UsbDevice;
+use crate::usbmon::parser::{TransferType, UrbType, UsbPacket, UsbSpeed};
+
+/// Simulated time advanced per step; packets for a step are sent together
+pub const DEMO_STEP: Duration = Duration::from_millis(100);
+
+const KEYBOARD: u8 = 3;
+const WEBCAM: u8 = 4;
+const FLAKY: u8 = 5;
+const SSD: u8 = 2;
+
+/// Small deterministic PRNG (SplitMix64) so a seed always replays the same scenario
+#[derive(Debug, Clone)]
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+    
+    /// Uniform in [0, 1)
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+    
+    fn chance(&mut self, probability: f64) -> bool {
+        self.next_f64() < probability
+    }
+    
+    /// Uniform in [low, high)
+    fn range(&mut self, low: u64, high: u64) -> u64 {
+        low + self.next_u64() % (high - low)
+    }
+}
+
+/// What the simulated SSD is doing
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SsdState {
+    Idle,
+    Reading,
+    Writing,
+}
+
+/// Synthetic devices with time-varying traffic for `--demo`
+/// A keyboard producing bursts of interrupt reports, a webcam streaming
+/// isochronous video, an SSD doing bulk read/write bursts and a flaky
+/// serial adapter that throws errors.
+#[derive(Debug, Clone)]
+pub struct DemoScenario {
+    rng: SplitMix64,
+    step: u64,
+    ssd_state: SsdState,
+    ssd_steps_left: u64,
+    next_urb: u64,
+}
+
+impl DemoScenario {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: SplitMix64(seed),
+            step: 0,
+            ssd_state: SsdState::Idle,
+            ssd_steps_left: 10,
+            next_urb: 0,
+        }
+    }
+    
+    /// The simulated devices, with the metadata sysfs would normally provide
+    pub fn devices() -> Vec<UsbDevice> {
+        vec![
+            demo_device(1, KEYBOARD, (0x1d50, 0x6001), "Demo", "USB Keyboard", UsbSpeed::Low, 0x0110),
+            demo_device(1, WEBCAM, (0x1d50, 0x6002), "Demo", "HD Webcam", UsbSpeed::High, 0x0200),
+            demo_device(1, FLAKY, (0x1d50, 0x6003), "Demo", "Flaky Serial Adapter", UsbSpeed::Full, 0x0200),
+            demo_device(2, SSD, (0x1d50, 0x6004), "Demo", "NVMe SSD Enclosure", UsbSpeed::SuperSpeedPlus, 0x0320),
+        ]
+    }
+    
+    /// Simulated seconds since the scenario started
+    fn elapsed_secs(&self) -> f64 {
+        self.step as f64 * DEMO_STEP.as_secs_f64()
+    }
+    
+    /// Packets for the next `DEMO_STEP` of simulated time
+    pub fn next_packets(&mut self) -> Vec<UsbPacket> {
+        let mut packets = Vec::new();
+        self.keyboard(&mut packets);
+        self.webcam(&mut packets);
+        self.ssd(&mut packets);
+        self.flaky(&mut packets);
+        
+        let timestamp_us = self.step * DEMO_STEP.as_micros() as u64;
+        for packet in &mut packets {
+            packet.timestamp_us = timestamp_us;
+        }
+        self.step += 1;
+        packets
+    }
+    
+    /// Typing comes in bursts of 8-byte reports
+    fn keyboard(&mut self, packets: &mut Vec<UsbPacket>) {
+        if !self.rng.chance(0.3) {
+            return;
+        }
+        for _ in 0..self.rng.range(1, 4) {
+            self.transfer(packets, 1, KEYBOARD, 1, true, TransferType::Interrupt, 8, 0);
+        }
+    }
+    
+    /// Roughly 20 MB/s of video, drifting slowly with scene changes
+    fn webcam(&mut self, packets: &mut Vec<UsbPacket>) {
+        let drift = 1.0 + 0.15 * (self.elapsed_secs() / 5.0).sin();
+        let noise = 0.9 + 0.2 * self.rng.next_f64();
+        let bytes_per_step = 20_000_000.0 * drift * noise * DEMO_STEP.as_secs_f64();
+        
+        let urbs = 8;
+        let length = (bytes_per_step / urbs as f64) as u32;
+        for _ in 0..urbs {
+            self.transfer(packets, 1, WEBCAM, 1, true, TransferType::Isochronous, length, 0);
+        }
+    }
+    
+    /// Idle periods alternating with multi-second read or write bursts
+    fn ssd(&mut self, packets: &mut Vec<UsbPacket>) {
+        if self.ssd_steps_left == 0 {
+            self.ssd_state = match self.ssd_state {
+                SsdState::Idle if self.rng.chance(0.5) => SsdState::Reading,
+                SsdState::Idle => SsdState::Writing,
+                _ => SsdState::Idle,
+            };
+            self.ssd_steps_left = self.rng.range(20, 60);
+        }
+        self.ssd_steps_left -= 1;
+        
+        let (endpoint, direction) = match self.ssd_state {
+            SsdState::Idle => return,
+            SsdState::Reading => (1, true),
+            SsdState::Writing => (2, false),
+        };
+        
+        // 1 MiB URBs at roughly 250-350 MB/s
+        let urbs = self.rng.range(24, 34);
+        for _ in 0..urbs {
+            self.transfer(packets, 2, SSD, endpoint, direction, TransferType::Bulk, 1024 * 1024, 0);
+        }
+    }
+    
+    /// Light polling traffic with occasional protocol errors and stalls
+    fn flaky(&mut self, packets: &mut Vec<UsbPacket>) {
+        if self.rng.chance(0.5) {
+            let length = self.rng.range(16, 64) as u32;
+            self.transfer(packets, 1, FLAKY, 2, true, TransferType::Bulk, length, 0);
+        }
+        if self.rng.chance(0.05) {
+            // -71 (EPROTO) on the submission, as seen with a bad cable
+            let urb_tag = self.urb_tag();
+            packets.push(demo_packet(urb_tag, UrbType::Error, TransferType::Bulk, 1, FLAKY, 2, true, 0, -71));
+        }
+        if self.rng.chance(0.02) {
+            // Endpoint stall (-32, EPIPE) completing a control request
+            self.transfer(packets, 1, FLAKY, 0, true, TransferType::Control, 0, -32);
+        }
+    }
+    
+    fn urb_tag(&mut self) -> String {
+        self.next_urb += 1;
+        format!("{:016x}", 0xffff_8800_0000_0000u64 + self.next_urb * 0x100)
+    }
+    
+    /// One URB: a submission and its callback, with the data on the side that carries it
+    #[allow(clippy::too_many_arguments)]
+    fn transfer(
+        &mut self,
+        packets: &mut Vec<UsbPacket>,
+        bus_id: u8,
+        device_id: u8,
+        endpoint: u8,
+        direction: bool,
+        transfer_type: TransferType,
+        length: u32,
+        status: i32,
+    ) {
+        let urb_tag = self.urb_tag();
+        let (submitted, completed) = if direction { (0, length) } else { (length, 0) };
+        packets.push(demo_packet(urb_tag.clone(), UrbType::Submission, transfer_type, bus_id, device_id, endpoint, direction, submitted, -115));
+        packets.push(demo_packet(urb_tag, UrbType::Callback, transfer_type, bus_id, device_id, endpoint, direction, completed, status));
+    }
+}
+
+fn demo_device(
+    bus_id: u8,
+    device_id: u8,
+    (vendor_id, product_id): (u16, u16),
+    vendor: &str,
+    product: &str,
+    speed: UsbSpeed,
+    usb_version: u16,
+) -> UsbDevice {
+    let mut device = UsbDevice::new(bus_id, device_id);
+    device.vendor_id = Some(vendor_id);
+    device.product_id = Some(product_id);
+    device.vendor = Some(vendor.to_string());
+    device.product = Some(product.to_string());
+    device.speed = speed;
+    device.usb_version = Some(usb_version);
+    // Not backed by sysfs, so descriptor re-reads must leave it alone
+    device.origin = Some("demo".to_string());
+    device
+}
+
+#[allow(clippy::too_many_arguments)]
+fn demo_packet(
+    urb_tag: String,
+    urb_type: UrbType,
+    transfer_type: TransferType,
+    bus_id: u8,
+    device_id: u8,
+    endpoint: u8,
+    direction: bool,
+    data_length: u32,
+    status: i32,
+) -> UsbPacket {
+    UsbPacket {
+        timestamp: Utc::now(),
+        timestamp_us: 0,
+        urb_tag,
+        urb_type,
+        transfer_type,
+        bus_id,
+        device_id,
+        endpoint,
+        direction,
+        data_length,
+        captured_length: 0,
+        status,
+        interval: None,
+        setup_packet: None,
+        data: None,
+    }
+}
+
+/// Run the scenario in real time, feeding the same packet channel the usbmon readers use
+pub fn spawn_demo(seed: u64) -> (UnboundedReceiver<UsbPacket>, ReaderTasks) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let mut tasks = ReaderTasks::new();
+    let mut shutdown = tasks.subscribe();
+    
+    tasks.push(tokio::spawn(async move {
+        let mut scenario = DemoScenario::new(seed);
+        let mut interval = tokio::time::interval(DEMO_STEP);
+        loop {
+            tokio::select! {
+                _ = shutdown.changed() => return,
+                _ = interval.tick() => {}
+            }
+            for packet in scenario.next_packets() {
+                if tx.send(packet).is_err() {
+                    return;
+                }
+            }
+        }
+    }));
+    
+    (rx, tasks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn summarize(scenario: &mut DemoScenario, steps: usize) -> Vec<(u8, u8, u32, i32)> {
+        (0..steps)
+            .flat_map(|_| scenario.next_packets())
+            .map(|packet| (packet.device_id, packet.endpoint, packet.data_length, packet.status))
+            .collect()
+    }
+    
+    #[test]
+    fn test_same_seed_replays_same_traffic() {
+        let first = summarize(&mut DemoScenario::new(7), 200);
+        let second = summarize(&mut DemoScenario::new(7), 200);
+        let other = summarize(&mut DemoScenario::new(8), 200);
+        
+        assert_eq!(first, second);
+        assert_ne!(first, other);
+    }
+    
+    #[test]
+    fn test_every_device_produces_traffic() {
+        let mut scenario = DemoScenario::new(1);
+        let packets: Vec<UsbPacket> = (0..300).flat_map(|_| scenario.next_packets()).collect();
+        
+        for device in DemoScenario::devices() {
+            assert!(packets.iter().any(|packet| packet.device_id == device.device_id && packet.bus_id == device.bus_id));
+        }
+        assert!(packets.iter().any(|packet| packet.urb_type == UrbType::Error));
+    }
+}
@@ -0,0 +1,229 @@
+use std::collections::HashMap;
+
+use crate::device::DeviceKey;
+use crate::usbmon::parser::{TransferType, UrbType, UsbPacket};
+
+/// GET_DESCRIPTOR (USB 2.0 table 9-4)
+const REQUEST_GET_DESCRIPTOR: u8 = 0x06;
+/// Standard, device-to-host, to the device
+const REQUEST_TYPE_STANDARD_IN: u8 = 0x80;
+const DESCRIPTOR_DEVICE: u8 = 0x01;
+const DESCRIPTOR_STRING: u8 = 0x03;
+const DEVICE_DESCRIPTOR_LEN: usize = 18;
+
+/// Payload bytes to keep on endpoint 0 reads regardless of `capture_len`:
+/// enough for any descriptor a string or device request returns
+pub const DESCRIPTOR_CAPTURE_LEN: u32 = 255;
+
+/// What a GET_DESCRIPTOR submission asked for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Requested {
+    Device,
+    String(u8),
+}
+
+/// Identity read from a device's own descriptors as they crossed the bus
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SniffedDevice {
+    pub vendor_id: Option<u16>,
+    pub product_id: Option<u16>,
+    pub usb_version: Option<u16>,
+    pub device_class: Option<u8>,
+    /// iManufacturer, iProduct and iSerialNumber; 0 means the device has none
+    manufacturer_index: u8,
+    product_index: u8,
+    serial_index: u8,
+    strings: HashMap<u8, String>,
+}
+
+impl SniffedDevice {
+    fn string(&self, index: u8) -> Option<&str> {
+        if index == 0 {
+            return None;
+        }
+        self.strings.get(&index).map(String::as_str)
+    }
+    
+    pub fn manufacturer(&self) -> Option<&str> {
+        self.string(self.manufacturer_index)
+    }
+    
+    pub fn product(&self) -> Option<&str> {
+        self.string(self.product_index)
+    }
+    
+    pub fn serial(&self) -> Option<&str> {
+        self.string(self.serial_index)
+    }
+}
+
+/// Rebuilds device identities from enumeration traffic on endpoint 0
+/// Pairs each GET_DESCRIPTOR submission with its completion by URB tag and
+/// decodes device and string descriptors from the returned payload. Only
+/// complete descriptors are used; a text capture's 32 bytes cut long strings
+/// short, so those are skipped rather than shown truncated.
+#[derive(Debug, Default)]
+pub struct DescriptorAssembler {
+    pending: HashMap<(DeviceKey, String), Requested>,
+    devices: HashMap<DeviceKey, SniffedDevice>,
+}
+
+impl DescriptorAssembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Look at a captured event; true when it taught something new about its device
+    pub fn observe(&mut self, packet: &UsbPacket) -> bool {
+        if packet.transfer_type != TransferType::Control || packet.endpoint != 0 {
+            return false;
+        }
+        let key = DeviceKey(packet.bus_id, packet.device_id);
+        let tag = (key, packet.urb_tag.clone());
+        
+        match packet.urb_type {
+            UrbType::Submission => {
+                match packet.setup_packet.as_deref().and_then(parse_request) {
+                    Some(requested) => {
+                        self.pending.insert(tag, requested);
+                    }
+                    None => {
+                        // A reused tag is a different URB now
+                        self.pending.remove(&tag);
+                    }
+                }
+                false
+            }
+            UrbType::Error => {
+                self.pending.remove(&tag);
+                false
+            }
+            UrbType::Callback => {
+                let Some(requested) = self.pending.remove(&tag) else {
+                    return false;
+                };
+                let Some(data) = packet.data.as_deref().filter(|_| packet.status == 0) else {
+                    return false;
+                };
+                self.complete(key, requested, data)
+            }
+        }
+    }
+    
+    fn complete(&mut self, key: DeviceKey, requested: Requested, data: &[u8]) -> bool {
+        match requested {
+            Requested::Device => {
+                let Some(descriptor) = data.get(..DEVICE_DESCRIPTOR_LEN).filter(|d| d[1] == DESCRIPTOR_DEVICE) else {
+                    return false;
+                };
+                let word = |at: usize| u16::from_le_bytes([descriptor[at], descriptor[at + 1]]);
+                let device = self.devices.entry(key).or_default();
+                let vendor_id = Some(word(8));
+                let product_id = Some(word(10));
+                // The same device re-read keeps its strings; a new one starts over
+                if device.vendor_id.is_some() && (device.vendor_id, device.product_id) != (vendor_id, product_id) {
+                    *device = SniffedDevice::default();
+                }
+                let before = device.clone();
+                device.usb_version = Some(word(2));
+                device.device_class = Some(descriptor[4]);
+                device.vendor_id = vendor_id;
+                device.product_id = product_id;
+                device.manufacturer_index = descriptor[14];
+                device.product_index = descriptor[15];
+                device.serial_index = descriptor[16];
+                *device != before
+            }
+            // Index 0 lists the supported languages rather than text
+            Requested::String(0) => false,
+            Requested::String(index) => {
+                let Some(text) = decode_string_descriptor(data) else {
+                    return false;
+                };
+                let device = self.devices.entry(key).or_default();
+                device.strings.insert(index, text.clone()) != Some(text)
+            }
+        }
+    }
+    
+    pub fn get(&self, key: DeviceKey) -> Option<&SniffedDevice> {
+        self.devices.get(&key)
+    }
+    
+    /// Drop what was learned about a device that went away
+    pub fn forget(&mut self, key: DeviceKey) {
+        self.devices.remove(&key);
+        self.pending.retain(|(pending_key, _), _| *pending_key != key);
+    }
+}
+
+/// The descriptor a standard GET_DESCRIPTOR setup packet asks for, if it's
+/// one that identifies the device
+fn parse_request(setup: &[u8]) -> Option<Requested> {
+    let [request_type, request, index, descriptor_type, ..] = *setup else {
+        return None;
+    };
+    if request_type != REQUEST_TYPE_STANDARD_IN || request != REQUEST_GET_DESCRIPTOR {
+        return None;
+    }
+    match descriptor_type {
+        DESCRIPTOR_DEVICE => Some(Requested::Device),
+        DESCRIPTOR_STRING => Some(Requested::String(index)),
+        _ => None,
+    }
+}
+
+/// UTF-16LE text of a complete string descriptor
+fn decode_string_descriptor(data: &[u8]) -> Option<String> {
+    let (&length, rest) = data.split_first()?;
+    let length = length as usize;
+    if rest.first() != Some(&DESCRIPTOR_STRING) || length < 2 || data.len() < length {
+        return None;
+    }
+    let units: Vec<u16> = data[2..length].chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect();
+    let text = String::from_utf16_lossy(&units);
+    let text = text.trim_end_matches('\0').trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::usbmon::parser::parse_usbmon_text_line;
+    
+    fn observe(assembler: &mut DescriptorAssembler, line: &str) -> bool {
+        assembler.observe(&parse_usbmon_text_line(line).unwrap())
+    }
+    
+    #[test]
+    fn test_identity_from_enumeration() {
+        let mut assembler = DescriptorAssembler::new();
+        let key = DeviceKey(1, 5);
+        
+        // GET_DESCRIPTOR(device) returns VID 0781, PID 5581, strings 1/2/3
+        assert!(!observe(&mut assembler, "ffff8800aa000100 100 S Ci:1:005:0 s 80 06 0100 0000 0012 18 <"));
+        assert!(observe(&mut assembler, "ffff8800aa000100 200 C Ci:1:005:0 0 18 = 12010002 00000040 81078155 00010102 0301"));
+        let device = assembler.get(key).unwrap();
+        assert_eq!((device.vendor_id, device.product_id), (Some(0x0781), Some(0x5581)));
+        assert_eq!(device.usb_version, Some(0x0200));
+        assert_eq!(device.product(), None);
+        
+        // String 2 (iProduct): "Ultra"
+        observe(&mut assembler, "ffff8800aa000200 300 S Ci:1:005:0 s 80 06 0302 0409 00ff 255 <");
+        assert!(observe(&mut assembler, "ffff8800aa000200 400 C Ci:1:005:0 0 12 = 0c035500 6c007400 72006100"));
+        assert_eq!(assembler.get(key).unwrap().product(), Some("Ultra"));
+        
+        // A string cut short by the capture is left out
+        observe(&mut assembler, "ffff8800aa000300 500 S Ci:1:005:0 s 80 06 0301 0409 00ff 255 <");
+        assert!(!observe(&mut assembler, "ffff8800aa000300 600 C Ci:1:005:0 0 40 = 28035300 61006e00"));
+        assert_eq!(assembler.get(key).unwrap().manufacturer(), None);
+        
+        // A completion nobody asked for, and a failed request
+        assert!(!observe(&mut assembler, "ffff8800aa000400 700 C Ci:1:005:0 0 12 = 0c035500 6c007400 72006100"));
+        observe(&mut assembler, "ffff8800aa000500 800 S Ci:1:005:0 s 80 06 0303 0409 00ff 255 <");
+        assert!(!observe(&mut assembler, "ffff8800aa000500 900 C Ci:1:005:0 -32 0"));
+        
+        assembler.forget(key);
+        assert!(assembler.get(key).is_none());
+    }
+}
@@ -0,0 +1,101 @@
+use std::collections::BTreeMap;
+
+use crate::device::hubs::HUB_CLASS;
+use crate::device::UsbDevice;
+use crate::usbmon::parser::UsbSpeed;
+
+/// What the devices on one bus could ask for against what the bus delivers
+/// Demand is each device's practical bandwidth at the fastest speed it
+/// supports, whatever it's doing now, so this is for planning where devices
+/// go rather than for reading current load.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BusBudget {
+    pub bus_id: u8,
+    pub devices: usize,
+    pub demand_bps: f64,
+    /// Practical bandwidth of the bus; 0.0 when its speed is unknown
+    pub capacity_bps: f64,
+}
+
+impl BusBudget {
+    /// Demand as a share of capacity, when the capacity is known
+    pub fn subscription(&self) -> Option<f64> {
+        (self.capacity_bps > 0.0).then(|| self.demand_bps / self.capacity_bps)
+    }
+    
+    pub fn is_oversubscribed(&self) -> bool {
+        self.subscription().is_some_and(|share| share > 1.0)
+    }
+}
+
+/// Sum each bus's device capabilities, in bus order
+/// Hubs only pass traffic through, so they add no demand of their own, and
+/// disconnected devices are left out. `capability` gives a device's fastest
+/// supported speed and `bus_speed` the speed of a bus's root hub.
+pub fn aggregate_budget<'a>(
+    devices: impl IntoIterator<Item = &'a UsbDevice>,
+    capability: impl Fn(&UsbDevice) -> UsbSpeed,
+    bus_speed: impl Fn(u8) -> Option<UsbSpeed>,
+) -> Vec<BusBudget> {
+    let mut buses: BTreeMap<u8, BusBudget> = BTreeMap::new();
+    
+    for device in devices {
+        if device.is_disconnected || device.device_class == Some(HUB_CLASS) {
+            continue;
+        }
+        let budget = buses.entry(device.bus_id).or_insert_with(|| BusBudget {
+            bus_id: device.bus_id,
+            devices: 0,
+            demand_bps: 0.0,
+            capacity_bps: bus_speed(device.bus_id).map_or(0.0, |speed| speed.to_practical_bytes_per_second()),
+        });
+        budget.devices += 1;
+        budget.demand_bps += capability(device).to_practical_bytes_per_second();
+    }
+    
+    buses.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn device(bus: u8, dev: u8, class: u8, speed: UsbSpeed) -> UsbDevice {
+        let mut device = UsbDevice::new(bus, dev);
+        device.device_class = Some(class);
+        device.speed = speed;
+        device
+    }
+    
+    #[test]
+    fn test_high_speed_devices_oversubscribe_a_usb2_bus() {
+        let mut unplugged = device(1, 7, 0x08, UsbSpeed::High);
+        unplugged.is_disconnected = true;
+        let devices = vec![
+            device(1, 1, HUB_CLASS, UsbSpeed::High),
+            device(1, 2, 0x08, UsbSpeed::High),
+            device(1, 3, 0x0e, UsbSpeed::High),
+            device(1, 4, 0x08, UsbSpeed::High),
+            unplugged,
+            device(2, 2, 0x03, UsbSpeed::Full),
+            device(3, 2, 0x08, UsbSpeed::SuperSpeed),
+        ];
+        let bus_speed = |bus_id: u8| match bus_id {
+            1 | 2 => Some(UsbSpeed::High),
+            _ => None,
+        };
+        
+        let budget = aggregate_budget(&devices, |device| device.speed.clone(), bus_speed);
+        assert_eq!(budget.iter().map(|bus| bus.bus_id).collect::<Vec<_>>(), [1, 2, 3]);
+        
+        assert_eq!(budget[0].devices, 3);
+        assert_eq!(budget[0].demand_bps, 3.0 * UsbSpeed::High.to_practical_bytes_per_second());
+        assert_eq!(budget[0].subscription(), Some(3.0));
+        assert!(budget[0].is_oversubscribed());
+        
+        assert!(!budget[1].is_oversubscribed());
+        // Nothing to compare against without the bus speed
+        assert_eq!(budget[2].subscription(), None);
+        assert!(!budget[2].is_oversubscribed());
+    }
+}
@@ -0,0 +1,97 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::device::descriptors::class_name;
+use crate::device::UsbDevice;
+
+/// Bucket for devices whose class isn't known or has no name
+pub const OTHER_CLASS: &str = "Other";
+
+/// Bytes moved by every tracked device of one class
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ClassTotals {
+    pub name: String,
+    pub devices: usize,
+    pub total_rx_bytes: u64,
+    pub total_tx_bytes: u64,
+}
+
+impl ClassTotals {
+    pub fn total_bytes(&self) -> u64 {
+        self.total_rx_bytes.saturating_add(self.total_tx_bytes)
+    }
+}
+
+/// Name a device is grouped under: its class, or "Other" when neither the
+/// device nor its first interface declares one lsusb has a name for
+pub fn class_label(device: &UsbDevice) -> &'static str {
+    match device.class().map(class_name) {
+        Some(name) if !name.is_empty() && name != "[unknown]" => name,
+        _ => OTHER_CLASS,
+    }
+}
+
+/// Aggregate session byte totals by device class, most data moved first
+pub fn aggregate_by_class<'a>(devices: impl IntoIterator<Item = &'a UsbDevice>) -> Vec<ClassTotals> {
+    let mut groups: HashMap<&'static str, ClassTotals> = HashMap::new();
+    
+    for device in devices {
+        let name = class_label(device);
+        let totals = groups.entry(name).or_insert_with(|| ClassTotals {
+            name: name.to_string(),
+            devices: 0,
+            total_rx_bytes: 0,
+            total_tx_bytes: 0,
+        });
+        
+        let stats = &device.bandwidth_stats;
+        totals.devices += 1;
+        totals.total_rx_bytes = totals.total_rx_bytes.saturating_add(stats.total_rx_bytes);
+        totals.total_tx_bytes = totals.total_tx_bytes.saturating_add(stats.total_tx_bytes);
+    }
+    
+    let mut classes: Vec<ClassTotals> = groups.into_values().collect();
+    classes.sort_by(|a, b| {
+        b.total_bytes().cmp(&a.total_bytes())
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    classes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn device(device_id: u8, device_class: Option<u8>, interface_class: Option<u8>, rx_bytes: u64) -> UsbDevice {
+        let mut device = UsbDevice::new(1, device_id);
+        device.device_class = device_class;
+        device.interface_class = interface_class;
+        device.bandwidth_stats.total_rx_bytes = rx_bytes;
+        device
+    }
+    
+    #[test]
+    fn test_aggregate_by_class() {
+        let devices = [
+            // Class declared per interface, as most devices do
+            device(2, Some(0x00), Some(0x08), 4_000_000),
+            device(3, Some(0x00), Some(0x08), 200_000),
+            device(4, Some(0x00), Some(0x03), 3_000),
+            // Interface association: the first function decides
+            device(5, Some(0xef), Some(0x0e), 1_100_000),
+            device(6, None, None, 10),
+            device(7, Some(0x42), None, 20),
+        ];
+        let classes = aggregate_by_class(&devices);
+        
+        let rows: Vec<(&str, usize, u64)> = classes.iter()
+            .map(|class| (class.name.as_str(), class.devices, class.total_bytes()))
+            .collect();
+        assert_eq!(rows, [
+            ("Mass Storage", 2, 4_200_000),
+            ("Video", 1, 1_100_000),
+            ("Human Interface Device", 1, 3_000),
+            ("Other", 2, 30),
+        ]);
+    }
+}
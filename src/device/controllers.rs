@@ -0,0 +1,161 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::usbmon::parser::UsbSpeed;
+
+/// Host controller interface, from the driver bound to the controller
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControllerKind {
+    Xhci,
+    Ehci,
+    Ohci,
+    Uhci,
+    Other,
+}
+
+impl ControllerKind {
+    /// Classify a driver name such as "xhci_hcd", "ehci-pci" or "xhci-plat-hcd"
+    pub fn from_driver(driver: &str) -> Self {
+        match driver.get(..4) {
+            Some("xhci") => ControllerKind::Xhci,
+            Some("ehci") => ControllerKind::Ehci,
+            Some("ohci") => ControllerKind::Ohci,
+            Some("uhci") => ControllerKind::Uhci,
+            _ => ControllerKind::Other,
+        }
+    }
+    
+    pub fn label(&self) -> &'static str {
+        match self {
+            ControllerKind::Xhci => "xHCI",
+            ControllerKind::Ehci => "EHCI",
+            ControllerKind::Ohci => "OHCI",
+            ControllerKind::Uhci => "UHCI",
+            ControllerKind::Other => "HCD",
+        }
+    }
+}
+
+/// One logical bus and the speed of its root hub's link
+#[derive(Debug, Clone, PartialEq)]
+pub struct RootHub {
+    pub bus_id: u8,
+    pub speed: UsbSpeed,
+}
+
+/// A physical host controller and the buses it exposes
+/// An xHCI controller registers two buses, one per root hub: a High Speed
+/// one for USB 2 devices and a SuperSpeed one for USB 3 devices. Each bus
+/// has its own link, so a bus is measured against its own root hub rather
+/// than the fastest speed the controller supports.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HostController {
+    /// Device name of the controller, e.g. its PCI address "0000:00:14.0"
+    pub name: String,
+    pub kind: ControllerKind,
+    pub root_hubs: Vec<RootHub>,
+}
+
+impl HostController {
+    /// Link speed of `bus_id` if it belongs to this controller
+    pub fn bus_speed(&self, bus_id: u8) -> Option<UsbSpeed> {
+        self.root_hubs.iter().find(|hub| hub.bus_id == bus_id).map(|hub| hub.speed.clone())
+    }
+    
+    /// Other buses on this controller, e.g. the USB 3 half of a USB 2 bus
+    pub fn sibling_buses(&self, bus_id: u8) -> Vec<u8> {
+        self.root_hubs.iter().map(|hub| hub.bus_id).filter(|id| *id != bus_id).collect()
+    }
+}
+
+/// Group the root hubs under the sysfs devices directory by controller
+/// Each `usbN` entry links into the device tree under its controller, whose
+/// `driver` link names the interface. Buses whose link can't be resolved are
+/// left out.
+pub fn discover_controllers(sysfs_root: &Path) -> Vec<HostController> {
+    let Ok(entries) = fs::read_dir(sysfs_root) else {
+        return Vec::new();
+    };
+    
+    let mut controllers: BTreeMap<String, HostController> = BTreeMap::new();
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let file_name = entry.file_name();
+        let Some(bus_id) = file_name.to_str().and_then(|name| name.strip_prefix("usb")).and_then(|id| id.parse::<u8>().ok()) else {
+            continue;
+        };
+        let Some(controller_dir) = fs::canonicalize(entry.path()).ok().and_then(|path| path.parent().map(Path::to_path_buf)) else {
+            continue;
+        };
+        let Some(name) = controller_dir.file_name().map(|name| name.to_string_lossy().into_owned()) else {
+            continue;
+        };
+        
+        let speed = fs::read_to_string(entry.path().join("speed"))
+            .map_or(UsbSpeed::Unknown, |speed| UsbSpeed::from_speed_str(speed.trim()));
+        let controller = controllers.entry(controller_dir.to_string_lossy().into_owned()).or_insert_with(|| {
+            let driver = fs::read_link(controller_dir.join("driver")).ok()
+                .and_then(|link| link.file_name().map(|name| name.to_string_lossy().into_owned()));
+            HostController {
+                name,
+                kind: driver.as_deref().map_or(ControllerKind::Other, ControllerKind::from_driver),
+                root_hubs: Vec::new(),
+            }
+        });
+        controller.root_hubs.push(RootHub { bus_id, speed });
+    }
+    
+    let mut controllers: Vec<HostController> = controllers.into_values().collect();
+    for controller in &mut controllers {
+        controller.root_hubs.sort_by_key(|hub| hub.bus_id);
+    }
+    controllers.sort_by_key(|controller| controller.root_hubs.first().map(|hub| hub.bus_id));
+    controllers
+}
+
+/// The controller that owns `bus_id`
+pub fn controller_for_bus(controllers: &[HostController], bus_id: u8) -> Option<&HostController> {
+    controllers.iter().find(|controller| controller.bus_speed(bus_id).is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+    
+    #[test]
+    fn test_driver_names() {
+        assert_eq!(ControllerKind::from_driver("xhci_hcd"), ControllerKind::Xhci);
+        assert_eq!(ControllerKind::from_driver("xhci-plat-hcd"), ControllerKind::Xhci);
+        assert_eq!(ControllerKind::from_driver("ehci-pci"), ControllerKind::Ehci);
+        assert_eq!(ControllerKind::from_driver("dwc2"), ControllerKind::Other);
+    }
+    
+    #[test]
+    fn test_xhci_root_hubs_are_paired() {
+        let root = std::env::temp_dir().join(format!("usbtop-ng-controllers-{}", std::process::id()));
+        let xhci = root.join("devices/pci0000:00/0000:00:14.0");
+        let ehci = root.join("devices/pci0000:00/0000:00:1d.0");
+        fs::create_dir_all(root.join("drivers/xhci_hcd")).unwrap();
+        fs::create_dir_all(root.join("bus")).unwrap();
+        for (controller, bus, speed) in [(&xhci, "usb1", "480"), (&xhci, "usb2", "10000"), (&ehci, "usb3", "480")] {
+            fs::create_dir_all(controller.join(bus)).unwrap();
+            fs::write(controller.join(bus).join("speed"), format!("{}\n", speed)).unwrap();
+            symlink(controller.join(bus), root.join("bus").join(bus)).unwrap();
+        }
+        symlink(root.join("drivers/xhci_hcd"), xhci.join("driver")).unwrap();
+        
+        let controllers = discover_controllers(&root.join("bus"));
+        fs::remove_dir_all(&root).unwrap();
+        
+        assert_eq!(controllers.len(), 2);
+        assert_eq!(controllers[0].name, "0000:00:14.0");
+        assert_eq!(controllers[0].kind, ControllerKind::Xhci);
+        // The USB 2 half of an xHCI controller is still a 480 Mb/s link
+        assert_eq!(controllers[0].bus_speed(1), Some(UsbSpeed::High));
+        assert_eq!(controllers[0].bus_speed(2), Some(UsbSpeed::SuperSpeedPlus));
+        assert_eq!(controllers[0].sibling_buses(1), vec![2]);
+        assert_eq!(controllers[1].kind, ControllerKind::Other);
+        assert_eq!(controller_for_bus(&controllers, 3).map(|controller| controller.name.as_str()), Some("0000:00:1d.0"));
+    }
+}
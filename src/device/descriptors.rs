@@ -0,0 +1,511 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::device::{format_speed, format_usb_version, parse_usb_version, DeviceKey};
+use crate::usbmon::parser::{TransferType, UsbSpeed};
+
+/// Where Linux exposes one directory per USB device and interface
+pub const SYSFS_USB_DEVICES: &str = "/sys/bus/usb/devices";
+
+/// Device descriptor fields as exposed by sysfs (active configuration only)
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceDescriptors {
+    pub key: DeviceKey,
+    pub sysfs_name: String,
+    pub usb_version: Option<u16>,
+    pub class: Option<u8>,
+    pub subclass: Option<u8>,
+    pub protocol: Option<u8>,
+    pub max_packet_size0: Option<u16>,
+    pub vendor_id: Option<u16>,
+    pub product_id: Option<u16>,
+    pub device_version: Option<u16>,
+    pub manufacturer: Option<String>,
+    pub product: Option<String>,
+    pub serial: Option<String>,
+    pub speed: UsbSpeed,
+    pub num_configurations: Option<u8>,
+    pub configuration: Option<ConfigDescriptor>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigDescriptor {
+    pub value: Option<u8>,
+    pub num_interfaces: Option<u8>,
+    pub attributes: Option<u8>,
+    pub max_power: Option<String>,
+    pub interfaces: Vec<InterfaceDescriptor>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InterfaceDescriptor {
+    pub number: Option<u8>,
+    pub alternate_setting: Option<u8>,
+    pub class: Option<u8>,
+    pub subclass: Option<u8>,
+    pub protocol: Option<u8>,
+    pub name: Option<String>,
+    pub driver: Option<String>,
+    pub endpoints: Vec<EndpointDescriptor>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EndpointDescriptor {
+    pub address: u8,
+    pub attributes: Option<u8>,
+    pub max_packet_size: Option<u16>,
+    pub interval: Option<u8>,
+}
+
+impl EndpointDescriptor {
+    pub fn transfer_type(&self) -> Option<TransferType> {
+        self.attributes.map(|attributes| match attributes & 0x03 {
+            0 => TransferType::Control,
+            1 => TransferType::Isochronous,
+            2 => TransferType::Bulk,
+            _ => TransferType::Interrupt,
+        })
+    }
+    
+    pub fn is_periodic(&self) -> bool {
+        self.transfer_type().is_some_and(|transfer_type| transfer_type.is_periodic())
+    }
+    
+    /// Bytes per transaction, without the high-bandwidth multiplier bits
+    pub fn packet_size(&self) -> Option<u16> {
+        self.max_packet_size.map(|size| size & 0x7ff)
+    }
+    
+    /// How often the host polls the endpoint (USB 2.0 §9.6.6)
+    /// Low/full-speed interrupt endpoints give bInterval in 1 ms frames;
+    /// everything else uses 2^(bInterval-1) frames, or 125 µs microframes
+    /// from high speed up
+    pub fn polling_interval(&self, speed: &UsbSpeed) -> Option<Duration> {
+        let interval = self.interval.filter(|&interval| interval > 0)?;
+        let unit = match speed {
+            UsbSpeed::Low | UsbSpeed::Full => Duration::from_millis(1),
+            UsbSpeed::High | UsbSpeed::SuperSpeed | UsbSpeed::SuperSpeedPlus => Duration::from_micros(125),
+            UsbSpeed::Unknown => return None,
+        };
+        
+        match (self.transfer_type()?, speed) {
+            (TransferType::Interrupt, UsbSpeed::Low | UsbSpeed::Full) => Some(unit * interval as u32),
+            (TransferType::Interrupt | TransferType::Isochronous, _) if interval <= 16 => {
+                Some(unit * (1u32 << (interval - 1)))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Readable endpoint name, e.g. "EP2 IN (Bulk)" for address 0x82
+/// The type is left out when it isn't known.
+pub fn endpoint_label(address: u8, transfer_type: Option<TransferType>) -> String {
+    let direction = if address & 0x80 != 0 { "IN" } else { "OUT" };
+    match transfer_type {
+        Some(transfer_type) => format!("EP{} {} ({})", address & 0x0f, direction, transfer_type.name()),
+        None => format!("EP{} {}", address & 0x0f, direction),
+    }
+}
+
+impl DeviceDescriptors {
+    /// Transfer type by endpoint address across the active configuration,
+    /// including both directions of endpoint 0
+    pub fn endpoint_types(&self) -> HashMap<u8, TransferType> {
+        let mut types: HashMap<u8, TransferType> = self.configuration.iter()
+            .flat_map(|config| &config.interfaces)
+            .flat_map(|interface| &interface.endpoints)
+            .filter_map(|endpoint| Some((endpoint.address, endpoint.transfer_type()?)))
+            .collect();
+        types.insert(0x00, TransferType::Control);
+        types.insert(0x80, TransferType::Control);
+        types
+    }
+    
+    /// Whether any interface of the active configuration has a kernel driver
+    /// bound (usbfs counts, for devices claimed from userspace); None when no
+    /// interfaces were read
+    /// bInterfaceClass of the first interface, where devices with a class
+    /// of 0 (per interface) or 0xef (interface association) say what they are
+    pub fn first_interface_class(&self) -> Option<u8> {
+        self.configuration.as_ref()?.interfaces.iter()
+            .min_by_key(|interface| (interface.number, interface.alternate_setting))?
+            .class
+    }
+    
+    pub fn has_bound_driver(&self) -> Option<bool> {
+        let interfaces = &self.configuration.as_ref()?.interfaces;
+        if interfaces.is_empty() {
+            return None;
+        }
+        Some(interfaces.iter().any(|interface| interface.driver.is_some()))
+    }
+    
+    /// Interrupt and isochronous endpoints across the active configuration
+    pub fn periodic_endpoints(&self) -> Vec<EndpointDescriptor> {
+        self.configuration.iter()
+            .flat_map(|config| &config.interfaces)
+            .flat_map(|interface| &interface.endpoints)
+            .filter(|endpoint| endpoint.is_periodic())
+            .cloned()
+            .collect()
+    }
+    
+    /// wMaxPacketSize by endpoint address, including endpoint 0
+    pub fn max_packet_sizes(&self) -> HashMap<u8, u16> {
+        let mut sizes: HashMap<u8, u16> = self.configuration.iter()
+            .flat_map(|config| &config.interfaces)
+            .flat_map(|interface| &interface.endpoints)
+            .filter_map(|endpoint| Some((endpoint.address, endpoint.packet_size()?)))
+            .collect();
+        if let Some(size) = self.max_packet_size0 {
+            sizes.insert(0x00, size);
+            sizes.insert(0x80, size);
+        }
+        sizes
+    }
+}
+
+fn read_attr(dir: &Path, name: &str) -> Option<String> {
+    fs::read_to_string(dir.join(name)).ok().map(|value| value.trim().to_string())
+}
+
+/// Attributes sysfs prints in hex, e.g. bDeviceClass "09" or idVendor "1d6b"
+fn read_hex(dir: &Path, name: &str) -> Option<u16> {
+    read_attr(dir, name).and_then(|value| u16::from_str_radix(&value, 16).ok())
+}
+
+/// Attributes sysfs prints in decimal, e.g. bMaxPacketSize0 "64"
+fn read_dec(dir: &Path, name: &str) -> Option<u16> {
+    read_attr(dir, name).and_then(|value| value.parse().ok())
+}
+
+fn subdirs(dir: &Path) -> Vec<(String, std::path::PathBuf)> {
+    let mut entries: Vec<(String, std::path::PathBuf)> = fs::read_dir(dir)
+        .map(|entries| {
+            entries.filter_map(|entry| entry.ok())
+                .map(|entry| (entry.file_name().to_string_lossy().into_owned(), entry.path()))
+                .filter(|(_, path)| path.is_dir())
+                .collect()
+        })
+        .unwrap_or_default();
+    entries.sort();
+    entries
+}
+
+/// Read one device directory; None for entries that aren't devices (interfaces, missing attrs)
+pub fn read_device_descriptors(dir: &Path) -> Option<DeviceDescriptors> {
+    let bus_id = read_dec(dir, "busnum")? as u8;
+    let device_id = read_dec(dir, "devnum")? as u8;
+    let sysfs_name = dir.file_name()?.to_string_lossy().into_owned();
+    
+    let interface_prefix = format!("{}:", sysfs_name);
+    let interfaces = subdirs(dir).into_iter()
+        .filter(|(name, _)| name.starts_with(&interface_prefix))
+        .map(|(_, path)| read_interface(&path))
+        .collect();
+    
+    let configuration = read_dec(dir, "bConfigurationValue").map(|value| ConfigDescriptor {
+        value: Some(value as u8),
+        num_interfaces: read_dec(dir, "bNumInterfaces").map(|n| n as u8),
+        attributes: read_hex(dir, "bmAttributes").map(|a| a as u8),
+        max_power: read_attr(dir, "bMaxPower"),
+        interfaces,
+    });
+    
+    Some(DeviceDescriptors {
+        key: DeviceKey(bus_id, device_id),
+        sysfs_name,
+        usb_version: read_attr(dir, "version").and_then(|v| parse_usb_version(&v)),
+        class: read_hex(dir, "bDeviceClass").map(|c| c as u8),
+        subclass: read_hex(dir, "bDeviceSubClass").map(|c| c as u8),
+        protocol: read_hex(dir, "bDeviceProtocol").map(|c| c as u8),
+        max_packet_size0: read_dec(dir, "bMaxPacketSize0"),
+        vendor_id: read_hex(dir, "idVendor"),
+        product_id: read_hex(dir, "idProduct"),
+        device_version: read_hex(dir, "bcdDevice"),
+        manufacturer: read_attr(dir, "manufacturer"),
+        product: read_attr(dir, "product"),
+        serial: read_attr(dir, "serial"),
+        speed: read_attr(dir, "speed").map_or(UsbSpeed::Unknown, |s| UsbSpeed::from_speed_str(&s)),
+        num_configurations: read_dec(dir, "bNumConfigurations").map(|n| n as u8),
+        configuration,
+    })
+}
+
+fn read_interface(dir: &Path) -> InterfaceDescriptor {
+    let endpoints = subdirs(dir).into_iter()
+        .filter(|(name, _)| name.starts_with("ep_"))
+        .filter_map(|(_, path)| {
+            Some(EndpointDescriptor {
+                address: read_hex(&path, "bEndpointAddress")? as u8,
+                attributes: read_hex(&path, "bmAttributes").map(|a| a as u8),
+                max_packet_size: read_hex(&path, "wMaxPacketSize"),
+                interval: read_hex(&path, "bInterval").map(|i| i as u8),
+            })
+        })
+        .collect();
+    
+    InterfaceDescriptor {
+        number: read_hex(dir, "bInterfaceNumber").map(|n| n as u8),
+        alternate_setting: read_dec(dir, "bAlternateSetting").map(|n| n as u8),
+        class: read_hex(dir, "bInterfaceClass").map(|c| c as u8),
+        subclass: read_hex(dir, "bInterfaceSubClass").map(|c| c as u8),
+        protocol: read_hex(dir, "bInterfaceProtocol").map(|c| c as u8),
+        name: read_attr(dir, "interface"),
+        driver: fs::read_link(dir.join("driver")).ok()
+            .and_then(|link| link.file_name().map(|name| name.to_string_lossy().into_owned())),
+        endpoints,
+    }
+}
+
+/// Every device under a sysfs root, ordered by bus and device number
+pub fn read_all_descriptors(root: &Path) -> Vec<DeviceDescriptors> {
+    let mut devices: Vec<DeviceDescriptors> = subdirs(root).into_iter()
+        .filter_map(|(_, path)| read_device_descriptors(&path))
+        .collect();
+    devices.sort_by_key(|device| device.key);
+    devices
+}
+
+/// Names lsusb uses for the common base classes
+pub fn class_name(class: u8) -> &'static str {
+    match class {
+        0x00 => "[unknown]",
+        0x01 => "Audio",
+        0x02 => "Communications",
+        0x03 => "Human Interface Device",
+        0x05 => "Physical Interface Device",
+        0x06 => "Imaging",
+        0x07 => "Printer",
+        0x08 => "Mass Storage",
+        0x09 => "Hub",
+        0x0a => "CDC Data",
+        0x0b => "Chip/SmartCard",
+        0x0d => "Content Security",
+        0x0e => "Video",
+        0x0f => "Personal Healthcare",
+        0x10 => "Audio/Video",
+        0x11 => "Billboard",
+        0xdc => "Diagnostic",
+        0xe0 => "Wireless",
+        0xef => "Miscellaneous Device",
+        0xfe => "Application Specific Interface",
+        0xff => "Vendor Specific Class",
+        _ => "",
+    }
+}
+
+fn transfer_type_name(attributes: u8) -> &'static str {
+    match attributes & 0x03 {
+        0 => "Control",
+        1 => "Isochronous",
+        2 => "Bulk",
+        _ => "Interrupt",
+    }
+}
+
+fn opt<T: ToString>(value: &Option<T>) -> String {
+    value.as_ref().map_or_else(|| "?".to_string(), |v| v.to_string())
+}
+
+fn opt_class(class: Option<u8>) -> String {
+    match class {
+        Some(class) => format!("{:>3} {}", class, class_name(class)),
+        None => "  ?".to_string(),
+    }
+}
+
+impl DeviceDescriptors {
+    /// Render in the layout of `lsusb -v`
+    /// sysfs only exposes string descriptor contents, so they are shown in
+    /// place of lsusb's index-plus-string columns.
+    pub fn format_lsusb(&self) -> String {
+        let mut out = String::new();
+        let id = match (self.vendor_id, self.product_id) {
+            (Some(vendor), Some(product)) => format!("{:04x}:{:04x}", vendor, product),
+            _ => "????:????".to_string(),
+        };
+        let name = [&self.manufacturer, &self.product].iter()
+            .filter_map(|s| s.as_deref())
+            .collect::<Vec<_>>()
+            .join(" ");
+        
+        // Writing to a String can't fail
+        let _ = writeln!(out, "Bus {:03} Device {:03}: ID {} {}", self.key.bus_id(), self.key.device_id(), id, name);
+        let _ = writeln!(out, "  sysfs path           {}/{}", SYSFS_USB_DEVICES, self.sysfs_name);
+        let _ = writeln!(out, "  Negotiated speed     {}", format_speed(&self.speed));
+        let _ = writeln!(out, "Device Descriptor:");
+        let _ = writeln!(out, "  bcdUSB             {:>5}", self.usb_version.map_or("?".to_string(), format_usb_version));
+        let _ = writeln!(out, "  bDeviceClass         {}", opt_class(self.class));
+        let _ = writeln!(out, "  bDeviceSubClass      {:>3}", opt(&self.subclass));
+        let _ = writeln!(out, "  bDeviceProtocol      {:>3}", opt(&self.protocol));
+        let _ = writeln!(out, "  bMaxPacketSize0      {:>3}", opt(&self.max_packet_size0));
+        let _ = writeln!(out, "  idVendor          {} {}", self.vendor_id.map_or("     ?".to_string(), |v| format!("0x{:04x}", v)), self.manufacturer.as_deref().unwrap_or(""));
+        let _ = writeln!(out, "  idProduct         {} {}", self.product_id.map_or("     ?".to_string(), |p| format!("0x{:04x}", p)), self.product.as_deref().unwrap_or(""));
+        let _ = writeln!(out, "  bcdDevice          {:>5}", self.device_version.map_or("?".to_string(), format_usb_version));
+        let _ = writeln!(out, "  iManufacturer        {}", self.manufacturer.as_deref().unwrap_or(""));
+        let _ = writeln!(out, "  iProduct             {}", self.product.as_deref().unwrap_or(""));
+        let _ = writeln!(out, "  iSerial              {}", self.serial.as_deref().unwrap_or(""));
+        let _ = writeln!(out, "  bNumConfigurations   {:>3}", opt(&self.num_configurations));
+        
+        if let Some(config) = &self.configuration {
+            let _ = writeln!(out, "  Configuration Descriptor:");
+            let _ = writeln!(out, "    bNumInterfaces       {:>3}", opt(&config.num_interfaces));
+            let _ = writeln!(out, "    bConfigurationValue  {:>3}", opt(&config.value));
+            match config.attributes {
+                Some(attributes) => {
+                    let _ = writeln!(out, "    bmAttributes        0x{:02x}", attributes);
+                    if attributes & 0x40 != 0 {
+                        let _ = writeln!(out, "      Self Powered");
+                    } else {
+                        let _ = writeln!(out, "      (Bus Powered)");
+                    }
+                    if attributes & 0x20 != 0 {
+                        let _ = writeln!(out, "      Remote Wakeup");
+                    }
+                }
+                None => {
+                    let _ = writeln!(out, "    bmAttributes           ?");
+                }
+            }
+            let _ = writeln!(out, "    MaxPower           {:>5}", opt(&config.max_power));
+            
+            for interface in &config.interfaces {
+                let _ = writeln!(out, "    Interface Descriptor:");
+                let _ = writeln!(out, "      bInterfaceNumber     {:>3}", opt(&interface.number));
+                let _ = writeln!(out, "      bAlternateSetting    {:>3}", opt(&interface.alternate_setting));
+                let _ = writeln!(out, "      bNumEndpoints        {:>3}", interface.endpoints.len());
+                let _ = writeln!(out, "      bInterfaceClass      {}", opt_class(interface.class));
+                let _ = writeln!(out, "      bInterfaceSubClass   {:>3}", opt(&interface.subclass));
+                let _ = writeln!(out, "      bInterfaceProtocol   {:>3}", opt(&interface.protocol));
+                let _ = writeln!(out, "      iInterface           {}", interface.name.as_deref().unwrap_or(""));
+                if let Some(driver) = &interface.driver {
+                    let _ = writeln!(out, "      Driver               {}", driver);
+                }
+                
+                for endpoint in &interface.endpoints {
+                    let direction = if endpoint.address & 0x80 != 0 { "IN" } else { "OUT" };
+                    let _ = writeln!(out, "      Endpoint Descriptor:");
+                    let _ = writeln!(out, "        bEndpointAddress  0x{:02x}  EP {} {}", endpoint.address, endpoint.address & 0x0f, direction);
+                    if let Some(attributes) = endpoint.attributes {
+                        let _ = writeln!(out, "        bmAttributes         {:>3}", attributes);
+                        let _ = writeln!(out, "          Transfer Type      {}", transfer_type_name(attributes));
+                    }
+                    if let Some(max_packet_size) = endpoint.max_packet_size {
+                        let _ = writeln!(
+                            out,
+                            "        wMaxPacketSize  0x{:04x}  {}x {} bytes",
+                            max_packet_size,
+                            ((max_packet_size >> 11) & 0x3) + 1,
+                            max_packet_size & 0x7ff,
+                        );
+                    }
+                    let _ = writeln!(out, "        bInterval            {:>3}", opt(&endpoint.interval));
+                }
+            }
+        }
+        
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    
+    fn write_attrs(dir: &Path, attrs: &[(&str, &str)]) {
+        fs::create_dir_all(dir).unwrap();
+        for (name, value) in attrs {
+            fs::write(dir.join(name), format!("{}\n", value)).unwrap();
+        }
+    }
+    
+    fn fake_sysfs() -> PathBuf {
+        let root = std::env::temp_dir().join(format!("usbtop-ng-sysfs-{}", std::process::id()));
+        let device = root.join("1-2");
+        write_attrs(&device, &[
+            ("busnum", "1"), ("devnum", "5"), ("version", " 2.00"), ("speed", "480"),
+            ("bDeviceClass", "00"), ("bDeviceSubClass", "00"), ("bDeviceProtocol", "00"),
+            ("bMaxPacketSize0", "64"), ("idVendor", "0781"), ("idProduct", "5567"),
+            ("bcdDevice", "0126"), ("manufacturer", "SanDisk"), ("product", "Cruzer Blade"),
+            ("serial", "4C530001"), ("bNumConfigurations", "1"), ("bConfigurationValue", "1"),
+            ("bNumInterfaces", " 1"), ("bmAttributes", "80"), ("bMaxPower", "200mA"),
+        ]);
+        let interface = device.join("1-2:1.0");
+        write_attrs(&interface, &[
+            ("bInterfaceNumber", "00"), ("bAlternateSetting", " 0"), ("bInterfaceClass", "08"),
+            ("bInterfaceSubClass", "06"), ("bInterfaceProtocol", "50"),
+        ]);
+        write_attrs(&interface.join("ep_81"), &[
+            ("bEndpointAddress", "81"), ("bmAttributes", "02"), ("wMaxPacketSize", "0200"), ("bInterval", "00"),
+        ]);
+        // Interface directories also appear at the top level and must be skipped
+        write_attrs(&root.join("1-2:1.0"), &[("bInterfaceNumber", "00")]);
+        root
+    }
+    
+    #[test]
+    fn test_reads_and_formats_sysfs_tree() {
+        let root = fake_sysfs();
+        let devices = read_all_descriptors(&root);
+        fs::remove_dir_all(&root).unwrap();
+        
+        assert_eq!(devices.len(), 1);
+        let device = &devices[0];
+        assert_eq!(device.key, DeviceKey(1, 5));
+        assert_eq!(device.vendor_id, Some(0x0781));
+        assert_eq!(device.usb_version, Some(0x0200));
+        
+        let config = device.configuration.as_ref().unwrap();
+        assert_eq!(config.interfaces.len(), 1);
+        assert_eq!(config.interfaces[0].class, Some(0x08));
+        assert_eq!(config.interfaces[0].endpoints[0].max_packet_size, Some(512));
+        
+        let text = device.format_lsusb();
+        assert!(text.starts_with("Bus 001 Device 005: ID 0781:5567 SanDisk Cruzer Blade\n"));
+        assert!(text.contains("bInterfaceClass        8 Mass Storage"));
+        assert!(text.contains("bEndpointAddress  0x81  EP 1 IN"));
+        assert!(text.contains("Transfer Type      Bulk"));
+        assert!(text.contains("wMaxPacketSize  0x0200  1x 512 bytes"));
+        
+        // Bulk only, nothing polled
+        assert!(device.periodic_endpoints().is_empty());
+        
+        let types = device.endpoint_types();
+        assert_eq!(types.get(&0x81), Some(&TransferType::Bulk));
+        assert_eq!(types.get(&0x80), Some(&TransferType::Control));
+        assert_eq!(endpoint_label(0x81, types.get(&0x81).copied()), "EP1 IN (Bulk)");
+        assert_eq!(endpoint_label(0x02, types.get(&0x02).copied()), "EP2 OUT");
+    }
+    
+    #[test]
+    fn test_endpoint_polling_interval() {
+        let endpoint = |attributes: u8, interval: u8| EndpointDescriptor {
+            address: 0x81,
+            attributes: Some(attributes),
+            max_packet_size: Some(0x0808),
+            interval: Some(interval),
+        };
+        
+        // Full-speed mouse polled every frame, high-speed one at 8 kHz
+        let interrupt = endpoint(0x03, 1);
+        assert!(interrupt.is_periodic());
+        assert_eq!(interrupt.polling_interval(&UsbSpeed::Full), Some(Duration::from_millis(1)));
+        assert_eq!(interrupt.polling_interval(&UsbSpeed::High), Some(Duration::from_micros(125)));
+        assert_eq!(endpoint(0x03, 10).polling_interval(&UsbSpeed::Full), Some(Duration::from_millis(10)));
+        assert_eq!(endpoint(0x03, 4).polling_interval(&UsbSpeed::High), Some(Duration::from_millis(1)));
+        assert_eq!(interrupt.packet_size(), Some(8));
+        
+        // Isochronous intervals are exponents at every speed
+        assert_eq!(endpoint(0x01, 4).polling_interval(&UsbSpeed::Full), Some(Duration::from_millis(8)));
+        
+        assert_eq!(endpoint(0x03, 0).polling_interval(&UsbSpeed::Full), None);
+        assert_eq!(endpoint(0x03, 1).polling_interval(&UsbSpeed::Unknown), None);
+        assert!(!endpoint(0x02, 0).is_periodic());
+    }
+}
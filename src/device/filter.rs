@@ -0,0 +1,214 @@
+use crate::config::FilterConfig;
+use crate::device::UsbDevice;
+
+/// One allowlist or blocklist entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum DevicePattern {
+    /// Vendor and product ids; `None` matches any id
+    Ids(Option<u16>, Option<u16>),
+    /// Serial number glob where `*` matches any run of characters
+    Serial(String),
+}
+
+impl DevicePattern {
+    /// Parse "VID:PID" in hex with `*` for either half, or "serial:<glob>"
+    fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        if let Some(glob) = value.strip_prefix("serial:") {
+            return Some(DevicePattern::Serial(glob.trim().to_string()));
+        }
+        
+        let (vendor_id, product_id) = value.split_once(':')?;
+        Some(DevicePattern::Ids(parse_id(vendor_id)?, parse_id(product_id)?))
+    }
+    
+    fn matches(&self, device: &UsbDevice) -> bool {
+        match self {
+            DevicePattern::Ids(vendor_id, product_id) => {
+                id_matches(*vendor_id, device.vendor_id) && id_matches(*product_id, device.product_id)
+            }
+            DevicePattern::Serial(glob) => device.serial.as_deref().is_some_and(|serial| glob_matches(glob, serial)),
+        }
+    }
+}
+
+/// `Some(None)` for a wildcard, `Some(Some(id))` for a hex id
+fn parse_id(value: &str) -> Option<Option<u16>> {
+    match value.trim() {
+        "*" => Some(None),
+        id => u16::from_str_radix(id, 16).ok().map(Some),
+    }
+}
+
+/// A wildcard matches anything, even a device whose descriptors couldn't be read
+fn id_matches(pattern: Option<u16>, id: Option<u16>) -> bool {
+    pattern.is_none_or(|pattern| id == Some(pattern))
+}
+
+/// Glob match supporting only `*`
+fn glob_matches(glob: &str, text: &str) -> bool {
+    let mut parts = glob.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No `*` at all: the whole text must match
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// Which devices are recorded, from the `[filter]` config section
+/// A device on the blocklist is never recorded, even if it is also on the
+/// allowlist. A non-empty allowlist records only the devices it matches; an
+/// empty one admits everything not blocked. Devices are matched on their
+/// sysfs descriptors, so one whose descriptors can't be read only matches
+/// wildcard VID:PID patterns. The control socket can narrow this further,
+/// but never record a device the config lists exclude.
+#[derive(Debug, Clone, Default)]
+pub struct DeviceFilter {
+    allowlist: Vec<DevicePattern>,
+    blocklist: Vec<DevicePattern>,
+    /// Set by the control socket's `filter` command; empty when not narrowed
+    narrowing: Vec<DevicePattern>,
+}
+
+impl DeviceFilter {
+    /// Build from the `[filter]` section, with warnings for patterns that are skipped
+    pub fn from_config(config: &FilterConfig) -> (Self, Vec<String>) {
+        let mut warnings = Vec::new();
+        let mut parse_list = |name: &str, values: &[String]| -> Vec<DevicePattern> {
+            values.iter()
+                .filter_map(|value| {
+                    let pattern = DevicePattern::parse(value);
+                    if pattern.is_none() {
+                        warnings.push(format!(
+                            "Ignoring {} entry '{}': expected VID:PID in hex (or *) or serial:<pattern>",
+                            name, value,
+                        ));
+                    }
+                    pattern
+                })
+                .collect()
+        };
+        
+        let allowlist = parse_list("allowlist", &config.allowlist);
+        let blocklist = parse_list("blocklist", &config.blocklist);
+        (Self { allowlist, blocklist, narrowing: Vec::new() }, warnings)
+    }
+    
+    /// Copy that also requires a match on one of `patterns`, on top of the
+    /// config lists; no patterns goes back to the config lists alone. Fails
+    /// on the first pattern that doesn't parse
+    pub fn narrowed_to(&self, patterns: &[&str]) -> Result<Self, String> {
+        let narrowing = patterns.iter()
+            .map(|value| DevicePattern::parse(value).ok_or_else(|| {
+                format!("invalid pattern '{}': expected VID:PID in hex (or *) or serial:<pattern>", value)
+            }))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { narrowing, ..self.clone() })
+    }
+    
+    pub fn is_narrowed(&self) -> bool {
+        !self.narrowing.is_empty()
+    }
+    
+    /// Whether the config lists let `device` be recorded at all
+    pub fn permits(&self, device: &UsbDevice) -> bool {
+        if self.blocklist.iter().any(|pattern| pattern.matches(device)) {
+            return false;
+        }
+        self.allowlist.is_empty() || self.allowlist.iter().any(|pattern| pattern.matches(device))
+    }
+    
+    /// Whether `device` should be recorded now, narrowing included
+    pub fn allows(&self, device: &UsbDevice) -> bool {
+        self.permits(device)
+            && (self.narrowing.is_empty() || self.narrowing.iter().any(|pattern| pattern.matches(device)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn device(vendor_id: u16, product_id: u16, serial: Option<&str>) -> UsbDevice {
+        let mut device = UsbDevice::new(1, 2);
+        device.vendor_id = Some(vendor_id);
+        device.product_id = Some(product_id);
+        device.serial = serial.map(str::to_string);
+        device
+    }
+    
+    fn filter(allowlist: &[&str], blocklist: &[&str]) -> (DeviceFilter, Vec<String>) {
+        DeviceFilter::from_config(&FilterConfig {
+            allowlist: allowlist.iter().map(|value| value.to_string()).collect(),
+            blocklist: blocklist.iter().map(|value| value.to_string()).collect(),
+            ..FilterConfig::default()
+        })
+    }
+    
+    #[test]
+    fn test_blocklist_wins_over_allowlist() {
+        let (filter, warnings) = filter(&["046d:*", "serial:AB*"], &["046d:c52b"]);
+        assert!(warnings.is_empty());
+        
+        assert!(filter.allows(&device(0x046d, 0xc077, None)));
+        assert!(!filter.allows(&device(0x046d, 0xc52b, None)));
+        assert!(filter.allows(&device(0x0781, 0x5567, Some("AB1234"))));
+        assert!(!filter.allows(&device(0x0781, 0x5567, Some("XAB1234"))));
+        // Allowlist mode drops devices that can't be identified
+        assert!(!filter.allows(&UsbDevice::new(1, 3)));
+    }
+    
+    #[test]
+    fn test_blocklist_only_and_invalid_patterns() {
+        let (filter, warnings) = filter(&[], &["serial:*TEST*", "zzzz:1", "1234"]);
+        assert_eq!(warnings.len(), 2);
+        
+        assert!(filter.allows(&UsbDevice::new(1, 3)));
+        assert!(filter.allows(&device(0x1234, 0x5678, Some("prod-01"))));
+        assert!(!filter.allows(&device(0x1234, 0x5678, Some("unit-TEST-01"))));
+        assert!(DeviceFilter::default().allows(&UsbDevice::new(1, 3)));
+    }
+    
+    #[test]
+    fn test_narrowing_never_widens_config_lists() {
+        let (filter, _) = filter(&["046d:*"], &["serial:*TEST*"]);
+        let narrowed = filter.narrowed_to(&["046d:c52b", "0781:5567"]).unwrap();
+        assert!(!narrowed.allows(&device(0x046d, 0xc077, None)));
+        assert!(narrowed.allows(&device(0x046d, 0xc52b, None)));
+        assert!(!narrowed.allows(&device(0x046d, 0xc52b, Some("TEST-1"))));
+        // Matches the narrowing but not the config allowlist
+        assert!(!narrowed.allows(&device(0x0781, 0x5567, None)));
+        assert!(narrowed.permits(&device(0x046d, 0xc077, None)));
+        
+        let widened = narrowed.narrowed_to(&["*:*"]).unwrap();
+        assert!(!widened.allows(&device(0x0781, 0x5567, None)));
+        let cleared = narrowed.narrowed_to(&[]).unwrap();
+        assert!(!cleared.is_narrowed());
+        assert!(cleared.allows(&device(0x046d, 0xc077, None)));
+        assert!(!cleared.allows(&UsbDevice::new(1, 3)));
+        assert!(filter.narrowed_to(&["nope"]).is_err());
+    }
+    
+    #[test]
+    fn test_glob_matches() {
+        assert!(glob_matches("abc", "abc"));
+        assert!(!glob_matches("abc", "abcd"));
+        assert!(glob_matches("a*c", "abbbc"));
+        assert!(glob_matches("*", ""));
+        assert!(glob_matches("a*b*c", "axbyc"));
+        assert!(!glob_matches("a*b*c", "axcyb"));
+        assert!(!glob_matches("ab*ba", "aba"));
+    }
+}
@@ -0,0 +1,117 @@
+use crate::device::{DeviceKey, UsbDevice};
+
+/// bDeviceClass of a hub
+pub const HUB_CLASS: u8 = 0x09;
+
+/// Share of a hub's usable upstream bandwidth at which its downstream
+/// devices are flagged as hub-limited
+pub const HUB_WARN_RATIO: f64 = 0.9;
+
+/// Combined bandwidth of every device behind an external hub
+/// Devices behind a hub share its single upstream link, so the hub rather
+/// than any one device can be the bottleneck.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HubLoad {
+    pub key: DeviceKey,
+    pub port_path: String,
+    pub downstream_devices: usize,
+    pub downstream_bps: f64,
+    /// Usable bandwidth of the hub's own link to its parent
+    pub upstream_bps: f64,
+}
+
+impl HubLoad {
+    /// Downstream traffic as a share of the upstream link (0.0 when its speed is unknown)
+    pub fn utilization(&self) -> f64 {
+        if self.upstream_bps > 0.0 { self.downstream_bps / self.upstream_bps } else { 0.0 }
+    }
+    
+    pub fn is_saturated(&self) -> bool {
+        self.utilization() >= HUB_WARN_RATIO
+    }
+}
+
+/// Whether `path` is anywhere below the hub at `hub_path`, e.g. "1-1.2.3" below "1-1"
+pub fn is_downstream_of(path: &str, hub_path: &str) -> bool {
+    path.strip_prefix(hub_path).is_some_and(|rest| rest.starts_with('.'))
+}
+
+/// Aggregate downstream bandwidth for each external hub with devices behind it
+/// Root hubs are left out: their upstream is the host controller, which the
+/// bus and controller summaries already cover. Nested hubs count toward every
+/// hub above them, just as their traffic does.
+pub fn aggregate_by_hub<'a>(devices: impl IntoIterator<Item = &'a UsbDevice>) -> Vec<HubLoad> {
+    let devices: Vec<&UsbDevice> = devices.into_iter().collect();
+    
+    let mut hubs: Vec<HubLoad> = devices.iter()
+        .filter(|device| device.device_class == Some(HUB_CLASS))
+        .filter_map(|hub| {
+            let hub_path = hub.port_path.as_deref()?;
+            let downstream: Vec<&&UsbDevice> = devices.iter()
+                .filter(|device| device.port_path.as_deref().is_some_and(|path| is_downstream_of(path, hub_path)))
+                .collect();
+            if downstream.is_empty() {
+                return None;
+            }
+            Some(HubLoad {
+                key: hub.key(),
+                port_path: hub_path.to_string(),
+                downstream_devices: downstream.len(),
+                downstream_bps: downstream.iter()
+                    .filter(|device| device.device_class != Some(HUB_CLASS))
+                    .map(|device| device.bandwidth_stats.current_bps)
+                    .sum(),
+                upstream_bps: hub.speed.to_practical_bytes_per_second(),
+            })
+        })
+        .collect();
+    
+    hubs.sort_by(|a, b| a.port_path.cmp(&b.port_path));
+    hubs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::usbmon::parser::UsbSpeed;
+    
+    fn device(dev: u8, path: &str, class: u8, speed: UsbSpeed, bps: f64) -> UsbDevice {
+        let mut device = UsbDevice::new(1, dev);
+        device.port_path = Some(path.to_string());
+        device.device_class = Some(class);
+        device.speed = speed;
+        device.bandwidth_stats.current_bps = bps;
+        device
+    }
+    
+    #[test]
+    fn test_hub_aggregates_downstream_devices() {
+        let devices = vec![
+            device(2, "1-1", HUB_CLASS, UsbSpeed::High, 0.0),
+            device(3, "1-1.1", 0x08, UsbSpeed::High, 20_000_000.0),
+            device(4, "1-1.2", HUB_CLASS, UsbSpeed::High, 0.0),
+            device(5, "1-1.2.1", 0x0e, UsbSpeed::High, 25_000_000.0),
+            device(6, "1-10", 0x03, UsbSpeed::Full, 1_000.0),
+        ];
+        
+        let hubs = aggregate_by_hub(&devices);
+        assert_eq!(hubs.len(), 2);
+        
+        assert_eq!(hubs[0].key, DeviceKey(1, 2));
+        assert_eq!(hubs[0].downstream_devices, 3);
+        assert_eq!(hubs[0].downstream_bps, 45_000_000.0);
+        assert!(hubs[0].is_saturated());
+        
+        assert_eq!(hubs[1].key, DeviceKey(1, 4));
+        assert_eq!(hubs[1].downstream_bps, 25_000_000.0);
+        assert!(!hubs[1].is_saturated());
+    }
+    
+    #[test]
+    fn test_downstream_paths() {
+        assert!(is_downstream_of("1-1.2", "1-1"));
+        assert!(is_downstream_of("1-1.2.4", "1-1"));
+        assert!(!is_downstream_of("1-10", "1-1"));
+        assert!(!is_downstream_of("1-1", "1-1"));
+    }
+}
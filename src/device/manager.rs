@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
 
+use crate::device::sysfs;
 use crate::device::UsbDevice;
 use crate::stats::BandwidthStats;
 use crate::usbmon::parser::UsbSpeed;
@@ -29,8 +29,8 @@ impl UsbBus {
         #[cfg(target_os = "linux")]
         {
             // Try to read the root hub speed (usually device 1 on the bus)
-            let root_hub_path = format!("/sys/bus/usb/devices/usb{}/speed", self.bus_id);
-            if Path::new(&root_hub_path).exists() {
+            let root_hub_path = sysfs::root().join(format!("usb{}", self.bus_id)).join("speed");
+            if root_hub_path.exists() {
                 if let Ok(speed_str) = fs::read_to_string(&root_hub_path) {
                     self.speed = UsbSpeed::from_speed_str(speed_str.trim());
                     return Ok(());
@@ -183,4 +183,4 @@ impl DeviceManager {
     pub fn get_total_bandwidth(&self) -> f64 {
         self.buses.values().map(|bus| bus.get_total_bps()).sum()
     }
-}
\ No newline at end of file
+}
@@ -1,10 +1,140 @@
 use chrono::{DateTime, Utc};
-use std::time::Instant;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
 
-use crate::usbmon::parser::UsbSpeed;
-use crate::stats::BandwidthStats;
+use crate::usbmon::parser::{TransferType, UrbType, UsbPacket, UsbSpeed};
+use crate::stats::{BandwidthStats, EventRate, UrbSizeHistogram};
 
+pub mod assembler;
+pub mod budget;
+pub mod classes;
+pub mod controllers;
+pub mod descriptors;
+pub mod filter;
+pub mod hubs;
 pub mod manager;
+pub mod sysfs;
+pub mod typec;
+pub mod vendors;
+
+/// Identity of a device: (bus_id, device_id)
+/// Device ids are only unique within a bus, so every map, selection and
+/// lookup keyed by device identity must use both parts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DeviceKey(pub u8, pub u8);
+
+impl DeviceKey {
+    pub fn bus_id(&self) -> u8 {
+        self.0
+    }
+    
+    pub fn device_id(&self) -> u8 {
+        self.1
+    }
+}
+
+impl fmt::Display for DeviceKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:03}:{:03}", self.0, self.1)
+    }
+}
+
+impl FromStr for DeviceKey {
+    type Err = String;
+    
+    /// Parses `BUS:DEV`, e.g. "2:5" or "002:005"
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (bus, device) = value.split_once(':')
+            .ok_or_else(|| format!("expected BUS:DEV, got '{}'", value))?;
+        let bus_id = bus.parse::<u8>().map_err(|_| format!("invalid bus id '{}'", bus))?;
+        let device_id = device.parse::<u8>().map_err(|_| format!("invalid device id '{}'", device))?;
+        Ok(DeviceKey(bus_id, device_id))
+    }
+}
+
+/// How long a disconnected device stays listed before it's removed
+pub const DISCONNECT_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// How recently a device must have been active for a new device number with
+/// the same identity to be taken as its re-enumeration (`reenumeration_merge_secs`)
+pub const DEFAULT_MERGE_WINDOW: Duration = Duration::from_secs(10);
+
+/// Bulk URBs below this size spend most of their time on per-transfer overhead
+const BULK_SMALL_URB_BYTES: f64 = 4096.0;
+const BULK_INEFFICIENT_URB_BYTES: f64 = 1024.0;
+
+/// Average URB size for a device's busiest non-control transfer type
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TransferEfficiency {
+    pub transfer_type: TransferType,
+    pub avg_bytes_per_urb: f64,
+}
+
+impl TransferEfficiency {
+    /// Short verdict for the detail view; interrupt and isochronous endpoints
+    /// move small fixed-size packets by design, so only bulk is judged
+    pub fn note(&self) -> &'static str {
+        match self.transfer_type {
+            TransferType::Bulk if self.avg_bytes_per_urb < BULK_INEFFICIENT_URB_BYTES => "inefficient for bulk",
+            TransferType::Bulk if self.avg_bytes_per_urb < BULK_SMALL_URB_BYTES => "small for bulk",
+            TransferType::Bulk => "efficient",
+            TransferType::Interrupt => "expected for interrupt",
+            TransferType::Isochronous => "expected for isochronous",
+            TransferType::Control => "control transfers",
+        }
+    }
+    
+    pub fn is_inefficient(&self) -> bool {
+        self.transfer_type == TransferType::Bulk && self.avg_bytes_per_urb < BULK_INEFFICIENT_URB_BYTES
+    }
+}
+
+/// Error rate across all devices and the device contributing the most
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ErrorRates {
+    pub total_per_sec: f64,
+    pub worst: Option<(DeviceKey, f64)>,
+}
+
+impl ErrorRates {
+    pub fn from_devices<'a>(devices: impl IntoIterator<Item = &'a UsbDevice>, now: Instant) -> Self {
+        let mut rates = Self::default();
+        for device in devices {
+            let rate = device.error_rate.rate_hz(now);
+            rates.total_per_sec += rate;
+            if rate > 0.0 && rates.worst.is_none_or(|(_, worst)| rate > worst) {
+                rates.worst = Some((device.key(), rate));
+            }
+        }
+        rates
+    }
+}
+
+/// Step-by-step derivation of a device's bandwidth ceiling and how much of it
+/// is in use, for explaining why e.g. 480 Mbps tops out around 48 MB/s
+#[derive(Debug, Clone, PartialEq)]
+pub struct BandwidthExplanation {
+    pub speed: UsbSpeed,
+    /// Signalling rate divided by 8 bits
+    pub theoretical_bps: f64,
+    /// Share of the signalling rate left after protocol overhead
+    pub efficiency: f64,
+    pub practical_bps: f64,
+    pub current_bps: f64,
+    /// Current bandwidth against `practical_bps`, as the device list shows it
+    pub busy_percentage: f64,
+    pub theoretical_percentage: f64,
+}
+
+/// The same hardware's entry on the companion bus: a USB 3 device that fell
+/// back to its USB 2 link, or came back up at SuperSpeed
+#[derive(Debug, Clone, PartialEq)]
+pub struct OtherLink {
+    pub key: DeviceKey,
+    pub speed: UsbSpeed,
+}
 
 #[derive(Debug, Clone)]
 pub struct UsbDevice {
@@ -15,11 +145,54 @@ pub struct UsbDevice {
     pub vendor: Option<String>,
     pub product: Option<String>,
     pub serial: Option<String>,
+    /// bDeviceClass, e.g. 0x09 for hubs
+    pub device_class: Option<u8>,
+    /// bInterfaceClass of the first interface, from sysfs
+    pub interface_class: Option<u8>,
+    /// sysfs name giving the bus and port chain, e.g. "1-1.2" (port 2 of the
+    /// hub on port 1); None for root hubs and off Linux
+    pub port_path: Option<String>,
+    /// Machine the device is attached to when it isn't this one, e.g.
+    /// "adb:SERIAL"; such devices have no local sysfs entry to read
+    pub origin: Option<String>,
+    /// Declared USB version (bcdUSB, e.g. 0x0320), independent of the negotiated speed
+    pub usb_version: Option<u16>,
     pub speed: UsbSpeed,
+    /// Where this device was last seen on the other bus of a USB 2/USB 3 pair
+    pub other_link: Option<OtherLink>,
     pub bandwidth_stats: BandwidthStats,
+    pub transfer_stats: HashMap<TransferType, BandwidthStats>,
+    /// Traffic per endpoint address, control endpoint included
+    pub endpoint_stats: HashMap<u8, BandwidthStats>,
+    /// Interrupt and isochronous endpoint descriptors read from sysfs; empty
+    /// when the device has none or sysfs doesn't expose endpoint directories
+    pub periodic_endpoints: Vec<descriptors::EndpointDescriptor>,
+    /// Completions per second on periodic endpoints, keyed by endpoint address
+    pub endpoint_rates: HashMap<u8, EventRate>,
+    /// wMaxPacketSize of every endpoint in the active configuration, from sysfs
+    pub max_packet_sizes: HashMap<u8, u16>,
+    /// Transfer type of every endpoint in the active configuration, from sysfs
+    pub endpoint_types: HashMap<u8, TransferType>,
+    /// Whether any interface has a kernel driver bound, from sysfs; None
+    /// when that isn't known
+    pub driver_bound: Option<bool>,
+    /// Largest URB payload seen per endpoint address since the last reset
+    pub endpoint_max_seen: HashMap<u8, u32>,
+    /// Payload sizes of the URBs that carried data since the last reset
+    pub urb_sizes: UrbSizeHistogram,
+    /// Failed URBs per second: error events and completions with an error status
+    pub error_rate: EventRate,
+    /// Endpoint 0 (control) traffic, tracked separately as control overhead
+    pub control_stats: BandwidthStats,
+    /// Keep endpoint 0 traffic out of `bandwidth_stats` so enumeration and
+    /// status polling don't make an idle device look active
+    pub exclude_control_endpoint: bool,
     pub is_disconnected: bool,
     pub disconnect_time: Option<Instant>,
     pub last_seen: Instant,
+    /// When the device connected; survives descriptor re-reads and
+    /// re-enumeration merges, and only starts over when it reconnects
+    pub first_seen: Instant,
 }
 
 impl UsbDevice {
@@ -32,11 +205,43 @@ impl UsbDevice {
             vendor: None,
             product: None,
             serial: None,
+            device_class: None,
+            interface_class: None,
+            port_path: None,
+            origin: None,
+            usb_version: None,
             speed: UsbSpeed::Unknown,
+            other_link: None,
             bandwidth_stats: BandwidthStats::new(),
+            transfer_stats: HashMap::new(),
+            endpoint_stats: HashMap::new(),
+            periodic_endpoints: Vec::new(),
+            endpoint_rates: HashMap::new(),
+            max_packet_sizes: HashMap::new(),
+            endpoint_types: HashMap::new(),
+            driver_bound: None,
+            endpoint_max_seen: HashMap::new(),
+            urb_sizes: UrbSizeHistogram::default(),
+            error_rate: EventRate::new(Instant::now()),
+            control_stats: BandwidthStats::new(),
+            exclude_control_endpoint: false,
             is_disconnected: false,
             disconnect_time: None,
             last_seen: Instant::now(),
+            first_seen: Instant::now(),
+        }
+    }
+    
+    pub fn key(&self) -> DeviceKey {
+        DeviceKey(self.bus_id, self.device_id)
+    }
+    
+    /// What the device is: bDeviceClass, or its first interface's class
+    /// when the device leaves that to its interfaces
+    pub fn class(&self) -> Option<u8> {
+        match self.device_class {
+            None | Some(0x00) | Some(0xef) => self.interface_class.or(self.device_class),
+            class => class,
         }
     }
     
@@ -60,59 +265,63 @@ impl UsbDevice {
     #[cfg(target_os = "linux")]
     fn update_linux_device_info(&mut self) -> Result<(), std::io::Error> {
         use std::fs;
-        use std::path::Path;
-        
-        // Find device path in sysfs
-        let sysfs_path = format!("/sys/bus/usb/devices/{}-{}", self.bus_id, self.device_id);
-        if !Path::new(&sysfs_path).exists() {
-            // Try alternative path patterns
-            let alt_paths = [
-                format!("/sys/bus/usb/devices/usb{}/{}-{}", self.bus_id, self.bus_id, self.device_id),
-                format!("/sys/bus/usb/devices/{}", self.device_id),
-            ];
-            
-            let mut found_path = None;
-            for path in &alt_paths {
-                if Path::new(path).exists() {
-                    found_path = Some(path.clone());
-                    break;
-                }
-            }
-            
-            if found_path.is_none() {
-                return Ok(()); // Device not found in sysfs, skip
-            }
-        }
+        
+        let sysfs_path = match sysfs::resolve_device_path(self.key()) {
+            Some(path) => path,
+            None => return Ok(()), // Device not found in sysfs, skip
+        };
         
         // Read device attributes
-        if let Ok(speed_str) = fs::read_to_string(format!("{}/speed", sysfs_path)) {
+        if let Ok(speed_str) = fs::read_to_string(sysfs_path.join("speed")) {
             self.speed = UsbSpeed::from_speed_str(speed_str.trim());
         }
         
-        if let Ok(vendor_str) = fs::read_to_string(format!("{}/idVendor", sysfs_path)) {
+        // sysfs exposes bcdUSB as the "version" attribute, e.g. " 2.00"
+        if let Ok(version_str) = fs::read_to_string(sysfs_path.join("version")) {
+            self.usb_version = parse_usb_version(&version_str);
+        }
+        
+        if let Ok(vendor_str) = fs::read_to_string(sysfs_path.join("idVendor")) {
             if let Ok(vendor_id) = u16::from_str_radix(vendor_str.trim(), 16) {
                 self.vendor_id = Some(vendor_id);
             }
         }
         
-        if let Ok(product_str) = fs::read_to_string(format!("{}/idProduct", sysfs_path)) {
+        if let Ok(product_str) = fs::read_to_string(sysfs_path.join("idProduct")) {
             if let Ok(product_id) = u16::from_str_radix(product_str.trim(), 16) {
                 self.product_id = Some(product_id);
             }
         }
         
-        if let Ok(manufacturer) = fs::read_to_string(format!("{}/manufacturer", sysfs_path)) {
+        if let Ok(manufacturer) = fs::read_to_string(sysfs_path.join("manufacturer")) {
             self.vendor = Some(manufacturer.trim().to_string());
         }
         
-        if let Ok(product) = fs::read_to_string(format!("{}/product", sysfs_path)) {
+        if let Ok(product) = fs::read_to_string(sysfs_path.join("product")) {
             self.product = Some(product.trim().to_string());
         }
         
-        if let Ok(serial) = fs::read_to_string(format!("{}/serial", sysfs_path)) {
+        if let Ok(serial) = fs::read_to_string(sysfs_path.join("serial")) {
             self.serial = Some(serial.trim().to_string());
         }
         
+        if let Ok(class_str) = fs::read_to_string(sysfs_path.join("bDeviceClass")) {
+            self.device_class = u8::from_str_radix(class_str.trim(), 16).ok();
+        }
+        
+        // Root hubs are named "usbN"; everything else is "<bus>-<port>[.<port>...]"
+        self.port_path = sysfs_path.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .filter(|name| name.contains('-'));
+        
+        if let Some(device_descriptors) = descriptors::read_device_descriptors(&sysfs_path) {
+            self.interface_class = device_descriptors.first_interface_class();
+            self.periodic_endpoints = device_descriptors.periodic_endpoints();
+            self.max_packet_sizes = device_descriptors.max_packet_sizes();
+            self.endpoint_types = device_descriptors.endpoint_types();
+            self.driver_bound = device_descriptors.has_bound_driver();
+        }
+        
         Ok(())
     }
     
@@ -130,6 +339,246 @@ impl UsbDevice {
         Ok(())
     }
     
+    /// Reset bandwidth totals and URB counters, keeping device metadata
+    pub fn reset_stats(&mut self) {
+        self.bandwidth_stats.reset();
+        self.control_stats.reset();
+        self.transfer_stats.clear();
+        self.endpoint_stats.clear();
+        self.endpoint_rates.clear();
+        self.endpoint_max_seen.clear();
+        self.urb_sizes.reset();
+        self.error_rate = EventRate::new(Instant::now());
+    }
+    
+    /// Restart rate windows, e.g. after capture was idle, keeping totals
+    pub fn reset_rates(&mut self) {
+        self.bandwidth_stats.reset_rates();
+        self.control_stats.reset_rates();
+        for stats in self.transfer_stats.values_mut().chain(self.endpoint_stats.values_mut()) {
+            stats.reset_rates();
+        }
+        self.endpoint_rates.clear();
+        self.error_rate = EventRate::new(Instant::now());
+    }
+    
+    /// Bring every rate up to `now`, so a device that went quiet reads zero
+    pub fn refresh_rates(&mut self, now: Instant) {
+        self.bandwidth_stats.refresh(now);
+        self.control_stats.refresh(now);
+        for stats in self.transfer_stats.values_mut().chain(self.endpoint_stats.values_mut()) {
+            stats.refresh(now);
+        }
+    }
+    
+    /// Average interrupt and isochronous traffic over `window` instead of
+    /// the bulk/control rate window
+    pub fn set_periodic_window(&mut self, window: Duration) {
+        self.bandwidth_stats.periodic_window = window;
+        for stats in self.transfer_stats.values_mut().chain(self.endpoint_stats.values_mut()) {
+            stats.periodic_window = window;
+        }
+    }
+    
+    /// Whether both entries describe the same physical device
+    /// Needs matching VID/PID and serial; devices without a serial can't be
+    /// told apart from an identical sibling, so they never match.
+    pub fn is_same_hardware(&self, other: &UsbDevice) -> bool {
+        self.vendor_id.is_some()
+            && self.product_id.is_some()
+            && self.serial.as_deref().is_some_and(|serial| !serial.is_empty())
+            && self.vendor_id == other.vendor_id
+            && self.product_id == other.product_id
+            && self.serial == other.serial
+    }
+    
+    /// Whether `other` is this device on the other half of a USB 2/USB 3
+    /// companion pair
+    /// Every USB 3 port has a USB 2 twin on a separate bus, and a device whose
+    /// SuperSpeed link fails enumerates there instead. Without a serial to go
+    /// by, the pair is recognized by matching VID/PID at the same port
+    /// position (1-2.3 and 2-2.3) with one link SuperSpeed and the other not.
+    pub fn is_companion_of(&self, other: &UsbDevice) -> bool {
+        let port_position = |device: &UsbDevice| {
+            device.port_path.as_deref().and_then(|path| path.split_once('-')).map(|(_, position)| position.to_string())
+        };
+        self.bus_id != other.bus_id
+            && self.vendor_id.is_some()
+            && self.product_id.is_some()
+            && self.vendor_id == other.vendor_id
+            && self.product_id == other.product_id
+            && self.serial == other.serial
+            && self.speed != UsbSpeed::Unknown
+            && other.speed != UsbSpeed::Unknown
+            && self.speed.is_superspeed() != other.speed.is_superspeed()
+            && port_position(self).is_some()
+            && port_position(self) == port_position(other)
+    }
+    
+    /// Both links of a USB 2/USB 3 pair and which one is in use, once the
+    /// device has been seen on each
+    pub fn link_summary(&self) -> Option<String> {
+        let other = self.other_link.as_ref()?;
+        let note = match (self.speed.is_superspeed(), other.speed.is_superspeed()) {
+            (false, true) => " — fell back to USB 2",
+            (true, false) => " — SuperSpeed link up",
+            _ => "",
+        };
+        Some(format!(
+            "{} on bus {} active, {} on bus {} before{}",
+            format_speed(&self.speed),
+            self.bus_id,
+            format_speed(&other.speed),
+            other.key.bus_id(),
+            note,
+        ))
+    }
+    
+    /// Whether `other` is a different device behind the same bus:dev number
+    /// Hubs can hand a freed number to the next device before the old one
+    /// was seen leaving. Only identities known on both sides are compared.
+    pub fn identity_changed(&self, other: &UsbDevice) -> bool {
+        let differs = |a: Option<u16>, b: Option<u16>| matches!((a, b), (Some(a), Some(b)) if a != b);
+        let serial_differs = match (self.serial.as_deref(), other.serial.as_deref()) {
+            (Some(a), Some(b)) => !a.is_empty() && !b.is_empty() && a != b,
+            _ => false,
+        };
+        differs(self.vendor_id, other.vendor_id) || differs(self.product_id, other.product_id) || serial_differs
+    }
+    
+    /// Take descriptors re-read for this entry's bus:dev number
+    /// When they belong to a different device, the entry starts over rather
+    /// than blending the new device's traffic into the old one's totals.
+    /// Returns whether that happened. A re-read that found nothing, e.g.
+    /// because the device already left, changes nothing.
+    pub fn adopt_descriptors(&mut self, fresh: &UsbDevice) -> bool {
+        if fresh.vendor_id.is_none() && fresh.product_id.is_none() {
+            return false;
+        }
+        let replaced = self.identity_changed(fresh);
+        
+        self.vendor_id = fresh.vendor_id;
+        self.product_id = fresh.product_id;
+        self.vendor = fresh.vendor.clone();
+        self.product = fresh.product.clone();
+        self.serial = fresh.serial.clone();
+        self.device_class = fresh.device_class;
+        self.interface_class = fresh.interface_class;
+        self.port_path = fresh.port_path.clone();
+        self.usb_version = fresh.usb_version;
+        self.speed = fresh.speed.clone();
+        self.periodic_endpoints = fresh.periodic_endpoints.clone();
+        self.max_packet_sizes = fresh.max_packet_sizes.clone();
+        self.endpoint_types = fresh.endpoint_types.clone();
+        self.driver_bound = fresh.driver_bound;
+        
+        if replaced {
+            self.reset_stats();
+            self.is_disconnected = false;
+            self.disconnect_time = None;
+            self.first_seen = Instant::now();
+        }
+        replaced
+    }
+    
+    /// Re-read this entry's descriptors from sysfs; see `adopt_descriptors`
+    /// Returns the entry as it was when a different device replaced it.
+    pub fn refresh_descriptors(&mut self) -> Result<Option<UsbDevice>, std::io::Error> {
+        if self.origin.is_some() {
+            return Ok(None);
+        }
+        let mut fresh = UsbDevice::new(self.bus_id, self.device_id);
+        fresh.update_from_sysfs()?;
+        let previous = self.identity_changed(&fresh).then(|| self.clone());
+        let replaced = self.adopt_descriptors(&fresh);
+        Ok(previous.filter(|_| replaced))
+    }
+    
+    /// Fill in identity read from captured enumeration traffic
+    /// Only fields still unknown are set, so sysfs and the vendor database
+    /// keep precedence where they are available. Returns whether anything
+    /// changed.
+    pub fn apply_sniffed(&mut self, sniffed: &assembler::SniffedDevice) -> bool {
+        fn fill<T: Clone>(field: &mut Option<T>, value: Option<T>) -> bool {
+            match (field.is_none(), value) {
+                (true, Some(value)) => {
+                    *field = Some(value);
+                    true
+                }
+                _ => false,
+            }
+        }
+        
+        let mut changed = fill(&mut self.vendor_id, sniffed.vendor_id);
+        changed |= fill(&mut self.product_id, sniffed.product_id);
+        changed |= fill(&mut self.usb_version, sniffed.usb_version);
+        changed |= fill(&mut self.device_class, sniffed.device_class);
+        changed |= fill(&mut self.vendor, sniffed.manufacturer().map(str::to_string));
+        changed |= fill(&mut self.product, sniffed.product().map(str::to_string));
+        changed |= fill(&mut self.serial, sniffed.serial().map(str::to_string));
+        changed
+    }
+    
+    /// Take over the accumulated totals of a retired entry for the same device
+    pub fn absorb(&mut self, ghost: &UsbDevice) {
+        if ghost.bus_id != self.bus_id {
+            self.other_link = Some(OtherLink { key: ghost.key(), speed: ghost.speed.clone() });
+        } else if self.other_link.is_none() {
+            self.other_link = ghost.other_link.clone();
+        }
+        self.bandwidth_stats.absorb_totals(&ghost.bandwidth_stats);
+        self.control_stats.absorb_totals(&ghost.control_stats);
+        for (transfer_type, stats) in &ghost.transfer_stats {
+            self.transfer_stats
+                .entry(*transfer_type)
+                .or_insert_with(BandwidthStats::new)
+                .absorb_totals(stats);
+        }
+        for (address, stats) in &ghost.endpoint_stats {
+            self.endpoint_stats
+                .entry(*address)
+                .or_insert_with(BandwidthStats::new)
+                .absorb_totals(stats);
+        }
+        for (address, size) in &ghost.endpoint_max_seen {
+            let seen = self.endpoint_max_seen.entry(*address).or_insert(0);
+            *seen = (*seen).max(*size);
+        }
+        self.urb_sizes.add(&ghost.urb_sizes);
+        // A reset isn't a new connection
+        self.first_seen = self.first_seen.min(ghost.first_seen);
+    }
+    
+    /// Keep the connection time of the entry this one replaces, unless that
+    /// entry had disconnected in the meantime or was a different device
+    pub fn carry_first_seen(&mut self, previous: &UsbDevice) {
+        if !previous.is_disconnected && !previous.identity_changed(self) {
+            self.first_seen = previous.first_seen;
+        }
+    }
+    
+    /// How long the device has been connected
+    pub fn connected_for(&self) -> Duration {
+        self.first_seen.elapsed()
+    }
+    
+    /// Plugged in for at least `threshold` without doing anything useful
+    /// sysfs decides when it says whether a driver is bound; otherwise the
+    /// device is flagged when endpoint 0 is all it has talked on, which is
+    /// what enumeration looks like with no driver to take over afterwards.
+    pub fn is_without_driver(&self, threshold: Duration) -> bool {
+        if threshold.is_zero() || self.is_disconnected || self.vendor_id.is_none() || self.connected_for() < threshold {
+            return false;
+        }
+        match self.driver_bound {
+            Some(bound) => !bound,
+            None => {
+                !self.transfer_stats.is_empty()
+                    && self.transfer_stats.keys().all(|transfer_type| *transfer_type == TransferType::Control)
+            }
+        }
+    }
+    
     pub fn mark_disconnected(&mut self) {
         if !self.is_disconnected {
             self.is_disconnected = true;
@@ -139,26 +588,169 @@ impl UsbDevice {
     
     pub fn should_remove(&self) -> bool {
         if let Some(disconnect_time) = self.disconnect_time {
-            // Remove after 5 seconds of being disconnected
-            disconnect_time.elapsed().as_secs() > 5
+            disconnect_time.elapsed() > DISCONNECT_GRACE_PERIOD
         } else {
             false
         }
     }
     
+    /// Whether the device is still plugged in, going by sysfs; None when that
+    /// can't be told, e.g. for a remote device or without sysfs
+    pub fn is_present(&self) -> Option<bool> {
+        #[cfg(target_os = "linux")]
+        {
+            if self.origin.is_some() || !sysfs::root().is_dir() {
+                return None;
+            }
+            Some(sysfs::resolve_device_path(self.key()).is_some())
+        }
+        
+        #[cfg(not(target_os = "linux"))]
+        {
+            None
+        }
+    }
+    
+    /// How faded the device's row is, from 0.0 (just active) to 1.0 after
+    /// `fade` without traffic; disconnected devices are fully faded
+    pub fn idle_fraction(&self, fade: Duration) -> f64 {
+        if self.is_disconnected {
+            1.0
+        } else if fade.is_zero() {
+            0.0
+        } else {
+            (self.last_seen.elapsed().as_secs_f64() / fade.as_secs_f64()).min(1.0)
+        }
+    }
+    
+    /// How far through the grace period a disconnected device is (0.0 - 1.0)
+    pub fn disconnect_progress(&self) -> Option<f64> {
+        let elapsed = self.disconnect_time?.elapsed();
+        Some((elapsed.as_secs_f64() / DISCONNECT_GRACE_PERIOD.as_secs_f64()).min(1.0))
+    }
+    
     pub fn update_activity(&mut self) {
         self.last_seen = Instant::now();
         if self.is_disconnected {
             self.is_disconnected = false;
             self.disconnect_time = None;
+            self.first_seen = self.last_seen;
+        }
+    }
+    
+    /// Account a captured packet against the overall and per-transfer-type stats
+    pub fn record_packet(&mut self, packet: &UsbPacket) {
+        let bytes = packet.bandwidth_bytes() as u64;
+        let device_to_host = packet.data_direction();
+        let periodic_window = self.bandwidth_stats.periodic_window;
+        let type_stats = self.transfer_stats
+            .entry(packet.transfer_type)
+            .or_insert_with(|| BandwidthStats { periodic_window, ..BandwidthStats::new() });
+        
+        type_stats.urb_counts.record_packet(packet);
+        self.bandwidth_stats.urb_counts.record_packet(packet);
+        
+        if packet.is_failure() {
+            self.error_rate.record(Instant::now());
+        }
+        
+        let address = packet.endpoint_address();
+        if bytes > 0 {
+            let seen = self.endpoint_max_seen.entry(address).or_insert(0);
+            *seen = (*seen).max(bytes as u32);
+            // Only the half of the URB carrying its data counts, once
+            self.urb_sizes.record(bytes);
+        }
+        
+        if packet.transfer_type.is_periodic() && packet.urb_type == UrbType::Callback {
+            let now = Instant::now();
+            self.endpoint_rates.entry(address)
+                .or_insert_with(|| EventRate::new(now))
+                .record(now);
+        }
+        
+        type_stats.update_typed(bytes, device_to_host, packet.transfer_type);
+        self.endpoint_stats
+            .entry(address)
+            .or_insert_with(|| BandwidthStats { periodic_window, ..BandwidthStats::new() })
+            .update_typed(bytes, device_to_host, packet.transfer_type);
+        
+        let is_control_endpoint = packet.endpoint == 0;
+        if is_control_endpoint {
+            if device_to_host {
+                self.control_stats.update_rx(bytes);
+            } else {
+                self.control_stats.update_tx(bytes);
+            }
         }
+        
+        if !(is_control_endpoint && self.exclude_control_endpoint) {
+            self.bandwidth_stats.update_typed(bytes, device_to_host, packet.transfer_type);
+        }
+        
+        self.update_activity();
+    }
+    
+    /// Readable name for an endpoint address, e.g. "EP2 IN (Bulk)", with
+    /// the type left out when descriptors didn't give it
+    pub fn endpoint_label(&self, address: u8) -> String {
+        descriptors::endpoint_label(address, self.endpoint_types.get(&address).copied())
+    }
+    
+    /// Largest URB seen on each endpoint with the endpoint's wMaxPacketSize
+    /// when sysfs provided it, in address order
+    pub fn endpoint_packet_sizes(&self) -> Vec<(u8, u32, Option<u16>)> {
+        let mut sizes: Vec<(u8, u32, Option<u16>)> = self.endpoint_max_seen.iter()
+            .map(|(address, seen)| (*address, *seen, self.max_packet_sizes.get(address).copied()))
+            .collect();
+        sizes.sort_by_key(|(address, _, _)| *address);
+        sizes
+    }
+    
+    /// Endpoints with traffic in their rate window and that rate, busiest
+    /// first; equal rates keep address order
+    pub fn active_endpoints(&self) -> Vec<(u8, f64)> {
+        let mut active: Vec<(u8, f64)> = self.endpoint_stats.iter()
+            .filter(|(_, stats)| stats.current_bps > 0.0)
+            .map(|(address, stats)| (*address, stats.current_bps))
+            .collect();
+        active.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        active
+    }
+    
+    /// Get the stats for a single transfer type, if any traffic of that type was seen
+    pub fn get_transfer_stats(&self, transfer_type: TransferType) -> Option<&BandwidthStats> {
+        self.transfer_stats.get(&transfer_type)
+    }
+    
+    /// Average URB size of the transfer type carrying the most bytes; control
+    /// traffic only counts when it's all the device has done
+    pub fn transfer_efficiency(&self) -> Option<TransferEfficiency> {
+        let total_bytes = |stats: &BandwidthStats| stats.total_rx_bytes.saturating_add(stats.total_tx_bytes);
+        
+        self.transfer_stats
+            .iter()
+            .filter(|(_, stats)| stats.urb_counts.callbacks > 0)
+            .max_by_key(|(transfer_type, stats)| (**transfer_type != TransferType::Control, total_bytes(stats)))
+            .and_then(|(transfer_type, stats)| {
+                stats.average_bytes_per_urb().map(|avg_bytes_per_urb| TransferEfficiency {
+                    transfer_type: *transfer_type,
+                    avg_bytes_per_urb,
+                })
+            })
     }
     
     /// Calculate the percentage of device bandwidth being utilized
     /// Uses practical bandwidth (accounting for protocol overhead)
     pub fn get_busy_percentage(&self) -> f64 {
+        self.share_of_capacity(self.bandwidth_stats.current_bps).unwrap_or(0.0)
+    }
+    
+    /// `bps` as a percentage of the practical bandwidth of the negotiated
+    /// speed; None while the speed is unknown
+    pub fn share_of_capacity(&self, bps: f64) -> Option<f64> {
         let max_bandwidth = self.speed.to_practical_bytes_per_second();
-        self.bandwidth_stats.get_utilization_percentage(max_bandwidth)
+        (max_bandwidth > 0.0).then(|| (bps / max_bandwidth * 100.0).min(100.0))
     }
     
     /// Calculate the percentage of device bandwidth being utilized (theoretical max)
@@ -168,29 +760,43 @@ impl UsbDevice {
         self.bandwidth_stats.get_utilization_percentage(max_bandwidth)
     }
     
+    /// Walk from the negotiated speed to current utilization; None until the
+    /// speed is known
+    pub fn explain_bandwidth(&self) -> Option<BandwidthExplanation> {
+        let theoretical_bps = self.speed.to_bytes_per_second();
+        if theoretical_bps <= 0.0 {
+            return None;
+        }
+        let practical_bps = self.speed.to_practical_bytes_per_second();
+        Some(BandwidthExplanation {
+            speed: self.speed.clone(),
+            theoretical_bps,
+            efficiency: practical_bps / theoretical_bps,
+            practical_bps,
+            current_bps: self.bandwidth_stats.current_bps,
+            busy_percentage: self.get_busy_percentage(),
+            theoretical_percentage: self.get_busy_percentage_theoretical(),
+        })
+    }
+    
     /// Get the maximum speed capability of this device from USB descriptors
     /// Note: This is a heuristic approach since actual device capability info
     /// may not always be available in sysfs
     #[cfg(target_os = "linux")]
     pub fn get_device_max_capability(&self) -> UsbSpeed {
         use std::fs;
-        use std::path::Path;
         
-        // Try to read bcdUSB version which indicates device capability
-        let sysfs_path = format!("/sys/bus/usb/devices/{}-{}", self.bus_id, self.device_id);
-        
-        if let Ok(bcd_device) = fs::read_to_string(format!("{}/bcdDevice", sysfs_path)) {
-            // Parse bcdDevice to infer capabilities (this is heuristic)
-            if let Ok(bcd_val) = u16::from_str_radix(bcd_device.trim(), 16) {
-                // Modern devices (post-2010) that support USB 3.0+ often have bcdDevice >= 0x300
-                if bcd_val >= 0x0300 {
-                    return UsbSpeed::SuperSpeed;
-                }
+        // The declared bcdUSB is authoritative for SuperSpeed capability
+        if let Some(usb_version) = self.usb_version {
+            if usb_version >= 0x0300 {
+                return UsbSpeed::SuperSpeed;
             }
         }
         
         // Fallback: Check for high-speed capable descriptors
-        if let Ok(bmaxpkts0) = fs::read_to_string(format!("{}/bMaxPacketSize0", sysfs_path)) {
+        let bmaxpkts0 = sysfs::resolve_device_path(self.key())
+            .and_then(|sysfs_path| fs::read_to_string(sysfs_path.join("bMaxPacketSize0")).ok());
+        if let Some(bmaxpkts0) = bmaxpkts0 {
             if let Ok(max_packet) = bmaxpkts0.trim().parse::<u16>() {
                 // High-speed devices can have larger max packet sizes
                 if max_packet >= 64 {
@@ -209,6 +815,12 @@ impl UsbDevice {
     
     #[cfg(not(target_os = "linux"))]
     pub fn get_device_max_capability(&self) -> UsbSpeed {
+        if let Some(usb_version) = self.usb_version {
+            if usb_version >= 0x0300 {
+                return UsbSpeed::SuperSpeed;
+            }
+        }
+        
         // For non-Linux systems, we can't easily detect device capabilities
         // so assume current speed is max capability
         self.speed.clone()
@@ -279,6 +891,44 @@ impl SpeedIndicator {
     }
 }
 
+/// Default cap on tracked devices (`monitoring.max_devices`)
+pub const DEFAULT_MAX_DEVICES: usize = 1000;
+
+/// Pick up to `excess` devices to drop when over the tracked-device cap
+/// Only disconnected devices are candidates, least recently active first, so a
+/// re-enumerating hub can't push out devices that are still talking.
+pub fn select_evictions<'a, I>(devices: I, excess: usize) -> Vec<DeviceKey>
+where
+    I: IntoIterator<Item = &'a UsbDevice>,
+{
+    let mut candidates: Vec<&UsbDevice> = devices.into_iter()
+        .filter(|device| device.is_disconnected)
+        .collect();
+    candidates.sort_by_key(|device| device.last_seen);
+    candidates.into_iter().take(excess).map(|device| device.key()).collect()
+}
+
+/// Find the stale entry a re-enumerated device left behind
+/// After a reset the device reappears under a new device number while the
+/// old one may still show late traffic. The ghost is another entry for the
+/// same hardware that was active within `window`; the most recent wins.
+/// With `pair_companions`, a serial-less device's entry on the companion bus
+/// of a USB 2/USB 3 pair counts as the same hardware too.
+pub fn find_ghost<'a, I>(devices: I, device: &UsbDevice, window: Duration, pair_companions: bool) -> Option<DeviceKey>
+where
+    I: IntoIterator<Item = &'a UsbDevice>,
+{
+    if window.is_zero() {
+        return None;
+    }
+    devices.into_iter()
+        .filter(|other| other.key() != device.key())
+        .filter(|other| other.is_same_hardware(device) || (pair_companions && other.is_companion_of(device)))
+        .filter(|other| other.last_seen.elapsed() <= window)
+        .max_by_key(|other| other.last_seen)
+        .map(|other| other.key())
+}
+
 /// Format USB speed for display
 pub fn format_speed(speed: &UsbSpeed) -> String {
     match speed {
@@ -291,20 +941,452 @@ pub fn format_speed(speed: &UsbSpeed) -> String {
     }
 }
 
+/// Parse a bcdUSB version string such as "2.00" or " 3.20" into BCD (0x0200, 0x0320)
+pub fn parse_usb_version(version: &str) -> Option<u16> {
+    let (major, minor) = version.trim().split_once('.')?;
+    let major: u16 = major.parse().ok()?;
+    let mut minor_digits = minor.chars().map(|c| c.to_digit(10));
+    let minor_high = minor_digits.next().flatten()? as u16;
+    let minor_low = minor_digits.next().flatten().unwrap_or(0) as u16;
+    
+    if major > 0xFF {
+        return None;
+    }
+    Some((major << 8) | (minor_high << 4) | minor_low)
+}
+
+/// Format a BCD USB version for display, e.g. 0x0320 -> "3.20"
+pub fn format_usb_version(bcd: u16) -> String {
+    format!("{}.{}{}", bcd >> 8, (bcd >> 4) & 0xF, bcd & 0xF)
+}
+
 /// Format bandwidth utilization percentage for display
 pub fn format_busy_percentage(percentage: f64) -> String {
     format!("{:5.1}%", percentage)
 }
 
+/// Most decimals a fixed rate precision may ask for; keeps table cells in their columns
+pub const MAX_RATE_PRECISION: usize = 3;
+
 /// Format bandwidth in human-readable units
 pub fn format_bandwidth(bps: f64) -> String {
-    if bps >= 1_000_000_000.0 {
-        format!("{:.1} GB/s", bps / 1_000_000_000.0)
+    format_bandwidth_with(bps, None)
+}
+
+/// Format bandwidth with a fixed number of decimals, or `None` for automatic
+/// precision: one decimal from KB/s up, and for B/s as many as it takes for
+/// a trickle of traffic not to read as zero
+pub fn format_bandwidth_with(bps: f64, precision: Option<usize>) -> String {
+    let (value, unit) = if bps >= 1_000_000_000.0 {
+        (bps / 1_000_000_000.0, "GB/s")
     } else if bps >= 1_000_000.0 {
-        format!("{:.1} MB/s", bps / 1_000_000.0)
+        (bps / 1_000_000.0, "MB/s")
     } else if bps >= 1_000.0 {
-        format!("{:.1} KB/s", bps / 1_000.0)
+        (bps / 1_000.0, "KB/s")
     } else {
-        format!("{:.0} B/s", bps)
+        (bps, "B/s")
+    };
+    
+    let decimals = match precision {
+        Some(precision) => precision.min(MAX_RATE_PRECISION),
+        None if unit != "B/s" => 1,
+        None if value == 0.0 || value >= 10.0 => 0,
+        None if value >= 1.0 => 1,
+        None => 2,
+    };
+    format!("{:.*} {}", decimals, value, unit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_device_key_parse_and_display() {
+        let key: DeviceKey = "2:5".parse().unwrap();
+        assert_eq!(key, DeviceKey(2, 5));
+        assert_eq!(key.to_string(), "002:005");
+        assert_eq!("002:005".parse::<DeviceKey>(), Ok(key));
+        
+        assert!("2".parse::<DeviceKey>().is_err());
+        assert!("2:x".parse::<DeviceKey>().is_err());
+        assert!("300:1".parse::<DeviceKey>().is_err());
+    }
+    
+    #[test]
+    fn test_usb_version_round_trip() {
+        assert_eq!(parse_usb_version(" 2.00\n"), Some(0x0200));
+        assert_eq!(parse_usb_version("3.20"), Some(0x0320));
+        assert_eq!(parse_usb_version("1.1"), Some(0x0110));
+        assert_eq!(parse_usb_version("abc"), None);
+        
+        assert_eq!(format_usb_version(0x0320), "3.20");
+        assert_eq!(format_usb_version(0x0110), "1.10");
+    }
+    
+    #[test]
+    fn test_declared_usb3_is_superspeed_capable() {
+        let mut device = UsbDevice::new(1, 2);
+        device.speed = UsbSpeed::High;
+        device.usb_version = Some(0x0310);
+        
+        assert_eq!(device.get_device_max_capability(), UsbSpeed::SuperSpeed);
+        assert_eq!(device.check_speed_mismatch(&UsbSpeed::High), Some(UsbSpeed::SuperSpeed));
+    }
+    
+    #[test]
+    fn test_transfer_efficiency_prefers_busiest_data_type() {
+        let mut device = UsbDevice::new(1, 2);
+        assert_eq!(device.transfer_efficiency(), None);
+        
+        let mut control = BandwidthStats::new();
+        control.update_rx(64_000);
+        control.urb_counts.callbacks = 1000;
+        device.transfer_stats.insert(TransferType::Control, control);
+        assert_eq!(device.transfer_efficiency().unwrap().transfer_type, TransferType::Control);
+        
+        let mut bulk = BandwidthStats::new();
+        bulk.update_tx(5120);
+        bulk.urb_counts.callbacks = 10;
+        device.transfer_stats.insert(TransferType::Bulk, bulk);
+        
+        let efficiency = device.transfer_efficiency().unwrap();
+        assert_eq!(efficiency.transfer_type, TransferType::Bulk);
+        assert_eq!(efficiency.avg_bytes_per_urb, 512.0);
+        assert!(efficiency.is_inefficient());
+        assert_eq!(efficiency.note(), "inefficient for bulk");
+        
+        let interrupt = TransferEfficiency { transfer_type: TransferType::Interrupt, avg_bytes_per_urb: 8.0 };
+        assert!(!interrupt.is_inefficient());
+        assert_eq!(interrupt.note(), "expected for interrupt");
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_explain_bandwidth() {
+        let mut device = UsbDevice::new(1, 2);
+        assert_eq!(device.explain_bandwidth(), None);
+        
+        device.speed = UsbSpeed::High;
+        device.bandwidth_stats.current_bps = 12_000_000.0;
+        let explanation = device.explain_bandwidth().unwrap();
+        assert_eq!(explanation.theoretical_bps, 60_000_000.0);
+        assert_eq!(explanation.practical_bps, 48_000_000.0);
+        assert!((explanation.efficiency - 0.8).abs() < 1e-9);
+        assert_eq!(explanation.busy_percentage, 25.0);
+        assert_eq!(explanation.theoretical_percentage, 20.0);
+    }
+    
+    #[test]
+    fn test_format_bandwidth_precision() {
+        assert_eq!(format_bandwidth(0.0), "0 B/s");
+        assert_eq!(format_bandwidth(0.25), "0.25 B/s");
+        assert_eq!(format_bandwidth(3.2), "3.2 B/s");
+        assert_eq!(format_bandwidth(512.0), "512 B/s");
+        assert_eq!(format_bandwidth(1_500_000.0), "1.5 MB/s");
+        
+        assert_eq!(format_bandwidth_with(1_234_567.0, Some(3)), "1.235 MB/s");
+        assert_eq!(format_bandwidth_with(40.0, Some(0)), "40 B/s");
+        assert_eq!(format_bandwidth_with(1_500.0, Some(9)), "1.500 KB/s");
+    }
+    
+    #[test]
+    fn test_error_rates_pick_worst_device() {
+        let start = Instant::now();
+        let mut quiet = UsbDevice::new(1, 2);
+        let mut flaky = UsbDevice::new(1, 3);
+        let mut noisy = UsbDevice::new(2, 4);
+        quiet.error_rate = EventRate::new(start);
+        flaky.error_rate = EventRate::new(start);
+        noisy.error_rate = EventRate::new(start);
+        for tenth in 0..=10 {
+            let at = start + Duration::from_millis(tenth * 100);
+            noisy.error_rate.record(at);
+            if tenth % 5 == 0 {
+                flaky.error_rate.record(at);
+            }
+        }
+        
+        let rates = ErrorRates::from_devices([&quiet, &flaky, &noisy], start + Duration::from_secs(1));
+        assert_eq!(rates.worst, Some((DeviceKey(2, 4), 10.0)));
+        assert_eq!(rates.total_per_sec, 12.0);
+        
+        assert_eq!(ErrorRates::from_devices([&quiet], start).worst, None);
+    }
+    
+    #[test]
+    fn test_failed_completions_count_as_errors() {
+        use crate::usbmon::parser::parse_usbmon_text_line;
+        
+        let mut device = UsbDevice::new(1, 2);
+        for line in [
+            "ffff88003b4a3c00 3575914555 S Bi:1:002:1 -115 512 <",
+            "ffff88003b4a3c00 3575914655 C Bi:1:002:1 -71 0",
+            "ffff88003b4a3d00 3575914700 S Bi:1:002:1 -115 512 <",
+            "ffff88003b4a3d00 3575914800 C Bi:1:002:1 -104 0",
+            "ffff88003b4a3e00 3575914900 S Bi:1:002:1 -115 512 <",
+            "ffff88003b4a3e00 3575915000 C Bi:1:002:1 0 512 = 00",
+        ] {
+            device.record_packet(&parse_usbmon_text_line(line).unwrap());
+        }
+        
+        // The EPROTO completion is an error; the cancellation is routine
+        let counts = &device.bandwidth_stats.urb_counts;
+        assert_eq!((counts.submissions, counts.callbacks, counts.errors), (3, 2, 1));
+        assert_eq!(device.get_transfer_stats(TransferType::Bulk).unwrap().urb_counts.errors, 1);
+    }
+    
+    #[test]
+    fn test_same_device_id_on_different_buses() {
+        let first = UsbDevice::new(1, 3);
+        let second = UsbDevice::new(2, 3);
+        assert_ne!(first.key(), second.key());
+    }
+    
+    #[test]
+    fn test_companion_link_needs_same_port_and_other_speed_class() {
+        let link = |bus_id: u8, device_id: u8, speed: UsbSpeed| {
+            let mut device = UsbDevice::new(bus_id, device_id);
+            device.vendor_id = Some(0x0bda);
+            device.product_id = Some(0x8153);
+            device.port_path = Some(format!("{}-2.1", bus_id));
+            device.speed = speed;
+            device
+        };
+        
+        assert!(link(2, 3, UsbSpeed::SuperSpeed).is_companion_of(&link(1, 7, UsbSpeed::High)));
+        assert!(!link(2, 3, UsbSpeed::SuperSpeed).is_companion_of(&UsbDevice { port_path: Some("1-4".to_string()), ..link(1, 7, UsbSpeed::High) }));
+        assert!(!link(2, 3, UsbSpeed::SuperSpeed).is_companion_of(&link(1, 7, UsbSpeed::SuperSpeedPlus)));
+        assert!(!link(2, 3, UsbSpeed::SuperSpeed).is_companion_of(&link(2, 7, UsbSpeed::High)));
+    }
+    
+    #[test]
+    fn test_reused_device_id_resets_stats() {
+        let identified = |product_id: u16| {
+            let mut device = UsbDevice::new(1, 5);
+            device.vendor_id = Some(0x0781);
+            device.product_id = Some(product_id);
+            device
+        };
+        let mut device = identified(0x5581);
+        device.first_seen = Instant::now() - Duration::from_secs(600);
+        device.bandwidth_stats.total_rx_bytes = 1_000_000;
+        device.control_stats.total_tx_bytes = 512;
+        
+        // The same device re-read keeps its totals
+        assert!(!device.adopt_descriptors(&identified(0x5581)));
+        assert_eq!(device.bandwidth_stats.total_rx_bytes, 1_000_000);
+        // Nothing readable any more doesn't count as a new device
+        assert!(!device.adopt_descriptors(&UsbDevice::new(1, 5)));
+        assert_eq!(device.product_id, Some(0x5581));
+        
+        // bus:dev unchanged but a different product behind it
+        assert!(device.adopt_descriptors(&identified(0x5583)));
+        assert_eq!(device.product_id, Some(0x5583));
+        assert_eq!(device.bandwidth_stats.total_rx_bytes, 0);
+        assert_eq!(device.control_stats.total_tx_bytes, 0);
+        assert!(device.connected_for() < Duration::from_secs(1));
+    }
+    
+    #[test]
+    fn test_without_driver_after_threshold() {
+        let threshold = Duration::from_secs(10);
+        let mut device = UsbDevice::new(1, 7);
+        device.vendor_id = Some(0x1234);
+        device.first_seen = Instant::now() - Duration::from_secs(60);
+        device.transfer_stats.insert(TransferType::Control, BandwidthStats::new());
+        
+        // Without sysfs driver info, control-only traffic is the tell
+        assert!(device.is_without_driver(threshold));
+        device.transfer_stats.insert(TransferType::Interrupt, BandwidthStats::new());
+        assert!(!device.is_without_driver(threshold));
+        
+        // sysfs overrides the traffic heuristic either way
+        device.driver_bound = Some(false);
+        assert!(device.is_without_driver(threshold));
+        device.driver_bound = Some(true);
+        device.transfer_stats.remove(&TransferType::Interrupt);
+        assert!(!device.is_without_driver(threshold));
+        
+        // Too soon after connecting, or disabled
+        device.driver_bound = Some(false);
+        device.first_seen = Instant::now();
+        assert!(!device.is_without_driver(threshold));
+        assert!(!device.is_without_driver(Duration::ZERO));
+    }
+    
+    #[test]
+    fn test_max_packet_seen_per_device_and_endpoint() {
+        use crate::usbmon::parser::parse_usbmon_text_line;
+        
+        let mut device = UsbDevice::new(1, 2);
+        device.max_packet_sizes.insert(0x81, 512);
+        device.record_packet(&parse_usbmon_text_line("ffff88003b4a3c00 3575914555 C Bi:1:002:1 0 16384 = 00").unwrap());
+        device.record_packet(&parse_usbmon_text_line("ffff88003b4a3c00 3575914600 C Bi:1:002:1 0 4096 = 00").unwrap());
+        device.record_packet(&parse_usbmon_text_line("ffff88003b4a3d00 3575915000 S Bo:1:002:2 -115 31 = 55534243").unwrap());
+        
+        assert_eq!(device.bandwidth_stats.max_packet_seen, 16384);
+        assert_eq!(device.get_transfer_stats(TransferType::Bulk).unwrap().max_packet_seen, 16384);
+        assert_eq!(device.endpoint_packet_sizes(), vec![(0x02, 31, None), (0x81, 16384, Some(512))]);
+        let busiest: Vec<u8> = device.active_endpoints().into_iter().map(|(address, _)| address).collect();
+        assert_eq!(busiest, vec![0x81, 0x02]);
+        assert_eq!(device.endpoint_stats[&0x81].total_rx_bytes, 16384 + 4096);
+        assert_eq!(device.urb_sizes.counts, [1, 0, 0, 2, 0]);
+        
+        device.reset_stats();
+        assert_eq!(device.bandwidth_stats.max_packet_seen, 0);
+        assert_eq!(device.urb_sizes.total(), 0);
+        assert!(device.endpoint_packet_sizes().is_empty());
+        assert!(device.active_endpoints().is_empty());
+    }
+    
+    #[test]
+    fn test_control_bytes_follow_bm_request_type() {
+        use crate::usbmon::parser::parse_usbmon_text_line;
+        
+        let mut device = UsbDevice::new(1, 2);
+        // GET_DESCRIPTOR reads 18 bytes even though this URB's direction bit says OUT;
+        // the submission only states the buffer size, the callback carries the data
+        let mut get_descriptor = parse_usbmon_text_line("ffff88003b4a3c00 3575914555 S Co:1:002:0 s 80 06 0100 0000 0012 18 <").unwrap();
+        get_descriptor.direction = false;
+        device.record_packet(&get_descriptor);
+        assert_eq!((device.control_stats.total_rx_bytes, device.control_stats.total_tx_bytes), (0, 0));
+        device.record_packet(&parse_usbmon_text_line("ffff88003b4a3c00 3575914655 C Ci:1:002:0 0 18 = 12010002 00000040").unwrap());
+        // SET_REPORT writes 8 bytes to the device
+        device.record_packet(&parse_usbmon_text_line("ffff88003b4a3d00 3575915000 S Co:1:002:0 s 21 09 0200 0000 0008 8 = 01020304 05060708").unwrap());
+        // SET_CONFIGURATION has no data stage
+        device.record_packet(&parse_usbmon_text_line("ffff88003b4a3e00 3575916000 S Co:1:002:0 s 00 09 0001 0000 0000 0").unwrap());
+        
+        assert_eq!(device.control_stats.total_rx_bytes, 18);
+        assert_eq!(device.control_stats.total_tx_bytes, 8);
+        let control = device.get_transfer_stats(TransferType::Control).unwrap();
+        assert_eq!((control.total_rx_bytes, control.total_tx_bytes), (18, 8));
+    }
+    
+    #[test]
+    fn test_excluded_control_endpoint_only_counts_as_control() {
+        use crate::usbmon::parser::parse_usbmon_text_line;
+        
+        let lines = [
+            "ffff88003b4a3c00 3575914655 C Ci:1:002:0 0 18 = 12010002 00000040",
+            "ffff88003b4a3d00 3575915000 S Co:1:002:0 s 21 09 0200 0000 0008 8 = 01020304 05060708",
+            "ffff88003b4a3e00 3575916000 C Bi:1:002:1 0 512 = 00",
+        ];
+        for exclude_control_endpoint in [false, true] {
+            let mut device = UsbDevice::new(1, 2);
+            device.exclude_control_endpoint = exclude_control_endpoint;
+            for line in lines {
+                device.record_packet(&parse_usbmon_text_line(line).unwrap());
+            }
+            
+            assert_eq!((device.control_stats.total_rx_bytes, device.control_stats.total_tx_bytes), (18, 8));
+            let control = device.get_transfer_stats(TransferType::Control).unwrap();
+            assert_eq!((control.total_rx_bytes, control.total_tx_bytes), (18, 8));
+            let stats = &device.bandwidth_stats;
+            let expected = if exclude_control_endpoint { (512, 0) } else { (512 + 18, 8) };
+            assert_eq!((stats.total_rx_bytes, stats.total_tx_bytes), expected, "excluded: {}", exclude_control_endpoint);
+        }
+    }
+    
+    #[test]
+    fn test_quiet_device_rates_drop_to_zero() {
+        use crate::stats::RATE_WINDOW;
+        use crate::usbmon::parser::parse_usbmon_text_line;
+        
+        let mut device = UsbDevice::new(1, 2);
+        device.record_packet(&parse_usbmon_text_line("ffff88003b4a3c00 3575914655 C Ci:1:002:0 0 18 = 12010002 00000040").unwrap());
+        device.record_packet(&parse_usbmon_text_line("ffff88003b4a3d00 3575915000 C Bi:1:002:1 0 512 = 00").unwrap());
+        assert!(device.bandwidth_stats.current_bps > 0.0);
+        
+        // Nothing arrives for longer than the rate window
+        device.refresh_rates(Instant::now() + RATE_WINDOW + Duration::from_secs(1));
+        assert_eq!(device.bandwidth_stats.current_bps, 0.0);
+        assert_eq!(device.control_stats.current_bps, 0.0);
+        assert_eq!(device.get_transfer_stats(TransferType::Bulk).unwrap().current_bps, 0.0);
+        assert!(device.active_endpoints().is_empty());
+        assert_eq!(device.bandwidth_stats.total_rx_bytes, 512 + 18);
+    }
+    
+    #[test]
+    fn test_idle_fraction_follows_last_activity() {
+        let fade = Duration::from_secs(60);
+        let mut device = UsbDevice::new(1, 3);
+        assert!(device.idle_fraction(fade) < 0.01);
+        
+        device.last_seen = Instant::now() - Duration::from_secs(30);
+        assert!((device.idle_fraction(fade) - 0.5).abs() < 0.01);
+        device.last_seen = Instant::now() - Duration::from_secs(600);
+        assert_eq!(device.idle_fraction(fade), 1.0);
+        assert_eq!(device.idle_fraction(Duration::ZERO), 0.0);
+        
+        device.update_activity();
+        device.mark_disconnected();
+        assert_eq!(device.idle_fraction(fade), 1.0);
+        assert_eq!(device.idle_fraction(Duration::ZERO), 1.0);
+    }
+    
+    #[test]
+    fn test_each_urb_counted_once() {
+        use crate::usbmon::parser::UsbPacket;
+        
+        let event = |transfer_type: TransferType, device_to_host: bool, urb_type: UrbType| UsbPacket {
+            timestamp: chrono::Utc::now(),
+            timestamp_us: 0,
+            urb_tag: "ffff88003b4a3c00".to_string(),
+            urb_type: urb_type.clone(),
+            transfer_type,
+            bus_id: 1,
+            device_id: 2,
+            endpoint: if transfer_type == TransferType::Control { 0 } else { 1 },
+            direction: device_to_host,
+            data_length: 64,
+            captured_length: 0,
+            status: if urb_type == UrbType::Submission { -115 } else { 0 },
+            interval: None,
+            // Only submissions carry the setup packet; bmRequestType bit 7 is the data direction
+            setup_packet: (transfer_type == TransferType::Control && urb_type == UrbType::Submission)
+                .then(|| vec![if device_to_host { 0x80 } else { 0x00 }, 0x06, 0, 1, 0, 0, 64, 0]),
+            data: None,
+        };
+        
+        let types = [TransferType::Control, TransferType::Bulk, TransferType::Interrupt, TransferType::Isochronous];
+        for transfer_type in types {
+            for device_to_host in [true, false] {
+                let submission = event(transfer_type, device_to_host, UrbType::Submission);
+                let callback = event(transfer_type, device_to_host, UrbType::Callback);
+                let error = event(transfer_type, device_to_host, UrbType::Error);
+                let case = format!("{:?} {}", transfer_type, if device_to_host { "IN" } else { "OUT" });
+                
+                // OUT data moves when the URB is submitted, IN data when it completes
+                let expected = if device_to_host { (0, 64) } else { (64, 0) };
+                assert_eq!((submission.bandwidth_bytes(), callback.bandwidth_bytes()), expected, "{}", case);
+                assert_eq!(error.bandwidth_bytes(), 0, "{}", case);
+                
+                let mut device = UsbDevice::new(1, 2);
+                for packet in [&submission, &callback, &error] {
+                    device.record_packet(packet);
+                }
+                let stats = &device.bandwidth_stats;
+                let expected = if device_to_host { (64, 0) } else { (0, 64) };
+                assert_eq!((stats.total_rx_bytes, stats.total_tx_bytes), expected, "{}", case);
+                let type_stats = device.get_transfer_stats(transfer_type).unwrap();
+                assert_eq!(type_stats.total_rx_bytes + type_stats.total_tx_bytes, 64, "{}", case);
+            }
+        }
+    }
+    
+    #[test]
+    fn test_share_of_capacity_normalizes_by_speed() {
+        let mut mouse = UsbDevice::new(1, 2);
+        mouse.speed = UsbSpeed::Low;
+        let mut disk = UsbDevice::new(1, 3);
+        disk.speed = UsbSpeed::High;
+        
+        // 65 KB/s is half of what Low Speed carries but nothing for High Speed
+        let mouse_share = mouse.share_of_capacity(65_625.0).unwrap();
+        let disk_share = disk.share_of_capacity(65_625.0).unwrap();
+        assert!((mouse_share - 50.0).abs() < 1e-9);
+        assert!(disk_share < 0.2);
+        assert_eq!(mouse.share_of_capacity(1e9), Some(100.0));
+        assert_eq!(UsbDevice::new(1, 4).share_of_capacity(1000.0), None);
+    }
+}
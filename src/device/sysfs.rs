@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::device::descriptors::SYSFS_USB_DEVICES;
+use crate::device::DeviceKey;
+
+static RESOLVER: Mutex<Option<SysfsResolver>> = Mutex::new(None);
+
+/// Maps (bus, device number) to the device's sysfs directory
+/// sysfs names devices by bus and port path (`1-1.2`), not by device number,
+/// so the mapping comes from each directory's `busnum` and `devnum`. Lookups
+/// are cached; a cached path is re-checked on use because device numbers are
+/// reused after unplugging, and a miss triggers a rescan.
+#[derive(Debug)]
+pub struct SysfsResolver {
+    root: PathBuf,
+    paths: HashMap<DeviceKey, PathBuf>,
+}
+
+impl SysfsResolver {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            paths: HashMap::new(),
+        }
+    }
+    
+    pub fn resolve(&mut self, key: DeviceKey) -> Option<PathBuf> {
+        if let Some(path) = self.paths.get(&key) {
+            if read_device_key(path) == Some(key) {
+                return Some(path.clone());
+            }
+        }
+        
+        self.rescan();
+        self.paths.get(&key).cloned()
+    }
+    
+    fn rescan(&mut self) {
+        self.paths = fs::read_dir(&self.root)
+            .map(|entries| {
+                entries.filter_map(|entry| entry.ok())
+                    .map(|entry| entry.path())
+                    .filter_map(|path| read_device_key(&path).map(|key| (key, path)))
+                    .collect()
+            })
+            .unwrap_or_default();
+    }
+}
+
+/// Bus and device number of a sysfs device directory; None for interfaces
+fn read_device_key(dir: &Path) -> Option<DeviceKey> {
+    let read = |name: &str| -> Option<u8> {
+        fs::read_to_string(dir.join(name)).ok()?.trim().parse().ok()
+    };
+    Some(DeviceKey(read("busnum")?, read("devnum")?))
+}
+
+/// Point the shared resolver at another devices directory (e.g. `--sysfs-path`)
+pub fn set_root(root: &Path) {
+    if let Ok(mut resolver) = RESOLVER.lock() {
+        *resolver = Some(SysfsResolver::new(root));
+    }
+}
+
+/// Devices directory the shared resolver reads, `/sys/bus/usb/devices` unless overridden
+pub fn root() -> PathBuf {
+    RESOLVER.lock()
+        .ok()
+        .and_then(|resolver| resolver.as_ref().map(|resolver| resolver.root.clone()))
+        .unwrap_or_else(|| PathBuf::from(SYSFS_USB_DEVICES))
+}
+
+/// Sysfs directory for `key` under the devices directory, using a shared cache
+pub fn resolve_device_path(key: DeviceKey) -> Option<PathBuf> {
+    let mut resolver = RESOLVER.lock().ok()?;
+    resolver.get_or_insert_with(|| SysfsResolver::new(SYSFS_USB_DEVICES)).resolve(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn write_device(root: &Path, name: &str, bus: u8, dev: u8) {
+        let dir = root.join(name);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("busnum"), format!("{}\n", bus)).unwrap();
+        fs::write(dir.join("devnum"), format!("{}\n", dev)).unwrap();
+    }
+    
+    #[test]
+    fn test_resolves_port_path_names() {
+        let root = std::env::temp_dir().join(format!("usbtop-ng-resolver-{}", std::process::id()));
+        write_device(&root, "usb1", 1, 1);
+        write_device(&root, "1-1.2", 1, 7);
+        fs::create_dir_all(root.join("1-1.2:1.0")).unwrap();
+        
+        let mut resolver = SysfsResolver::new(&root);
+        assert_eq!(resolver.resolve(DeviceKey(1, 7)), Some(root.join("1-1.2")));
+        assert_eq!(resolver.resolve(DeviceKey(1, 1)), Some(root.join("usb1")));
+        assert_eq!(resolver.resolve(DeviceKey(1, 9)), None);
+        
+        // Device number reused by a device on another port after a replug
+        fs::remove_dir_all(root.join("1-1.2")).unwrap();
+        write_device(&root, "1-3", 1, 7);
+        let resolved = resolver.resolve(DeviceKey(1, 7));
+        fs::remove_dir_all(&root).unwrap();
+        
+        assert_eq!(resolved, Some(root.join("1-3")));
+    }
+}
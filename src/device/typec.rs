@@ -0,0 +1,220 @@
+use std::fs;
+use std::path::Path;
+
+use crate::device::UsbDevice;
+use crate::usbmon::parser::UsbSpeed;
+
+/// Where the kernel lists USB-C connectors, their partners and alt modes
+pub const SYSFS_TYPEC: &str = "/sys/class/typec";
+
+/// Standard and vendor ids of the alternate modes worth naming
+const SVID_DISPLAYPORT: u16 = 0xff01;
+const SVID_THUNDERBOLT: u16 = 0x8087;
+
+/// An alternate mode the partner supports
+#[derive(Debug, Clone, PartialEq)]
+pub struct AltMode {
+    pub svid: u16,
+    pub active: bool,
+}
+
+impl AltMode {
+    pub fn name(&self) -> String {
+        match self.svid {
+            SVID_DISPLAYPORT => "DP".to_string(),
+            SVID_THUNDERBOLT => "Thunderbolt".to_string(),
+            svid => format!("SVID {:04x}", svid),
+        }
+    }
+}
+
+/// Whatever is plugged into a USB-C connector
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TypecPartner {
+    /// "audio" or "debug" for accessory-mode partners
+    pub accessory: Option<String>,
+    pub alt_modes: Vec<AltMode>,
+}
+
+/// One USB-C connector from the typec class
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypecPort {
+    /// Class device name, e.g. "port0"
+    pub name: String,
+    /// Root hub ports wired to the connector as (bus, port), from the
+    /// `usbN-portM` links newer kernels add; usually one per USB generation
+    pub usb_ports: Vec<(u8, u8)>,
+    /// Current data role, "host" or "device"
+    pub data_role: Option<String>,
+    pub partner: Option<TypecPartner>,
+}
+
+impl TypecPort {
+    /// Fastest tracked device attached through one of this connector's root
+    /// ports; None when no USB device is on it or the ports aren't linked
+    pub fn usb_speed<'a>(&self, devices: impl IntoIterator<Item = &'a UsbDevice>) -> Option<UsbSpeed> {
+        devices.into_iter()
+            .filter(|device| !device.is_disconnected)
+            .filter(|device| device.port_path.as_deref().and_then(root_port).is_some_and(|port| self.usb_ports.contains(&port)))
+            .map(|device| device.speed.clone())
+            .max_by_key(|speed| speed.to_practical_bytes_per_second() as u64)
+    }
+    
+    /// What the connector is carrying, e.g. "USB3 + DP alt mode"; None when
+    /// nothing is plugged in
+    pub fn describe(&self, usb_speed: Option<&UsbSpeed>) -> Option<String> {
+        let partner = self.partner.as_ref()?;
+        if let Some(accessory) = &partner.accessory {
+            return Some(format!("{} accessory", accessory));
+        }
+        
+        let mut parts: Vec<String> = Vec::new();
+        match usb_speed {
+            Some(UsbSpeed::SuperSpeed | UsbSpeed::SuperSpeedPlus) => parts.push("USB3".to_string()),
+            Some(UsbSpeed::Unknown) => parts.push("USB".to_string()),
+            Some(_) => parts.push("USB2".to_string()),
+            None => {}
+        }
+        parts.extend(partner.alt_modes.iter().filter(|mode| mode.active).map(|mode| format!("{} alt mode", mode.name())));
+        if parts.is_empty() {
+            parts.push("no USB data".to_string());
+        }
+        let mut description = parts.join(" + ");
+        if self.data_role.as_deref() == Some("device") {
+            description.push_str(" (as device)");
+        }
+        Some(description)
+    }
+}
+
+/// Read every connector under the typec class directory, in name order
+/// Kernels without the class (or machines without USB-C) have no such
+/// directory, which gives an empty list rather than an error.
+pub fn discover_typec_ports(root: &Path) -> Vec<TypecPort> {
+    let Ok(entries) = fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries.filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| name.strip_prefix("port").is_some_and(|number| number.parse::<u32>().is_ok()))
+        .collect();
+    names.sort();
+    
+    names.into_iter()
+        .map(|name| {
+            let dir = root.join(&name);
+            let partner_dir = root.join(format!("{}-partner", name));
+            TypecPort {
+                usb_ports: linked_usb_ports(&dir),
+                data_role: read_attr(&dir, "data_role").as_deref().and_then(selected_choice),
+                partner: partner_dir.is_dir().then(|| read_partner(&partner_dir)),
+                name,
+            }
+        })
+        .collect()
+}
+
+fn read_attr(dir: &Path, name: &str) -> Option<String> {
+    fs::read_to_string(dir.join(name)).ok().map(|value| value.trim().to_string())
+}
+
+/// The bracketed choice of a sysfs selection such as "[host] device"
+fn selected_choice(value: &str) -> Option<String> {
+    let start = value.find('[')?;
+    let end = value[start..].find(']')? + start;
+    Some(value[start + 1..end].to_string())
+}
+
+/// Root hub ports from link names such as "usb3-port1"
+fn linked_usb_ports(dir: &Path) -> Vec<(u8, u8)> {
+    let mut ports: Vec<(u8, u8)> = fs::read_dir(dir)
+        .map(|entries| {
+            entries.filter_map(|entry| entry.ok())
+                .filter_map(|entry| {
+                    let name = entry.file_name().to_string_lossy().into_owned();
+                    let (bus, port) = name.strip_prefix("usb")?.split_once("-port")?;
+                    Some((bus.parse().ok()?, port.parse().ok()?))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    ports.sort();
+    ports
+}
+
+fn read_partner(dir: &Path) -> TypecPartner {
+    let prefix = format!("{}.", dir.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default());
+    let mut modes: Vec<(String, AltMode)> = fs::read_dir(dir)
+        .map(|entries| {
+            entries.filter_map(|entry| entry.ok())
+                .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+                .filter_map(|entry| {
+                    let path = entry.path();
+                    let svid = u16::from_str_radix(&read_attr(&path, "svid")?, 16).ok()?;
+                    let active = read_attr(&path, "active").as_deref() == Some("yes");
+                    Some((entry.file_name().to_string_lossy().into_owned(), AltMode { svid, active }))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    modes.sort_by(|a, b| a.0.cmp(&b.0));
+    
+    TypecPartner {
+        accessory: read_attr(dir, "accessory_mode").filter(|mode| mode != "none"),
+        alt_modes: modes.into_iter().map(|(_, mode)| mode).collect(),
+    }
+}
+
+/// Bus and root hub port a device hangs off, from a sysfs name like "2-1.3"
+fn root_port(port_path: &str) -> Option<(u8, u8)> {
+    let (bus, ports) = port_path.split_once('-')?;
+    let port = ports.split('.').next()?;
+    Some((bus.parse().ok()?, port.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+    
+    #[test]
+    fn test_dp_alt_mode_port() {
+        let root = std::env::temp_dir().join(format!("usbtop-ng-typec-{}", std::process::id()));
+        let port = root.join("port0");
+        let partner = root.join("port0-partner");
+        fs::create_dir_all(&port).unwrap();
+        fs::create_dir_all(root.join("port1")).unwrap();
+        fs::write(port.join("data_role"), "[host] device\n").unwrap();
+        symlink(&root, port.join("usb1-port1")).unwrap();
+        symlink(&root, port.join("usb2-port1")).unwrap();
+        for (mode, svid, active) in [("port0-partner.0", "ff01", "yes"), ("port0-partner.1", "8087", "no")] {
+            fs::create_dir_all(partner.join(mode)).unwrap();
+            fs::write(partner.join(mode).join("svid"), format!("{}\n", svid)).unwrap();
+            fs::write(partner.join(mode).join("active"), format!("{}\n", active)).unwrap();
+        }
+        fs::write(partner.join("accessory_mode"), "none\n").unwrap();
+        
+        let ports = discover_typec_ports(&root);
+        fs::remove_dir_all(&root).unwrap();
+        
+        assert_eq!(ports.len(), 2);
+        assert_eq!(ports[0].usb_ports, [(1, 1), (2, 1)]);
+        assert_eq!(ports[0].data_role.as_deref(), Some("host"));
+        
+        // A dock's USB 3 hub on the SuperSpeed bus, a keyboard elsewhere
+        let mut hub = UsbDevice::new(2, 2);
+        hub.port_path = Some("2-1".to_string());
+        hub.speed = UsbSpeed::SuperSpeed;
+        let mut keyboard = UsbDevice::new(1, 5);
+        keyboard.port_path = Some("1-3.2".to_string());
+        keyboard.speed = UsbSpeed::Full;
+        let speed = ports[0].usb_speed([&hub, &keyboard]);
+        assert_eq!(speed, Some(UsbSpeed::SuperSpeed));
+        assert_eq!(ports[0].describe(speed.as_ref()).as_deref(), Some("USB3 + DP alt mode"));
+        assert_eq!(ports[0].describe(None).as_deref(), Some("DP alt mode"));
+        
+        // Nothing plugged into the second connector
+        assert_eq!(ports[1].describe(None), None);
+        assert!(discover_typec_ports(&root).is_empty());
+    }
+}
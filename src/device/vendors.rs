@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::device::UsbDevice;
+
+/// Bandwidth of every tracked device from one vendor
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VendorTotals {
+    pub vendor_id: Option<u16>,
+    pub name: String,
+    pub devices: usize,
+    pub rx_bps: f64,
+    pub tx_bps: f64,
+    pub total_rx_bytes: u64,
+    pub total_tx_bytes: u64,
+}
+
+impl VendorTotals {
+    pub fn current_bps(&self) -> f64 {
+        self.rx_bps + self.tx_bps
+    }
+    
+    pub fn total_bytes(&self) -> u64 {
+        self.total_rx_bytes.saturating_add(self.total_tx_bytes)
+    }
+}
+
+/// Grouping key: the vendor id when known, otherwise the vendor name
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum VendorKey {
+    Id(u16),
+    Name(String),
+}
+
+/// Aggregate device bandwidth by vendor, busiest vendor first
+/// Devices without a vendor id are grouped by name, and devices with neither
+/// end up together under "Unknown".
+pub fn aggregate_by_vendor<'a>(devices: impl IntoIterator<Item = &'a UsbDevice>) -> Vec<VendorTotals> {
+    let mut groups: HashMap<VendorKey, VendorTotals> = HashMap::new();
+    
+    for device in devices {
+        let name = device.vendor.clone()
+            .or_else(|| device.vendor_id.map(|id| format!("{:04x}", id)))
+            .unwrap_or_else(|| "Unknown".to_string());
+        let key = match device.vendor_id {
+            Some(id) => VendorKey::Id(id),
+            None => VendorKey::Name(name.clone()),
+        };
+        
+        let totals = groups.entry(key).or_insert_with(|| VendorTotals {
+            vendor_id: device.vendor_id,
+            name,
+            devices: 0,
+            rx_bps: 0.0,
+            tx_bps: 0.0,
+            total_rx_bytes: 0,
+            total_tx_bytes: 0,
+        });
+        
+        let stats = &device.bandwidth_stats;
+        totals.devices += 1;
+        totals.rx_bps += stats.rx_bps;
+        totals.tx_bps += stats.tx_bps;
+        totals.total_rx_bytes = totals.total_rx_bytes.saturating_add(stats.total_rx_bytes);
+        totals.total_tx_bytes = totals.total_tx_bytes.saturating_add(stats.total_tx_bytes);
+    }
+    
+    let mut vendors: Vec<VendorTotals> = groups.into_values().collect();
+    vendors.sort_by(|a, b| {
+        b.current_bps().partial_cmp(&a.current_bps()).unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| b.total_bytes().cmp(&a.total_bytes()))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    vendors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn device(device_id: u8, vendor_id: Option<u16>, vendor: Option<&str>, rx_bps: f64) -> UsbDevice {
+        let mut device = UsbDevice::new(1, device_id);
+        device.vendor_id = vendor_id;
+        device.vendor = vendor.map(str::to_string);
+        device.bandwidth_stats.rx_bps = rx_bps;
+        device.bandwidth_stats.total_rx_bytes = rx_bps as u64;
+        device
+    }
+    
+    #[test]
+    fn test_groups_by_vendor_id_then_name() {
+        let devices = vec![
+            device(2, Some(0x05e0), Some("Zebra"), 1_000.0),
+            device(3, Some(0x05e0), Some("Zebra"), 3_000.0),
+            device(4, Some(0x046d), Some("Logitech"), 50_000.0),
+            device(5, None, Some("Acme"), 10.0),
+            device(6, None, None, 5.0),
+            device(7, None, None, 5.0),
+        ];
+        
+        let vendors = aggregate_by_vendor(&devices);
+        let summary: Vec<(&str, usize, f64)> = vendors.iter()
+            .map(|vendor| (vendor.name.as_str(), vendor.devices, vendor.current_bps()))
+            .collect();
+        
+        assert_eq!(summary, vec![
+            ("Logitech", 1, 50_000.0),
+            ("Zebra", 2, 4_000.0),
+            ("Acme", 1, 10.0),
+            ("Unknown", 2, 10.0),
+        ]);
+        assert_eq!(vendors[1].vendor_id, Some(0x05e0));
+        assert_eq!(vendors[1].total_bytes(), 4_000);
+    }
+}
@@ -0,0 +1,191 @@
+use anyhow::{anyhow, Result};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::device::{format_bandwidth, DeviceKey};
+use crate::snapshot::{DeviceSnapshot, Message};
+
+/// Whether a device is in both snapshots or only one of them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffStatus {
+    Changed,
+    Appeared,
+    Disappeared,
+}
+
+/// Per-device change between two snapshots; a device missing from one side
+/// is compared against zeros
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceDiff {
+    pub key: DeviceKey,
+    pub label: String,
+    pub status: DiffStatus,
+    pub rx_bps_delta: f64,
+    pub tx_bps_delta: f64,
+    pub rx_bytes_delta: i128,
+    pub tx_bytes_delta: i128,
+    pub errors_delta: i128,
+}
+
+impl DeviceDiff {
+    pub fn bandwidth_delta(&self) -> f64 {
+        self.rx_bps_delta + self.tx_bps_delta
+    }
+    
+    pub fn bytes_delta(&self) -> i128 {
+        self.rx_bytes_delta + self.tx_bytes_delta
+    }
+}
+
+/// Read the devices from a JSON snapshot (a `snapshot` message as `/stream` and the control socket send)
+pub fn load_snapshot(path: &Path) -> Result<Vec<DeviceSnapshot>> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| anyhow!("Failed to read snapshot {}: {}", path.display(), e))?;
+    
+    match serde_json::from_str(&contents) {
+        Ok(Message::Snapshot { devices, .. }) => Ok(devices),
+        Err(e) => Err(anyhow!("Failed to parse snapshot {}: {}", path.display(), e)),
+    }
+}
+
+/// Compare two snapshots device by device, largest bandwidth change first
+pub fn diff_snapshots(before: &[DeviceSnapshot], after: &[DeviceSnapshot]) -> Vec<DeviceDiff> {
+    let mut pairs: BTreeMap<DeviceKey, (Option<&DeviceSnapshot>, Option<&DeviceSnapshot>)> = BTreeMap::new();
+    for device in before {
+        pairs.entry(DeviceKey(device.bus_id, device.device_id)).or_default().0 = Some(device);
+    }
+    for device in after {
+        pairs.entry(DeviceKey(device.bus_id, device.device_id)).or_default().1 = Some(device);
+    }
+    
+    let mut diffs: Vec<DeviceDiff> = pairs.into_iter()
+        .filter_map(|(key, (before, after))| {
+            let status = match (before, after) {
+                (Some(_), Some(_)) => DiffStatus::Changed,
+                (None, Some(_)) => DiffStatus::Appeared,
+                (Some(_), None) => DiffStatus::Disappeared,
+                (None, None) => return None,
+            };
+            let value = |snapshot: Option<&DeviceSnapshot>, field: fn(&DeviceSnapshot) -> f64| {
+                snapshot.map_or(0.0, field)
+            };
+            let count = |snapshot: Option<&DeviceSnapshot>, field: fn(&DeviceSnapshot) -> u64| {
+                snapshot.map_or(0, field) as i128
+            };
+            
+            Some(DeviceDiff {
+                key,
+                label: label(after.or(before)?),
+                status,
+                rx_bps_delta: value(after, |d| d.rx_bps) - value(before, |d| d.rx_bps),
+                tx_bps_delta: value(after, |d| d.tx_bps) - value(before, |d| d.tx_bps),
+                rx_bytes_delta: count(after, |d| d.total_rx_bytes) - count(before, |d| d.total_rx_bytes),
+                tx_bytes_delta: count(after, |d| d.total_tx_bytes) - count(before, |d| d.total_tx_bytes),
+                errors_delta: count(after, |d| d.errors) - count(before, |d| d.errors),
+            })
+        })
+        .collect();
+    
+    diffs.sort_by(|a, b| {
+        b.bandwidth_delta().abs()
+            .partial_cmp(&a.bandwidth_delta().abs())
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| b.bytes_delta().abs().cmp(&a.bytes_delta().abs()))
+            .then_with(|| a.key.cmp(&b.key))
+    });
+    diffs
+}
+
+fn label(device: &DeviceSnapshot) -> String {
+    [&device.vendor, &device.product].iter()
+        .filter_map(|s| s.as_deref())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn signed_bandwidth(delta: f64) -> String {
+    let sign = if delta < 0.0 { "-" } else { "+" };
+    format!("{}{}", sign, format_bandwidth(delta.abs()))
+}
+
+pub fn print_diff(diffs: &[DeviceDiff]) {
+    if diffs.is_empty() {
+        println!("No devices in either snapshot");
+        return;
+    }
+    
+    println!(
+        "{:<8} {:<12} {:>14} {:>14} {:>16} {:>16} {:>8}  Name",
+        "Device", "Status", "Δ RX", "Δ TX", "Δ RX bytes", "Δ TX bytes", "Δ errors",
+    );
+    for diff in diffs {
+        let status = match diff.status {
+            DiffStatus::Changed => "",
+            DiffStatus::Appeared => "+ appeared",
+            DiffStatus::Disappeared => "- removed",
+        };
+        println!(
+            "{:<8} {:<12} {:>14} {:>14} {:>+16} {:>+16} {:>+8}  {}",
+            diff.key.to_string(),
+            status,
+            signed_bandwidth(diff.rx_bps_delta),
+            signed_bandwidth(diff.tx_bps_delta),
+            diff.rx_bytes_delta,
+            diff.tx_bytes_delta,
+            diff.errors_delta,
+            diff.label,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn snapshot(device_id: u8, rx_bps: f64, total_rx_bytes: u64, errors: u64) -> DeviceSnapshot {
+        DeviceSnapshot {
+            bus_id: 1,
+            device_id,
+            vendor: Some("ACME".to_string()),
+            product: Some(format!("Device {}", device_id)),
+            rx_bps,
+            tx_bps: 0.0,
+            total_rx_bytes,
+            total_tx_bytes: 0,
+            errors,
+        }
+    }
+    
+    #[test]
+    fn test_diff_sorted_by_largest_change() {
+        let before = vec![snapshot(2, 1000.0, 10_000, 0), snapshot(3, 500.0, 5_000, 0), snapshot(4, 50.0, 0, 0)];
+        let after = vec![snapshot(2, 1100.0, 12_000, 3), snapshot(3, 100.0, 6_000, 0), snapshot(5, 200.0, 800, 0)];
+        
+        let diffs = diff_snapshots(&before, &after);
+        let order: Vec<(u8, DiffStatus)> = diffs.iter().map(|d| (d.key.device_id(), d.status)).collect();
+        assert_eq!(order, vec![
+            (3, DiffStatus::Changed),
+            (5, DiffStatus::Appeared),
+            (2, DiffStatus::Changed),
+            (4, DiffStatus::Disappeared),
+        ]);
+        
+        assert_eq!(diffs[0].rx_bps_delta, -400.0);
+        assert_eq!(diffs[2].rx_bytes_delta, 2_000);
+        assert_eq!(diffs[2].errors_delta, 3);
+        assert_eq!(diffs[3].bandwidth_delta(), -50.0);
+        assert_eq!(diffs[1].label, "ACME Device 5");
+    }
+    
+    #[test]
+    fn test_load_rejects_non_snapshot() {
+        let path = std::env::temp_dir().join(format!("usbtop-ng-diff-{}.json", std::process::id()));
+        fs::write(&path, r#"{"type":"hello","host":"a","version":"1","timestamp_ms":0}"#).unwrap();
+        let result = load_snapshot(&path);
+        fs::remove_file(&path).unwrap();
+        
+        assert!(result.unwrap_err().to_string().contains("Failed to parse snapshot"));
+    }
+}
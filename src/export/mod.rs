@@ -0,0 +1,146 @@
+use anyhow::{Result, anyhow};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Writer for CSV/capture outputs
+/// Paths ending in `.gz` are transparently gzip-compressed. Compressed
+/// output can't be tailed live; call `finish` on shutdown so the gzip
+/// trailer is written and the stream isn't truncated.
+pub enum OutputWriter {
+    Plain(BufWriter<File>),
+    Gzip(GzEncoder<BufWriter<File>>),
+}
+
+impl OutputWriter {
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::create(path)
+            .map_err(|e| anyhow!("Failed to create {}: {}", path.display(), e))?;
+        let writer = BufWriter::new(file);
+        
+        if Self::is_compressed_path(path) {
+            Ok(OutputWriter::Gzip(GzEncoder::new(writer, Compression::default())))
+        } else {
+            Ok(OutputWriter::Plain(writer))
+        }
+    }
+    
+    pub fn is_compressed_path(path: &Path) -> bool {
+        path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("gz"))
+    }
+    
+    /// Flush everything and, for gzip, write the stream trailer
+    pub fn finish(self) -> Result<()> {
+        match self {
+            OutputWriter::Plain(mut writer) => writer.flush()?,
+            OutputWriter::Gzip(encoder) => encoder.finish()?.flush()?,
+        }
+        Ok(())
+    }
+}
+
+impl Write for OutputWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            OutputWriter::Plain(writer) => writer.write(buf),
+            OutputWriter::Gzip(encoder) => encoder.write(buf),
+        }
+    }
+    
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            OutputWriter::Plain(writer) => writer.flush(),
+            OutputWriter::Gzip(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Size of a tar header and of the blocks file contents are padded to
+const TAR_BLOCK: usize = 512;
+
+/// Append a regular file to a tar stream in the ustar layout every `tar`
+/// reads; `name` may contain directories but must fit the 100-byte field
+pub fn write_tar_entry<W: Write>(out: &mut W, name: &str, data: &[u8], mtime: u64) -> io::Result<()> {
+    if name.len() > 100 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("tar entry name too long: {}", name)));
+    }
+    let mut header = [0u8; TAR_BLOCK];
+    let mut field = |offset: usize, value: &[u8]| header[offset..offset + value.len()].copy_from_slice(value);
+    field(0, name.as_bytes());
+    field(100, b"0000644\0");
+    field(108, b"0000000\0");
+    field(116, b"0000000\0");
+    field(124, format!("{:011o}\0", data.len()).as_bytes());
+    field(136, format!("{:011o}\0", mtime).as_bytes());
+    // The checksum is taken with its own field read as spaces
+    field(148, b"        ");
+    field(156, b"0");
+    field(257, b"ustar\0");
+    field(263, b"00");
+    let checksum: u32 = header.iter().map(|&byte| byte as u32).sum();
+    header[148..156].copy_from_slice(format!("{:06o}\0 ", checksum).as_bytes());
+    
+    out.write_all(&header)?;
+    out.write_all(data)?;
+    let padding = (TAR_BLOCK - data.len() % TAR_BLOCK) % TAR_BLOCK;
+    out.write_all(&[0u8; TAR_BLOCK][..padding])
+}
+
+/// End a tar stream with the two empty blocks readers expect
+pub fn finish_tar<W: Write>(out: &mut W) -> io::Result<()> {
+    out.write_all(&[0u8; 2 * TAR_BLOCK])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+    
+    #[test]
+    fn test_gz_extension_compresses() {
+        let path = std::env::temp_dir().join(format!("usbtop-ng-test-{}.csv.gz", std::process::id()));
+        
+        let mut writer = OutputWriter::create(&path).unwrap();
+        assert!(matches!(writer, OutputWriter::Gzip(_)));
+        writeln!(writer, "device,rx_bps,tx_bps").unwrap();
+        writer.finish().unwrap();
+        
+        let mut contents = String::new();
+        GzDecoder::new(File::open(&path).unwrap()).read_to_string(&mut contents).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        
+        assert_eq!(contents, "device,rx_bps,tx_bps\n");
+    }
+    
+    #[test]
+    fn test_tar_entry_layout() {
+        let mut archive = Vec::new();
+        write_tar_entry(&mut archive, "repro/summary.txt", b"hello\n", 1_700_000_000).unwrap();
+        finish_tar(&mut archive).unwrap();
+        
+        assert_eq!(archive.len(), 4 * TAR_BLOCK);
+        let header = &archive[..TAR_BLOCK];
+        assert!(header.starts_with(b"repro/summary.txt\0"));
+        assert_eq!(&header[124..136], b"00000000006\0");
+        assert_eq!(&header[257..263], b"ustar\0");
+        let stored = u32::from_str_radix(std::str::from_utf8(&header[148..154]).unwrap(), 8).unwrap();
+        let computed: u32 = header.iter().enumerate()
+            .map(|(i, &byte)| if (148..156).contains(&i) { b' ' as u32 } else { byte as u32 })
+            .sum();
+        assert_eq!(stored, computed);
+        assert_eq!(&archive[TAR_BLOCK..TAR_BLOCK + 6], b"hello\n");
+        assert!(archive[TAR_BLOCK + 6..].iter().all(|&byte| byte == 0));
+        
+        assert!(write_tar_entry(&mut Vec::new(), &"x".repeat(101), b"", 0).is_err());
+    }
+    
+    #[test]
+    fn test_plain_extension() {
+        assert!(!OutputWriter::is_compressed_path(Path::new("capture.csv")));
+        assert!(OutputWriter::is_compressed_path(Path::new("capture.csv.GZ")));
+    }
+}
@@ -0,0 +1,402 @@
+use anyhow::{Result, anyhow};
+use chrono::Utc;
+use log::{debug, info};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::process::Stdio;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader, ReadBuf};
+use tokio::process::{Child, ChildStdout, Command};
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+use crate::monitor::ReaderTasks;
+use crate::snapshot::Message;
+use crate::ui::UsbTopApp;
+use crate::usbmon::parser::UsbPacket;
+
+/// Run on each host by `--connect` unless `--agent-command` says otherwise
+pub const DEFAULT_AGENT_COMMAND: &str = "usbtop-ng --agent";
+
+/// Upper bound on a single frame; anything larger is treated as a corrupt stream
+pub const MAX_FRAME_LEN: usize = 4 * 1024 * 1024;
+
+/// Wait before the first reconnect; doubled after each failed attempt
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Clock offset samples kept per host
+const CLOCK_SAMPLES: usize = 16;
+
+/// Write `message` as one frame: a 4-byte big-endian length, then the JSON body
+pub async fn write_frame<W: AsyncWrite + Unpin>(writer: &mut W, message: &Message) -> Result<()> {
+    let body = serde_json::to_vec(message)?;
+    if body.len() > MAX_FRAME_LEN {
+        return Err(anyhow!("Frame too large: {} bytes", body.len()));
+    }
+    writer.write_all(&(body.len() as u32).to_be_bytes()).await?;
+    writer.write_all(&body).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Read the next frame; `None` when the stream ends cleanly between frames
+pub async fn read_frame<R: AsyncRead + Unpin>(reader: &mut R) -> Result<Option<Message>> {
+    let mut len = [0u8; 4];
+    if reader.read(&mut len[..1]).await? == 0 {
+        return Ok(None);
+    }
+    reader.read_exact(&mut len[1..]).await?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(anyhow!("Frame length {} exceeds the {} byte limit", len, MAX_FRAME_LEN));
+    }
+    let mut body = vec![0u8; len];
+    reader.read_exact(&mut body).await?;
+    Ok(Some(serde_json::from_slice(&body)?))
+}
+
+/// Capture headlessly and write a snapshot frame to stdout every refresh
+/// The reader on the other end is a `--connect` client, through ssh. Runs
+/// until the client goes away or the packet source closes.
+pub async fn run_agent(
+    mut app: UsbTopApp,
+    mut packets: UnboundedReceiver<UsbPacket>,
+    tasks: ReaderTasks,
+) -> Result<()> {
+    let mut stdout = tokio::io::stdout();
+    let mut sample = tokio::time::interval(app.sample_interval);
+    let mut refresh = tokio::time::interval(app.refresh_rate);
+    
+    loop {
+        tokio::select! {
+            packet = packets.recv() => match packet {
+                Some(packet) => app.process_packet(&packet),
+                None => {
+                    info!("Packet source closed; stopping the agent");
+                    break;
+                }
+            },
+            _ = sample.tick() => app.update_bandwidth_history(),
+            _ = refresh.tick() => {
+                if let Err(e) = write_frame(&mut stdout, &Message::snapshot(app.devices.values())).await {
+                    info!("Client went away: {}", e);
+                    break;
+                }
+            }
+        }
+    }
+    
+    tasks.shutdown().await;
+    app.save_state();
+    app.flush_trigger();
+    Ok(())
+}
+
+/// How far a host's clock is from ours
+/// Each sample is the local receive time minus the snapshot's own timestamp:
+/// the skew plus however long that frame was in transit. The smallest recent
+/// sample is the one least inflated by transit, so it stands for the skew.
+#[derive(Debug, Clone, Default)]
+pub struct ClockOffset {
+    samples: VecDeque<i64>,
+}
+
+impl ClockOffset {
+    pub fn record(&mut self, local_ms: i64, remote_ms: i64) {
+        if self.samples.len() == CLOCK_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(local_ms - remote_ms);
+    }
+    
+    /// Milliseconds to add to a remote timestamp to place it on the local clock
+    pub fn offset_ms(&self) -> Option<i64> {
+        self.samples.iter().min().copied()
+    }
+}
+
+/// Exponential reconnect backoff for hosts that went away
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    current: Duration,
+    initial: Duration,
+    max: Duration,
+}
+
+impl Backoff {
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self { current: initial, initial, max }
+    }
+    
+    /// Delay before the next attempt; doubles up to `max`
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self.current;
+        self.current = (self.current * 2).min(self.max);
+        delay
+    }
+    
+    /// Call once a connection has delivered something
+    pub fn reset(&mut self) {
+        self.current = self.initial;
+    }
+}
+
+/// What happened on the connection to one host
+#[derive(Debug, Clone, PartialEq)]
+pub enum HostEvent {
+    Connected,
+    /// A snapshot, with the local time it arrived for the clock offset
+    Snapshot { message: Message, received_ms: i64 },
+    /// The connection failed or ended; the next attempt is in `retry_in`
+    Disconnected { reason: String, retry_in: Duration },
+}
+
+/// A connection to one host's agent
+trait AgentLink: AsyncRead + Unpin + Send {
+    /// Close the connection once the stream has ended, and say why it did
+    /// when the other end left a reason, e.g. an ssh error
+    fn close(self) -> impl Future<Output = Option<String>> + Send;
+}
+
+/// The agent's stdout, through ssh
+struct SshLink {
+    child: Child,
+    stdout: ChildStdout,
+    /// Drains stderr so a chatty agent can't stall on a full pipe, keeping
+    /// the last line as the reason the connection ended
+    last_error: JoinHandle<Option<String>>,
+}
+
+impl AsyncRead for SshLink {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stdout).poll_read(cx, buf)
+    }
+}
+
+impl AgentLink for SshLink {
+    async fn close(mut self) -> Option<String> {
+        let _ = self.child.kill().await;
+        self.last_error.await.ok().flatten()
+    }
+}
+
+/// Arguments for running the agent on `host` over ssh
+/// BatchMode keeps ssh from prompting for a password under the TUI, the
+/// keepalive notices a host that vanished without closing the connection,
+/// and `--` keeps a host name starting with `-` from reading as an option.
+fn ssh_args(host: &str, agent_command: &str) -> Vec<String> {
+    ["-T", "-o", "BatchMode=yes", "-o", "ServerAliveInterval=5", "--", host, agent_command]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect()
+}
+
+async fn connect_ssh(args: Vec<String>) -> Result<SshLink> {
+    let mut child = Command::new("ssh")
+        .args(&args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| anyhow!("Failed to run ssh: {}", e))?;
+    let stdout = child.stdout.take().ok_or_else(|| anyhow!("ssh has no stdout"))?;
+    let stderr = child.stderr.take().ok_or_else(|| anyhow!("ssh has no stderr"))?;
+    let last_error = tokio::spawn(async move {
+        let mut lines = BufReader::new(stderr).lines();
+        let mut last = None;
+        while let Ok(Some(line)) = lines.next_line().await {
+            if !line.trim().is_empty() {
+                last = Some(line.trim().to_string());
+            }
+        }
+        last
+    });
+    Ok(SshLink { child, stdout, last_error })
+}
+
+/// Run the agent on each host over ssh, reporting events by host index
+/// Every host is retried with backoff whenever its connection fails or
+/// ends, until shutdown.
+pub fn spawn_clients(hosts: &[String], agent_command: &str) -> (UnboundedReceiver<(usize, HostEvent)>, ReaderTasks) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let mut tasks = ReaderTasks::new();
+    
+    for (index, host) in hosts.iter().enumerate() {
+        let args = ssh_args(host, agent_command);
+        let tx = tx.clone();
+        tasks.push(tokio::spawn(follow_host(
+            host.clone(),
+            move || connect_ssh(args.clone()),
+            Backoff::new(INITIAL_BACKOFF, MAX_BACKOFF),
+            move |event| {
+                let _ = tx.send((index, event));
+            },
+            tasks.subscribe(),
+        )));
+    }
+    
+    (rx, tasks)
+}
+
+/// Keep a connection to one host's agent, reporting what arrives
+/// A connection that fails or ends is retried after the backoff, which
+/// starts over once a connection has delivered a snapshot.
+async fn follow_host<C, F, L>(
+    host: String,
+    mut connect: C,
+    mut backoff: Backoff,
+    report: impl Fn(HostEvent),
+    mut shutdown: watch::Receiver<bool>,
+) where
+    C: FnMut() -> F,
+    F: Future<Output = Result<L>>,
+    L: AgentLink,
+{
+    while !*shutdown.borrow() {
+        let reason = match connect().await {
+            Ok(mut link) => {
+                info!("Connected to {}", host);
+                report(HostEvent::Connected);
+                let ended = loop {
+                    let frame = tokio::select! {
+                        _ = shutdown.changed() => return,
+                        frame = read_frame(&mut link) => frame,
+                    };
+                    match frame {
+                        Ok(Some(message)) => {
+                            backoff.reset();
+                            report(HostEvent::Snapshot { message, received_ms: Utc::now().timestamp_millis() });
+                        }
+                        Ok(None) => break None,
+                        Err(e) => break Some(e.to_string()),
+                    }
+                };
+                // ssh's own message says more than the stream ending
+                link.close().await.or(ended).unwrap_or_else(|| "connection closed".to_string())
+            }
+            Err(e) => e.to_string(),
+        };
+        
+        let retry_in = backoff.next_delay();
+        debug!("Lost {}: {}; retrying in {:?}", host, reason, retry_in);
+        report(HostEvent::Disconnected { reason, retry_in });
+        tokio::select! {
+            _ = shutdown.changed() => break,
+            _ = tokio::time::sleep(retry_in) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::sync::{Arc, Mutex};
+    
+    impl AgentLink for Cursor<Vec<u8>> {
+        async fn close(self) -> Option<String> {
+            None
+        }
+    }
+    
+    fn snapshot(timestamp_ms: i64) -> Message {
+        Message::Snapshot { timestamp_ms, devices: Vec::new(), vendors: Vec::new() }
+    }
+    
+    #[tokio::test]
+    async fn test_frames_round_trip() {
+        let mut stream = Vec::new();
+        write_frame(&mut stream, &snapshot(1)).await.unwrap();
+        write_frame(&mut stream, &snapshot(2)).await.unwrap();
+        assert_eq!(u32::from_be_bytes(stream[..4].try_into().unwrap()) as usize, serde_json::to_vec(&snapshot(1)).unwrap().len());
+        
+        let mut reader = Cursor::new(stream.clone());
+        assert_eq!(read_frame(&mut reader).await.unwrap(), Some(snapshot(1)));
+        assert_eq!(read_frame(&mut reader).await.unwrap(), Some(snapshot(2)));
+        assert_eq!(read_frame(&mut reader).await.unwrap(), None);
+        
+        // Cut off inside a frame, or claiming more than the limit
+        assert!(read_frame(&mut Cursor::new(stream[..10].to_vec())).await.is_err());
+        assert!(read_frame(&mut Cursor::new(u32::MAX.to_be_bytes().to_vec())).await.is_err());
+    }
+    
+    #[test]
+    fn test_clock_offset_takes_the_least_delayed_sample() {
+        let mut clock = ClockOffset::default();
+        assert_eq!(clock.offset_ms(), None);
+        // The host runs 5 s behind; frames take 30-200 ms to arrive
+        clock.record(10_200, 5_000);
+        clock.record(11_030, 6_000);
+        clock.record(12_100, 7_000);
+        assert_eq!(clock.offset_ms(), Some(5_030));
+        
+        // Old samples age out, e.g. after the host's clock was corrected
+        for second in 0..CLOCK_SAMPLES as i64 {
+            clock.record(20_000 + second * 1_000 + 40, 20_000 + second * 1_000);
+        }
+        assert_eq!(clock.offset_ms(), Some(40));
+    }
+    
+    #[test]
+    fn test_backoff() {
+        let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(4));
+        assert_eq!(backoff.next_delay(), Duration::from_secs(1));
+        assert_eq!(backoff.next_delay(), Duration::from_secs(2));
+        assert_eq!(backoff.next_delay(), Duration::from_secs(4));
+        assert_eq!(backoff.next_delay(), Duration::from_secs(4));
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), Duration::from_secs(1));
+    }
+    
+    #[test]
+    fn test_ssh_args_run_the_agent_without_prompting() {
+        assert_eq!(
+            ssh_args("root@rack1", DEFAULT_AGENT_COMMAND),
+            vec!["-T", "-o", "BatchMode=yes", "-o", "ServerAliveInterval=5", "--", "root@rack1", "usbtop-ng --agent"],
+        );
+    }
+    
+    #[tokio::test]
+    async fn test_reconnects_with_backoff() {
+        let mut frames = Vec::new();
+        write_frame(&mut frames, &snapshot(7)).await.unwrap();
+        
+        // Refused, then one snapshot before the agent exits, then refused twice
+        let mut attempts = vec![Err("refused"), Ok(frames), Err("refused"), Err("refused")].into_iter();
+        let connect = move || {
+            let attempt = attempts.next().unwrap_or(Err("refused"));
+            async move { attempt.map(Cursor::new).map_err(|e| anyhow!(e)) }
+        };
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let (shutdown_tx, shutdown) = watch::channel(false);
+        let report = {
+            let events = events.clone();
+            move |event: HostEvent| {
+                let mut events = events.lock().unwrap();
+                events.push(event);
+                if events.len() == 6 {
+                    let _ = shutdown_tx.send(true);
+                }
+            }
+        };
+        let ms = Duration::from_millis;
+        follow_host("rack1".to_string(), connect, Backoff::new(ms(1), ms(8)), report, shutdown).await;
+        
+        let events = events.lock().unwrap();
+        let disconnected = |retry_in| HostEvent::Disconnected { reason: "refused".to_string(), retry_in };
+        assert_eq!(events[0], disconnected(ms(1)));
+        assert_eq!(events[1], HostEvent::Connected);
+        assert!(matches!(&events[2], HostEvent::Snapshot { message, .. } if *message == snapshot(7)));
+        // The snapshot started the backoff over
+        assert_eq!(events[3], HostEvent::Disconnected { reason: "connection closed".to_string(), retry_in: ms(1) });
+        assert_eq!(events[4], disconnected(ms(2)));
+        assert_eq!(events[5], disconnected(ms(4)));
+    }
+}
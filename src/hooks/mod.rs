@@ -0,0 +1,340 @@
+use log::{debug, warn};
+use std::collections::HashMap;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::{HookConfig, HookEvent};
+use crate::device::{DeviceKey, UsbDevice};
+
+impl HookEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HookEvent::DeviceConnected => "device_connected",
+            HookEvent::DeviceDisconnected => "device_disconnected",
+            HookEvent::ThresholdExceeded => "threshold_exceeded",
+        }
+    }
+}
+
+/// A configured hook with its command already split into arguments
+#[derive(Debug, Clone)]
+struct Hook {
+    event: HookEvent,
+    argv: Vec<String>,
+    /// Only fire for this (vendor id, product id)
+    device: Option<(u16, u16)>,
+    threshold_bps: f64,
+    cooldown: Duration,
+}
+
+impl Hook {
+    fn matches(&self, device: &UsbDevice) -> bool {
+        self.device.is_none_or(|(vendor_id, product_id)| {
+            device.vendor_id == Some(vendor_id) && device.product_id == Some(product_id)
+        })
+    }
+}
+
+/// Runs user commands on device events, e.g. notify-send or curl to a webhook
+/// Placeholders such as `{product}` are substituted into each argument after
+/// the command is split, and the command is executed directly rather than
+/// through a shell, so descriptor strings can't inject shell syntax. The same
+/// values are exported as `USBTOP_*` environment variables for hooks that do
+/// wrap themselves in `sh -c`. Each hook fires at most once per cooldown per device.
+#[derive(Debug, Clone, Default)]
+pub struct HookRunner {
+    hooks: Vec<Hook>,
+    last_fired: HashMap<(usize, DeviceKey), Instant>,
+}
+
+impl HookRunner {
+    /// Build from the `[[hooks]]` sections, with warnings for entries that are skipped
+    pub fn from_config(configs: &[HookConfig]) -> (Self, Vec<String>) {
+        let mut warnings = Vec::new();
+        let mut hooks = Vec::new();
+        
+        for config in configs {
+            let Some(argv) = split_command(&config.command).filter(|argv| !argv.is_empty()) else {
+                warnings.push(format!("Ignoring {} hook: can't parse command '{}'", config.event.name(), config.command));
+                continue;
+            };
+            let device = match config.device.as_deref().map(parse_vid_pid) {
+                None => None,
+                Some(Some(ids)) => Some(ids),
+                Some(None) => {
+                    warnings.push(format!(
+                        "Ignoring {} hook: device '{}' is not VID:PID in hex",
+                        config.event.name(),
+                        config.device.as_deref().unwrap_or_default(),
+                    ));
+                    continue;
+                }
+            };
+            if config.event == HookEvent::ThresholdExceeded && config.threshold_bps <= 0.0 {
+                warnings.push("Ignoring threshold_exceeded hook without a positive threshold_bps".to_string());
+                continue;
+            }
+            
+            hooks.push(Hook {
+                event: config.event,
+                argv,
+                device,
+                threshold_bps: config.threshold_bps,
+                cooldown: Duration::from_secs(config.cooldown_secs),
+            });
+        }
+        
+        (Self { hooks, last_fired: HashMap::new() }, warnings)
+    }
+    
+    /// Fire hooks for a connect or disconnect
+    pub fn device_event(&mut self, event: HookEvent, device: &UsbDevice, now: Instant) {
+        for command in self.prepare(event, device, now) {
+            spawn(command);
+        }
+    }
+    
+    /// Fire `threshold_exceeded` hooks for devices above their threshold
+    /// Called once per refresh; the cooldown limits how often a device that
+    /// stays above the threshold triggers again
+    pub fn check_thresholds<'a>(&mut self, devices: impl IntoIterator<Item = &'a UsbDevice>, now: Instant) {
+        if !self.hooks.iter().any(|hook| hook.event == HookEvent::ThresholdExceeded) {
+            return;
+        }
+        for device in devices {
+            for command in self.prepare(HookEvent::ThresholdExceeded, device, now) {
+                spawn(command);
+            }
+        }
+    }
+    
+    /// Commands due for `event` on `device`, recording them against the cooldown
+    fn prepare(&mut self, event: HookEvent, device: &UsbDevice, now: Instant) -> Vec<Command> {
+        let values = placeholders(event, device);
+        let mut commands = Vec::new();
+        
+        for (index, hook) in self.hooks.iter().enumerate() {
+            if hook.event != event || !hook.matches(device) {
+                continue;
+            }
+            if event == HookEvent::ThresholdExceeded && device.bandwidth_stats.current_bps <= hook.threshold_bps {
+                continue;
+            }
+            
+            let key = (index, device.key());
+            if self.last_fired.get(&key).is_some_and(|last| now.duration_since(*last) < hook.cooldown) {
+                continue;
+            }
+            self.last_fired.insert(key, now);
+            
+            let argv: Vec<String> = hook.argv.iter().map(|arg| expand(arg, &values)).collect();
+            let mut command = Command::new(&argv[0]);
+            command.args(&argv[1..]);
+            for (name, value) in &values {
+                command.env(format!("USBTOP_{}", name.to_ascii_uppercase()), value);
+            }
+            commands.push(command);
+        }
+        
+        commands
+    }
+}
+
+/// Start a hook without waiting for it; a detached thread reaps the child
+fn spawn(mut command: Command) {
+    command.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null());
+    match command.spawn() {
+        Ok(mut child) => {
+            debug!("Started hook {:?}", command.get_program());
+            thread::spawn(move || {
+                let _ = child.wait();
+            });
+        }
+        Err(e) => warn!("Failed to run hook {:?}: {}", command.get_program(), e),
+    }
+}
+
+/// Values available to a hook as `{name}` placeholders and `USBTOP_NAME` variables
+fn placeholders(event: HookEvent, device: &UsbDevice) -> Vec<(&'static str, String)> {
+    vec![
+        ("event", event.name().to_string()),
+        ("device", device.key().to_string()),
+        ("bus", device.bus_id.to_string()),
+        ("vendor", device.vendor.clone().unwrap_or_else(|| "Unknown".to_string())),
+        ("product", device.product.clone().unwrap_or_else(|| "Unknown".to_string())),
+        ("vendor_id", device.vendor_id.map(|id| format!("{:04x}", id)).unwrap_or_default()),
+        ("product_id", device.product_id.map(|id| format!("{:04x}", id)).unwrap_or_default()),
+        ("serial", device.serial.clone().unwrap_or_default()),
+        ("bps", format!("{:.0}", device.bandwidth_stats.current_bps)),
+    ]
+}
+
+/// Replace `{name}` placeholders in one pass, so substituted text is never
+/// expanded again; unknown names are left as written
+fn expand(arg: &str, values: &[(&'static str, String)]) -> String {
+    let mut expanded = String::new();
+    let mut rest = arg;
+    while let Some(start) = rest.find('{') {
+        expanded.push_str(&rest[..start]);
+        let tail = &rest[start..];
+        let value = tail.find('}').and_then(|end| {
+            let name = &tail[1..end];
+            values.iter().find(|(key, _)| *key == name).map(|(_, value)| (value, end))
+        });
+        match value {
+            Some((value, end)) => {
+                expanded.push_str(value);
+                rest = &tail[end + 1..];
+            }
+            None => {
+                expanded.push('{');
+                rest = &tail[1..];
+            }
+        }
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+/// Split a command line into arguments on whitespace, honoring single and
+/// double quotes and backslash escapes outside single quotes
+/// Returns None for an unterminated quote.
+pub fn split_command(command: &str) -> Option<Vec<String>> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut quote: Option<char> = None;
+    let mut chars = command.chars();
+    
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') | (None, '\\') => {
+                current.push(chars.next()?);
+                in_arg = true;
+            }
+            (Some(_), c) => current.push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                in_arg = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            (None, c) => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    
+    if quote.is_some() {
+        return None;
+    }
+    if in_arg {
+        args.push(current);
+    }
+    Some(args)
+}
+
+/// Parse "VID:PID" in hex, e.g. "0781:5581"
+pub fn parse_vid_pid(value: &str) -> Option<(u16, u16)> {
+    let (vendor_id, product_id) = value.split_once(':')?;
+    Some((
+        u16::from_str_radix(vendor_id.trim(), 16).ok()?,
+        u16::from_str_radix(product_id.trim(), 16).ok()?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn hook(event: HookEvent, command: &str) -> HookConfig {
+        HookConfig {
+            event,
+            command: command.to_string(),
+            device: None,
+            threshold_bps: 0.0,
+            cooldown_secs: 60,
+        }
+    }
+    
+    fn args(command: &Command) -> Vec<String> {
+        command.get_args().map(|arg| arg.to_string_lossy().into_owned()).collect()
+    }
+    
+    #[test]
+    fn test_split_command_quoting() {
+        assert_eq!(
+            split_command(r#"notify-send "USB {product}" 'it''s here' a\ b"#),
+            Some(vec!["notify-send".to_string(), "USB {product}".to_string(), "its here".to_string(), "a b".to_string()]),
+        );
+        assert_eq!(split_command("  "), Some(Vec::new()));
+        assert_eq!(split_command("echo 'unterminated"), None);
+    }
+    
+    #[test]
+    fn test_placeholders_stay_inside_their_argument() {
+        let (mut runner, warnings) = HookRunner::from_config(&[hook(HookEvent::DeviceConnected, "notify-send {product}")]);
+        assert!(warnings.is_empty());
+        
+        let mut device = UsbDevice::new(1, 4);
+        device.product = Some("Stick; rm -rf ~".to_string());
+        let commands = runner.prepare(HookEvent::DeviceConnected, &device, Instant::now());
+        assert_eq!(commands.len(), 1);
+        assert_eq!(commands[0].get_program(), "notify-send");
+        assert_eq!(args(&commands[0]), vec!["Stick; rm -rf ~".to_string()]);
+        
+        // Other events don't fire this hook
+        assert!(runner.prepare(HookEvent::DeviceDisconnected, &device, Instant::now()).is_empty());
+        
+        // Substituted text is not expanded again
+        let values = [("vendor", "{product}".to_string()), ("product", "P".to_string())];
+        assert_eq!(expand("{vendor}-{product}-{other}", &values), "{product}-P-{other}");
+    }
+    
+    #[test]
+    fn test_threshold_and_cooldown() {
+        let mut config = hook(HookEvent::ThresholdExceeded, "alert {bps}");
+        config.threshold_bps = 1000.0;
+        config.device = Some("0781:5581".to_string());
+        let (mut runner, _) = HookRunner::from_config(&[config]);
+        
+        let mut device = UsbDevice::new(1, 4);
+        device.vendor_id = Some(0x0781);
+        device.product_id = Some(0x5581);
+        let start = Instant::now();
+        assert!(runner.prepare(HookEvent::ThresholdExceeded, &device, start).is_empty());
+        
+        device.bandwidth_stats.current_bps = 5000.0;
+        let commands = runner.prepare(HookEvent::ThresholdExceeded, &device, start);
+        assert_eq!(args(&commands[0]), vec!["5000".to_string()]);
+        
+        assert!(runner.prepare(HookEvent::ThresholdExceeded, &device, start + Duration::from_secs(30)).is_empty());
+        assert_eq!(runner.prepare(HookEvent::ThresholdExceeded, &device, start + Duration::from_secs(61)).len(), 1);
+        
+        // A different device doesn't match the VID:PID filter
+        let mut other = UsbDevice::new(1, 5);
+        other.bandwidth_stats.current_bps = 5000.0;
+        assert!(runner.prepare(HookEvent::ThresholdExceeded, &other, start).is_empty());
+    }
+    
+    #[test]
+    fn test_invalid_hooks_are_skipped() {
+        let mut bad_device = hook(HookEvent::DeviceConnected, "true");
+        bad_device.device = Some("sandisk".to_string());
+        let configs = [
+            hook(HookEvent::DeviceConnected, ""),
+            bad_device,
+            hook(HookEvent::ThresholdExceeded, "true"),
+        ];
+        let (runner, warnings) = HookRunner::from_config(&configs);
+        assert!(runner.hooks.is_empty());
+        assert_eq!(warnings.len(), 3);
+    }
+}
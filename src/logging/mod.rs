@@ -0,0 +1,177 @@
+use log::{Level, Log, Metadata, Record};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Default minimum gap between two messages of the same category
+pub const LOG_THROTTLE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Notifications kept while the TUI owns the terminal; older ones are dropped
+const MAX_NOTIFICATIONS: usize = 50;
+
+static TUI_ACTIVE: AtomicBool = AtomicBool::new(false);
+static NOTIFICATIONS: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+static PLAIN_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+/// Rate limit for repetitive log messages
+/// Each category logs at most once per interval; occurrences in between are
+/// counted and reported with the next message that gets through.
+#[derive(Debug)]
+pub struct LogThrottle {
+    interval: Duration,
+    categories: HashMap<String, (Instant, u64)>,
+}
+
+impl LogThrottle {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            categories: HashMap::new(),
+        }
+    }
+    
+    /// Returns the number of suppressed messages if `category` may log now
+    pub fn allow(&mut self, category: &str) -> Option<u64> {
+        let now = Instant::now();
+        match self.categories.get_mut(category) {
+            Some((last, suppressed)) if now.duration_since(*last) < self.interval => {
+                *suppressed += 1;
+                None
+            }
+            Some((last, suppressed)) => {
+                let count = *suppressed;
+                *last = now;
+                *suppressed = 0;
+                Some(count)
+            }
+            None => {
+                self.categories.insert(category.to_string(), (now, 0));
+                Some(0)
+            }
+        }
+    }
+}
+
+/// Suffix noting how many messages were held back, empty when none were
+pub fn suppressed_suffix(suppressed: u64) -> String {
+    if suppressed > 0 {
+        format!(" ({} similar messages suppressed)", suppressed)
+    } else {
+        String::new()
+    }
+}
+
+/// Logger that stays off the terminal while the TUI is drawn
+/// Writing to stdout/stderr under the alternate screen corrupts the display,
+/// so warnings and errors are queued for the UI's notification line instead
+/// and lower levels are dropped until the terminal is restored.
+struct TuiAwareLogger {
+    inner: env_logger::Logger,
+}
+
+impl Log for TuiAwareLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+    
+    fn log(&self, record: &Record) {
+        if !self.inner.matches(record) {
+            return;
+        }
+        
+        if !TUI_ACTIVE.load(Ordering::Relaxed) {
+            self.inner.log(record);
+            return;
+        }
+        
+        if record.level() <= Level::Warn {
+            if let Ok(mut queue) = NOTIFICATIONS.lock() {
+                if queue.len() == MAX_NOTIFICATIONS {
+                    queue.pop_front();
+                }
+                queue.push_back(format!("{}: {}", record.level(), record.args()));
+            }
+        }
+    }
+    
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Install the logger built from `builder`
+pub fn init(mut builder: env_logger::Builder) {
+    let inner = builder.build();
+    let max_level = inner.filter();
+    
+    if log::set_boxed_logger(Box::new(TuiAwareLogger { inner })).is_ok() {
+        log::set_max_level(max_level);
+    }
+}
+
+/// Divert log output into the notification queue while the TUI is active
+pub fn set_tui_active(active: bool) {
+    TUI_ACTIVE.store(active, Ordering::Relaxed);
+}
+
+/// Take the notifications queued since the last call
+pub fn drain_notifications() -> Vec<String> {
+    NOTIFICATIONS.lock()
+        .map(|mut queue| queue.drain(..).collect())
+        .unwrap_or_default()
+}
+
+/// Whether the `NO_COLOR` convention (https://no-color.org) asks for plain output
+pub fn no_color_requested() -> bool {
+    std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
+
+/// Leave the emoji off headings and status lines, for `--quiet` and `NO_COLOR`
+pub fn set_plain_output(plain: bool) {
+    PLAIN_OUTPUT.store(plain, Ordering::Relaxed);
+}
+
+/// `text` led by `emoji`, or on its own when output is plain
+pub fn decorated(emoji: &str, text: &str) -> String {
+    if PLAIN_OUTPUT.load(Ordering::Relaxed) {
+        text.to_string()
+    } else {
+        format!("{} {}", emoji, text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread::sleep;
+    
+    #[test]
+    fn test_throttle_counts_suppressed_messages() {
+        let mut throttle = LogThrottle::new(Duration::from_millis(100));
+        
+        assert_eq!(throttle.allow("parse:1"), Some(0));
+        assert_eq!(throttle.allow("parse:1"), None);
+        assert_eq!(throttle.allow("parse:1"), None);
+        // Categories are independent
+        assert_eq!(throttle.allow("parse:2"), Some(0));
+        
+        sleep(Duration::from_millis(150));
+        assert_eq!(throttle.allow("parse:1"), Some(2));
+        assert_eq!(throttle.allow("parse:1"), None);
+    }
+    
+    #[test]
+    fn test_suppressed_suffix() {
+        assert_eq!(suppressed_suffix(0), "");
+        assert_eq!(suppressed_suffix(3), " (3 similar messages suppressed)");
+    }
+    
+    #[test]
+    fn test_plain_output_drops_emoji() {
+        assert_eq!(decorated("📊", "Capture summary"), "📊 Capture summary");
+        set_plain_output(true);
+        assert_eq!(decorated("📊", "Capture summary"), "Capture summary");
+        set_plain_output(false);
+    }
+}
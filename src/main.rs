@@ -1,37 +1,71 @@
 use anyhow::Result;
 use clap::Parser;
-use log::{info, error, warn};
+use log::{debug, info, error, warn};
 use std::process;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::env;
-use std::io::{self, Write};
+use std::fmt::Write as _;
+use std::io::{self, IsTerminal, Write};
 use std::fs::OpenOptions;
+use std::time::Duration;
 
 mod usbmon;
 mod device;
 mod stats;
 mod ui;
 mod config;
+mod control;
+mod export;
+mod monitor;
+mod snapshot;
+mod demo;
+mod diff;
+mod fleet;
+mod hooks;
+mod logging;
+#[cfg(feature = "web")]
+mod metrics;
+mod profiling;
+mod repro;
+mod state;
+mod trigger;
+#[cfg(feature = "web")]
+mod web;
 
-use usbmon::{check_usbmon_status, prompt_user_to_load_module, attempt_load_usbmon, print_platform_instructions};
+use config::Config;
+use config::paths::PathOverrides;
+use device::DeviceKey;
+use logging::decorated;
+use usbmon::{check_usbmon_status, is_running_in_container, prompt_user_to_load_module, attempt_load_usbmon, print_container_hint, print_platform_instructions};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 #[command(name = "usbtop-ng")]
 #[command(about = "Next-generation USB monitoring tool with real-time bandwidth tracking")]
+#[command(group(clap::ArgGroup::new("timed").args(["benchmark", "guided_repro"])))]
 struct Cli {
     /// Enable verbose logging
     #[arg(short, long)]
     verbose: bool,
     
+    /// Print only errors and the requested output: no startup chatter and no
+    /// emoji in headings (also implied for headings by NO_COLOR)
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+    
     /// Configuration file path
     #[arg(short, long)]
     config: Option<String>,
     
-    /// Refresh rate in milliseconds
-    #[arg(short, long, default_value = "1000")]
+    /// Redraw interval in milliseconds
+    #[arg(short, long, default_value = "1000", value_parser = clap::value_parser!(u64).range(1..))]
     refresh: u64,
     
+    /// Bandwidth sampling interval in milliseconds for the graph and history
+    /// (default: the redraw interval)
+    #[arg(long, value_name = "MS", value_parser = clap::value_parser!(u64).range(1..))]
+    sample_interval: Option<u64>,
+    
     /// Force run without usbmon (limited functionality)
     #[arg(long)]
     force: bool,
@@ -40,9 +74,172 @@ struct Cli {
     #[arg(long)]
     setup: bool,
     
+    /// Report whether the current user can read each usbmon node, and what
+    /// would grant access without root
+    #[arg(long)]
+    check_permissions: bool,
+    
+    /// With --check-permissions, create a usbmon group and install a udev rule
+    /// giving it read access (runs sudo; every step is shown first)
+    #[arg(long, requires = "check_permissions")]
+    fix: bool,
+    
+    /// With --fix, apply the steps without asking; required when stdin isn't
+    /// a terminal
+    #[arg(long, requires = "fix")]
+    yes: bool,
+    
     /// Create shell alias for 'usbtop' command
     #[arg(long)]
     create_alias: bool,
+    
+    /// Exclude endpoint 0 (control) traffic from device bandwidth and activity
+    #[arg(long)]
+    exclude_ep0: bool,
+    
+    /// Capture only this bus (repeatable); other records are dropped before parsing
+    #[arg(long = "bus", value_name = "BUS")]
+    buses: Vec<u8>,
+    
+    /// Capture only this device address (repeatable)
+    #[arg(long = "device", value_name = "BUS:DEV")]
+    devices: Vec<DeviceKey>,
+    
+    /// Capture only this endpoint number, either direction (repeatable)
+    #[arg(long = "endpoint", value_name = "EP", value_parser = clap::value_parser!(u8).range(0..16))]
+    endpoints: Vec<u8>,
+    
+    /// Decimals for bandwidth figures (0-3); automatic by default so small
+    /// rates don't round to zero
+    #[arg(long, value_name = "DIGITS")]
+    precision: Option<usize>,
+    
+    /// Open with a live hex dump of the selected device's payloads, showing
+    /// up to BYTES per packet (binary capture keeps at least that much)
+    #[arg(long, value_name = "BYTES", num_args = 0..=1, default_missing_value = "256")]
+    raw_hex: Option<usize>,
+    
+    /// Grow each bus's usbmon ring when it is small for the bus speed, instead
+    /// of only warning (needs the /dev/usbmonN character device)
+    #[arg(long)]
+    grow_ring: bool,
+    
+    /// Keep cumulative device totals in this file across restarts (saved every
+    /// minute and on exit)
+    #[arg(long, value_name = "PATH")]
+    state_file: Option<String>,
+    
+    /// Accept line commands (snapshot, reset, pause, resume, filter, quit) on
+    /// a Unix socket at this path, for scripting the TUI
+    #[arg(long, value_name = "PATH")]
+    control_socket: Option<String>,
+    
+    /// Print version, build and capability details for bug reports
+    #[arg(long)]
+    version_extended: bool,
+    
+    /// Measure the throughput of a single device (e.g. 2:005)
+    #[arg(long, value_name = "BUS:DEV")]
+    benchmark: Option<DeviceKey>,
+    
+    /// Stop the benchmark, or the --guided-repro capture, after this many
+    /// seconds; the benchmark then runs headless without a terminal
+    #[arg(long, value_name = "SECS", requires = "timed")]
+    duration: Option<u64>,
+    
+    /// Exit non-zero unless the benchmark average is within --tolerance of this
+    /// rate in bytes per second (K, M and G suffixes allowed)
+    #[arg(long, value_name = "BPS", requires = "duration", value_parser = ui::benchmark::parse_rate)]
+    expect: Option<f64>,
+    
+    /// Allowed deviation from --expect, in percent
+    #[arg(long, value_name = "PCT", default_value_t = 10.0, requires = "expect")]
+    tolerance: f64,
+    
+    /// Print lsusb -v style descriptors for one device (e.g. 2:005) or all devices
+    #[arg(long, value_name = "BUS:DEV", num_args = 0..=1)]
+    dump_descriptors: Option<Option<DeviceKey>>,
+    
+    /// Compare two JSON device snapshots and print per-device deltas (no usbmon needed)
+    #[arg(long, num_args = 2, value_names = ["BEFORE", "AFTER"])]
+    diff: Option<Vec<String>>,
+    
+    /// Read a usbmon capture (text or binary), write each packet back out as
+    /// text and read it again, reporting fields that don't survive
+    #[arg(long, value_name = "FILE")]
+    verify_capture: Option<PathBuf>,
+    
+    /// Walk through plugging in a device, reproducing a problem and unplugging
+    /// it, then bundle the capture, descriptors and environment into an
+    /// archive for a bug report (captures for --duration, default 60 s)
+    #[arg(long)]
+    guided_repro: bool,
+    
+    /// Show simulated devices instead of capturing (no root or usbmon needed)
+    #[arg(long)]
+    demo: bool,
+    
+    /// Seed for the --demo scenario; the same seed replays the same traffic
+    #[arg(long, value_name = "SEED", default_value = "1")]
+    demo_seed: u64,
+    
+    /// Read a usbmon capture (text or binary) piped into stdin instead of capturing
+    #[arg(long)]
+    stdin: bool,
+    
+    /// Capture from an Android device over adb (needs a rooted or userdebug
+    /// build with usbmon); reconnects when the connection drops
+    #[arg(long, value_name = "SERIAL")]
+    adb: Option<String>,
+    
+    /// Show the devices of several hosts, reached over ssh, instead of
+    /// capturing here (comma-separated, e.g. root@rack1,rack2)
+    #[arg(long, value_name = "HOSTS", value_delimiter = ',')]
+    connect: Vec<String>,
+    
+    /// Command --connect runs on each host to start its agent
+    #[arg(long, value_name = "CMD", default_value = fleet::DEFAULT_AGENT_COMMAND, requires = "connect")]
+    agent_command: String,
+    
+    /// Capture without the TUI and write framed snapshots to stdout, for a
+    /// --connect client on another host
+    #[arg(long, conflicts_with = "connect")]
+    agent: bool,
+    
+    /// debugfs mount point (default: /sys/kernel/debug, or $USBTOP_DEBUGFS_PATH)
+    #[arg(long, value_name = "DIR")]
+    debugfs_path: Option<String>,
+    
+    /// usbmon directory to capture from (default: <debugfs>/usb/usbmon,
+    /// or $USBTOP_USBMON_PATH)
+    #[arg(long, value_name = "DIR")]
+    usbmon_path: Option<String>,
+    
+    /// sysfs USB devices directory (default: /sys/bus/usb/devices, or $USBTOP_SYSFS_PATH)
+    #[arg(long, value_name = "DIR")]
+    sysfs_path: Option<String>,
+    
+    /// Capture without the TUI and serve a browser dashboard, a live snapshot
+    /// stream and metrics on this address (e.g. 0.0.0.0:9171)
+    #[cfg(feature = "web")]
+    #[arg(long, value_name = "ADDR")]
+    serve: Option<String>,
+    
+    /// How often --serve recomputes /metrics, in milliseconds; rates are
+    /// averaged over the interval (default: the redraw interval)
+    #[cfg(feature = "web")]
+    #[arg(long, value_name = "MS", value_parser = clap::value_parser!(u64).range(1..))]
+    export_interval: Option<u64>,
+    
+    /// Export only the monotonic byte and error counters, without rate gauges
+    #[cfg(feature = "web")]
+    #[arg(long)]
+    export_counters: bool,
+    
+    /// Write a Chrome trace of parse, stats update and draw timings on exit
+    #[cfg(feature = "profile")]
+    #[arg(long, value_name = "FILE", num_args = 0..=1, default_missing_value = profiling::DEFAULT_TRACE_FILE)]
+    profile: Option<String>,
 }
 
 #[tokio::main]
@@ -50,21 +247,123 @@ async fn main() -> Result<()> {
     let cli = Cli::parse();
     
     // Initialize logging
+    let mut log_builder = env_logger::Builder::from_default_env();
     if cli.verbose {
-        env_logger::Builder::from_default_env()
-            .filter_level(log::LevelFilter::Debug)
-            .init();
+        log_builder.filter_level(log::LevelFilter::Debug);
+    } else if cli.quiet {
+        log_builder.filter_level(log::LevelFilter::Warn);
     } else {
-        env_logger::Builder::from_default_env()
-            .filter_level(log::LevelFilter::Info)
-            .init();
+        log_builder.filter_level(log::LevelFilter::Info);
+    }
+    let no_color = logging::no_color_requested();
+    if no_color {
+        log_builder.write_style(env_logger::WriteStyle::Never);
     }
+    logging::init(log_builder);
+    logging::set_plain_output(cli.quiet || no_color);
+    
+    // Held until main returns; dropping it flushes the trace file
+    #[cfg(feature = "profile")]
+    let _profiler = match cli.profile.as_deref() {
+        Some(path) => {
+            info!("Writing profiling trace to {}", path);
+            Some(profiling::Profiler::start(Path::new(path))?)
+        }
+        None => None,
+    };
     
     info!("Starting usbtop-ng v{}", env!("CARGO_PKG_VERSION"));
     
+    let mut config = match Config::load(cli.config.as_deref()) {
+        Ok(config) => config,
+        Err(e) => {
+            error!("{}", e);
+            process::exit(1);
+        }
+    };
+    if cli.exclude_ep0 {
+        config.monitoring.exclude_control_endpoint = true;
+    }
+    if cli.precision.is_some() {
+        config.ui.rate_precision = cli.precision;
+    }
+    if cli.grow_ring {
+        config.monitoring.grow_ring_buffer = true;
+    }
+    config.filter.buses.extend(&cli.buses);
+    config.filter.devices.extend(cli.devices.iter().map(DeviceKey::to_string));
+    config.filter.endpoints.extend(&cli.endpoints);
+    if let Some(bytes) = cli.raw_hex {
+        config.ui.show_hexdump = true;
+        config.ui.hexdump_bytes = bytes;
+        config.monitoring.capture_len = config.monitoring.capture_len.max(bytes as u32);
+    }
+    let refresh_rate = Duration::from_millis(cli.refresh);
+    let sample_interval = Duration::from_millis(cli.sample_interval.unwrap_or(cli.refresh));
+    debug!("Loaded configuration: {:?}", config);
+    for warning in ui::keybindings::KeyBindings::from_config(&config.keybindings).1 {
+        warn!("{}", warning);
+    }
+    for warning in ui::colors::UtilizationScale::from_config(&config.colors.utilization).1 {
+        warn!("{}", warning);
+    }
+    let (thousands_separator, separator_warnings) = ui::widgets::thousands_separator_from_config(&config.ui.thousands_separator);
+    for warning in separator_warnings {
+        warn!("{}", warning);
+    }
+    ui::widgets::set_thousands_separator(thousands_separator);
+    for warning in hooks::HookRunner::from_config(&config.hooks).1 {
+        warn!("{}", warning);
+    }
+    for warning in device::filter::DeviceFilter::from_config(&config.filter).1 {
+        warn!("{}", warning);
+    }
+    let (capture_filter, capture_filter_warnings) = usbmon::capture_filter::CaptureFilter::from_config(&config.filter);
+    for warning in capture_filter_warnings {
+        warn!("{}", warning);
+    }
+    let (packet_trigger, trigger_warnings) = trigger::PacketTrigger::from_config(&config.trigger);
+    for warning in trigger_warnings {
+        warn!("{}", warning);
+    }
+    if packet_trigger.is_enabled() {
+        // The pre-trigger context needs payloads from before anything fired
+        config.monitoring.capture_len = config.monitoring.max_data_length;
+    }
+    let (schedule, schedule_warnings) = monitor::schedule::Schedule::from_config(&config.schedule);
+    for warning in schedule_warnings {
+        warn!("{}", warning);
+    }
+    
+    let overrides = PathOverrides {
+        debugfs: cli.debugfs_path.as_deref().map(PathBuf::from),
+        usbmon: cli.usbmon_path.as_deref().map(PathBuf::from),
+        sysfs: cli.sysfs_path.as_deref().map(PathBuf::from),
+    }
+    .with_fallbacks(&config.advanced);
+    if let Err(e) = overrides.validate() {
+        error!("{}", e);
+        process::exit(1);
+    }
+    let paths = overrides.resolve();
+    debug!("Using paths: {:?}", paths);
+    device::sysfs::set_root(&paths.sysfs);
+    
+    if cli.version_extended {
+        print!("{}", version_report(&paths));
+        return Ok(());
+    }
+    
     // Show setup instructions if requested
     if cli.setup {
-        print_platform_instructions();
+        print_platform_instructions(&mut io::stdout())?;
+        return Ok(());
+    }
+    
+    if cli.check_permissions {
+        if !usbmon::permissions::run(&paths, cli.fix, cli.yes)? {
+            process::exit(1);
+        }
         return Ok(());
     }
     
@@ -74,57 +373,115 @@ async fn main() -> Result<()> {
         return Ok(());
     }
     
-    // Check usbmon status
-    let usbmon_status = match check_usbmon_status() {
-        Ok(status) => status,
-        Err(e) => {
-            error!("Failed to check usbmon status: {}", e);
-            if !cli.force {
-                process::exit(1);
-            }
-            warn!("Continuing in force mode with limited functionality");
-            usbmon::UsbmonStatus {
-                module_loaded: false,
-                debugfs_mounted: false,
-                usbmon_available: false,
-                available_buses: Vec::new(),
-            }
+    if let Some(filter) = cli.dump_descriptors {
+        dump_descriptors(&paths.sysfs, filter);
+        return Ok(());
+    }
+    
+    if let Some(files) = &cli.diff {
+        let before = diff::load_snapshot(Path::new(&files[0]))?;
+        let after = diff::load_snapshot(Path::new(&files[1]))?;
+        diff::print_diff(&diff::diff_snapshots(&before, &after));
+        return Ok(());
+    }
+    
+    if let Some(path) = &cli.verify_capture {
+        if !usbmon::verify::run(path)? {
+            process::exit(1);
         }
-    };
+        return Ok(());
+    }
+    
+    if cli.demo {
+        info!("Running demo scenario with seed {}", cli.demo_seed);
+        let mut app = ui::UsbTopApp::new(sample_interval, refresh_rate);
+        app.apply_config(&config);
+        for device in demo::DemoScenario::devices() {
+            app.update_device(device);
+        }
+        let (packets, mut tasks) = demo::spawn_demo(cli.demo_seed);
+        app.control = open_control_socket(cli.control_socket.as_deref(), &mut tasks);
+        return ui::run_ui(app, packets, tasks);
+    }
+    
+    if !cli.connect.is_empty() {
+        info!("Connecting to {} hosts", cli.connect.len());
+        let (events, tasks) = fleet::spawn_clients(&cli.connect, &cli.agent_command);
+        return ui::fleet::run_fleet(ui::fleet::FleetApp::new(&cli.connect), events, tasks);
+    }
+    
+    if cli.stdin {
+        if io::stdin().is_terminal() {
+            error!("--stdin expects a piped capture, e.g. cat /sys/kernel/debug/usb/usbmon/1u | usbtop-ng --stdin");
+            process::exit(1);
+        }
+        let mut app = ui::UsbTopApp::new(sample_interval, refresh_rate);
+        app.apply_config(&config);
+        app.state = open_state_file(cli.state_file.as_deref());
+        let (packets, mut tasks) = monitor::spawn_stdin_reader(config.monitoring.max_data_length, config.monitoring.capture_len, &capture_filter);
+        app.control = open_control_socket(cli.control_socket.as_deref(), &mut tasks);
+        let result = ui::run_ui(app, packets, tasks);
+        
+        // tokio reads stdin on a blocking thread that can't be cancelled, so
+        // exit directly instead of waiting on it during runtime shutdown
+        if let Err(e) = result {
+            error!("{}", e);
+            process::exit(1);
+        }
+        process::exit(0);
+    }
+    
+    if let Some(serial) = &cli.adb {
+        let mut app = ui::UsbTopApp::new(sample_interval, refresh_rate);
+        app.apply_config(&config);
+        app.origin = Some(monitor::adb::adb_origin(serial));
+        app.state = open_state_file(cli.state_file.as_deref());
+        let (packets, mut tasks) = monitor::adb::spawn_adb_reader(serial, config.monitoring.max_data_length, config.monitoring.capture_len, &capture_filter);
+        app.control = open_control_socket(cli.control_socket.as_deref(), &mut tasks);
+        return ui::run_ui(app, packets, tasks);
+    }
+    
+    // Check usbmon status
+    let usbmon_status = check_usbmon_status(&paths);
     
     // Handle usbmon not being available
     if !usbmon_status.usbmon_available && !cli.force {
-        if !usbmon_status.module_loaded {
+        if is_running_in_container() {
+            // modprobe and mount can't fix this from inside the container
+            error!("usbmon interface not available at {}", paths.usbmon.display());
+            print_container_hint(&mut io::stderr())?;
+            process::exit(1);
+        } else if !usbmon_status.module_loaded {
             // Prompt user to load module
             if prompt_user_to_load_module()? {
-                if let Err(e) = attempt_load_usbmon() {
+                if let Err(e) = attempt_load_usbmon(&paths.debugfs) {
                     error!("Failed to load usbmon: {}", e);
-                    println!();
-                    print_platform_instructions();
+                    eprintln!();
+                    print_platform_instructions(&mut io::stderr())?;
                     process::exit(1);
                 }
                 
                 // Re-check status after loading
-                let new_status = check_usbmon_status()?;
+                let new_status = check_usbmon_status(&paths);
                 if !new_status.usbmon_available {
                     error!("usbmon still not available after loading module");
-                    print_platform_instructions();
+                    print_platform_instructions(&mut io::stderr())?;
                     process::exit(1);
                 }
                 
                 info!("usbmon module loaded successfully");
             } else {
-                println!("Cannot continue without usbmon. Use --force to run with limited functionality.");
-                println!("Run with --setup to see platform-specific instructions.");
+                eprintln!("Cannot continue without usbmon. Use --force to run with limited functionality.");
+                eprintln!("Run with --setup to see platform-specific instructions.");
                 process::exit(1);
             }
         } else if !usbmon_status.debugfs_mounted {
-            error!("debugfs is not mounted");
-            print_platform_instructions();
+            error!("debugfs is not mounted at {}", paths.debugfs.display());
+            print_platform_instructions(&mut io::stderr())?;
             process::exit(1);
         } else {
-            error!("usbmon interface not available");
-            print_platform_instructions();
+            error!("usbmon interface not available at {}", paths.usbmon.display());
+            print_platform_instructions(&mut io::stderr())?;
             process::exit(1);
         }
     }
@@ -136,24 +493,153 @@ async fn main() -> Result<()> {
         warn!("No USB buses detected");
     }
     
+    let reader_options = monitor::ReaderOptions::from_config(&config.monitoring, &paths.usbmon, &capture_filter);
+    
+    if cli.guided_repro {
+        let (packets, tasks) = monitor::spawn_readers(&usbmon_status.available_buses, &reader_options);
+        let window = cli.duration.map_or(repro::DEFAULT_REPRO_WINDOW, Duration::from_secs);
+        repro::run(&paths.sysfs, packets, tasks, window, version_report(&paths)).await?;
+        return Ok(());
+    }
+    
+    if cli.agent {
+        let (packets, tasks) = monitor::spawn_readers(&usbmon_status.available_buses, &reader_options);
+        let mut app = ui::UsbTopApp::new(sample_interval, refresh_rate);
+        app.apply_config(&config);
+        app.state = open_state_file(cli.state_file.as_deref());
+        return fleet::run_agent(app, packets, tasks).await;
+    }
+    
+    #[cfg(feature = "web")]
+    if let Some(addr) = &cli.serve {
+        let (packets, tasks) = monitor::spawn_readers(&usbmon_status.available_buses, &reader_options);
+        let mut app = ui::UsbTopApp::new(sample_interval, refresh_rate);
+        app.apply_config(&config);
+        app.state = open_state_file(cli.state_file.as_deref());
+        let export_interval = cli.export_interval.map_or(refresh_rate, Duration::from_millis);
+        let exporter = metrics::MetricsExporter::new(export_interval, cli.export_counters);
+        return web::serve(addr, app, packets, tasks, exporter).await;
+    }
+    
+    if let Some(key) = cli.benchmark {
+        info!("Benchmarking device {}", key);
+        let (packets, tasks) = monitor::spawn_readers(&[key.bus_id()], &reader_options);
+        let app = ui::benchmark::BenchmarkApp::new(key, sample_interval)
+            .with_duration(cli.duration.map(Duration::from_secs));
+        let summary = ui::benchmark::run_benchmark(app, packets, tasks)?;
+        summary.print();
+        if let Some(expected) = cli.expect {
+            if !summary.check_expectation(expected, cli.tolerance) {
+                process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+    
     // Initialize and run the UI
     info!("Starting USB monitoring interface...");
     
-    // TODO: Initialize the actual monitoring and UI
-    println!("🚀 usbtop-ng starting...");
-    println!("📊 Monitoring {} USB buses", usbmon_status.available_buses.len());
-    println!("⏱️  Refresh rate: {}ms", cli.refresh);
-    println!("📁 Available buses: {:?}", usbmon_status.available_buses);
+    let (packets, mut tasks) = if schedule.is_always_active() {
+        monitor::spawn_readers(&usbmon_status.available_buses, &reader_options)
+    } else {
+        info!("Capturing only during scheduled windows: {:?}", config.schedule.windows);
+        monitor::spawn_scheduled_readers(&usbmon_status.available_buses, &reader_options, schedule)
+    };
+    let mut app = ui::UsbTopApp::new(sample_interval, refresh_rate);
+    app.apply_config(&config);
+    app.detect_controllers();
+    app.detect_disconnects = true;
+    app.state = open_state_file(cli.state_file.as_deref());
+    app.control = open_control_socket(cli.control_socket.as_deref(), &mut tasks);
+    ui::run_ui(app, packets, tasks)
+}
+
+/// Everything a maintainer needs to triage a report, from the same checks
+/// startup uses
+fn version_report(paths: &config::paths::SystemPaths) -> String {
+    let enabled = |on: bool| if on { "enabled" } else { "disabled" };
+    let yes_no = |on: bool| if on { "yes" } else { "no" };
     
-    // For now, just show status and exit
-    println!("\n✅ usbtop-ng initialized successfully!");
-    println!("🔧 Full monitoring interface coming next...");
+    // Writing to a String can't fail
+    let mut out = String::new();
     
-    Ok(())
+    let _ = writeln!(out, "usbtop-ng {}", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(out, 
+        "Build:    {}-{} ({})",
+        env::consts::ARCH,
+        env::consts::OS,
+        if cfg!(debug_assertions) { "debug" } else { "release" },
+    );
+    let _ = writeln!(out, "Kernel:   {}", usbmon::kernel_version().unwrap_or_else(|| "n/a".to_string()));
+    let _ = writeln!(out, "Features: profile {}, web {}", enabled(cfg!(feature = "profile")), enabled(cfg!(feature = "web")));
+    
+    let status = check_usbmon_status(paths);
+    let _ = writeln!(out);
+    let _ = writeln!(out, "usbmon module loaded:  {}", yes_no(status.module_loaded));
+    let _ = writeln!(out, "debugfs mounted:       {} ({})", yes_no(status.debugfs_mounted), paths.debugfs.display());
+    let _ = writeln!(out, "usbmon directory:      {} ({})", yes_no(status.usbmon_available), paths.usbmon.display());
+    let _ = writeln!(out, 
+        "Binary capture:        {}",
+        if status.available_buses.is_empty() {
+            "no binary nodes found".to_string()
+        } else {
+            format!("buses {:?}", status.available_buses)
+        },
+    );
+    let _ = writeln!(out, "sysfs devices:         {} ({})", yes_no(paths.sysfs.is_dir()), paths.sysfs.display());
+    let _ = writeln!(out, "Container:             {}", yes_no(is_running_in_container()));
+    out
+}
+
+/// Open `--state-file`; a file that exists but can't be read stops startup
+/// rather than being overwritten with fresh totals
+fn open_state_file(path: Option<&str>) -> Option<state::SessionStore> {
+    let store = match state::SessionStore::open(Path::new(path?)) {
+        Ok(store) => store,
+        Err(e) => {
+            error!("{}", e);
+            process::exit(1);
+        }
+    };
+    info!("Keeping device totals in {} ({} saved devices)", store.path().display(), store.pending_count());
+    Some(store)
+}
+
+/// Start listening on `--control-socket`; failing to is fatal, since a
+/// script driving the TUI would otherwise wait on a socket that never appears
+fn open_control_socket(path: Option<&str>, tasks: &mut monitor::ReaderTasks) -> Option<tokio::sync::mpsc::UnboundedReceiver<control::ControlRequest>> {
+    match control::listen(Path::new(path?), tasks) {
+        Ok(requests) => Some(requests),
+        Err(e) => {
+            error!("{}", e);
+            process::exit(1);
+        }
+    }
+}
+
+fn dump_descriptors(sysfs_path: &Path, filter: Option<DeviceKey>) {
+    use device::descriptors::read_all_descriptors;
+    
+    let devices: Vec<_> = read_all_descriptors(sysfs_path)
+        .into_iter()
+        .filter(|device| filter.is_none_or(|key| device.key == key))
+        .collect();
+    
+    if devices.is_empty() {
+        match filter {
+            Some(key) => error!("Device {} not found in {}", key, sysfs_path.display()),
+            None => error!("No USB devices found in {}", sysfs_path.display()),
+        }
+        process::exit(1);
+    }
+    
+    for device in devices {
+        println!("{}", device.format_lsusb());
+    }
 }
 
 fn create_shell_alias() -> Result<()> {
-    println!("🔗 Creating shell alias for 'usbtop' command...\n");
+    println!("{}\n", decorated("🔗", "Creating shell alias for 'usbtop' command..."));
     
     // Get the current executable path
     let current_exe = env::current_exe()?;
@@ -202,7 +688,7 @@ fn create_shell_alias() -> Result<()> {
     if Path::new(&config_file).exists() {
         let content = std::fs::read_to_string(&config_file)?;
         if content.contains("alias usbtop") {
-            println!("⚠️  An 'usbtop' alias already exists in {}!", config_file);
+            println!("{}", decorated("⚠️", &format!("An 'usbtop' alias already exists in {}!", config_file)));
             print!("Do you want to replace it? (y/N): ");
             io::stdout().flush()?;
             
@@ -225,7 +711,7 @@ fn create_shell_alias() -> Result<()> {
     writeln!(file, "\n# usbtop-ng alias (added by usbtop-ng --create-alias)")?;
     writeln!(file, "{}", alias_command)?;
     
-    println!("✅ Successfully added alias to {}", config_file);
+    println!("{}", decorated("✅", &format!("Successfully added alias to {}", config_file)));
     println!("\nTo use the alias in your current session, run:");
     println!("  source {}", config_file);
     println!("\nOr start a new terminal session.");
@@ -0,0 +1,187 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use crate::device::{format_speed, DeviceKey, UsbDevice};
+
+/// Rate and total series, labeled only by bus/device so descriptor strings
+/// arriving late never change a series' identity
+const DEVICE_SERIES: [(&str, &str, &str); 5] = [
+    ("usb_device_rx_bytes_per_second", "gauge", "Current device-to-host bandwidth"),
+    ("usb_device_tx_bytes_per_second", "gauge", "Current host-to-device bandwidth"),
+    ("usb_device_rx_bytes_total", "counter", "Bytes received from the device; monotonic until the device's counters reset"),
+    ("usb_device_tx_bytes_total", "counter", "Bytes sent to the device; monotonic until the device's counters reset"),
+    ("usb_errors_total", "counter", "Failed URBs: usbmon error events and completions with an error status"),
+];
+
+/// Series left out when only counters are exported
+const RATE_SERIES: [&str; 2] = ["usb_device_rx_bytes_per_second", "usb_device_tx_bytes_per_second"];
+
+/// Renders metrics at an interval that can be coarser than the UI refresh
+/// Rate gauges are averaged over the whole interval from the byte totals, so
+/// a scrape sees every byte rather than the last refresh's rate. A device
+/// whose totals went backwards (its counters were reset, or it reconnected
+/// under the same number) reports its current rate instead; the counters
+/// themselves simply restart from zero, which Prometheus' `rate()` treats as
+/// a counter reset.
+#[derive(Debug, Clone)]
+pub struct MetricsExporter {
+    pub interval: Duration,
+    /// Leave out the rate gauges, for databases that derive rates from counters
+    pub counters_only: bool,
+    /// Byte totals at the previous render
+    previous: HashMap<DeviceKey, (u64, u64)>,
+    previous_at: Option<Instant>,
+}
+
+impl MetricsExporter {
+    pub fn new(interval: Duration, counters_only: bool) -> Self {
+        Self {
+            interval,
+            counters_only,
+            previous: HashMap::new(),
+            previous_at: None,
+        }
+    }
+    
+    /// Prometheus text for `devices`, with rates averaged since the previous call
+    pub fn render<'a>(&mut self, devices: impl IntoIterator<Item = &'a UsbDevice>, now: Instant) -> String {
+        let devices: Vec<&UsbDevice> = devices.into_iter().collect();
+        let elapsed = self.previous_at.map(|at| now.saturating_duration_since(at).as_secs_f64());
+        
+        let rates: HashMap<DeviceKey, (f64, f64)> = devices.iter()
+            .map(|device| {
+                let stats = &device.bandwidth_stats;
+                let averaged = elapsed.filter(|secs| *secs > 0.0).and_then(|secs| {
+                    let (rx, tx) = self.previous.get(&device.key())?;
+                    let rx = stats.total_rx_bytes.checked_sub(*rx)?;
+                    let tx = stats.total_tx_bytes.checked_sub(*tx)?;
+                    Some((rx as f64 / secs, tx as f64 / secs))
+                });
+                (device.key(), averaged.unwrap_or((stats.rx_bps, stats.tx_bps)))
+            })
+            .collect();
+        
+        self.previous = devices.iter()
+            .map(|device| (device.key(), (device.bandwidth_stats.total_rx_bytes, device.bandwidth_stats.total_tx_bytes)))
+            .collect();
+        self.previous_at = Some(now);
+        
+        format_metrics(devices, &rates, self.counters_only)
+    }
+}
+
+/// Render device metrics in the Prometheus text exposition format
+/// Descriptive labels live only on `usb_device_info{...} 1` (the "info"
+/// pattern); dashboards join it to the rate series on `bus` and `device`.
+fn format_metrics(mut devices: Vec<&UsbDevice>, rates: &HashMap<DeviceKey, (f64, f64)>, counters_only: bool) -> String {
+    devices.sort_by_key(|device| device.key());
+    
+    let mut out = String::new();
+    for (name, kind, help) in DEVICE_SERIES {
+        if counters_only && RATE_SERIES.contains(&name) {
+            continue;
+        }
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} {}", name, kind);
+        for device in &devices {
+            let stats = &device.bandwidth_stats;
+            let (rx_bps, tx_bps) = rates.get(&device.key()).copied().unwrap_or_default();
+            let value = match name {
+                "usb_device_rx_bytes_per_second" => rx_bps,
+                "usb_device_tx_bytes_per_second" => tx_bps,
+                "usb_device_rx_bytes_total" => stats.total_rx_bytes as f64,
+                "usb_device_tx_bytes_total" => stats.total_tx_bytes as f64,
+                _ => stats.urb_counts.errors as f64,
+            };
+            let _ = writeln!(out, "{}{{bus=\"{}\",device=\"{}\"}} {}", name, device.bus_id, device.device_id, value);
+        }
+    }
+    
+    let _ = writeln!(out, "# HELP usb_device_info Descriptive labels for each device; always 1");
+    let _ = writeln!(out, "# TYPE usb_device_info gauge");
+    for device in &devices {
+        let labels = [
+            ("bus", device.bus_id.to_string()),
+            ("device", device.device_id.to_string()),
+            ("vendor_id", device.vendor_id.map(|id| format!("{:04x}", id)).unwrap_or_default()),
+            ("product_id", device.product_id.map(|id| format!("{:04x}", id)).unwrap_or_default()),
+            ("vendor", device.vendor.clone().unwrap_or_default()),
+            ("product", device.product.clone().unwrap_or_default()),
+            ("speed", format_speed(&device.speed)),
+        ];
+        let labels: Vec<String> = labels.iter()
+            .map(|(label, value)| format!("{}=\"{}\"", label, escape_label_value(value)))
+            .collect();
+        let _ = writeln!(out, "usb_device_info{{{}}} 1", labels.join(","));
+    }
+    
+    out
+}
+
+/// Escape a label value: backslash, double quote and newline
+fn escape_label_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::usbmon::parser::UsbSpeed;
+    
+    #[test]
+    fn test_info_metric_carries_descriptors() {
+        let mut device = UsbDevice::new(1, 4);
+        device.vendor_id = Some(0x046d);
+        device.product_id = Some(0x0825);
+        device.vendor = Some("Logitech \"Inc\"".to_string());
+        device.product = Some("Webcam C270".to_string());
+        device.speed = UsbSpeed::High;
+        device.bandwidth_stats.rx_bps = 2500.0;
+        device.bandwidth_stats.total_rx_bytes = 10_000;
+        device.bandwidth_stats.urb_counts.errors = 3;
+        
+        let text = MetricsExporter::new(Duration::from_secs(1), false).render([&device], Instant::now());
+        
+        assert!(text.contains("usb_device_rx_bytes_per_second{bus=\"1\",device=\"4\"} 2500\n"));
+        assert!(text.contains("usb_device_rx_bytes_total{bus=\"1\",device=\"4\"} 10000\n"));
+        assert!(text.contains("# TYPE usb_errors_total counter\n"));
+        assert!(text.contains("usb_errors_total{bus=\"1\",device=\"4\"} 3\n"));
+        assert!(text.contains(
+            "usb_device_info{bus=\"1\",device=\"4\",vendor_id=\"046d\",product_id=\"0825\",\
+             vendor=\"Logitech \\\"Inc\\\"\",product=\"Webcam C270\",speed=\"480 Mbps (High Speed)\"} 1\n"
+        ));
+        // Rate series never carry descriptive labels
+        assert!(text.lines()
+            .filter(|line| line.starts_with("usb_device_rx") || line.starts_with("usb_device_tx"))
+            .all(|line| !line.contains("vendor")));
+    }
+    
+    #[test]
+    fn test_exporter_averages_rates_and_survives_resets() {
+        let mut device = UsbDevice::new(1, 4);
+        device.bandwidth_stats.rx_bps = 9999.0;
+        device.bandwidth_stats.total_rx_bytes = 1_000;
+        let mut exporter = MetricsExporter::new(Duration::from_secs(10), false);
+        let start = Instant::now();
+        
+        // The first render has nothing to average over
+        assert!(exporter.render([&device], start).contains("usb_device_rx_bytes_per_second{bus=\"1\",device=\"4\"} 9999\n"));
+        
+        device.bandwidth_stats.total_rx_bytes = 51_000;
+        let text = exporter.render([&device], start + Duration::from_secs(10));
+        assert!(text.contains("usb_device_rx_bytes_per_second{bus=\"1\",device=\"4\"} 5000\n"));
+        assert!(text.contains("usb_device_rx_bytes_total{bus=\"1\",device=\"4\"} 51000\n"));
+        
+        // A reset restarts the counter and falls back to the current rate
+        device.bandwidth_stats.total_rx_bytes = 200;
+        device.bandwidth_stats.rx_bps = 20.0;
+        let text = exporter.render([&device], start + Duration::from_secs(20));
+        assert!(text.contains("usb_device_rx_bytes_per_second{bus=\"1\",device=\"4\"} 20\n"));
+        assert!(text.contains("usb_device_rx_bytes_total{bus=\"1\",device=\"4\"} 200\n"));
+        
+        let counters = MetricsExporter::new(Duration::from_secs(10), true).render([&device], start);
+        assert!(!counters.contains("bytes_per_second"));
+        assert!(counters.contains("# TYPE usb_device_tx_bytes_total counter\n"));
+    }
+}
@@ -0,0 +1,109 @@
+use anyhow::anyhow;
+use log::{info, warn};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, BufReader};
+use tokio::process::Command;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+use crate::usbmon::capture_filter::CaptureFilter;
+use crate::usbmon::parser::UsbPacket;
+use crate::usbmon::reader::UsbmonReader;
+
+use super::ReaderTasks;
+
+/// usbmon node read on the phone; bus 0 covers every bus
+const ADB_USBMON_NODE: &str = "/sys/kernel/debug/usb/usbmon/0u";
+
+/// Wait before starting adb again after the connection drops
+const ADB_RECONNECT_DELAY: Duration = Duration::from_secs(2);
+
+/// Label for devices captured from the phone with this adb serial
+pub fn adb_origin(serial: &str) -> String {
+    format!("adb:{}", serial)
+}
+
+/// Arguments for streaming the phone's usbmon node
+/// `exec-out` rather than `shell` so the stream isn't passed through a pty,
+/// which would mangle a binary capture. Reading debugfs needs a rooted or
+/// userdebug build (`adb root`).
+fn adb_args(serial: &str) -> Vec<String> {
+    ["-s", serial, "exec-out", "cat", ADB_USBMON_NODE].iter().map(|arg| arg.to_string()).collect()
+}
+
+/// Stream an Android device's usbmon capture over adb into the packet channel
+/// adb is started again whenever it exits (cable pulled, device rebooted,
+/// adbd restarted) until shutdown.
+pub fn spawn_adb_reader(serial: &str, max_data_length: u32, capture_len: u32, filter: &CaptureFilter) -> (UnboundedReceiver<UsbPacket>, ReaderTasks) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let mut tasks = ReaderTasks::new();
+    let mut shutdown = tasks.subscribe();
+    let serial = serial.to_string();
+    let filter = filter.clone();
+    
+    tasks.push(tokio::spawn(async move {
+        let reader = UsbmonReader::piped(&adb_origin(&serial))
+            .with_max_data_length(max_data_length)
+            .with_capture_len(capture_len)
+            .with_filter(filter);
+        
+        while !*shutdown.borrow() {
+            let child = Command::new("adb")
+                .args(adb_args(&serial))
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .kill_on_drop(true)
+                .spawn();
+            
+            match child {
+                Ok(mut child) => {
+                    info!("Capturing from {} over adb", serial);
+                    if let Some(stdout) = child.stdout.take() {
+                        let result = reader.read_piped(BufReader::new(stdout), |packet| {
+                            tx.send(packet).map_err(|_| anyhow!("Packet channel closed"))
+                        }, shutdown.clone()).await;
+                        if let Err(e) = result {
+                            warn!("Capture from {} failed: {}", serial, e);
+                        }
+                    }
+                    if *shutdown.borrow() {
+                        break;
+                    }
+                    
+                    let _ = child.kill().await;
+                    let mut stderr = String::new();
+                    if let Some(mut pipe) = child.stderr.take() {
+                        let _ = pipe.read_to_string(&mut stderr).await;
+                    }
+                    match stderr.lines().find(|line| !line.trim().is_empty()) {
+                        Some(reason) => warn!("adb connection to {} lost: {}; reconnecting", serial, reason.trim()),
+                        None => warn!("adb connection to {} lost; reconnecting", serial),
+                    }
+                }
+                Err(e) => warn!("Failed to run adb for {}: {}; retrying", serial, e),
+            }
+            
+            tokio::select! {
+                _ = shutdown.changed() => break,
+                _ = tokio::time::sleep(ADB_RECONNECT_DELAY) => {}
+            }
+        }
+    }));
+    
+    (rx, tasks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_adb_args_stream_usbmon_without_pty() {
+        assert_eq!(
+            adb_args("R58M12ABCDE"),
+            vec!["-s", "R58M12ABCDE", "exec-out", "cat", "/sys/kernel/debug/usb/usbmon/0u"],
+        );
+        assert_eq!(adb_origin("R58M12ABCDE"), "adb:R58M12ABCDE");
+    }
+}
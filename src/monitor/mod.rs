@@ -0,0 +1,203 @@
+use anyhow::anyhow;
+use chrono::Local;
+use log::{debug, info, warn};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+use crate::config::MonitoringConfig;
+use crate::usbmon::capture_filter::CaptureFilter;
+use crate::usbmon::parser::UsbPacket;
+use crate::usbmon::reader::UsbmonReader;
+
+pub mod adb;
+pub mod schedule;
+
+use schedule::Schedule;
+
+/// How often the schedule is checked for the start or end of a window
+const SCHEDULE_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Background tasks feeding the packet channel, stopped together on exit
+pub struct ReaderTasks {
+    shutdown: watch::Sender<bool>,
+    handles: Vec<JoinHandle<()>>,
+    capture_active: Option<watch::Receiver<bool>>,
+}
+
+impl Default for ReaderTasks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReaderTasks {
+    pub fn new() -> Self {
+        let (shutdown, _) = watch::channel(false);
+        Self {
+            shutdown,
+            handles: Vec::new(),
+            capture_active: None,
+        }
+    }
+    
+    /// Whether scheduled capture is currently inside a window; None when unscheduled
+    pub fn capture_state(&self) -> Option<watch::Receiver<bool>> {
+        self.capture_active.clone()
+    }
+    
+    /// Receiver a task selects on to notice shutdown
+    pub fn subscribe(&self) -> watch::Receiver<bool> {
+        self.shutdown.subscribe()
+    }
+    
+    pub fn push(&mut self, handle: JoinHandle<()>) {
+        self.handles.push(handle);
+    }
+    
+    /// Signal every task to stop and wait for them to finish
+    pub async fn shutdown(self) {
+        let _ = self.shutdown.send(true);
+        for handle in self.handles {
+            if let Err(e) = handle.await {
+                warn!("Reader task failed: {}", e);
+            }
+        }
+    }
+    
+    /// `shutdown` for synchronous callers running inside the multi-threaded runtime
+    pub fn shutdown_blocking(self) {
+        let runtime = tokio::runtime::Handle::current();
+        tokio::task::block_in_place(|| runtime.block_on(self.shutdown()));
+    }
+}
+
+/// How the usbmon node of each bus is opened and read
+#[derive(Debug, Clone)]
+pub struct ReaderOptions {
+    pub usbmon_dir: PathBuf,
+    pub use_binary: bool,
+    pub max_data_length: u32,
+    pub capture_len: u32,
+    pub grow_ring: bool,
+    pub filter: CaptureFilter,
+}
+
+impl ReaderOptions {
+    /// The `[monitoring]` settings, reading nodes from `usbmon_dir`
+    pub fn from_config(config: &MonitoringConfig, usbmon_dir: &Path, filter: &CaptureFilter) -> Self {
+        Self {
+            usbmon_dir: usbmon_dir.to_path_buf(),
+            use_binary: config.binary_mode,
+            max_data_length: config.max_data_length,
+            capture_len: config.capture_len,
+            grow_ring: config.grow_ring_buffer,
+            filter: filter.clone(),
+        }
+    }
+    
+    fn reader(&self, bus_id: u8) -> UsbmonReader {
+        UsbmonReader::new(bus_id, self.use_binary, &self.usbmon_dir)
+            .with_max_data_length(self.max_data_length)
+            .with_capture_len(self.capture_len)
+            .with_ring_growth(self.grow_ring)
+            .with_filter(self.filter.clone())
+    }
+}
+
+/// Spawn one reader task per bus, all feeding a single packet channel
+/// The receiving side is drained by the UI loop on every frame
+pub fn spawn_readers(buses: &[u8], options: &ReaderOptions) -> (UnboundedReceiver<UsbPacket>, ReaderTasks) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let mut tasks = ReaderTasks::new();
+    
+    for bus_id in options.filter.capture_buses(buses) {
+        spawn_reader(&mut tasks, tx.clone(), options.reader(bus_id));
+    }
+    
+    (rx, tasks)
+}
+
+/// Like `spawn_readers`, but readers only run inside the schedule's windows
+/// A supervisor task opens the usbmon nodes when a window starts and closes
+/// them when it ends, so nothing is read while idle. The current state is
+/// published through `ReaderTasks::capture_state`.
+pub fn spawn_scheduled_readers(
+    buses: &[u8],
+    options: &ReaderOptions,
+    schedule: Schedule,
+) -> (UnboundedReceiver<UsbPacket>, ReaderTasks) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let mut tasks = ReaderTasks::new();
+    let (active_tx, active_rx) = watch::channel(schedule.is_active(Local::now().time()));
+    tasks.capture_active = Some(active_rx);
+    
+    let buses = options.filter.capture_buses(buses);
+    let options = options.clone();
+    let mut shutdown = tasks.subscribe();
+    
+    tasks.push(tokio::spawn(async move {
+        let mut readers: Option<ReaderTasks> = None;
+        
+        loop {
+            let active = schedule.is_active(Local::now().time());
+            if active && readers.is_none() {
+                info!("Scheduled capture window started");
+                let mut window_readers = ReaderTasks::new();
+                for &bus_id in &buses {
+                    spawn_reader(&mut window_readers, tx.clone(), options.reader(bus_id));
+                }
+                readers = Some(window_readers);
+            } else if !active {
+                if let Some(window_readers) = readers.take() {
+                    info!("Scheduled capture window ended; idle until the next one");
+                    window_readers.shutdown().await;
+                }
+            }
+            active_tx.send_if_modified(|current| std::mem::replace(current, active) != active);
+            
+            tokio::select! {
+                _ = shutdown.changed() => break,
+                _ = tokio::time::sleep(SCHEDULE_CHECK_INTERVAL) => {}
+            }
+        }
+        
+        if let Some(window_readers) = readers {
+            window_readers.shutdown().await;
+        }
+    }));
+    
+    (rx, tasks)
+}
+
+/// Spawn a single reader for a capture piped into stdin
+/// The channel closes once the input ends, which the UI treats as end of capture
+pub fn spawn_stdin_reader(max_data_length: u32, capture_len: u32, filter: &CaptureFilter) -> (UnboundedReceiver<UsbPacket>, ReaderTasks) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let mut tasks = ReaderTasks::new();
+    
+    let reader = UsbmonReader::stdin()
+        .with_max_data_length(max_data_length)
+        .with_capture_len(capture_len)
+        .with_filter(filter.clone());
+    spawn_reader(&mut tasks, tx, reader);
+    
+    (rx, tasks)
+}
+
+fn spawn_reader(tasks: &mut ReaderTasks, tx: UnboundedSender<UsbPacket>, reader: UsbmonReader) {
+    let shutdown = tasks.subscribe();
+    
+    tasks.push(tokio::spawn(async move {
+        debug!("Starting reader task for {}", reader.path);
+        let result = reader.read_packets(|packet| {
+            tx.send(packet).map_err(|_| anyhow!("Packet channel closed"))
+        }, shutdown).await;
+        
+        if let Err(e) = result {
+            warn!("Reader for {} stopped: {}", reader.path, e);
+        }
+    }));
+}
@@ -0,0 +1,93 @@
+use chrono::NaiveTime;
+
+use crate::config::ScheduleConfig;
+
+/// A daily capture window; `end` before `start` means it runs past midnight
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeWindow {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl TimeWindow {
+    /// Parse "HH:MM-HH:MM"
+    pub fn parse(value: &str) -> Option<Self> {
+        let (start, end) = value.split_once('-')?;
+        Some(Self {
+            start: NaiveTime::parse_from_str(start.trim(), "%H:%M").ok()?,
+            end: NaiveTime::parse_from_str(end.trim(), "%H:%M").ok()?,
+        })
+    }
+    
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            time >= self.start && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+/// When capture runs; without windows it always does
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Schedule {
+    pub windows: Vec<TimeWindow>,
+}
+
+impl Schedule {
+    /// Build from the `[schedule]` section, with warnings for unparseable windows
+    pub fn from_config(config: &ScheduleConfig) -> (Self, Vec<String>) {
+        let mut warnings = Vec::new();
+        let windows = config.windows.iter()
+            .filter_map(|value| {
+                let window = TimeWindow::parse(value);
+                if window.is_none() {
+                    warnings.push(format!("Ignoring schedule window '{}': expected HH:MM-HH:MM", value));
+                }
+                window
+            })
+            .collect();
+        (Self { windows }, warnings)
+    }
+    
+    pub fn is_always_active(&self) -> bool {
+        self.windows.is_empty()
+    }
+    
+    pub fn is_active(&self, time: NaiveTime) -> bool {
+        self.is_always_active() || self.windows.iter().any(|window| window.contains(time))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn at(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+    
+    #[test]
+    fn test_windows_including_overnight() {
+        let config = ScheduleConfig {
+            windows: vec!["09:00-17:30".to_string(), "22:00-02:00".to_string(), "9am".to_string()],
+        };
+        let (schedule, warnings) = Schedule::from_config(&config);
+        assert_eq!(warnings.len(), 1);
+        
+        assert!(schedule.is_active(at(9, 0)));
+        assert!(schedule.is_active(at(17, 29)));
+        assert!(!schedule.is_active(at(17, 30)));
+        assert!(!schedule.is_active(at(8, 59)));
+        assert!(schedule.is_active(at(23, 15)));
+        assert!(schedule.is_active(at(1, 59)));
+        assert!(!schedule.is_active(at(2, 0)));
+    }
+    
+    #[test]
+    fn test_empty_schedule_is_always_active() {
+        let schedule = Schedule::default();
+        assert!(schedule.is_always_active());
+        assert!(schedule.is_active(at(3, 0)));
+    }
+}
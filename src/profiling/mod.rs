@@ -0,0 +1,44 @@
+#[cfg(feature = "profile")]
+pub use tracing;
+
+/// Default trace file when `--profile` is given without a path
+#[cfg(feature = "profile")]
+pub const DEFAULT_TRACE_FILE: &str = "usbtop-trace.json";
+
+/// Time the rest of the enclosing scope under `name`
+/// Expands to nothing unless built with the `profile` feature, so the
+/// instrumented hot paths carry no overhead in normal builds.
+macro_rules! profile_span {
+    ($name:literal) => {
+        #[cfg(feature = "profile")]
+        let _profile_span = $crate::profiling::tracing::trace_span!($name).entered();
+    };
+}
+
+pub(crate) use profile_span;
+
+/// Records spans to a Chrome trace for `--profile`
+/// Open the file in Perfetto or chrome://tracing, or fold it into a flamegraph.
+/// The trace is only complete once this is dropped.
+#[cfg(feature = "profile")]
+pub struct Profiler {
+    _guard: tracing_chrome::FlushGuard,
+}
+
+#[cfg(feature = "profile")]
+impl Profiler {
+    /// Install a subscriber writing Chrome trace events to `path`
+    pub fn start(path: &std::path::Path) -> anyhow::Result<Self> {
+        use tracing_subscriber::prelude::*;
+        
+        let (layer, guard) = tracing_chrome::ChromeLayerBuilder::new()
+            .file(path)
+            .include_args(true)
+            .build();
+        
+        // Set directly rather than via `init()` so the `log` logger is left alone
+        tracing::subscriber::set_global_default(tracing_subscriber::registry().with(layer))?;
+        
+        Ok(Self { _guard: guard })
+    }
+}
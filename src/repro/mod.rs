@@ -0,0 +1,285 @@
+use anyhow::{anyhow, Result};
+use chrono::Local;
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc::UnboundedReceiver;
+
+use crate::device::descriptors::{read_all_descriptors, DeviceDescriptors};
+use crate::device::{format_speed, DeviceKey, UsbDevice};
+use crate::export::{finish_tar, write_tar_entry, OutputWriter};
+use crate::logging::decorated;
+use crate::monitor::ReaderTasks;
+use crate::ui::widgets::{format_bytes, format_count, format_urb_counts};
+use crate::usbmon::parser::{format_usbmon_text_line, UsbPacket};
+
+/// Capture window once the device is in, unless `--duration` says otherwise
+pub const DEFAULT_REPRO_WINDOW: Duration = Duration::from_secs(60);
+
+/// How long to wait for the device to be plugged in
+const PLUG_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// How long to wait for it to be unplugged before bundling anyway
+const UNPLUG_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often sysfs is checked for the device coming and going
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Traffic kept from before the device showed up in sysfs: enumeration
+/// happens first, partly at address 0, and sysfs lists the device after
+const ENUMERATION_LOOKBACK: Duration = Duration::from_secs(5);
+
+/// strftime pattern of the archive written to the working directory
+const ARCHIVE_NAME: &str = "usbtop-repro-%Y%m%d-%H%M%S.tar.gz";
+
+/// What the guided flow saw of one device
+#[derive(Debug, Clone)]
+pub struct Recording {
+    pub key: DeviceKey,
+    /// Read from sysfs while the device was plugged in
+    pub descriptors: Option<DeviceDescriptors>,
+    /// The device's packets, enumeration and disconnect included
+    pub packets: Vec<UsbPacket>,
+    /// From the first prompt until the device appeared
+    pub plugged_after: Duration,
+    /// Capture time after the device appeared
+    pub captured_for: Duration,
+    /// Whether the device left before the flow stopped waiting for it
+    pub unplugged: bool,
+}
+
+/// Walk a user through plugging in a device, reproducing the problem and
+/// unplugging it, then bundle what was captured
+/// `packets` should come from readers on every bus, started before this is
+/// called so the device's enumeration is captured. Returns the archive path.
+pub async fn run(
+    sysfs: &Path,
+    mut packets: UnboundedReceiver<UsbPacket>,
+    tasks: ReaderTasks,
+    window: Duration,
+    environment: String,
+) -> Result<PathBuf> {
+    let present: HashSet<DeviceKey> = read_all_descriptors(sysfs).into_iter().map(|device| device.key).collect();
+    let started = Instant::now();
+    println!("{}", decorated("🔌", &format!("Plug in your device now (waiting up to {}s)...", PLUG_TIMEOUT.as_secs())));
+    
+    let mut recent: VecDeque<(Instant, UsbPacket)> = VecDeque::new();
+    let key = loop {
+        let new_device = read_all_descriptors(sysfs).into_iter().map(|device| device.key).find(|key| !present.contains(key));
+        if let Some(key) = new_device {
+            break key;
+        }
+        if started.elapsed() > PLUG_TIMEOUT {
+            tasks.shutdown().await;
+            return Err(anyhow!("No new USB device appeared within {}s", PLUG_TIMEOUT.as_secs()));
+        }
+        collect_for(&mut packets, POLL_INTERVAL, |packet| recent.push_back((Instant::now(), packet))).await;
+        if let Some(cutoff) = Instant::now().checked_sub(ENUMERATION_LOOKBACK) {
+            while recent.front().is_some_and(|(at, _)| *at < cutoff) {
+                recent.pop_front();
+            }
+        }
+    };
+    let plugged_after = started.elapsed();
+    let early_descriptors = read_all_descriptors(sysfs).into_iter().find(|device| device.key == key);
+    println!("Found {}: {}", key, early_descriptors.as_ref().map_or_else(|| "unknown device".to_string(), device_name));
+    
+    let mut captured: Vec<UsbPacket> = recent.into_iter()
+        .map(|(_, packet)| packet)
+        .filter(|packet| belongs_to(packet, key))
+        .collect();
+    println!("{}", decorated("🎬", &format!("Now reproduce the issue; capturing for {}s...", window.as_secs())));
+    let capture_started = Instant::now();
+    collect_for(&mut packets, window, |packet| keep(&mut captured, packet, key)).await;
+    let captured_for = capture_started.elapsed();
+    // Drivers have bound by now, so a late read says more than the first one
+    let descriptors = read_all_descriptors(sysfs).into_iter().find(|device| device.key == key).or(early_descriptors);
+    
+    println!("{}", decorated("⏏️", "Done capturing. Unplug the device now..."));
+    let unplug_started = Instant::now();
+    let mut unplugged = false;
+    while !unplugged && unplug_started.elapsed() < UNPLUG_TIMEOUT {
+        collect_for(&mut packets, POLL_INTERVAL, |packet| keep(&mut captured, packet, key)).await;
+        unplugged = read_all_descriptors(sysfs).iter().all(|device| device.key != key);
+    }
+    if unplugged {
+        // The last completions of a disconnect trail sysfs slightly
+        collect_for(&mut packets, POLL_INTERVAL, |packet| keep(&mut captured, packet, key)).await;
+    } else {
+        println!("Device still connected after {}s; bundling what was captured", UNPLUG_TIMEOUT.as_secs());
+    }
+    tasks.shutdown().await;
+    
+    let recording = Recording { key, descriptors, packets: captured, plugged_after, captured_for, unplugged };
+    let path = PathBuf::from(Local::now().format(ARCHIVE_NAME).to_string());
+    write_bundle(&path, &recording, &environment)?;
+    println!("{}", decorated("📦", &format!("Wrote {}; attach it to your bug report", path.display())));
+    Ok(path)
+}
+
+/// Hand packets to `keep` until `span` has passed
+async fn collect_for(packets: &mut UnboundedReceiver<UsbPacket>, span: Duration, mut keep: impl FnMut(UsbPacket)) {
+    let deadline = tokio::time::sleep(span);
+    tokio::pin!(deadline);
+    loop {
+        tokio::select! {
+            _ = &mut deadline => return,
+            packet = packets.recv() => match packet {
+                Some(packet) => keep(packet),
+                // Readers gone; the prompts still take their time
+                None => {
+                    deadline.as_mut().await;
+                    return;
+                }
+            },
+        }
+    }
+}
+
+fn keep(captured: &mut Vec<UsbPacket>, packet: UsbPacket, key: DeviceKey) {
+    if belongs_to(&packet, key) {
+        captured.push(packet);
+    }
+}
+
+/// The device's own packets plus default-address traffic on its bus, where
+/// enumeration starts before the device has an address
+fn belongs_to(packet: &UsbPacket, key: DeviceKey) -> bool {
+    packet.bus_id == key.bus_id() && (packet.device_id == key.device_id() || packet.device_id == 0)
+}
+
+fn device_name(descriptors: &DeviceDescriptors) -> String {
+    let id = match (descriptors.vendor_id, descriptors.product_id) {
+        (Some(vendor_id), Some(product_id)) => format!("{:04x}:{:04x}", vendor_id, product_id),
+        _ => "????:????".to_string(),
+    };
+    let name: Vec<&str> = [&descriptors.manufacturer, &descriptors.product].iter().filter_map(|s| s.as_deref()).collect();
+    format!("{} {}", id, if name.is_empty() { "unnamed".to_string() } else { name.join(" ") })
+}
+
+/// Plain-text account of the recording for the top of the bundle
+pub fn summarize(recording: &Recording) -> String {
+    let mut device = UsbDevice::new(recording.key.bus_id(), recording.key.device_id());
+    if let Some(descriptors) = &recording.descriptors {
+        device.speed = descriptors.speed.clone();
+        device.endpoint_types = descriptors.endpoint_types();
+    }
+    let mut failures: BTreeMap<i32, u64> = BTreeMap::new();
+    for packet in recording.packets.iter().filter(|packet| packet.device_id == recording.key.device_id()) {
+        device.record_packet(packet);
+        if packet.is_failure() {
+            *failures.entry(packet.status).or_insert(0) += 1;
+        }
+    }
+    let stats = &device.bandwidth_stats;
+    
+    // Writing to a String can't fail
+    let mut out = String::new();
+    let _ = writeln!(out, "usbtop-ng {} guided repro", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(out);
+    let _ = writeln!(
+        out,
+        "Device:     {} {}",
+        recording.key,
+        recording.descriptors.as_ref().map_or_else(|| "(no sysfs descriptors)".to_string(), device_name),
+    );
+    let _ = writeln!(out, "Speed:      {}", format_speed(&device.speed));
+    let _ = writeln!(out, "Plugged in: {:.1}s after the prompt", recording.plugged_after.as_secs_f64());
+    let _ = writeln!(
+        out,
+        "Captured:   {:.1}s, {} packets (enumeration included)",
+        recording.captured_for.as_secs_f64(),
+        format_count(recording.packets.len() as u64),
+    );
+    let _ = writeln!(
+        out,
+        "Unplugged:  {}",
+        if recording.unplugged { "yes" } else { "no, still connected when the capture ended" },
+    );
+    let _ = writeln!(out, "Traffic:    ↓ {}  ↑ {}", format_bytes(stats.total_rx_bytes), format_bytes(stats.total_tx_bytes));
+    let _ = writeln!(out, "URBs:       {}", format_urb_counts(&stats.urb_counts));
+    let errors = if failures.is_empty() {
+        "none".to_string()
+    } else {
+        failures.iter().map(|(status, count)| format!("status {} ×{}", status, count)).collect::<Vec<_>>().join(", ")
+    };
+    let _ = writeln!(out, "Errors:     {}", errors);
+    
+    let mut endpoints: Vec<(&u8, u64)> = device.endpoint_stats.iter()
+        .map(|(address, stats)| (address, stats.total_rx_bytes + stats.total_tx_bytes))
+        .collect();
+    endpoints.sort();
+    if !endpoints.is_empty() {
+        let _ = writeln!(out, "Endpoints:");
+        for (address, bytes) in endpoints {
+            let _ = writeln!(out, "  {:<22} {}", device.endpoint_label(*address), format_bytes(bytes));
+        }
+    }
+    out
+}
+
+/// Write the summary, the capture as usbmon text (which `--stdin` replays),
+/// the descriptors and `environment` into a tar archive, gzipped when the
+/// path ends in `.gz`
+pub fn write_bundle(path: &Path, recording: &Recording, environment: &str) -> Result<()> {
+    let folder = path.file_name()
+        .and_then(|name| name.to_str())
+        .map_or("usbtop-repro", |name| name.trim_end_matches(".gz").trim_end_matches(".tar"));
+    let mtime = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs());
+    let capture: String = recording.packets.iter().map(|packet| format_usbmon_text_line(packet) + "\n").collect();
+    let descriptors = recording.descriptors.as_ref()
+        .map_or_else(|| "No descriptors could be read from sysfs\n".to_string(), |descriptors| descriptors.format_lsusb());
+    
+    let mut out = OutputWriter::create(path)?;
+    for (name, contents) in [
+        ("summary.txt", summarize(recording)),
+        ("capture.txt", capture),
+        ("descriptors.txt", descriptors),
+        ("environment.txt", environment.to_string()),
+    ] {
+        write_tar_entry(&mut out, &format!("{}/{}", folder, name), contents.as_bytes(), mtime)?;
+    }
+    finish_tar(&mut out)?;
+    out.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::usbmon::parser::parse_usbmon_text_line;
+    
+    #[test]
+    fn test_summary_counts_real_failures() {
+        let lines = [
+            "ffff880000000001 100 S Ci:1:000:0 s 80 06 0100 0000 0040 64 <",
+            "ffff880000000002 200 C Bi:1:005:1 0 512 = 00",
+            "ffff880000000003 300 C Bi:1:005:1 -32 0",
+            "ffff880000000004 400 C Bi:1:005:1 -104 0",
+            "ffff880000000005 500 C Bi:1:006:1 0 64 = 00",
+        ];
+        let key = DeviceKey(1, 5);
+        let packets: Vec<UsbPacket> = lines.iter()
+            .map(|line| parse_usbmon_text_line(line).unwrap())
+            .filter(|packet| belongs_to(packet, key))
+            .collect();
+        assert_eq!(packets.len(), 4);
+        
+        let recording = Recording {
+            key,
+            descriptors: None,
+            packets,
+            plugged_after: Duration::from_millis(4200),
+            captured_for: Duration::from_secs(60),
+            unplugged: true,
+        };
+        let summary = summarize(&recording);
+        assert!(summary.contains("Device:     001:005 (no sysfs descriptors)"));
+        assert!(summary.contains("Captured:   60.0s, 4 packets"));
+        assert!(summary.contains("Unplugged:  yes"));
+        assert!(summary.contains("Traffic:    ↓ 512 B  ↑ 0 B"));
+        // The cancelled URB is routine, the stall is not
+        assert!(summary.contains("Errors:     status -32 ×1\n"));
+        assert!(summary.contains("  EP1 IN "));
+    }
+}
@@ -0,0 +1,126 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::device::vendors::VendorTotals;
+use crate::device::UsbDevice;
+
+/// JSON messages describing this host's devices
+/// Sent by the web dashboard's `/stream`, the control socket's `snapshot`
+/// command, and read back by `--diff`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Message {
+    /// Per-device state at `timestamp_ms`, optionally with per-vendor totals
+    Snapshot {
+        timestamp_ms: i64,
+        devices: Vec<DeviceSnapshot>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        vendors: Vec<VendorTotals>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceSnapshot {
+    pub bus_id: u8,
+    pub device_id: u8,
+    pub vendor: Option<String>,
+    pub product: Option<String>,
+    pub rx_bps: f64,
+    pub tx_bps: f64,
+    /// Totals and URB errors since capture started
+    #[serde(default)]
+    pub total_rx_bytes: u64,
+    #[serde(default)]
+    pub total_tx_bytes: u64,
+    #[serde(default)]
+    pub errors: u64,
+}
+
+impl DeviceSnapshot {
+    pub fn from_device(device: &UsbDevice) -> Self {
+        let stats = &device.bandwidth_stats;
+        Self {
+            bus_id: device.bus_id,
+            device_id: device.device_id,
+            vendor: device.vendor.clone(),
+            product: device.product.clone(),
+            rx_bps: stats.rx_bps,
+            tx_bps: stats.tx_bps,
+            total_rx_bytes: stats.total_rx_bytes,
+            total_tx_bytes: stats.total_tx_bytes,
+            errors: stats.urb_counts.errors,
+        }
+    }
+}
+
+impl Message {
+    /// Current state of `devices`, ordered by bus and device number
+    pub fn snapshot<'a>(devices: impl IntoIterator<Item = &'a UsbDevice>) -> Self {
+        let mut devices: Vec<DeviceSnapshot> = devices.into_iter().map(DeviceSnapshot::from_device).collect();
+        devices.sort_by_key(|device| (device.bus_id, device.device_id));
+        Message::Snapshot {
+            timestamp_ms: Utc::now().timestamp_millis(),
+            devices,
+            vendors: Vec::new(),
+        }
+    }
+    
+    /// Add the vendor section, as `aggregate_by_vendor` orders it
+    pub fn with_vendors(self, vendors: Vec<VendorTotals>) -> Self {
+        let Message::Snapshot { timestamp_ms, devices, .. } = self;
+        Message::Snapshot { timestamp_ms, devices, vendors }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_snapshot_round_trip() {
+        let message = Message::Snapshot {
+            timestamp_ms: 1_700_000_000_000,
+            devices: vec![DeviceSnapshot {
+                bus_id: 2,
+                device_id: 5,
+                vendor: Some("ACME".to_string()),
+                product: None,
+                rx_bps: 1000.0,
+                tx_bps: 0.0,
+                total_rx_bytes: 4096,
+                total_tx_bytes: 0,
+                errors: 1,
+            }],
+            vendors: Vec::new(),
+        };
+        let json = serde_json::to_string(&message).unwrap();
+        assert!(json.starts_with("{\"type\":\"snapshot\""));
+        // The vendor section is left out unless asked for
+        assert!(!json.contains("vendors"));
+        assert_eq!(serde_json::from_str::<Message>(&json).unwrap(), message);
+        
+        let vendors = vec![VendorTotals {
+            vendor_id: Some(0x05e0),
+            name: "Zebra Technologies, Inc.".to_string(),
+            devices: 2,
+            rx_bps: 1500.0,
+            tx_bps: 20.0,
+            total_rx_bytes: 90_000,
+            total_tx_bytes: 1_200,
+        }];
+        let message = message.with_vendors(vendors);
+        let json: serde_json::Value = serde_json::to_value(&message).unwrap();
+        assert_eq!(json["vendors"][0]["vendor_id"], 0x05e0);
+        assert_eq!(json["vendors"][0]["devices"], 2);
+        assert_eq!(serde_json::from_value::<Message>(json).unwrap(), message);
+    }
+    
+    #[test]
+    fn test_snapshot_without_totals_still_decodes() {
+        let body = r#"{"type":"snapshot","timestamp_ms":0,"devices":[
+            {"bus_id":1,"device_id":3,"vendor":null,"product":null,"rx_bps":10.0,"tx_bps":0.0}]}"#;
+        let Message::Snapshot { devices, .. } = serde_json::from_str::<Message>(body).unwrap();
+        assert_eq!(devices[0].total_rx_bytes, 0);
+        assert_eq!(devices[0].errors, 0);
+    }
+}
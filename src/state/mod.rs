@@ -0,0 +1,264 @@
+use anyhow::{Result, anyhow};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::device::UsbDevice;
+use crate::stats::UrbCounts;
+
+/// How often cumulative totals are written while running
+pub const STATE_SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Cumulative per-device counters carried across restarts
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionState {
+    pub saved_at_ms: i64,
+    pub devices: Vec<PersistedDevice>,
+}
+
+/// Totals and identity of one device; live rate windows are never persisted
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PersistedDevice {
+    /// `VID:PID:serial`, or `VID:PID` for devices without a serial
+    pub identity: String,
+    pub vendor: Option<String>,
+    pub product: Option<String>,
+    pub total_rx_bytes: u64,
+    pub total_tx_bytes: u64,
+    pub peak_bps: f64,
+    pub max_packet_seen: u64,
+    pub submissions: u64,
+    pub callbacks: u64,
+    pub errors: u64,
+    pub control_rx_bytes: u64,
+    pub control_tx_bytes: u64,
+}
+
+impl PersistedDevice {
+    pub fn from_device(identity: String, device: &UsbDevice) -> Self {
+        let stats = &device.bandwidth_stats;
+        Self {
+            identity,
+            vendor: device.vendor.clone(),
+            product: device.product.clone(),
+            total_rx_bytes: stats.total_rx_bytes,
+            total_tx_bytes: stats.total_tx_bytes,
+            peak_bps: stats.peak_bps,
+            max_packet_seen: stats.max_packet_seen,
+            submissions: stats.urb_counts.submissions,
+            callbacks: stats.urb_counts.callbacks,
+            errors: stats.urb_counts.errors,
+            control_rx_bytes: device.control_stats.total_rx_bytes,
+            control_tx_bytes: device.control_stats.total_tx_bytes,
+        }
+    }
+    
+    /// Add the saved counters to a freshly seen device
+    pub fn apply(&self, device: &mut UsbDevice) {
+        let stats = &mut device.bandwidth_stats;
+        stats.total_rx_bytes = stats.total_rx_bytes.saturating_add(self.total_rx_bytes);
+        stats.total_tx_bytes = stats.total_tx_bytes.saturating_add(self.total_tx_bytes);
+        stats.peak_bps = stats.peak_bps.max(self.peak_bps);
+        stats.max_packet_seen = stats.max_packet_seen.max(self.max_packet_seen);
+        stats.urb_counts.add(&UrbCounts {
+            submissions: self.submissions,
+            callbacks: self.callbacks,
+            errors: self.errors,
+        });
+        
+        let control = &mut device.control_stats;
+        control.total_rx_bytes = control.total_rx_bytes.saturating_add(self.control_rx_bytes);
+        control.total_tx_bytes = control.total_tx_bytes.saturating_add(self.control_tx_bytes);
+        
+        if device.vendor.is_none() {
+            device.vendor = self.vendor.clone();
+        }
+        if device.product.is_none() {
+            device.product = self.product.clone();
+        }
+    }
+}
+
+/// Key that finds the same physical device after a restart, when it has one
+/// Device numbers change on every enumeration, so only descriptor identity is
+/// used; devices whose VID/PID isn't known yet can't be persisted.
+pub fn device_identity(device: &UsbDevice) -> Option<String> {
+    let (vendor_id, product_id) = (device.vendor_id?, device.product_id?);
+    Some(match device.serial.as_deref().filter(|serial| !serial.is_empty()) {
+        Some(serial) => format!("{:04x}:{:04x}:{}", vendor_id, product_id, serial),
+        None => format!("{:04x}:{:04x}", vendor_id, product_id),
+    })
+}
+
+/// A `--state-file`: saved totals waiting for their device to appear, and
+/// the schedule for writing current totals back
+#[derive(Debug)]
+pub struct SessionStore {
+    path: PathBuf,
+    /// Entries for devices not currently tracked: loaded at startup and not
+    /// seen yet, or handed back when their device left; they are written back
+    /// so an absent device keeps its history
+    pending: HashMap<String, PersistedDevice>,
+    last_saved: Instant,
+}
+
+impl SessionStore {
+    /// Load `path` if it exists; a missing file starts an empty session
+    pub fn open(path: &Path) -> Result<Self> {
+        let state = match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str::<SessionState>(&contents)
+                .map_err(|e| anyhow!("Failed to parse state file {}: {}", path.display(), e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => SessionState::default(),
+            Err(e) => return Err(anyhow!("Failed to read state file {}: {}", path.display(), e)),
+        };
+        
+        Ok(Self {
+            path: path.to_path_buf(),
+            pending: state.devices.into_iter()
+                .map(|device| (device.identity.clone(), device))
+                .collect(),
+            last_saved: Instant::now(),
+        })
+    }
+    
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+    
+    /// Carry saved totals over to a device seen for the first time this session
+    pub fn restore(&mut self, device: &mut UsbDevice) -> bool {
+        let Some(saved) = device_identity(device).and_then(|identity| self.pending.remove(&identity)) else {
+            return false;
+        };
+        saved.apply(device);
+        true
+    }
+    
+    /// Keep the totals of a device that is no longer tracked for the next save
+    pub fn retain(&mut self, device: &UsbDevice) {
+        if let Some(identity) = device_identity(device) {
+            self.pending.insert(identity.clone(), PersistedDevice::from_device(identity, device));
+        }
+    }
+    
+    pub fn is_due(&self, now: Instant) -> bool {
+        now.duration_since(self.last_saved) >= STATE_SAVE_INTERVAL
+    }
+    
+    /// Current totals plus saved entries for devices not tracked right now
+    /// Devices sharing a VID:PID with no serial can't be told apart after a
+    /// restart, so they are left out rather than attached to the wrong one.
+    /// A tracked device supersedes a saved entry with the same identity.
+    pub fn snapshot<'a>(&self, devices: impl IntoIterator<Item = &'a UsbDevice>) -> SessionState {
+        let mut entries: HashMap<String, Option<PersistedDevice>> = HashMap::new();
+        for device in devices {
+            let Some(identity) = device_identity(device) else {
+                continue;
+            };
+            entries.entry(identity.clone())
+                .and_modify(|entry| *entry = None)
+                .or_insert_with(|| Some(PersistedDevice::from_device(identity, device)));
+        }
+        
+        let pending = self.pending.values().filter(|saved| !entries.contains_key(&saved.identity));
+        let mut devices: Vec<PersistedDevice> = pending.cloned().collect();
+        devices.extend(entries.into_values().flatten());
+        devices.sort_by(|a, b| a.identity.cmp(&b.identity));
+        SessionState {
+            saved_at_ms: Utc::now().timestamp_millis(),
+            devices,
+        }
+    }
+    
+    /// Write the snapshot next to the state file and rename it into place so
+    /// an interrupted write never leaves a truncated file behind
+    pub fn save<'a>(&mut self, devices: impl IntoIterator<Item = &'a UsbDevice>) -> Result<()> {
+        self.last_saved = Instant::now();
+        let state = self.snapshot(devices);
+        
+        let mut temp_name = self.path.file_name().unwrap_or_default().to_os_string();
+        temp_name.push(".tmp");
+        let temp_path = self.path.with_file_name(temp_name);
+        fs::write(&temp_path, serde_json::to_vec_pretty(&state)?)
+            .map_err(|e| anyhow!("Failed to write state file {}: {}", temp_path.display(), e))?;
+        fs::rename(&temp_path, &self.path)
+            .map_err(|e| anyhow!("Failed to replace state file {}: {}", self.path.display(), e))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn identified(device_id: u8, serial: Option<&str>, rx_bytes: u64) -> UsbDevice {
+        let mut device = UsbDevice::new(1, device_id);
+        device.vendor_id = Some(0x0781);
+        device.product_id = Some(0x5581);
+        device.serial = serial.map(str::to_string);
+        device.bandwidth_stats.total_rx_bytes = rx_bytes;
+        device
+    }
+    
+    #[test]
+    fn test_totals_survive_a_restart() {
+        let path = std::env::temp_dir().join(format!("usbtop-ng-state-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+        
+        let mut store = SessionStore::open(&path).unwrap();
+        let mut stick = identified(4, Some("4C530001"), 1_000);
+        stick.bandwidth_stats.peak_bps = 30_000_000.0;
+        let absent = identified(5, Some("4C530002"), 500);
+        store.save([&stick, &absent]).unwrap();
+        
+        // After a restart only the first stick is back, under a new device number
+        let mut store = SessionStore::open(&path).unwrap();
+        let mut replugged = identified(9, Some("4C530001"), 24);
+        assert!(store.restore(&mut replugged));
+        assert!(!store.restore(&mut identified(10, Some("other"), 0)));
+        assert_eq!(replugged.bandwidth_stats.total_rx_bytes, 1_024);
+        assert_eq!(replugged.bandwidth_stats.peak_bps, 30_000_000.0);
+        
+        // The absent stick's entry is kept for a later session
+        let identities: Vec<String> = store.snapshot([&replugged]).devices.into_iter().map(|device| device.identity).collect();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(identities, vec!["0781:5581:4C530001", "0781:5581:4C530002"]);
+    }
+    
+    #[test]
+    fn test_tracked_device_supersedes_retained_entry() {
+        let mut store = SessionStore {
+            path: PathBuf::from("unused"),
+            pending: HashMap::new(),
+            last_saved: Instant::now(),
+        };
+        store.retain(&identified(4, Some("4C530001"), 100));
+        
+        let saved = store.snapshot([&identified(5, Some("4C530001"), 300)]).devices;
+        assert_eq!(saved.len(), 1);
+        assert_eq!(saved[0].total_rx_bytes, 300);
+    }
+    
+    #[test]
+    fn test_ambiguous_devices_are_not_persisted() {
+        let store = SessionStore {
+            path: PathBuf::from("unused"),
+            pending: HashMap::new(),
+            last_saved: Instant::now(),
+        };
+        let first = identified(4, None, 10);
+        let second = identified(5, None, 20);
+        let unknown = UsbDevice::new(1, 6);
+        
+        assert!(store.snapshot([&first, &second, &unknown]).devices.is_empty());
+        assert_eq!(store.snapshot([&first]).devices[0].identity, "0781:5581");
+    }
+}
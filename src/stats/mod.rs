@@ -1,6 +1,130 @@
 use std::collections::VecDeque;
 use std::time::{Duration, Instant};
 
+use crate::usbmon::parser::{TransferType, UrbType, UsbPacket};
+
+/// Shortest span rates are averaged over, so the first packets after startup
+/// don't divide by a near-zero elapsed time and produce an absurd peak
+const MIN_RATE_SPAN: Duration = Duration::from_secs(1);
+
+/// Span rates are averaged over once enough history has built up
+pub const RATE_WINDOW: Duration = Duration::from_secs(10);
+
+/// Span an `EventRate` counts over before publishing a new rate
+const EVENT_RATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// Raw URB event counts since the session started (or the last reset)
+/// A persistent gap between submissions and callbacks hints at pending or lost URBs
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UrbCounts {
+    pub submissions: u64,
+    pub callbacks: u64,
+    pub errors: u64,
+}
+
+impl UrbCounts {
+    pub fn record(&mut self, urb_type: &UrbType) {
+        match urb_type {
+            UrbType::Submission => self.submissions += 1,
+            UrbType::Callback => self.callbacks += 1,
+            UrbType::Error => self.errors += 1,
+        }
+    }
+    
+    /// Count a captured event; a completion with an error status counts as
+    /// an error rather than a callback
+    pub fn record_packet(&mut self, packet: &UsbPacket) {
+        if packet.is_failure() {
+            self.errors += 1;
+        } else {
+            self.record(&packet.urb_type);
+        }
+    }
+    
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+    
+    pub fn add(&mut self, other: &UrbCounts) {
+        self.submissions = self.submissions.saturating_add(other.submissions);
+        self.callbacks = self.callbacks.saturating_add(other.callbacks);
+        self.errors = self.errors.saturating_add(other.errors);
+    }
+}
+
+/// Upper bounds (exclusive) of the URB size buckets; the last bucket is open
+const URB_SIZE_BOUNDS: [u64; 4] = [64, 512, 4 * 1024, 64 * 1024];
+
+/// Labels of the URB size buckets, smallest first
+pub const URB_SIZE_LABELS: [&str; 5] = ["<64", "64-512", "512-4K", "4K-64K", ">64K"];
+
+/// How many URB payloads fell into each size bucket, log-spaced so a serial
+/// adapter's 64 B transfers and a disk's 128 KB ones both stand out
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UrbSizeHistogram {
+    pub counts: [u64; 5],
+}
+
+impl UrbSizeHistogram {
+    pub fn record(&mut self, bytes: u64) {
+        let bucket = URB_SIZE_BOUNDS.iter().position(|&bound| bytes < bound).unwrap_or(URB_SIZE_BOUNDS.len());
+        self.counts[bucket] = self.counts[bucket].saturating_add(1);
+    }
+    
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+    
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+    
+    pub fn add(&mut self, other: &UrbSizeHistogram) {
+        for (count, other) in self.counts.iter_mut().zip(other.counts) {
+            *count = count.saturating_add(other);
+        }
+    }
+}
+
+/// Events per second over the last completed window, e.g. completions on a
+/// polled endpoint
+#[derive(Debug, Clone)]
+pub struct EventRate {
+    window_start: Instant,
+    window_count: u64,
+    rate_hz: f64,
+}
+
+impl EventRate {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            window_start: now,
+            window_count: 0,
+            rate_hz: 0.0,
+        }
+    }
+    
+    pub fn record(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.window_start);
+        if elapsed >= EVENT_RATE_WINDOW {
+            self.rate_hz = self.window_count as f64 / elapsed.as_secs_f64();
+            self.window_start = now;
+            self.window_count = 0;
+        }
+        self.window_count += 1;
+    }
+    
+    /// Rate of the last full window; a window that never closed because the
+    /// events stopped counts as idle
+    pub fn rate_hz(&self, now: Instant) -> f64 {
+        if now.duration_since(self.window_start) >= EVENT_RATE_WINDOW * 2 {
+            0.0
+        } else {
+            self.rate_hz
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct BandwidthStats {
     pub rx_bps: f64,        // Bytes per second received (device to host)
@@ -9,9 +133,19 @@ pub struct BandwidthStats {
     pub peak_bps: f64,      // Peak bandwidth seen
     pub total_rx_bytes: u64,
     pub total_tx_bytes: u64,
+    /// Largest single URB payload seen in either direction
+    pub max_packet_seen: u64,
+    pub urb_counts: UrbCounts,
     pub rx_history: VecDeque<(Instant, u64)>,
     pub tx_history: VecDeque<(Instant, u64)>,
     pub history_window: Duration,
+    /// Interrupt and isochronous bytes, averaged over `periodic_window`
+    pub periodic_rx_history: VecDeque<(Instant, u64)>,
+    pub periodic_tx_history: VecDeque<(Instant, u64)>,
+    /// Span periodic traffic is averaged over; a window that doesn't line up
+    /// with the polling interval makes a steady poll read as jitter
+    pub periodic_window: Duration,
+    pub started: Instant,
 }
 
 impl BandwidthStats {
@@ -23,58 +157,107 @@ impl BandwidthStats {
             peak_bps: 0.0,
             total_rx_bytes: 0,
             total_tx_bytes: 0,
+            max_packet_seen: 0,
+            urb_counts: UrbCounts::default(),
             rx_history: VecDeque::new(),
             tx_history: VecDeque::new(),
-            history_window: Duration::from_secs(10), // 10-second window
+            history_window: RATE_WINDOW,
+            periodic_rx_history: VecDeque::new(),
+            periodic_tx_history: VecDeque::new(),
+            periodic_window: RATE_WINDOW,
+            started: Instant::now(),
         }
     }
     
     pub fn update_rx(&mut self, bytes: u64) {
         let now = Instant::now();
-        self.total_rx_bytes += bytes;
+        self.total_rx_bytes = self.total_rx_bytes.saturating_add(bytes);
+        self.max_packet_seen = self.max_packet_seen.max(bytes);
         self.rx_history.push_back((now, bytes));
-        self.cleanup_old_entries();
-        self.recalculate_rates();
+        self.refresh(now);
     }
     
     pub fn update_tx(&mut self, bytes: u64) {
         let now = Instant::now();
-        self.total_tx_bytes += bytes;
+        self.total_tx_bytes = self.total_tx_bytes.saturating_add(bytes);
+        self.max_packet_seen = self.max_packet_seen.max(bytes);
         self.tx_history.push_back((now, bytes));
-        self.cleanup_old_entries();
-        self.recalculate_rates();
+        self.refresh(now);
     }
     
-    fn cleanup_old_entries(&mut self) {
-        let cutoff = Instant::now() - self.history_window;
-        
-        while let Some(&(timestamp, _)) = self.rx_history.front() {
-            if timestamp < cutoff {
-                self.rx_history.pop_front();
+    /// Account bytes of a given transfer type; interrupt and isochronous
+    /// traffic goes into the periodic history and is averaged over
+    /// `periodic_window`, everything else behaves as `update_rx`/`update_tx`
+    pub fn update_typed(&mut self, bytes: u64, device_to_host: bool, transfer_type: TransferType) {
+        if !transfer_type.is_periodic() {
+            if device_to_host {
+                self.update_rx(bytes);
             } else {
-                break;
+                self.update_tx(bytes);
             }
+            return;
+        }
+        let now = Instant::now();
+        self.max_packet_seen = self.max_packet_seen.max(bytes);
+        if device_to_host {
+            self.total_rx_bytes = self.total_rx_bytes.saturating_add(bytes);
+            self.periodic_rx_history.push_back((now, bytes));
+        } else {
+            self.total_tx_bytes = self.total_tx_bytes.saturating_add(bytes);
+            self.periodic_tx_history.push_back((now, bytes));
         }
+        self.refresh(now);
+    }
+    
+    /// Drop what has aged out of the windows and recompute the rates as of `now`
+    /// Packets only refresh the rates of the stats they land in, so this is
+    /// what brings a device that went quiet back down to zero.
+    pub fn refresh(&mut self, now: Instant) {
+        self.cleanup_old_entries(now);
+        self.recalculate_rates(now);
+    }
+    
+    fn cleanup_old_entries(&mut self, now: Instant) {
+        let cutoff = now - self.history_window;
+        let periodic_cutoff = now - self.periodic_window;
         
-        while let Some(&(timestamp, _)) = self.tx_history.front() {
-            if timestamp < cutoff {
-                self.tx_history.pop_front();
-            } else {
-                break;
+        for (history, cutoff) in [
+            (&mut self.rx_history, cutoff),
+            (&mut self.tx_history, cutoff),
+            (&mut self.periodic_rx_history, periodic_cutoff),
+            (&mut self.periodic_tx_history, periodic_cutoff),
+        ] {
+            while let Some(&(timestamp, _)) = history.front() {
+                if timestamp < cutoff {
+                    history.pop_front();
+                } else {
+                    break;
+                }
             }
         }
     }
     
-    fn recalculate_rates(&mut self) {
-        let window_secs = self.history_window.as_secs_f64();
+    /// Span a history of `window` actually covers: the full window once it
+    /// has filled, otherwise the time since stats started (so rates aren't
+    /// under-reported while the window is warming up)
+    fn rate_span(&self, window: Duration, now: Instant) -> Duration {
+        now.saturating_duration_since(self.started)
+            .max(MIN_RATE_SPAN)
+            .min(window)
+    }
+    
+    fn recalculate_rates(&mut self, now: Instant) {
+        let window_secs = self.rate_span(self.history_window, now).as_secs_f64();
+        let periodic_secs = self.rate_span(self.periodic_window, now).as_secs_f64();
+        let rate = |history: &VecDeque<(Instant, u64)>, secs: f64| {
+            history.iter().map(|(_, bytes)| bytes).sum::<u64>() as f64 / secs
+        };
         
         // Calculate RX rate
-        let rx_bytes: u64 = self.rx_history.iter().map(|(_, bytes)| bytes).sum();
-        self.rx_bps = (rx_bytes as f64) / window_secs;
+        self.rx_bps = rate(&self.rx_history, window_secs) + rate(&self.periodic_rx_history, periodic_secs);
         
         // Calculate TX rate
-        let tx_bytes: u64 = self.tx_history.iter().map(|(_, bytes)| bytes).sum();
-        self.tx_bps = (tx_bytes as f64) / window_secs;
+        self.tx_bps = rate(&self.tx_history, window_secs) + rate(&self.periodic_tx_history, periodic_secs);
         
         // Calculate total current bandwidth
         self.current_bps = self.rx_bps + self.tx_bps;
@@ -100,8 +283,44 @@ impl BandwidthStats {
         self.peak_bps = 0.0;
         self.total_rx_bytes = 0;
         self.total_tx_bytes = 0;
+        self.max_packet_seen = 0;
+        self.urb_counts.reset();
+        self.rx_history.clear();
+        self.tx_history.clear();
+        self.periodic_rx_history.clear();
+        self.periodic_tx_history.clear();
+        self.started = Instant::now();
+    }
+    
+    /// Bytes moved per completed URB since the session started (or the last reset)
+    pub fn average_bytes_per_urb(&self) -> Option<f64> {
+        if self.urb_counts.callbacks == 0 {
+            return None;
+        }
+        let total = self.total_rx_bytes.saturating_add(self.total_tx_bytes);
+        Some(total as f64 / self.urb_counts.callbacks as f64)
+    }
+    
+    /// Carry another session's totals, URB counts and peaks over into this one;
+    /// rates keep measuring only this session's traffic
+    pub fn absorb_totals(&mut self, other: &BandwidthStats) {
+        self.total_rx_bytes = self.total_rx_bytes.saturating_add(other.total_rx_bytes);
+        self.total_tx_bytes = self.total_tx_bytes.saturating_add(other.total_tx_bytes);
+        self.urb_counts.add(&other.urb_counts);
+        self.max_packet_seen = self.max_packet_seen.max(other.max_packet_seen);
+        self.peak_bps = self.peak_bps.max(other.peak_bps);
+    }
+    
+    /// Restart rate measurement without touching totals, peaks or URB counts
+    pub fn reset_rates(&mut self) {
+        self.rx_bps = 0.0;
+        self.tx_bps = 0.0;
+        self.current_bps = 0.0;
         self.rx_history.clear();
         self.tx_history.clear();
+        self.periodic_rx_history.clear();
+        self.periodic_tx_history.clear();
+        self.started = Instant::now();
     }
     
     pub fn get_history_data(&self, max_points: usize) -> Vec<(f64, f64, f64)> {
@@ -172,6 +391,120 @@ mod tests {
         assert_eq!(stats.peak_bps, stats.current_bps);
     }
     
+    #[test]
+    fn test_rates_not_diluted_during_warmup() {
+        let mut stats = BandwidthStats::new();
+        stats.update_rx(1000);
+        
+        // Only ~0s of a 10s window has elapsed, so the rate is averaged over
+        // the minimum span rather than the whole window
+        assert_eq!(stats.rx_bps, 1000.0);
+    }
+    
+    #[test]
+    fn test_totals_saturate() {
+        let mut stats = BandwidthStats::new();
+        stats.total_rx_bytes = u64::MAX - 10;
+        stats.update_rx(1000);
+        assert_eq!(stats.total_rx_bytes, u64::MAX);
+    }
+    
+    #[test]
+    fn test_urb_size_buckets() {
+        let mut histogram = UrbSizeHistogram::default();
+        for bytes in [1, 63, 64, 511, 512, 4095, 4096, 65535, 65536, 131072] {
+            histogram.record(bytes);
+        }
+        assert_eq!(histogram.counts, [2, 2, 2, 2, 2]);
+        
+        let mut merged = UrbSizeHistogram::default();
+        merged.record(128 * 1024);
+        merged.add(&histogram);
+        assert_eq!(merged.counts, [2, 2, 2, 2, 3]);
+        assert_eq!(merged.total(), 11);
+    }
+    
+    #[test]
+    fn test_urb_counts() {
+        let mut stats = BandwidthStats::new();
+        stats.urb_counts.record(&UrbType::Submission);
+        stats.urb_counts.record(&UrbType::Submission);
+        stats.urb_counts.record(&UrbType::Callback);
+        stats.urb_counts.record(&UrbType::Error);
+        
+        assert_eq!(stats.urb_counts, UrbCounts { submissions: 2, callbacks: 1, errors: 1 });
+        
+        // A stalled completion is an error, a cancelled one an ordinary callback
+        for line in ["ffff88003b4a3c00 3575914655 C Bi:1:002:1 -32 0", "ffff88003b4a3c00 3575914655 C Bi:1:002:1 -104 0"] {
+            stats.urb_counts.record_packet(&crate::usbmon::parser::parse_usbmon_text_line(line).unwrap());
+        }
+        assert_eq!(stats.urb_counts, UrbCounts { submissions: 2, callbacks: 2, errors: 2 });
+        
+        stats.reset();
+        assert_eq!(stats.urb_counts, UrbCounts::default());
+    }
+    
+    #[test]
+    fn test_average_bytes_per_urb() {
+        let mut stats = BandwidthStats::new();
+        assert_eq!(stats.average_bytes_per_urb(), None);
+        
+        stats.update_rx(1024);
+        stats.update_tx(512);
+        for _ in 0..3 {
+            stats.urb_counts.record(&UrbType::Callback);
+        }
+        assert_eq!(stats.average_bytes_per_urb(), Some(512.0));
+    }
+    
+    #[test]
+    fn test_event_rate() {
+        let start = Instant::now();
+        let mut rate = EventRate::new(start);
+        
+        // 1 kHz polling for a little over a second
+        for ms in 0..=1000 {
+            rate.record(start + Duration::from_millis(ms));
+        }
+        assert_eq!(rate.rate_hz(start + Duration::from_millis(1000)), 1000.0);
+        
+        // Events stopped: the stale rate isn't reported forever
+        assert_eq!(rate.rate_hz(start + Duration::from_secs(3)), 0.0);
+    }
+    
+    #[test]
+    fn test_periodic_traffic_uses_its_own_window() {
+        let mut stats = BandwidthStats::new();
+        stats.history_window = Duration::from_millis(100);
+        stats.periodic_window = Duration::from_secs(10);
+        
+        stats.update_typed(64, true, TransferType::Interrupt);
+        stats.update_typed(512, true, TransferType::Bulk);
+        sleep(Duration::from_millis(150));
+        stats.update_typed(64, true, TransferType::Interrupt);
+        
+        // The bulk transfer aged out of its window, both interrupt reports stay
+        assert!(stats.rx_history.is_empty());
+        assert_eq!(stats.periodic_rx_history.len(), 2);
+        assert_eq!(stats.total_rx_bytes, 640);
+        assert_eq!(stats.rx_bps, 128.0);
+    }
+    
+    #[test]
+    fn test_rates_fall_to_zero_once_traffic_stops() {
+        let mut stats = BandwidthStats::new();
+        stats.history_window = Duration::from_millis(100);
+        stats.periodic_window = Duration::from_millis(100);
+        stats.update_rx(1000);
+        stats.update_typed(64, false, TransferType::Interrupt);
+        assert!(stats.rx_bps > 0.0 && stats.tx_bps > 0.0);
+        
+        stats.refresh(Instant::now() + Duration::from_millis(150));
+        assert_eq!((stats.rx_bps, stats.tx_bps, stats.current_bps), (0.0, 0.0, 0.0));
+        assert!(stats.peak_bps > 0.0);
+        assert_eq!(stats.total_rx_bytes, 1000);
+    }
+    
     #[test]
     fn test_history_cleanup() {
         let mut stats = BandwidthStats::new();
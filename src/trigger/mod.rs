@@ -0,0 +1,246 @@
+use anyhow::Result;
+use chrono::Local;
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::config::{TriggerConfig, TriggerKind};
+use crate::device::UsbDevice;
+use crate::export::OutputWriter;
+use crate::hooks::parse_vid_pid;
+use crate::usbmon::parser::{format_usbmon_text_line, UsbPacket};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Condition {
+    Endpoint(u8),
+    Error,
+    Bandwidth(f64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Armed,
+    /// Fired; this many packets still to take after the trigger
+    Recording(usize),
+    Stopped,
+}
+
+/// What a packet did to the trigger, for the status line
+#[derive(Debug, Clone, PartialEq)]
+pub enum TriggerEvent {
+    Fired(String),
+    /// Dump written: path and packet count
+    Saved(PathBuf, usize),
+    Failed(String),
+}
+
+/// Capture-on-condition for intermittent faults
+/// Packets of the watched devices pass through a ring of `pre_packets`
+/// until the condition is met, then `post_packets` more are kept and the
+/// whole window is written out as usbmon text, which `--stdin` replays.
+/// Payloads are only as complete as the reader captured them, so main
+/// raises `capture_len` while a trigger is configured.
+#[derive(Debug, Clone)]
+pub struct PacketTrigger {
+    condition: Option<Condition>,
+    /// Only watch this (vendor id, product id)
+    device: Option<(u16, u16)>,
+    pre_packets: usize,
+    post_packets: usize,
+    output: String,
+    rearm: bool,
+    buffer: VecDeque<UsbPacket>,
+    state: State,
+}
+
+impl Default for PacketTrigger {
+    fn default() -> Self {
+        Self::from_config(&TriggerConfig::default()).0
+    }
+}
+
+impl PacketTrigger {
+    /// Build from the `[trigger]` section; a section that can't be used
+    /// leaves the trigger disabled, with a warning saying why
+    pub fn from_config(config: &TriggerConfig) -> (Self, Vec<String>) {
+        let mut warnings = Vec::new();
+        let mut trigger = Self {
+            condition: None,
+            device: None,
+            pre_packets: config.pre_packets,
+            post_packets: config.post_packets,
+            output: config.output.clone(),
+            rearm: config.rearm,
+            buffer: VecDeque::new(),
+            state: State::Armed,
+        };
+        let Some(kind) = config.on else {
+            return (trigger, warnings);
+        };
+        
+        let condition = match kind {
+            TriggerKind::Endpoint => config.endpoint.map(Condition::Endpoint),
+            TriggerKind::Error => Some(Condition::Error),
+            TriggerKind::Bandwidth => Some(Condition::Bandwidth(config.threshold_bps)).filter(|_| config.threshold_bps > 0.0),
+        };
+        let device = match config.device.as_deref().map(parse_vid_pid) {
+            None => Ok(None),
+            Some(Some(ids)) => Ok(Some(ids)),
+            Some(None) => Err(format!("device '{}' is not VID:PID in hex", config.device.as_deref().unwrap_or_default())),
+        };
+        let mut probe = String::new();
+        let output = write!(probe, "{}", Local::now().format(&config.output))
+            .map_err(|_| format!("output '{}' has an invalid strftime field", config.output));
+        
+        match (condition, device, output) {
+            (None, _, _) if kind == TriggerKind::Endpoint => warnings.push("Ignoring endpoint trigger without an endpoint".to_string()),
+            (None, _, _) => warnings.push("Ignoring bandwidth trigger without a positive threshold_bps".to_string()),
+            (_, Err(e), _) | (_, _, Err(e)) => warnings.push(format!("Ignoring trigger: {}", e)),
+            (Some(condition), Ok(device), Ok(())) => {
+                trigger.condition = Some(condition);
+                trigger.device = device;
+            }
+        }
+        (trigger, warnings)
+    }
+    
+    pub fn is_enabled(&self) -> bool {
+        self.condition.is_some()
+    }
+    
+    /// Feed a packet together with its device, after the device's stats took it in
+    pub fn record(&mut self, packet: &UsbPacket, device: &UsbDevice) -> Option<TriggerEvent> {
+        let condition = self.condition?;
+        if self.state == State::Stopped || !self.watches(device) {
+            return None;
+        }
+        self.buffer.push_back(packet.clone());
+        
+        match self.state {
+            State::Armed => {
+                let Some(reason) = condition_met(condition, packet, device) else {
+                    self.keep_armed();
+                    return None;
+                };
+                // The triggering packet stays on top of the pre-trigger context
+                if self.post_packets == 0 {
+                    return Some(self.dump());
+                }
+                self.state = State::Recording(self.post_packets);
+                Some(TriggerEvent::Fired(format!("{} on {}", reason, device.key())))
+            }
+            State::Recording(remaining) => {
+                if remaining > 1 {
+                    self.state = State::Recording(remaining - 1);
+                    None
+                } else {
+                    Some(self.dump())
+                }
+            }
+            State::Stopped => None,
+        }
+    }
+    
+    /// Write out a capture cut short, e.g. by quitting before the post-trigger
+    /// window filled; nothing when the trigger hasn't fired
+    pub fn flush(&mut self) -> Option<TriggerEvent> {
+        matches!(self.state, State::Recording(_)).then(|| self.dump())
+    }
+    
+    fn watches(&self, device: &UsbDevice) -> bool {
+        self.device.is_none_or(|(vendor_id, product_id)| {
+            device.vendor_id == Some(vendor_id) && device.product_id == Some(product_id)
+        })
+    }
+    
+    /// Trim the ring to its pre-trigger size and carry on with the next packet
+    fn keep_armed(&mut self) {
+        if self.buffer.len() > self.pre_packets {
+            self.buffer.drain(..self.buffer.len() - self.pre_packets);
+        }
+    }
+    
+    fn dump(&mut self) -> TriggerEvent {
+        let packets: Vec<UsbPacket> = self.buffer.drain(..).collect();
+        self.state = if self.rearm { State::Armed } else { State::Stopped };
+        
+        let path = PathBuf::from(Local::now().format(&self.output).to_string());
+        match write_dump(&path, &packets) {
+            Ok(()) => TriggerEvent::Saved(path, packets.len()),
+            Err(e) => TriggerEvent::Failed(format!("Failed to write trigger capture {}: {}", path.display(), e)),
+        }
+    }
+}
+
+/// Why `packet` meets `condition`, if it does
+fn condition_met(condition: Condition, packet: &UsbPacket, device: &UsbDevice) -> Option<String> {
+    match condition {
+        Condition::Endpoint(address) => (packet.endpoint_address() == address && packet.is_data_packet())
+            .then(|| format!("traffic on endpoint {:#04x}", address)),
+        Condition::Error => packet.is_failure().then(|| format!("status {}", packet.status)),
+        Condition::Bandwidth(threshold_bps) => (device.bandwidth_stats.current_bps > threshold_bps)
+            .then(|| format!("{:.0} B/s over {:.0} B/s", device.bandwidth_stats.current_bps, threshold_bps)),
+    }
+}
+
+fn write_dump(path: &Path, packets: &[UsbPacket]) -> Result<()> {
+    let mut out = OutputWriter::create(path)?;
+    for packet in packets {
+        writeln!(out, "{}", format_usbmon_text_line(packet))?;
+    }
+    out.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::usbmon::parser::parse_usbmon_text_line;
+    
+    #[test]
+    fn test_error_trigger_keeps_context_around_the_fault() {
+        let dir = std::env::temp_dir().join(format!("usbtop-ng-trigger-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = TriggerConfig {
+            on: Some(TriggerKind::Error),
+            pre_packets: 2,
+            post_packets: 2,
+            output: dir.join("capture.txt").to_string_lossy().into_owned(),
+            ..TriggerConfig::default()
+        };
+        let (mut trigger, warnings) = PacketTrigger::from_config(&config);
+        assert!(warnings.is_empty());
+        let device = UsbDevice::new(2, 4);
+        let mut feed = |line: &str| trigger.record(&parse_usbmon_text_line(line).unwrap(), &device);
+        
+        for tag in ["01", "02", "03"] {
+            assert_eq!(feed(&format!("ffff8800000000{} 100 S Bo:2:004:2 -115 4 = 01020304", tag)), None);
+        }
+        // A cancelled URB is routine, a stall is not
+        assert_eq!(feed("ffff880000000004 200 C Bi:2:004:1 -104 0"), None);
+        assert_eq!(feed("ffff880000000005 300 C Bi:2:004:1 -32 0"), Some(TriggerEvent::Fired("status -32 on 002:004".to_string())));
+        assert_eq!(feed("ffff880000000006 400 C Bi:2:004:1 0 0"), None);
+        let Some(TriggerEvent::Saved(path, count)) = feed("ffff880000000007 500 C Bi:2:004:1 0 0") else {
+            panic!("post-trigger window should have been written");
+        };
+        // One-shot: later faults are ignored
+        assert_eq!(feed("ffff880000000008 600 C Bi:2:004:1 -71 0"), None);
+        
+        let dump = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(count, 5);
+        let tags: Vec<&str> = dump.lines().map(|line| &line[14..16]).collect();
+        assert_eq!(tags, ["03", "04", "05", "06", "07"]);
+    }
+    
+    #[test]
+    fn test_unusable_trigger_is_disabled() {
+        let config = TriggerConfig { on: Some(TriggerKind::Endpoint), ..TriggerConfig::default() };
+        let (trigger, warnings) = PacketTrigger::from_config(&config);
+        assert!(!trigger.is_enabled());
+        assert_eq!(warnings, vec!["Ignoring endpoint trigger without an endpoint"]);
+        
+        let config = TriggerConfig { on: Some(TriggerKind::Error), device: Some("usb".to_string()), ..TriggerConfig::default() };
+        assert!(!PacketTrigger::from_config(&config).0.is_enabled());
+    }
+}
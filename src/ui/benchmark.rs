@@ -0,0 +1,404 @@
+use anyhow::{Result, anyhow};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Gauge, Paragraph},
+    Frame, Terminal,
+};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use std::io::{self, IsTerminal};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::UnboundedReceiver;
+
+use super::widgets::format_count;
+use crate::device::{format_bandwidth, format_speed, DeviceKey, UsbDevice};
+use crate::logging::decorated;
+use crate::monitor::ReaderTasks;
+use crate::usbmon::parser::UsbPacket;
+
+use super::colors::*;
+use super::{restore_terminal, setup_terminal};
+
+/// Single-device throughput dashboard state
+pub struct BenchmarkApp {
+    pub device: UsbDevice,
+    pub started: Instant,
+    pub samples: Vec<f64>,
+    pub sample_interval: Duration,
+    /// Stop on its own after this long (`--duration`)
+    pub duration: Option<Duration>,
+    last_sample: Instant,
+    /// First and latest packet for the device, bounding the average
+    first_packet: Option<Instant>,
+    last_packet: Option<Instant>,
+}
+
+/// Results printed when a benchmark run ends
+#[derive(Debug, Clone)]
+pub struct BenchmarkSummary {
+    pub device_label: String,
+    pub duration: Duration,
+    pub total_bytes: u64,
+    pub min_bps: f64,
+    pub max_bps: f64,
+    pub avg_bps: f64,
+    pub speed_label: String,
+    pub max_bandwidth: f64,
+}
+
+impl BenchmarkApp {
+    pub fn new(key: DeviceKey, sample_interval: Duration) -> Self {
+        let mut device = UsbDevice::new(key.bus_id(), key.device_id());
+        let _ = device.update_from_sysfs();
+        
+        Self {
+            device,
+            started: Instant::now(),
+            samples: Vec::new(),
+            sample_interval,
+            duration: None,
+            last_sample: Instant::now(),
+            first_packet: None,
+            last_packet: None,
+        }
+    }
+    
+    pub fn with_duration(mut self, duration: Option<Duration>) -> Self {
+        self.duration = duration;
+        self
+    }
+    
+    /// Only packets for the benchmarked device are accounted
+    pub fn process_packet(&mut self, packet: &UsbPacket) {
+        if DeviceKey(packet.bus_id, packet.device_id) == self.device.key() {
+            self.device.record_packet(packet);
+            let now = Instant::now();
+            self.first_packet.get_or_insert(now);
+            self.last_packet = Some(now);
+        }
+    }
+    
+    fn is_finished(&self) -> bool {
+        self.duration.is_some_and(|duration| self.started.elapsed() >= duration)
+    }
+    
+    pub fn total_bytes(&self) -> u64 {
+        self.device.bandwidth_stats.total_rx_bytes.saturating_add(self.device.bandwidth_stats.total_tx_bytes)
+    }
+    
+    /// Average over the whole transfer, not just the sliding window
+    /// Timed from the first packet to the latest, so idle time before the
+    /// workload starts or after it ends doesn't dilute the figure.
+    pub fn average_bps(&self) -> f64 {
+        let (Some(first), Some(last)) = (self.first_packet, self.last_packet) else {
+            return 0.0;
+        };
+        let elapsed = last.duration_since(first).as_secs_f64();
+        if elapsed > 0.0 {
+            self.total_bytes() as f64 / elapsed
+        } else {
+            0.0
+        }
+    }
+    
+    /// Bring the rates up to `now` and take a sample when one is due
+    /// Rates only move when packets arrive, so without the refresh "Current"
+    /// and the trailing samples would hold the last rate after traffic stops.
+    fn tick(&mut self, now: Instant) {
+        self.device.refresh_rates(now);
+        if now.saturating_duration_since(self.last_sample) >= self.sample_interval {
+            self.samples.push(self.device.bandwidth_stats.current_bps);
+            self.last_sample = now;
+        }
+    }
+    
+    /// Min and max over the samples taken while traffic was flowing
+    /// Idle samples before the workload starts or after it ends are left out
+    /// of the minimum, the same way they're kept out of the average.
+    pub fn summary(&self) -> BenchmarkSummary {
+        let min_bps = self.samples.iter().cloned().filter(|&bps| bps > 0.0).fold(f64::INFINITY, f64::min);
+        let max_bps = self.samples.iter().cloned().fold(0.0, f64::max);
+        
+        BenchmarkSummary {
+            device_label: device_label(&self.device),
+            duration: self.started.elapsed(),
+            total_bytes: self.total_bytes(),
+            min_bps: if min_bps.is_finite() { min_bps } else { 0.0 },
+            max_bps,
+            avg_bps: self.average_bps(),
+            speed_label: format_speed(&self.device.speed),
+            max_bandwidth: self.device.speed.to_practical_bytes_per_second(),
+        }
+    }
+}
+
+impl BenchmarkSummary {
+    /// How far the average is from `expected_bps`, in percent of it
+    pub fn deviation_from(&self, expected_bps: f64) -> f64 {
+        if expected_bps > 0.0 {
+            (self.avg_bps - expected_bps) / expected_bps * 100.0
+        } else if self.avg_bps > 0.0 {
+            f64::INFINITY
+        } else {
+            0.0
+        }
+    }
+    
+    /// Print the `--expect` verdict; true when the average is within
+    /// `tolerance_pct` percent of `expected_bps`
+    pub fn check_expectation(&self, expected_bps: f64, tolerance_pct: f64) -> bool {
+        let deviation = self.deviation_from(expected_bps);
+        let within = deviation.abs() <= tolerance_pct;
+        let verdict = format!(
+            "Average {} is {:+.1}% from the expected {} (tolerance ±{}%)",
+            format_bandwidth(self.avg_bps),
+            deviation,
+            format_bandwidth(expected_bps),
+            tolerance_pct,
+        );
+        println!("{}", decorated(if within { "✅" } else { "❌" }, &verdict));
+        within
+    }
+    
+    fn utilization(&self, bps: f64) -> f64 {
+        if self.max_bandwidth > 0.0 {
+            bps / self.max_bandwidth * 100.0
+        } else {
+            0.0
+        }
+    }
+    
+    pub fn print(&self) {
+        println!("{}", decorated("📊", &format!("Benchmark summary for {}", self.device_label)));
+        println!("   Duration:    {:.1}s", self.duration.as_secs_f64());
+        println!("   Transferred: {:.1} MB ({} bytes)", self.total_bytes as f64 / 1_000_000.0, format_count(self.total_bytes));
+        println!("   Min:         {}", format_bandwidth(self.min_bps));
+        println!("   Max:         {}", format_bandwidth(self.max_bps));
+        println!("   Average:     {}", format_bandwidth(self.avg_bps));
+        if self.max_bandwidth > 0.0 {
+            println!(
+                "   Utilization: {:.1}% average, {:.1}% peak of {}",
+                self.utilization(self.avg_bps),
+                self.utilization(self.max_bps),
+                self.speed_label,
+            );
+        } else {
+            println!("   Utilization: unknown (device speed not detected)");
+        }
+    }
+}
+
+fn device_label(device: &UsbDevice) -> String {
+    let name = device.product.clone().unwrap_or_else(|| "Unknown device".to_string());
+    format!("{} {}", device.key(), name)
+}
+
+/// Parse a rate in bytes per second, with an optional decimal K, M or G suffix
+/// (e.g. "30M" for 30 MB/s)
+pub fn parse_rate(value: &str) -> Result<f64, String> {
+    let value = value.trim();
+    let (number, scale) = match value.char_indices().last() {
+        Some((index, 'K' | 'k')) => (&value[..index], 1e3),
+        Some((index, 'M' | 'm')) => (&value[..index], 1e6),
+        Some((index, 'G' | 'g')) => (&value[..index], 1e9),
+        _ => (value, 1.0),
+    };
+    match number.trim().parse::<f64>() {
+        Ok(rate) if rate >= 0.0 && rate.is_finite() => Ok(rate * scale),
+        _ => Err(format!("expected bytes per second such as 30000000 or 30M, got '{}'", value)),
+    }
+}
+
+pub fn run_benchmark(
+    mut app: BenchmarkApp,
+    mut packets: UnboundedReceiver<UsbPacket>,
+    tasks: ReaderTasks,
+) -> Result<BenchmarkSummary> {
+    // Without a terminal (e.g. in CI) a timed run goes headless
+    if !io::stdout().is_terminal() {
+        if app.duration.is_none() {
+            tasks.shutdown_blocking();
+            return Err(anyhow!("--benchmark needs a terminal, or --duration to run without one"));
+        }
+        run_headless(&mut app, &mut packets);
+        tasks.shutdown_blocking();
+        return Ok(app.summary());
+    }
+    
+    let mut terminal = setup_terminal()?;
+    
+    let result = run_benchmark_loop(&mut terminal, &mut app, &mut packets);
+    tasks.shutdown_blocking();
+    
+    restore_terminal(&mut terminal)?;
+    
+    result.map(|_| app.summary())
+}
+
+fn run_headless(app: &mut BenchmarkApp, packets: &mut UnboundedReceiver<UsbPacket>) {
+    while !app.is_finished() {
+        while let Ok(packet) = packets.try_recv() {
+            app.process_packet(&packet);
+        }
+        app.tick(Instant::now());
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+fn run_benchmark_loop(
+    terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>,
+    app: &mut BenchmarkApp,
+    packets: &mut UnboundedReceiver<UsbPacket>,
+) -> Result<()> {
+    loop {
+        while let Ok(packet) = packets.try_recv() {
+            app.process_packet(&packet);
+        }
+        app.tick(Instant::now());
+        if app.is_finished() {
+            return Ok(());
+        }
+        
+        terminal.draw(|f| draw_benchmark(f, app))?;
+        
+        if event::poll(Duration::from_millis(50))? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press
+                    && matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+                {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+fn draw_benchmark(f: &mut Frame, app: &BenchmarkApp) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),     // Device
+            Constraint::Length(5),     // Readouts
+            Constraint::Length(3),     // Utilization gauge
+            Constraint::Min(0),
+            Constraint::Length(1),     // Footer
+        ])
+        .split(f.size());
+    
+    let title = Paragraph::new(Line::from(vec![
+        Span::styled(device_label(&app.device), Style::default().fg(ACCENT_COLOR).add_modifier(Modifier::BOLD)),
+        Span::raw(format!("  {}  ", format_speed(&app.device.speed))),
+        Span::raw(format!("elapsed {:.0}s", app.started.elapsed().as_secs_f64())),
+    ]))
+    .block(Block::default().borders(Borders::ALL).title(" Benchmark "));
+    f.render_widget(title, chunks[0]);
+    
+    let readouts = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(25); 4])
+        .split(chunks[1]);
+    
+    let stats = &app.device.bandwidth_stats;
+    draw_readout(f, readouts[0], "Current", format_bandwidth(stats.current_bps));
+    draw_readout(f, readouts[1], "Peak", format_bandwidth(stats.peak_bps));
+    draw_readout(f, readouts[2], "Average", format_bandwidth(app.average_bps()));
+    draw_readout(f, readouts[3], "Total", format!("{:.1} MB", app.total_bytes() as f64 / 1_000_000.0));
+    
+    let max_bandwidth = app.device.speed.to_practical_bytes_per_second();
+    let ratio = if max_bandwidth > 0.0 { (stats.current_bps / max_bandwidth).min(1.0) } else { 0.0 };
+    let gauge = Gauge::default()
+        .block(Block::default().borders(Borders::ALL).title(" Utilization (practical max) "))
+        .gauge_style(Style::default().fg(PRIMARY_COLOR))
+        .ratio(ratio)
+        .label(format!("{:.1}%", ratio * 100.0));
+    f.render_widget(gauge, chunks[2]);
+    
+    let footer = Paragraph::new("Press q/Esc to stop and print the summary")
+        .style(Style::default().fg(TEXT_COLOR));
+    f.render_widget(footer, chunks[4]);
+}
+
+fn draw_readout(f: &mut Frame, area: Rect, label: &str, value: String) {
+    let readout = Paragraph::new(vec![
+        Line::from(""),
+        Line::from(Span::styled(value, Style::default().fg(PRIMARY_COLOR).add_modifier(Modifier::BOLD))),
+    ])
+    .alignment(Alignment::Center)
+    .block(Block::default().borders(Borders::ALL).title(format!(" {} ", label)));
+    
+    f.render_widget(readout, area);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parse_rate_suffixes() {
+        assert_eq!(parse_rate("30000000"), Ok(30_000_000.0));
+        assert_eq!(parse_rate("35M"), Ok(35_000_000.0));
+        assert_eq!(parse_rate("1.5k"), Ok(1500.0));
+        assert_eq!(parse_rate("2G"), Ok(2_000_000_000.0));
+        assert!(parse_rate("fast").is_err());
+        assert!(parse_rate("-5M").is_err());
+    }
+    
+    #[test]
+    fn test_samples_fall_to_zero_after_the_workload_stops() {
+        use crate::stats::RATE_WINDOW;
+        use crate::usbmon::parser::parse_usbmon_text_line;
+        
+        let interval = Duration::from_secs(1);
+        let mut app = BenchmarkApp::new(DeviceKey(2, 5), interval);
+        // Idle before the workload starts
+        let start = Instant::now() + interval;
+        app.tick(start);
+        app.process_packet(&parse_usbmon_text_line("ffff88003b4a3c00 100 C Bi:2:005:1 0 16384 = 00").unwrap());
+        std::thread::sleep(Duration::from_millis(50));
+        app.process_packet(&parse_usbmon_text_line("ffff88003b4a3c00 200 C Bi:2:005:1 0 4096 = 00").unwrap());
+        // Another device's traffic isn't counted
+        app.process_packet(&parse_usbmon_text_line("ffff88003b4a3d00 300 C Bi:2:006:1 0 4096 = 00").unwrap());
+        app.tick(start + interval);
+        let busy = app.device.bandwidth_stats.current_bps;
+        assert!(busy > 0.0);
+        
+        // Idle again for longer than the rate window
+        let stopped = start + interval * 2 + RATE_WINDOW;
+        app.tick(stopped);
+        assert_eq!(app.device.bandwidth_stats.current_bps, 0.0);
+        assert_eq!(app.samples, vec![0.0, busy, 0.0]);
+        
+        let summary = app.summary();
+        assert_eq!(summary.total_bytes, 16384 + 4096);
+        assert_eq!((summary.min_bps, summary.max_bps), (busy, busy));
+        // Timed from the first packet to the last, at least 50ms apart
+        assert!(summary.avg_bps > 0.0 && summary.avg_bps <= (16384 + 4096) as f64 / 0.05);
+        
+        // Nothing moved at all
+        let mut idle = BenchmarkApp::new(DeviceKey(2, 5), interval);
+        idle.tick(start);
+        idle.tick(start + interval);
+        let summary = idle.summary();
+        assert_eq!((summary.min_bps, summary.max_bps, summary.avg_bps), (0.0, 0.0, 0.0));
+    }
+    
+    #[test]
+    fn test_deviation_from_expected() {
+        let summary = BenchmarkSummary {
+            device_label: "2:005".to_string(),
+            duration: Duration::from_secs(10),
+            total_bytes: 330_000_000,
+            min_bps: 30e6,
+            max_bps: 36e6,
+            avg_bps: 33e6,
+            speed_label: "480 Mbps".to_string(),
+            max_bandwidth: 40e6,
+        };
+        assert!((summary.deviation_from(30e6) - 10.0).abs() < 1e-9);
+        assert!((summary.deviation_from(40e6) + 17.5).abs() < 1e-9);
+        assert!(summary.check_expectation(32e6, 5.0));
+        assert!(!summary.check_expectation(40e6, 5.0));
+    }
+}
@@ -1,5 +1,7 @@
 use ratatui::style::Color;
 
+use crate::config::UtilizationColorsConfig;
+
 // Color palette inspired by bashtop
 pub const PRIMARY_COLOR: Color = Color::Rgb(0, 191, 255);      // Bright blue
 pub const SECONDARY_COLOR: Color = Color::Rgb(255, 140, 0);     // Orange
@@ -24,6 +26,117 @@ pub const BANDWIDTH_MEDIUM: Color = Color::Rgb(255, 255, 0);   // Yellow (medium
 pub const BANDWIDTH_HIGH: Color = Color::Rgb(255, 165, 0);     // Orange (high usage)
 pub const BANDWIDTH_CRITICAL: Color = Color::Rgb(255, 0, 0);   // Red (critical usage)
 
-// Disconnected device styling
-pub const DISCONNECTED_BG: Color = Color::Gray;
-pub const DISCONNECTED_FG: Color = Color::White;
\ No newline at end of file
+// Disconnected device styling: dimmed text instead of a background, so the
+// selection highlight still shows on a disconnected row
+pub const DISCONNECTED_FG: Color = Color::Rgb(128, 128, 128);   // Gray
+pub const DISCONNECTED_FADED: Color = Color::Rgb(60, 60, 60);   // Near background
+
+/// Color transitions for utilization displays
+/// A ratio below `thresholds[0]` uses `colors[0]`, below `thresholds[1]`
+/// uses `colors[1]`, and so on; at or above the last threshold the last color.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UtilizationScale {
+    pub thresholds: Vec<f64>,
+    pub colors: Vec<Color>,
+}
+
+impl Default for UtilizationScale {
+    fn default() -> Self {
+        Self {
+            thresholds: vec![0.25, 0.5, 0.75],
+            colors: vec![BANDWIDTH_LOW, BANDWIDTH_MEDIUM, BANDWIDTH_HIGH, BANDWIDTH_CRITICAL],
+        }
+    }
+}
+
+impl UtilizationScale {
+    /// Build from `[colors.utilization]`, falling back to the defaults on invalid input
+    pub fn from_config(config: &UtilizationColorsConfig) -> (Self, Vec<String>) {
+        let colors: Option<Vec<Color>> = config.colors.iter().map(|c| parse_hex_color(c)).collect();
+        let Some(colors) = colors else {
+            return (Self::default(), vec!["Invalid color in [colors.utilization]; using the default scale".to_string()]);
+        };
+        
+        let thresholds: Vec<f64> = config.thresholds.iter().map(|percent| percent / 100.0).collect();
+        if colors.len() != thresholds.len() + 1 {
+            return (Self::default(), vec![format!(
+                "[colors.utilization] needs one more color than thresholds ({} colors, {} thresholds); using the default scale",
+                colors.len(), thresholds.len(),
+            )]);
+        }
+        if thresholds.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return (Self::default(), vec!["[colors.utilization] thresholds must be ascending; using the default scale".to_string()]);
+        }
+        
+        (Self { thresholds, colors }, Vec::new())
+    }
+    
+    /// Color for a utilization ratio (0.0 - 1.0)
+    pub fn color_for(&self, ratio: f64) -> Color {
+        let level = self.thresholds.iter().take_while(|threshold| ratio >= **threshold).count();
+        self.colors[level]
+    }
+}
+
+/// Blend two RGB colors; `t` = 0.0 gives `from`, 1.0 gives `to`
+/// Non-RGB colors can't be blended and switch over halfway
+pub fn blend(from: Color, to: Color, t: f64) -> Color {
+    let t = t.clamp(0.0, 1.0);
+    match (from, to) {
+        (Color::Rgb(r1, g1, b1), Color::Rgb(r2, g2, b2)) => {
+            let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+            Color::Rgb(mix(r1, r2), mix(g1, g2), mix(b1, b2))
+        }
+        _ if t < 0.5 => from,
+        _ => to,
+    }
+}
+
+/// Parse "#RRGGBB" (the leading '#' is optional)
+pub fn parse_hex_color(hex: &str) -> Option<Color> {
+    let hex = hex.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some(Color::Rgb(channel(0)?, channel(2)?, channel(4)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_default_scale_matches_builtin_thresholds() {
+        let (scale, warnings) = UtilizationScale::from_config(&UtilizationColorsConfig::default());
+        assert!(warnings.is_empty());
+        assert_eq!(scale.color_for(0.1), BANDWIDTH_LOW);
+        assert_eq!(scale.color_for(0.25), BANDWIDTH_MEDIUM);
+        assert_eq!(scale.color_for(0.6), BANDWIDTH_HIGH);
+        assert_eq!(scale.color_for(1.0), BANDWIDTH_CRITICAL);
+    }
+    
+    #[test]
+    fn test_blend() {
+        assert_eq!(blend(ERROR_COLOR, DISCONNECTED_FADED, 0.0), ERROR_COLOR);
+        assert_eq!(blend(ERROR_COLOR, DISCONNECTED_FADED, 2.0), DISCONNECTED_FADED);
+        assert_eq!(blend(Color::Rgb(0, 0, 0), Color::Rgb(200, 100, 50), 0.5), Color::Rgb(100, 50, 25));
+    }
+    
+    #[test]
+    fn test_custom_scale_red_only_above_90() {
+        let config = UtilizationColorsConfig {
+            thresholds: vec![90.0],
+            colors: vec!["#00FF00".to_string(), "#FF0000".to_string()],
+        };
+        let (scale, warnings) = UtilizationScale::from_config(&config);
+        assert!(warnings.is_empty());
+        assert_eq!(scale.color_for(0.89), Color::Rgb(0, 255, 0));
+        assert_eq!(scale.color_for(0.95), Color::Rgb(255, 0, 0));
+        
+        let bad = UtilizationColorsConfig { thresholds: vec![50.0, 20.0], colors: config.colors.clone() };
+        let (scale, warnings) = UtilizationScale::from_config(&bad);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(scale, UtilizationScale::default());
+    }
+}
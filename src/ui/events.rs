@@ -0,0 +1,84 @@
+use std::collections::VecDeque;
+
+use crate::device::DeviceKey;
+
+/// Events kept in memory; enough to cover the graph's time span on a busy system
+pub const EVENT_LOG_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceEventKind {
+    Connected,
+    Disconnected,
+}
+
+impl DeviceEventKind {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            DeviceEventKind::Connected => "▲",
+            DeviceEventKind::Disconnected => "▼",
+        }
+    }
+}
+
+/// A topology change, timestamped on the same clock as the bandwidth graph
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceEvent {
+    pub at_secs: f64,
+    pub key: DeviceKey,
+    pub kind: DeviceEventKind,
+}
+
+/// Bounded log of device connect/disconnect events, oldest first
+#[derive(Debug, Clone)]
+pub struct EventLog {
+    events: VecDeque<DeviceEvent>,
+    capacity: usize,
+}
+
+impl Default for EventLog {
+    fn default() -> Self {
+        Self::new(EVENT_LOG_CAPACITY)
+    }
+}
+
+impl EventLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            events: VecDeque::new(),
+            capacity,
+        }
+    }
+    
+    pub fn record(&mut self, at_secs: f64, key: DeviceKey, kind: DeviceEventKind) {
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(DeviceEvent { at_secs, key, kind });
+    }
+    
+    /// Events with `start <= at_secs <= end`
+    pub fn in_span(&self, start: f64, end: f64) -> impl Iterator<Item = &DeviceEvent> {
+        self.events.iter().filter(move |event| event.at_secs >= start && event.at_secs <= end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_span_filter_and_capacity() {
+        let mut log = EventLog::new(3);
+        log.record(1.0, DeviceKey(1, 2), DeviceEventKind::Connected);
+        log.record(5.0, DeviceKey(1, 3), DeviceEventKind::Connected);
+        log.record(9.0, DeviceKey(1, 2), DeviceEventKind::Disconnected);
+        
+        let visible: Vec<f64> = log.in_span(4.0, 10.0).map(|event| event.at_secs).collect();
+        assert_eq!(visible, vec![5.0, 9.0]);
+        
+        // Oldest event is dropped once the log is full
+        log.record(12.0, DeviceKey(2, 1), DeviceEventKind::Connected);
+        assert_eq!(log.in_span(0.0, f64::MAX).count(), 3);
+        assert_eq!(log.in_span(0.0, 2.0).count(), 0);
+    }
+}
@@ -0,0 +1,353 @@
+use anyhow::Result;
+use chrono::Utc;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Row, Table},
+    Frame, Terminal,
+};
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedReceiver;
+
+use super::colors::*;
+use super::widgets::{format_ago, format_bytes, format_count};
+use super::{restore_terminal, setup_terminal};
+use crate::device::format_bandwidth;
+use crate::fleet::{ClockOffset, HostEvent};
+use crate::monitor::ReaderTasks;
+use crate::snapshot::{DeviceSnapshot, Message};
+
+/// A host's devices are shown as out of date once its latest snapshot is this old
+const STALE_AFTER: Duration = Duration::from_secs(5);
+
+/// Skew worth pointing out; below it the offset is mostly transit time
+const SKEW_WARNING: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HostStatus {
+    Connecting,
+    Connected,
+    /// Lost, with the local time of the next attempt
+    Retrying { reason: String, at_ms: i64 },
+}
+
+/// What `--connect` knows about one host
+#[derive(Debug, Clone)]
+pub struct HostState {
+    pub name: String,
+    pub status: HostStatus,
+    /// Devices in the latest snapshot, kept while the host is away
+    pub devices: Vec<DeviceSnapshot>,
+    /// When the latest snapshot was taken, by the host's clock
+    snapshot_ms: Option<i64>,
+    clock: ClockOffset,
+}
+
+impl HostState {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            status: HostStatus::Connecting,
+            devices: Vec::new(),
+            snapshot_ms: None,
+            clock: ClockOffset::default(),
+        }
+    }
+    
+    /// How long ago the latest snapshot was taken, by the local clock
+    /// The snapshot's own timestamp is shifted by the host's clock offset
+    /// first, so a host whose clock runs ahead or behind doesn't look fresher
+    /// or staler than it is.
+    pub fn age(&self, now_ms: i64) -> Option<Duration> {
+        let taken_ms = self.snapshot_ms? + self.clock.offset_ms()?;
+        Some(Duration::from_millis(now_ms.saturating_sub(taken_ms).max(0) as u64))
+    }
+    
+    /// Whether the devices shown for the host no longer reflect it
+    pub fn is_stale(&self, now_ms: i64) -> bool {
+        self.status != HostStatus::Connected || self.age(now_ms).is_none_or(|age| age > STALE_AFTER)
+    }
+    
+    /// How far the host's clock is behind ours (positive) or ahead (negative)
+    pub fn skew(&self) -> Option<i64> {
+        self.clock.offset_ms()
+    }
+}
+
+/// Devices of several hosts, each reached through its own agent
+pub struct FleetApp {
+    pub hosts: Vec<HostState>,
+}
+
+impl FleetApp {
+    pub fn new(hosts: &[String]) -> Self {
+        Self {
+            hosts: hosts.iter().map(|name| HostState::new(name)).collect(),
+        }
+    }
+    
+    pub fn apply(&mut self, index: usize, event: HostEvent, now_ms: i64) {
+        let Some(host) = self.hosts.get_mut(index) else {
+            return;
+        };
+        match event {
+            HostEvent::Connected => {
+                host.status = HostStatus::Connected;
+                // The host may have had its clock corrected while away
+                host.clock = ClockOffset::default();
+            }
+            HostEvent::Snapshot { message: Message::Snapshot { timestamp_ms, devices, .. }, received_ms } => {
+                host.clock.record(received_ms, timestamp_ms);
+                host.snapshot_ms = Some(timestamp_ms);
+                host.devices = devices;
+            }
+            HostEvent::Disconnected { reason, retry_in } => {
+                host.status = HostStatus::Retrying { reason, at_ms: now_ms + retry_in.as_millis() as i64 };
+            }
+        }
+    }
+    
+    /// Every host's devices tagged with their host, busiest first
+    /// Devices with equal rates keep host, then bus:dev order.
+    pub fn rows(&self) -> Vec<(&HostState, &DeviceSnapshot)> {
+        let mut rows: Vec<_> = self.hosts.iter()
+            .flat_map(|host| host.devices.iter().map(move |device| (host, device)))
+            .collect();
+        rows.sort_by(|a, b| (b.1.rx_bps + b.1.tx_bps).total_cmp(&(a.1.rx_bps + a.1.tx_bps)));
+        rows
+    }
+    
+    /// Combined rate of the hosts whose figures are current
+    pub fn total_bps(&self, now_ms: i64) -> f64 {
+        self.hosts.iter()
+            .filter(|host| !host.is_stale(now_ms))
+            .flat_map(|host| &host.devices)
+            .map(|device| device.rx_bps + device.tx_bps)
+            .sum()
+    }
+}
+
+/// Show the hosts' devices until q or Esc, then stop the connections
+pub fn run_fleet(
+    mut app: FleetApp,
+    mut events: UnboundedReceiver<(usize, HostEvent)>,
+    tasks: ReaderTasks,
+) -> Result<()> {
+    let mut terminal = setup_terminal()?;
+    let result = run_fleet_loop(&mut terminal, &mut app, &mut events);
+    tasks.shutdown_blocking();
+    restore_terminal(&mut terminal)?;
+    result
+}
+
+fn run_fleet_loop(
+    terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>,
+    app: &mut FleetApp,
+    events: &mut UnboundedReceiver<(usize, HostEvent)>,
+) -> Result<()> {
+    loop {
+        let now_ms = Utc::now().timestamp_millis();
+        while let Ok((index, event)) = events.try_recv() {
+            app.apply(index, event, now_ms);
+        }
+        
+        terminal.draw(|f| draw_fleet(f, app, now_ms))?;
+        
+        if event::poll(Duration::from_millis(250))? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press
+                    && matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+                {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+fn draw_fleet(f: &mut Frame, app: &FleetApp, now_ms: i64) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),                              // Title
+            Constraint::Length(app.hosts.len() as u16 + 3),     // Hosts
+            Constraint::Min(0),                                 // Devices
+            Constraint::Length(1),                              // Footer
+        ])
+        .split(f.size());
+    
+    let rows = app.rows();
+    let connected = app.hosts.iter().filter(|host| host.status == HostStatus::Connected).count();
+    let title = Paragraph::new(Line::from(vec![
+        Span::styled(format_bandwidth(app.total_bps(now_ms)), Style::default().fg(PRIMARY_COLOR).add_modifier(Modifier::BOLD)),
+        Span::raw(format!("  {} of {} hosts connected, {} devices", connected, app.hosts.len(), rows.len())),
+    ]))
+    .block(Block::default().borders(Borders::ALL).title(" usbtop-ng fleet "));
+    f.render_widget(title, chunks[0]);
+    
+    let header = Row::new(vec!["Host", "Status", "Devices", "Bandwidth", "Clock", "Updated"])
+        .style(Style::default().fg(ACCENT_COLOR).add_modifier(Modifier::BOLD))
+        .height(1);
+    let hosts: Vec<Row> = app.hosts.iter()
+        .map(|host| {
+            let (status, color) = match &host.status {
+                HostStatus::Connecting => ("connecting".to_string(), WARNING_COLOR),
+                HostStatus::Connected if host.is_stale(now_ms) => ("no recent data".to_string(), WARNING_COLOR),
+                HostStatus::Connected => ("connected".to_string(), SUCCESS_COLOR),
+                HostStatus::Retrying { reason, at_ms } => (
+                    format!("retry in {}s: {}", ((at_ms - now_ms).max(0) + 999) / 1000, reason),
+                    ERROR_COLOR,
+                ),
+            };
+            let bandwidth: f64 = host.devices.iter().map(|device| device.rx_bps + device.tx_bps).sum();
+            let clock = match host.skew() {
+                Some(skew) if skew.unsigned_abs() >= SKEW_WARNING.as_millis() as u64 => format!("{:+.1}s skew", skew as f64 / 1000.0),
+                Some(_) => "in sync".to_string(),
+                None => "-".to_string(),
+            };
+            Row::new(vec![
+                host.name.clone(),
+                status,
+                format_count(host.devices.len() as u64),
+                format_bandwidth(bandwidth),
+                clock,
+                host.age(now_ms).map(format_ago).unwrap_or_else(|| "-".to_string()),
+            ])
+            .style(Style::default().fg(color))
+        })
+        .collect();
+    let widths = [
+        Constraint::Length(20),  // Host
+        Constraint::Min(20),     // Status
+        Constraint::Length(8),   // Devices
+        Constraint::Length(12),  // Bandwidth
+        Constraint::Length(12),  // Clock
+        Constraint::Length(12),  // Updated
+    ];
+    let table = Table::new(hosts, widths)
+        .header(header)
+        .block(Block::default().borders(Borders::ALL).title(" Hosts "));
+    f.render_widget(table, chunks[1]);
+    
+    let header = Row::new(vec!["Host", "Device", "Vendor", "Product", "RX", "TX", "Total", "Errors"])
+        .style(Style::default().fg(ACCENT_COLOR).add_modifier(Modifier::BOLD))
+        .height(1);
+    let devices: Vec<Row> = rows.iter()
+        .map(|(host, device)| {
+            // Last known figures of a host that's away, dimmed
+            let color = if host.is_stale(now_ms) { DISCONNECTED_FG } else { TEXT_COLOR };
+            Row::new(vec![
+                host.name.clone(),
+                format!("{:03}:{:03}", device.bus_id, device.device_id),
+                device.vendor.clone().unwrap_or_else(|| "Unknown".to_string()),
+                device.product.clone().unwrap_or_else(|| "Unknown".to_string()),
+                format_bandwidth(device.rx_bps),
+                format_bandwidth(device.tx_bps),
+                format_bytes(device.total_rx_bytes.saturating_add(device.total_tx_bytes)),
+                format_count(device.errors),
+            ])
+            .style(Style::default().fg(color))
+        })
+        .collect();
+    let widths = [
+        Constraint::Length(20),  // Host
+        Constraint::Length(8),   // Device
+        Constraint::Min(16),     // Vendor
+        Constraint::Min(20),     // Product
+        Constraint::Length(12),  // RX
+        Constraint::Length(12),  // TX
+        Constraint::Length(10),  // Total
+        Constraint::Length(8),   // Errors
+    ];
+    let table = Table::new(devices, widths)
+        .header(header)
+        .block(Block::default().borders(Borders::ALL).title(" Devices "));
+    f.render_widget(table, chunks[2]);
+    
+    let footer = Paragraph::new("Press q/Esc to quit")
+        .style(Style::default().fg(TEXT_COLOR));
+    f.render_widget(footer, chunks[3]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn device(bus_id: u8, device_id: u8, rx_bps: f64) -> DeviceSnapshot {
+        DeviceSnapshot {
+            bus_id,
+            device_id,
+            vendor: None,
+            product: None,
+            rx_bps,
+            tx_bps: 0.0,
+            total_rx_bytes: 0,
+            total_tx_bytes: 0,
+            errors: 0,
+        }
+    }
+    
+    fn snapshot(timestamp_ms: i64, received_ms: i64, devices: Vec<DeviceSnapshot>) -> HostEvent {
+        HostEvent::Snapshot {
+            message: Message::Snapshot { timestamp_ms, devices, vendors: Vec::new() },
+            received_ms,
+        }
+    }
+    
+    #[test]
+    fn test_devices_are_tagged_with_their_host() {
+        let mut app = FleetApp::new(&["rack1".to_string(), "rack2".to_string()]);
+        let now = 1_700_000_000_000;
+        app.apply(0, HostEvent::Connected, now);
+        app.apply(1, HostEvent::Connected, now);
+        // The same bus:dev on both hosts stays two devices
+        app.apply(0, snapshot(now, now, vec![device(1, 4, 100.0)]), now);
+        app.apply(1, snapshot(now, now, vec![device(1, 4, 300.0), device(2, 2, 0.0)]), now);
+        
+        let rows: Vec<(&str, u8, f64)> = app.rows().iter().map(|(host, device)| (host.name.as_str(), device.device_id, device.rx_bps)).collect();
+        assert_eq!(rows, vec![("rack2", 4, 300.0), ("rack1", 4, 100.0), ("rack2", 2, 0.0)]);
+        assert_eq!(app.total_bps(now), 400.0);
+    }
+    
+    #[test]
+    fn test_host_clock_skew_is_corrected() {
+        let mut app = FleetApp::new(&["rack1".to_string()]);
+        let now = 1_700_000_000_000;
+        app.apply(0, HostEvent::Connected, now);
+        // rack1's clock is a minute behind; its snapshots arrive 50-100 ms after they are taken
+        app.apply(0, snapshot(now - 60_000, now + 50, Vec::new()), now);
+        app.apply(0, snapshot(now - 59_000, now + 1_100, Vec::new()), now);
+        
+        let host = &app.hosts[0];
+        assert_eq!(host.skew(), Some(60_050));
+        // Taken 1 s later by its own clock, so fresh, not a minute old
+        assert_eq!(host.age(now + 1_100), Some(Duration::from_millis(50)));
+        assert!(!host.is_stale(now + 1_100));
+        assert!(host.is_stale(now + 1_050 + STALE_AFTER.as_millis() as i64 + 1));
+    }
+    
+    #[test]
+    fn test_lost_host_keeps_its_devices_until_it_is_back() {
+        let mut app = FleetApp::new(&["rack1".to_string()]);
+        let now = 1_700_000_000_000;
+        app.apply(0, HostEvent::Connected, now);
+        app.apply(0, snapshot(now, now, vec![device(1, 4, 100.0)]), now);
+        app.apply(0, HostEvent::Disconnected { reason: "Connection refused".to_string(), retry_in: Duration::from_secs(4) }, now);
+        
+        let host = &app.hosts[0];
+        assert_eq!(host.status, HostStatus::Retrying { reason: "Connection refused".to_string(), at_ms: now + 4_000 });
+        assert_eq!(host.devices.len(), 1);
+        assert!(host.is_stale(now));
+        // Figures of a lost host don't count towards the total
+        assert_eq!(app.total_bps(now), 0.0);
+        
+        // Back, with a clock that was corrected in the meantime
+        app.apply(0, HostEvent::Connected, now + 5_000);
+        app.apply(0, snapshot(now + 5_000, now + 5_020, vec![device(1, 4, 50.0)]), now + 5_020);
+        assert_eq!(app.hosts[0].skew(), Some(20));
+        assert_eq!(app.total_bps(now + 5_020), 50.0);
+    }
+}
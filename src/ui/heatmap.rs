@@ -0,0 +1,141 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+use crate::device::DeviceKey;
+
+/// Upper bound on configured buckets; memory is buckets x tracked devices
+pub const MAX_HEATMAP_BUCKETS: usize = 1440;
+
+/// Cell glyphs from idle to the busiest bucket on screen
+const INTENSITY_GLYPHS: [char; 5] = [' ', '░', '▒', '▓', '█'];
+
+/// Coarse per-device byte totals over fixed time buckets
+/// Each row holds the same number of buckets, newest last, on the graph
+/// clock. Rows whose every bucket has aged out to zero are dropped, so only
+/// devices active within the span take memory.
+#[derive(Debug, Clone)]
+pub struct ActivityHeatmap {
+    bucket_secs: f64,
+    buckets: usize,
+    /// Bucket number (`at_secs / bucket_secs`) of the newest column
+    newest: u64,
+    rows: HashMap<DeviceKey, VecDeque<u64>>,
+}
+
+impl ActivityHeatmap {
+    pub fn new(bucket: Duration, buckets: usize) -> Self {
+        Self {
+            bucket_secs: bucket.as_secs_f64().max(1.0),
+            buckets: buckets.clamp(1, MAX_HEATMAP_BUCKETS),
+            newest: 0,
+            rows: HashMap::new(),
+        }
+    }
+    
+    pub fn bucket_secs(&self) -> f64 {
+        self.bucket_secs
+    }
+    
+    pub fn buckets(&self) -> usize {
+        self.buckets
+    }
+    
+    /// Add `bytes` to the device's bucket covering `at_secs`
+    pub fn record(&mut self, at_secs: f64, key: DeviceKey, bytes: u64) {
+        self.advance(at_secs);
+        let buckets = self.buckets;
+        let row = self.rows.entry(key).or_insert_with(|| VecDeque::from(vec![0; buckets]));
+        if let Some(newest) = row.back_mut() {
+            *newest = newest.saturating_add(bytes);
+        }
+    }
+    
+    /// Rotate every row so the newest column covers `at_secs`
+    pub fn advance(&mut self, at_secs: f64) {
+        let bucket = (at_secs.max(0.0) / self.bucket_secs) as u64;
+        if bucket <= self.newest {
+            return;
+        }
+        
+        let shift = (bucket - self.newest).min(self.buckets as u64) as usize;
+        self.newest = bucket;
+        for row in self.rows.values_mut() {
+            row.drain(..shift);
+            row.extend(std::iter::repeat_n(0, shift));
+        }
+        self.rows.retain(|_, row| row.iter().any(|bytes| *bytes > 0));
+    }
+    
+    pub fn remove(&mut self, key: DeviceKey) {
+        self.rows.remove(&key);
+    }
+    
+    pub fn clear(&mut self) {
+        self.rows.clear();
+    }
+    
+    /// Devices with activity inside the span, in key order
+    pub fn rows(&self) -> Vec<(DeviceKey, &VecDeque<u64>)> {
+        let mut rows: Vec<(DeviceKey, &VecDeque<u64>)> = self.rows.iter().map(|(key, row)| (*key, row)).collect();
+        rows.sort_by_key(|(key, _)| *key);
+        rows
+    }
+    
+    /// Busiest bucket across all rows, the scale for cell intensity
+    pub fn max_bucket(&self) -> u64 {
+        self.rows.values().flat_map(|row| row.iter().copied()).max().unwrap_or(0)
+    }
+}
+
+/// Intensity of a bucket relative to the busiest one, in `0.0..=1.0`
+pub fn intensity(bytes: u64, max: u64) -> f64 {
+    if max == 0 { 0.0 } else { bytes as f64 / max as f64 }
+}
+
+/// Block character for an intensity; any traffic at all gets at least the lightest shade
+pub fn intensity_glyph(intensity: f64) -> char {
+    if intensity <= 0.0 {
+        return INTENSITY_GLYPHS[0];
+    }
+    let steps = (INTENSITY_GLYPHS.len() - 1) as f64;
+    let index = (intensity.min(1.0) * steps).ceil().max(1.0) as usize;
+    INTENSITY_GLYPHS[index]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_buckets_rotate_and_idle_rows_drop() {
+        let mut heatmap = ActivityHeatmap::new(Duration::from_secs(10), 3);
+        heatmap.record(1.0, DeviceKey(1, 2), 100);
+        heatmap.record(9.0, DeviceKey(1, 2), 50);
+        heatmap.record(12.0, DeviceKey(1, 3), 7);
+        
+        let rows = heatmap.rows();
+        assert_eq!(rows[0].1.iter().copied().collect::<Vec<_>>(), vec![0, 150, 0]);
+        assert_eq!(rows[1].1.iter().copied().collect::<Vec<_>>(), vec![0, 0, 7]);
+        assert_eq!(heatmap.max_bucket(), 150);
+        
+        // Device 2 ages out of the three-bucket span; device 3 is still inside it
+        heatmap.advance(35.0);
+        let rows = heatmap.rows();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].0, DeviceKey(1, 3));
+        assert_eq!(rows[0].1.iter().copied().collect::<Vec<_>>(), vec![7, 0, 0]);
+        
+        // A jump longer than the span clears everything
+        heatmap.advance(1000.0);
+        assert!(heatmap.rows().is_empty());
+    }
+    
+    #[test]
+    fn test_intensity_glyphs() {
+        assert_eq!(intensity_glyph(intensity(0, 100)), ' ');
+        assert_eq!(intensity_glyph(intensity(1, 100)), '░');
+        assert_eq!(intensity_glyph(intensity(60, 100)), '▓');
+        assert_eq!(intensity_glyph(intensity(100, 100)), '█');
+        assert_eq!(intensity(5, 0), 0.0);
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::device::descriptors::endpoint_label;
+use crate::device::DeviceKey;
+use crate::ui::widgets::format_ago;
+use crate::usbmon::parser::{TransferType, UrbType, UsbPacket, ENDPOINT_DIR_IN};
+
+/// Packets kept for the hex dump view; older ones scroll away
+const HEXDUMP_CAPACITY: usize = 256;
+
+/// Payload bytes shown per packet when no limit is configured
+pub const DEFAULT_HEXDUMP_BYTES: usize = 256;
+
+/// Bytes per `hexdump -C` row
+const ROW_BYTES: usize = 16;
+
+/// One captured payload of the dumped device
+#[derive(Debug, Clone, PartialEq)]
+pub struct DumpEntry {
+    pub timestamp_us: u64,
+    pub urb_type: UrbType,
+    pub transfer_type: TransferType,
+    pub endpoint: u8,
+    /// true = IN (device to host)
+    pub direction: bool,
+    /// Length of the URB, which the captured data may fall short of
+    pub data_length: u32,
+    pub data: Vec<u8>,
+}
+
+impl DumpEntry {
+    /// Header line: time, event, endpoint and how much of the payload is shown
+    /// The time is the capture clock, or how long ago when `age` is given.
+    pub fn header(&self, age: Option<Duration>) -> String {
+        let event = match self.urb_type {
+            UrbType::Submission => 'S',
+            UrbType::Callback => 'C',
+            UrbType::Error => 'E',
+        };
+        let address = self.endpoint | if self.direction { ENDPOINT_DIR_IN } else { 0 };
+        let time = match age {
+            Some(age) => format_ago(age),
+            None => format!("{}.{:06}", self.timestamp_us / 1_000_000, self.timestamp_us % 1_000_000),
+        };
+        format!(
+            "{} {} {} {} B{}",
+            time,
+            event,
+            endpoint_label(address, Some(self.transfer_type)),
+            self.data_length,
+            if (self.data.len() as u32) < self.data_length {
+                format!(" ({} shown)", self.data.len())
+            } else {
+                String::new()
+            },
+        )
+    }
+}
+
+/// Recent payloads of one device for the hex dump view
+/// Switching to another device starts over. Only packets that came with
+/// captured data are kept, each cut to `byte_limit` bytes.
+#[derive(Debug, Clone)]
+pub struct HexDumpLog {
+    key: Option<DeviceKey>,
+    byte_limit: usize,
+    entries: VecDeque<DumpEntry>,
+}
+
+impl HexDumpLog {
+    pub fn new(byte_limit: usize) -> Self {
+        Self {
+            key: None,
+            byte_limit: byte_limit.max(1),
+            entries: VecDeque::new(),
+        }
+    }
+    
+    pub fn byte_limit(&self) -> usize {
+        self.byte_limit
+    }
+    
+    /// Follow `key`, dropping what was kept for a previously dumped device
+    pub fn follow(&mut self, key: Option<DeviceKey>) {
+        if self.key != key {
+            self.key = key;
+            self.entries.clear();
+        }
+    }
+    
+    /// Keep `packet`'s payload if it belongs to the followed device
+    pub fn record(&mut self, packet: &UsbPacket) {
+        if self.key != Some(DeviceKey(packet.bus_id, packet.device_id)) {
+            return;
+        }
+        let Some(data) = packet.data.as_deref().filter(|data| !data.is_empty()) else {
+            return;
+        };
+        
+        if self.entries.len() == HEXDUMP_CAPACITY {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(DumpEntry {
+            timestamp_us: packet.timestamp_us,
+            urb_type: packet.urb_type.clone(),
+            transfer_type: packet.transfer_type,
+            endpoint: packet.endpoint,
+            direction: packet.direction,
+            data_length: packet.data_length,
+            data: data[..data.len().min(self.byte_limit)].to_vec(),
+        });
+    }
+    
+    /// Kept payloads, oldest first
+    pub fn entries(&self) -> impl DoubleEndedIterator<Item = &DumpEntry> {
+        self.entries.iter()
+    }
+}
+
+/// `hexdump -C` rows: offset, two groups of eight hex bytes, and the printable ASCII
+pub fn hexdump_rows(data: &[u8]) -> Vec<String> {
+    data.chunks(ROW_BYTES)
+        .enumerate()
+        .map(|(row, bytes)| {
+            let mut hex = String::new();
+            for index in 0..ROW_BYTES {
+                if index == ROW_BYTES / 2 {
+                    hex.push(' ');
+                }
+                match bytes.get(index) {
+                    Some(byte) => hex.push_str(&format!("{:02x} ", byte)),
+                    None => hex.push_str("   "),
+                }
+            }
+            let ascii: String = bytes.iter()
+                .map(|byte| if byte.is_ascii_graphic() || *byte == b' ' { *byte as char } else { '.' })
+                .collect();
+            format!("{:08x}  {} |{}|", row * ROW_BYTES, hex, ascii)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::usbmon::parser::parse_usbmon_text_line;
+    
+    #[test]
+    fn test_hexdump_rows_match_hexdump_c() {
+        let rows = hexdump_rows(b"USBC\x01\x00\x00\x00\x00\x02\x00\x00\x80\x00\x0a\x28ok");
+        assert_eq!(rows, vec![
+            "00000000  55 53 42 43 01 00 00 00  00 02 00 00 80 00 0a 28  |USBC...........(|",
+            "00000010  6f 6b                                             |ok|",
+        ]);
+        assert!(hexdump_rows(&[]).is_empty());
+    }
+    
+    #[test]
+    fn test_log_follows_selected_device() {
+        let mut log = HexDumpLog::new(2);
+        let packet = parse_usbmon_text_line("ffff88003b4a3c00 3575914655 C Bi:1:002:1 0 4 = 01020304").unwrap();
+        log.record(&packet);
+        assert_eq!(log.entries().count(), 0);
+        
+        log.follow(Some(DeviceKey(1, 2)));
+        log.record(&packet);
+        // No payload captured: nothing to dump
+        log.record(&parse_usbmon_text_line("ffff88003b4a3c00 3575914555 S Bi:1:002:1 -115 4 <").unwrap());
+        let entries: Vec<&DumpEntry> = log.entries().collect();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].data, vec![0x01, 0x02]);
+        assert_eq!(entries[0].header(None), "3575.914655 C EP1 IN (Bulk) 4 B (2 shown)");
+        assert_eq!(entries[0].header(Some(Duration::from_millis(2340))), "2.3s ago C EP1 IN (Bulk) 4 B (2 shown)");
+        
+        log.follow(Some(DeviceKey(1, 3)));
+        assert_eq!(log.entries().count(), 0);
+    }
+}
@@ -0,0 +1,351 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::HashMap;
+
+/// User-facing actions that can be bound to keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    Help,
+    NavUp,
+    NavDown,
+    Pause,
+    Detail,
+    Filter,
+    ResetStats,
+    GraphSource,
+    GraphLayout,
+    PeakHold,
+    LogScale,
+    VendorView,
+    ClassView,
+    BudgetView,
+    HeatmapView,
+    HexDump,
+    RelativeBandwidth,
+    Explain,
+    Rescan,
+    PanBack,
+    PanForward,
+    ZoomIn,
+    ZoomOut,
+    CommandPalette,
+    ClearDisconnected,
+    ToggleTimeFormat,
+    FollowBusiest,
+}
+
+impl Action {
+    pub const ALL: [Action; 28] = [
+        Action::Quit,
+        Action::Help,
+        Action::NavUp,
+        Action::NavDown,
+        Action::Pause,
+        Action::Detail,
+        Action::Filter,
+        Action::ResetStats,
+        Action::GraphSource,
+        Action::GraphLayout,
+        Action::PeakHold,
+        Action::LogScale,
+        Action::VendorView,
+        Action::ClassView,
+        Action::BudgetView,
+        Action::HeatmapView,
+        Action::HexDump,
+        Action::RelativeBandwidth,
+        Action::Explain,
+        Action::Rescan,
+        Action::PanBack,
+        Action::PanForward,
+        Action::ZoomIn,
+        Action::ZoomOut,
+        Action::CommandPalette,
+        Action::ClearDisconnected,
+        Action::ToggleTimeFormat,
+        Action::FollowBusiest,
+    ];
+    
+    /// Name used in the `[keybindings]` config section
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Help => "help",
+            Action::NavUp => "nav_up",
+            Action::NavDown => "nav_down",
+            Action::Pause => "pause",
+            Action::Detail => "detail",
+            Action::Filter => "filter",
+            Action::ResetStats => "reset_stats",
+            Action::GraphSource => "graph_source",
+            Action::GraphLayout => "graph_layout",
+            Action::PeakHold => "peak_hold",
+            Action::LogScale => "log_scale",
+            Action::VendorView => "vendor_view",
+            Action::ClassView => "class_view",
+            Action::BudgetView => "budget_view",
+            Action::HeatmapView => "heatmap_view",
+            Action::HexDump => "hex_dump",
+            Action::RelativeBandwidth => "relative_bandwidth",
+            Action::Explain => "explain",
+            Action::Rescan => "rescan",
+            Action::PanBack => "pan_back",
+            Action::PanForward => "pan_forward",
+            Action::ZoomIn => "zoom_in",
+            Action::ZoomOut => "zoom_out",
+            Action::CommandPalette => "command_palette",
+            Action::ClearDisconnected => "clear_disconnected",
+            Action::ToggleTimeFormat => "toggle_time_format",
+            Action::FollowBusiest => "follow_busiest",
+        }
+    }
+    
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            // "up"/"down" are accepted for older config files
+            "up" => Some(Action::NavUp),
+            "down" => Some(Action::NavDown),
+            _ => Self::ALL.iter().copied().find(|action| action.name() == name),
+        }
+    }
+    
+    fn default_keys(&self) -> Vec<KeyPress> {
+        let keys = match self {
+            Action::Quit => vec![KeyCode::Char('q'), KeyCode::Esc],
+            Action::Help => vec![KeyCode::Char('h'), KeyCode::F(1)],
+            Action::NavUp => vec![KeyCode::Up, KeyCode::Char('k')],
+            Action::NavDown => vec![KeyCode::Down, KeyCode::Char('j')],
+            Action::Pause => vec![KeyCode::Char('p')],
+            Action::Detail => vec![KeyCode::Enter],
+            Action::Filter => vec![KeyCode::Char('f')],
+            Action::ResetStats => vec![KeyCode::Char('z')],
+            Action::GraphSource => vec![KeyCode::Char('G')],
+            Action::GraphLayout => vec![KeyCode::Char('c')],
+            Action::PeakHold => vec![KeyCode::Char('m')],
+            Action::LogScale => vec![KeyCode::Char('L')],
+            Action::VendorView => vec![KeyCode::Char('v')],
+            Action::ClassView => vec![KeyCode::Char('u')],
+            Action::BudgetView => vec![KeyCode::Char('b')],
+            Action::HeatmapView => vec![KeyCode::Char('d')],
+            Action::HexDump => vec![KeyCode::Char('x')],
+            Action::RelativeBandwidth => vec![KeyCode::Char('%')],
+            Action::Explain => vec![KeyCode::Char('i')],
+            Action::Rescan => return vec![KeyPress::plain(KeyCode::F(5)), KeyPress::ctrl(KeyCode::Char('r'))],
+            Action::PanBack => vec![KeyCode::Left],
+            Action::PanForward => vec![KeyCode::Right],
+            Action::ZoomIn => vec![KeyCode::Char('+'), KeyCode::Char('=')],
+            Action::ZoomOut => vec![KeyCode::Char('-')],
+            Action::CommandPalette => vec![KeyCode::Char(':')],
+            Action::ClearDisconnected => vec![KeyCode::Char('C')],
+            Action::ToggleTimeFormat => vec![KeyCode::Char('t')],
+            Action::FollowBusiest => vec![KeyCode::Char('F')],
+        };
+        keys.into_iter().map(KeyPress::plain).collect()
+    }
+}
+
+/// A key with or without Ctrl held
+/// Shift is already part of the character (`G` vs `g`) and other modifiers
+/// aren't bindable, so Ctrl is the only one tracked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyPress {
+    pub code: KeyCode,
+    pub ctrl: bool,
+}
+
+impl KeyPress {
+    pub fn plain(code: KeyCode) -> Self {
+        Self { code, ctrl: false }
+    }
+    
+    pub fn ctrl(code: KeyCode) -> Self {
+        Self { code, ctrl: true }
+    }
+    
+    pub fn from_event(event: &KeyEvent) -> Self {
+        Self {
+            code: event.code,
+            ctrl: event.modifiers.contains(KeyModifiers::CONTROL),
+        }
+    }
+}
+
+impl std::fmt::Display for KeyPress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.ctrl {
+            write!(f, "Ctrl-")?;
+        }
+        write!(f, "{:?}", self.code)
+    }
+}
+
+/// Parse a key name such as "q", "Esc", "Up", "F5" or "Ctrl-r"
+pub fn parse_key(name: &str) -> Option<KeyPress> {
+    let ctrl_key = ["ctrl-", "ctrl+", "c-"].iter().find_map(|prefix| {
+        name.get(..prefix.len())
+            .filter(|head| head.eq_ignore_ascii_case(prefix))
+            .map(|_| &name[prefix.len()..])
+    });
+    match ctrl_key {
+        // Terminals report Ctrl-R and Ctrl-r alike, so bind the lowercase letter
+        Some(key) => parse_key_code(key).map(|code| match code {
+            KeyCode::Char(c) => KeyPress::ctrl(KeyCode::Char(c.to_ascii_lowercase())),
+            code => KeyPress::ctrl(code),
+        }),
+        None => parse_key_code(name).map(KeyPress::plain),
+    }
+}
+
+fn parse_key_code(name: &str) -> Option<KeyCode> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(c));
+    }
+    
+    match name.to_ascii_lowercase().as_str() {
+        "esc" | "escape" => Some(KeyCode::Esc),
+        "enter" | "return" => Some(KeyCode::Enter),
+        "tab" => Some(KeyCode::Tab),
+        "backspace" => Some(KeyCode::Backspace),
+        "space" => Some(KeyCode::Char(' ')),
+        "up" => Some(KeyCode::Up),
+        "down" => Some(KeyCode::Down),
+        "left" => Some(KeyCode::Left),
+        "right" => Some(KeyCode::Right),
+        "home" => Some(KeyCode::Home),
+        "end" => Some(KeyCode::End),
+        "pageup" => Some(KeyCode::PageUp),
+        "pagedown" => Some(KeyCode::PageDown),
+        lower => lower.strip_prefix('f')
+            .and_then(|n| n.parse::<u8>().ok())
+            .filter(|n| (1..=12).contains(n))
+            .map(KeyCode::F),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct KeyBindings {
+    map: HashMap<KeyPress, Action>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self::from_config(&HashMap::new()).0
+    }
+}
+
+impl KeyBindings {
+    /// Build bindings from the `[keybindings]` section
+    /// Actions listed in the config replace their default keys; the rest keep
+    /// their defaults. Returns warnings for unknown names and conflicting keys.
+    pub fn from_config(overrides: &HashMap<String, Vec<String>>) -> (Self, Vec<String>) {
+        let mut warnings = Vec::new();
+        let mut bindings: Vec<(Action, Vec<KeyPress>, bool)> = Action::ALL.iter()
+            .map(|action| (*action, action.default_keys(), false))
+            .collect();
+        
+        let mut names: Vec<&String> = overrides.keys().collect();
+        names.sort();
+        for name in names {
+            let Some(action) = Action::from_name(name) else {
+                warnings.push(format!("Unknown keybinding action '{}'", name));
+                continue;
+            };
+            
+            let mut keys = Vec::new();
+            for key_name in &overrides[name] {
+                match parse_key(key_name) {
+                    Some(key) => keys.push(key),
+                    None => warnings.push(format!("Unknown key '{}' for action '{}'", key_name, name)),
+                }
+            }
+            
+            if let Some(binding) = bindings.iter_mut().find(|(a, _, _)| *a == action) {
+                binding.1 = keys;
+                binding.2 = true;
+            }
+        }
+        
+        // Defaults first so explicitly configured keys win any conflict
+        bindings.sort_by_key(|(_, _, overridden)| *overridden);
+        
+        let mut map = HashMap::new();
+        for (action, keys, _) in bindings {
+            for key in keys {
+                if let Some(previous) = map.insert(key, action) {
+                    if previous != action {
+                        warnings.push(format!(
+                            "Key {} is bound to both '{}' and '{}'; using '{}'",
+                            key, previous.name(), action.name(), action.name(),
+                        ));
+                    }
+                }
+            }
+        }
+        
+        (Self { map }, warnings)
+    }
+    
+    pub fn action_for_press(&self, key: KeyPress) -> Option<Action> {
+        self.map.get(&key).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// Build a config section from (action, space-separated keys) pairs
+    fn overrides(entries: &[(&str, &str)]) -> HashMap<String, Vec<String>> {
+        entries.iter()
+            .map(|(action, keys)| (action.to_string(), keys.split_whitespace().map(|k| k.to_string()).collect()))
+            .collect()
+    }
+    
+    #[test]
+    fn test_defaults_match_builtin_keys() {
+        let bindings = KeyBindings::default();
+        assert_eq!(bindings.action_for_press(KeyPress::plain(KeyCode::Char('q'))), Some(Action::Quit));
+        assert_eq!(bindings.action_for_press(KeyPress::plain(KeyCode::Esc)), Some(Action::Quit));
+        assert_eq!(bindings.action_for_press(KeyPress::plain(KeyCode::Char('f'))), Some(Action::Filter));
+        assert_eq!(bindings.action_for_press(KeyPress::plain(KeyCode::Enter)), Some(Action::Detail));
+        assert_eq!(bindings.action_for_press(KeyPress::plain(KeyCode::Char('y'))), None);
+    }
+    
+    #[test]
+    fn test_override_replaces_default_keys() {
+        let (bindings, warnings) = KeyBindings::from_config(&overrides(&[("quit", "y F10")]));
+        assert!(warnings.is_empty());
+        assert_eq!(bindings.action_for_press(KeyPress::plain(KeyCode::Char('y'))), Some(Action::Quit));
+        assert_eq!(bindings.action_for_press(KeyPress::plain(KeyCode::F(10))), Some(Action::Quit));
+        assert_eq!(bindings.action_for_press(KeyPress::plain(KeyCode::Char('q'))), None);
+    }
+    
+    #[test]
+    fn test_conflicts_and_unknown_names_warn() {
+        let (bindings, warnings) = KeyBindings::from_config(&overrides(&[
+            ("quit", "h"),
+            ("launch", "l"),
+            ("help", "F13"),
+        ]));
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(bindings.action_for_press(KeyPress::plain(KeyCode::Char('h'))), Some(Action::Quit));
+        
+        let (_, warnings) = KeyBindings::from_config(&overrides(&[("quit", "f")]));
+        assert_eq!(warnings.len(), 1);
+    }
+    
+    #[test]
+    fn test_ctrl_keys() {
+        let bindings = KeyBindings::default();
+        assert_eq!(bindings.action_for_press(KeyPress::plain(KeyCode::F(5))), Some(Action::Rescan));
+        assert_eq!(bindings.action_for_press(KeyPress::ctrl(KeyCode::Char('r'))), Some(Action::Rescan));
+        assert_eq!(bindings.action_for_press(KeyPress::plain(KeyCode::Char('r'))), None);
+        
+        assert_eq!(parse_key("Ctrl-R"), Some(KeyPress::ctrl(KeyCode::Char('r'))));
+        assert_eq!(parse_key("c-F2"), Some(KeyPress::ctrl(KeyCode::F(2))));
+        assert_eq!(parse_key("ctrl+Up"), Some(KeyPress::ctrl(KeyCode::Up)));
+        assert_eq!(parse_key("c"), Some(KeyPress::plain(KeyCode::Char('c'))));
+        assert_eq!(parse_key("Ctrl-"), None);
+    }
+}
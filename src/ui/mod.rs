@@ -1,4 +1,6 @@
 use anyhow::Result;
+use chrono::{Local, NaiveDate};
+use log::{debug, info, warn};
 use ratatui::{
     backend::CrosstermBackend,
     layout::{Constraint, Direction, Layout, Rect},
@@ -6,62 +8,694 @@ use ratatui::{
     symbols,
     text::{Line, Span, Text},
     widgets::{
-        Axis, Block, Borders, Chart, Clear, Dataset, Gauge, List, ListItem, Paragraph, Row, Table, Wrap,
+        Axis, Block, Borders, Cell, Chart, Clear, Dataset, Gauge, GraphType, List, ListItem, Paragraph, Row, Sparkline,
+        Table, Wrap,
     },
     Frame, Terminal,
 };
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use std::{
     collections::HashMap,
     io,
+    path::Path,
     time::{Duration, Instant},
 };
+use tokio::sync::mpsc::{error::TryRecvError, UnboundedReceiver};
+use tokio::sync::watch;
 
-use crate::device::UsbDevice;
-use crate::stats::BandwidthStats;
-use crate::usbmon::parser::UsbSpeed;
+use crate::config::{Column, Config, ControllerConfig, GraphLayout, HookEvent, InactiveDevices, SortKey, SortOrder, SortStrategy, TimeFormat};
+use crate::control::{ControlCommand, ControlRequest};
+use crate::device::assembler::DescriptorAssembler;
+use crate::device::budget::aggregate_budget;
+use crate::device::controllers::{controller_for_bus, discover_controllers, HostController};
+use crate::device::filter::DeviceFilter;
+use crate::device::hubs::{aggregate_by_hub, HubLoad};
+use crate::device::sysfs;
+use crate::device::typec::{discover_typec_ports, TypecPort, SYSFS_TYPEC};
+use crate::device::classes::aggregate_by_class;
+use crate::device::vendors::aggregate_by_vendor;
+use crate::device::{
+    find_ghost, format_speed, format_usb_version, select_evictions, DeviceKey, ErrorRates, UsbDevice,
+    DEFAULT_MAX_DEVICES, DEFAULT_MERGE_WINDOW, DISCONNECT_GRACE_PERIOD,
+};
+use crate::hooks::HookRunner;
+use crate::trigger::{PacketTrigger, TriggerEvent};
+use crate::logging;
+use crate::monitor::ReaderTasks;
+use crate::profiling::profile_span;
+use crate::snapshot::Message;
+use crate::state::SessionStore;
+use crate::stats::{BandwidthStats, RATE_WINDOW, URB_SIZE_LABELS};
+use crate::usbmon::parser::{TransferType, UsbPacket, UsbSpeed};
 
+pub mod benchmark;
 pub mod colors;
+pub mod events;
+pub mod fleet;
+pub mod heatmap;
+pub mod hexdump;
+pub mod keybindings;
+pub mod ordering;
+pub mod pacing;
+pub mod palette;
+pub mod summary;
+pub mod timeline;
 pub mod widgets;
 
 use colors::*;
+use events::{DeviceEventKind, EventLog};
+use heatmap::{intensity, intensity_glyph, ActivityHeatmap};
+use hexdump::{hexdump_rows, HexDumpLog, DEFAULT_HEXDUMP_BYTES};
+use keybindings::{Action, KeyBindings, KeyPress};
+use ordering::StableOrder;
+use pacing::{FramePacer, MIN_FRAME_INTERVAL};
+use palette::PaletteCommand;
+use summary::{print_class_report, CaptureSummary};
+use timeline::{dashed_line, history_capacity, log_axis_labels, log_bounds, log_scale, window_peak, GraphViewport};
 use widgets::*;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferFilter {
+    All,
+    Bulk,
+    Control,
+    Interrupt,
+    Isochronous,
+}
+
+impl TransferFilter {
+    /// Cycle order used by the `f` key
+    pub fn next(&self) -> Self {
+        match self {
+            TransferFilter::All => TransferFilter::Bulk,
+            TransferFilter::Bulk => TransferFilter::Control,
+            TransferFilter::Control => TransferFilter::Interrupt,
+            TransferFilter::Interrupt => TransferFilter::Isochronous,
+            TransferFilter::Isochronous => TransferFilter::All,
+        }
+    }
+    
+    pub fn transfer_type(&self) -> Option<TransferType> {
+        match self {
+            TransferFilter::All => None,
+            TransferFilter::Bulk => Some(TransferType::Bulk),
+            TransferFilter::Control => Some(TransferType::Control),
+            TransferFilter::Interrupt => Some(TransferType::Interrupt),
+            TransferFilter::Isochronous => Some(TransferType::Isochronous),
+        }
+    }
+    
+    pub fn label(&self) -> &'static str {
+        match self.transfer_type() {
+            Some(transfer_type) => transfer_type.name(),
+            None => "All",
+        }
+    }
+}
+
+/// Refresh intervals the graph spans before zooming; sampling faster than
+/// the refresh puts more points in the same span
+const DEFAULT_GRAPH_REFRESHES: usize = 60;
+
+/// History kept for panning back when no config is applied
+const DEFAULT_HISTORY_RETENTION: Duration = Duration::from_secs(3600);
+
+/// Heatmap bucketing when no config is applied: 10 minutes at 5 s resolution
+const DEFAULT_HEATMAP_BUCKET: Duration = Duration::from_secs(5);
+const DEFAULT_HEATMAP_BUCKETS: usize = 120;
+
+/// How long a device the filter rejected is ignored before its descriptors are read again
+const FILTER_RECHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How long a logged warning stays in the notification line
+const NOTIFICATION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Devices connected for less than this are highlighted in the list
+const NEW_DEVICE_HIGHLIGHT: Duration = Duration::from_secs(30);
+
+/// How often descriptors are re-read, to catch a driver binding late or a
+/// different device taking over a bus:dev number
+const DESCRIPTOR_RECHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// `ui.no_driver_secs` when no config is applied
+const DEFAULT_NO_DRIVER_AFTER: Duration = Duration::from_secs(10);
+
+/// Endpoint gauges in the detail view before the rest are summarized
+const MAX_ENDPOINT_GAUGES: usize = 6;
+
+/// Columns of the longest bar in the detail view's URB size histogram
+const URB_SIZE_BAR_WIDTH: usize = 16;
+
+/// Series plotted in the bandwidth graph, cycled with `G`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphSource {
+    Total,
+    SelectedDevice,
+    PerBus,
+}
+
+impl GraphSource {
+    pub fn next(&self) -> Self {
+        match self {
+            GraphSource::Total => GraphSource::SelectedDevice,
+            GraphSource::SelectedDevice => GraphSource::PerBus,
+            GraphSource::PerBus => GraphSource::Total,
+        }
+    }
+}
+
+/// Terminal rows below which `GraphLayout::Auto` trades the graph panel for a sparkline
+const SPARKLINE_AUTO_HEIGHT: u16 = 40;
+
+impl GraphLayout {
+    /// Cycled with `c`
+    pub fn next(&self) -> Self {
+        match self {
+            GraphLayout::Auto => GraphLayout::Full,
+            GraphLayout::Full => GraphLayout::Sparkline,
+            GraphLayout::Sparkline => GraphLayout::Auto,
+        }
+    }
+    
+    pub fn label(&self) -> &'static str {
+        match self {
+            GraphLayout::Auto => "Auto",
+            GraphLayout::Full => "Full graph",
+            GraphLayout::Sparkline => "Sparkline",
+        }
+    }
+    
+    /// Whether a terminal `height` rows tall shows the header sparkline
+    pub fn uses_sparkline(&self, height: u16) -> bool {
+        match self {
+            GraphLayout::Auto => height < SPARKLINE_AUTO_HEIGHT,
+            GraphLayout::Full => false,
+            GraphLayout::Sparkline => true,
+        }
+    }
+}
+
+impl TimeFormat {
+    /// Toggled with `t`
+    pub fn toggle(&self) -> Self {
+        match self {
+            TimeFormat::Absolute => TimeFormat::Relative,
+            TimeFormat::Relative => TimeFormat::Absolute,
+        }
+    }
+    
+    pub fn label(&self) -> &'static str {
+        match self {
+            TimeFormat::Absolute => "Absolute",
+            TimeFormat::Relative => "Relative",
+        }
+    }
+}
+
+/// What a round of input handling did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputOutcome {
+    Idle,
+    /// Something changed on screen and should be redrawn promptly
+    Handled,
+    Quit,
+}
+
+/// Time spent draining packets before input and drawing get a turn
+const PACKET_DRAIN_BUDGET: Duration = Duration::from_millis(20);
+
+/// Packets processed between checks of the drain budget
+const PACKET_DRAIN_BATCH: usize = 256;
+
+/// Append a sample and drop the oldest ones beyond `capacity`
+fn push_sample<T>(history: &mut Vec<T>, sample: T, capacity: usize) {
+    history.push(sample);
+    if history.len() > capacity {
+        history.drain(0..history.len() - capacity);
+    }
+}
+
 pub struct UsbTopApp {
-    pub devices: HashMap<String, UsbDevice>,
+    pub devices: HashMap<DeviceKey, UsbDevice>,
     pub bandwidth_history: Vec<(f64, f64)>, // (timestamp, total_bandwidth)
-    pub selected_device: Option<String>,
+    pub device_history: HashMap<DeviceKey, Vec<(f64, f64, f64)>>, // (timestamp, rx_bps, tx_bps)
+    pub bus_history: HashMap<u8, Vec<(f64, f64)>>, // (timestamp, bus_bandwidth)
+    pub graph_source: GraphSource,
+    pub graph_layout: GraphLayout,
+    pub time_format: TimeFormat,
+    /// Capture timestamp of the newest packet and when it arrived, to tell
+    /// how long ago an earlier capture timestamp was
+    capture_clock: Option<(u64, Instant)>,
+    pub viewport: GraphViewport,
+    pub history_retention: Duration,
+    pub history_capacity: usize,
+    pub events: EventLog,
+    pub heatmap: ActivityHeatmap,
+    /// Recent payloads of the selected device, kept while the hex dump view is open
+    pub hexdump: HexDumpLog,
+    pub hooks: HookRunner,
+    /// `[trigger]` capture-on-condition
+    pub trigger: PacketTrigger,
+    pub filter: DeviceFilter,
+    /// Where captured devices are attached when it isn't this machine (`--adb`);
+    /// their descriptors aren't read from the local sysfs
+    pub origin: Option<String>,
+    /// Devices the filter rejected and when, so their packets skip the sysfs lookup
+    filtered: HashMap<DeviceKey, Instant>,
+    /// Identities read from enumeration traffic, for devices sysfs can't describe
+    descriptors: DescriptorAssembler,
+    /// `--state-file` totals carried across restarts
+    pub state: Option<SessionStore>,
+    /// Commands from `--control-socket`, answered once per loop
+    pub control: Option<UnboundedReceiver<ControlRequest>>,
+    pub show_event_markers: bool,
+    pub show_peak_hold: bool,
+    pub graph_log_scale: bool,
+    pub started: Instant,
+    pub selected_device: Option<DeviceKey>,
     pub show_help: bool,
+    pub show_detail: bool,
+    pub show_packet_counts: bool,
+    pub show_vendors: bool,
+    pub show_classes: bool,
+    /// Per-bus capability demand against capacity, for planning device placement
+    pub show_budget: bool,
+    pub show_heatmap: bool,
+    pub show_hexdump: bool,
+    /// Bandwidth columns as a share of each device's own capability
+    pub show_relative_bandwidth: bool,
+    /// Keep the top row of the device list selected; moving the selection turns it off
+    pub follow_busiest: bool,
+    /// How long a device may sit without a driver before it's flagged; zero never flags
+    pub no_driver_after: Duration,
+    pub inactive_devices: InactiveDevices,
+    /// Time without traffic over which a row fades in dim mode
+    pub idle_fade: Duration,
+    /// Mark devices disconnected when they leave sysfs; only meaningful when
+    /// capturing this machine's own buses
+    pub detect_disconnects: bool,
+    last_descriptor_check: Instant,
+    pub show_explain: bool,
+    pub paused: bool,
+    pub input_ended: bool,
+    /// Frames are being spaced out because drawing can't keep up
+    pub slow_refresh: bool,
+    pub capture_idle: bool,
+    /// Local day being summarized and the byte total when it began (scheduled capture only)
+    summary_day: Option<(NaiveDate, u64)>,
+    pub keybindings: KeyBindings,
+    /// Decimals for bandwidth figures; None for automatic precision
+    pub rate_precision: Option<usize>,
+    pub transfer_filter: TransferFilter,
+    pub sort_strategy: SortStrategy,
+    pub sort_by: SortKey,
+    pub sort_order: SortOrder,
+    pub device_order: StableOrder<DeviceKey>,
+    pub columns: Vec<Column>,
+    /// Text typed after `:` while the command palette is open
+    pub command_line: Option<String>,
+    pub controllers: Vec<ControllerConfig>,
+    /// Host controllers found in sysfs, for each bus's own link speed
+    pub host_controllers: Vec<HostController>,
+    /// USB-C connectors from the typec class; empty without USB-C or the class
+    pub typec_ports: Vec<TypecPort>,
+    pub exclude_control_endpoint: bool,
+    /// Rate window for interrupt and isochronous traffic
+    pub periodic_window: Duration,
+    pub max_devices: usize,
+    pub merge_window: Duration,
+    pub pair_companion_links: bool,
+    pub utilization_scale: UtilizationScale,
+    pub notifications: Vec<(Instant, String)>,
     pub last_update: Instant,
+    /// How often bandwidth is sampled into the history and graph
+    pub sample_interval: Duration,
+    /// How often the screen is redrawn
     pub refresh_rate: Duration,
     pub total_bandwidth: f64,
     pub peak_bandwidth: f64,
 }
 
 impl UsbTopApp {
-    pub fn new(refresh_rate: Duration) -> Self {
+    pub fn new(sample_interval: Duration, refresh_rate: Duration) -> Self {
         Self {
             devices: HashMap::new(),
             bandwidth_history: Vec::new(),
+            device_history: HashMap::new(),
+            bus_history: HashMap::new(),
+            graph_source: GraphSource::Total,
+            graph_layout: GraphLayout::Auto,
+            time_format: TimeFormat::Absolute,
+            capture_clock: None,
+            viewport: GraphViewport::new(DEFAULT_GRAPH_REFRESHES as f64 * refresh_rate.as_secs_f64()),
+            history_retention: DEFAULT_HISTORY_RETENTION,
+            history_capacity: history_capacity(DEFAULT_HISTORY_RETENTION, sample_interval),
+            events: EventLog::default(),
+            heatmap: ActivityHeatmap::new(DEFAULT_HEATMAP_BUCKET, DEFAULT_HEATMAP_BUCKETS),
+            hexdump: HexDumpLog::new(DEFAULT_HEXDUMP_BYTES),
+            hooks: HookRunner::default(),
+            trigger: PacketTrigger::default(),
+            filter: DeviceFilter::default(),
+            origin: None,
+            filtered: HashMap::new(),
+            descriptors: DescriptorAssembler::new(),
+            state: None,
+            control: None,
+            show_event_markers: false,
+            show_peak_hold: true,
+            graph_log_scale: false,
+            started: Instant::now(),
             selected_device: None,
             show_help: false,
+            show_detail: false,
+            show_packet_counts: false,
+            show_vendors: false,
+            show_classes: false,
+            show_budget: false,
+            show_heatmap: false,
+            show_hexdump: false,
+            show_relative_bandwidth: false,
+            follow_busiest: false,
+            no_driver_after: DEFAULT_NO_DRIVER_AFTER,
+            inactive_devices: InactiveDevices::Remove,
+            idle_fade: Duration::from_secs(60),
+            detect_disconnects: false,
+            last_descriptor_check: Instant::now(),
+            show_explain: false,
+            paused: false,
+            input_ended: false,
+            slow_refresh: false,
+            capture_idle: false,
+            summary_day: None,
+            keybindings: KeyBindings::default(),
+            rate_precision: None,
+            transfer_filter: TransferFilter::All,
+            sort_strategy: SortStrategy::Immediate,
+            sort_by: SortKey::Bandwidth,
+            sort_order: SortOrder::Desc,
+            device_order: StableOrder::new(2, 3),
+            columns: Column::DEFAULT.to_vec(),
+            command_line: None,
+            controllers: Vec::new(),
+            host_controllers: Vec::new(),
+            typec_ports: Vec::new(),
+            exclude_control_endpoint: false,
+            periodic_window: RATE_WINDOW,
+            max_devices: DEFAULT_MAX_DEVICES,
+            merge_window: DEFAULT_MERGE_WINDOW,
+            pair_companion_links: true,
+            utilization_scale: UtilizationScale::default(),
+            notifications: Vec::new(),
             last_update: Instant::now(),
+            sample_interval,
             refresh_rate,
             total_bandwidth: 0.0,
             peak_bandwidth: 0.0,
         }
     }
     
-    pub fn update_device(&mut self, device: UsbDevice) {
-        let device_key = format!("{}:{}", device.bus_id, device.device_id);
+    pub fn apply_config(&mut self, config: &Config) {
+        self.show_help = config.display.show_help_on_startup;
+        self.show_packet_counts = config.ui.show_packet_counts;
+        self.show_event_markers = config.ui.show_event_markers;
+        self.show_peak_hold = config.ui.show_peak_hold;
+        self.graph_log_scale = config.ui.graph_log_scale;
+        self.history_retention = Duration::from_secs(config.ui.history_retention_secs);
+        self.history_capacity = history_capacity(self.history_retention, self.sample_interval);
+        self.heatmap = ActivityHeatmap::new(Duration::from_secs(config.ui.heatmap_bucket_secs), config.ui.heatmap_buckets);
+        self.hexdump = HexDumpLog::new(config.ui.hexdump_bytes);
+        self.show_hexdump = config.ui.show_hexdump;
+        self.show_relative_bandwidth = config.ui.show_relative_bandwidth;
+        self.follow_busiest = config.ui.follow_busiest;
+        self.no_driver_after = Duration::from_secs(config.ui.no_driver_secs);
+        self.inactive_devices = config.ui.inactive_devices;
+        self.idle_fade = Duration::from_secs(config.ui.idle_fade_secs);
+        self.sort_strategy = config.ui.sort_strategy;
+        self.device_order = StableOrder::new(config.ui.sort_hysteresis_ranks, config.ui.sort_hysteresis_frames);
+        self.sort_by = config.ui.sort_by;
+        self.sort_order = config.ui.sort_order.unwrap_or_else(|| config.ui.sort_by.default_order());
+        if !config.ui.columns.is_empty() {
+            self.columns = config.ui.columns.clone();
+        }
+        self.controllers = config.controllers.clone();
+        self.exclude_control_endpoint = config.monitoring.exclude_control_endpoint;
+        self.periodic_window = match config.monitoring.periodic_window_secs {
+            0 => RATE_WINDOW,
+            secs => Duration::from_secs(secs),
+        };
+        self.max_devices = config.monitoring.max_devices;
+        self.merge_window = Duration::from_secs(config.monitoring.reenumeration_merge_secs);
+        self.pair_companion_links = config.monitoring.pair_companion_links;
+        self.utilization_scale = UtilizationScale::from_config(&config.colors.utilization).0;
+        self.keybindings = KeyBindings::from_config(&config.keybindings).0;
+        self.hooks = HookRunner::from_config(&config.hooks).0;
+        self.trigger = PacketTrigger::from_config(&config.trigger).0;
+        self.filter = DeviceFilter::from_config(&config.filter).0;
+        self.filtered.clear();
+        self.rate_precision = config.ui.rate_precision;
+        self.graph_layout = config.ui.graph_layout;
+        self.time_format = config.ui.time_format;
+    }
+    
+    /// Account a captured packet, creating the device on first sight
+    pub fn process_packet(&mut self, packet: &UsbPacket) {
+        profile_span!("stats_update");
+        
+        let key = DeviceKey(packet.bus_id, packet.device_id);
+        if self.capture_clock.is_none_or(|(latest, _)| packet.timestamp_us >= latest) {
+            self.capture_clock = Some((packet.timestamp_us, Instant::now()));
+        }
+        // Enumeration happens once, so it's followed even while paused
+        let sniffed = self.descriptors.observe(packet);
+        if sniffed {
+            // A device rejected before its ids were known gets another look
+            self.filtered.remove(&key);
+        }
+        
+        if self.paused {
+            return;
+        }
+        
+        let exclude_control_endpoint = self.exclude_control_endpoint;
+        
+        let new_device = if let Some(device) = self.devices.get(&key) {
+            // Narrowed out by the control socket: the entry and its totals stay
+            if self.filter.is_narrowed() && !self.filter.allows(device) {
+                return;
+            }
+            None
+        } else {
+            match self.probe_device(key) {
+                Some(device) => Some(device),
+                None => return,
+            }
+        };
+        
+        // New devices and disconnected ones seen again count as connects
+        let connected = self.devices.get(&key).is_none_or(|device| device.is_disconnected);
+        if connected {
+            self.events.record(self.graph_time(), key, DeviceEventKind::Connected);
+        }
+        
+        if let Some(device) = new_device {
+            self.track_new_device(device);
+        }
+        if let Some(device) = self.devices.get_mut(&key) {
+            if sniffed {
+                if let Some(identity) = self.descriptors.get(key) {
+                    device.apply_sniffed(identity);
+                }
+            }
+            device.record_packet(packet);
+        }
+        if self.trigger.is_enabled() {
+            let event = self.devices.get(&key).and_then(|device| self.trigger.record(packet, device));
+            if let Some(event) = event {
+                self.report_trigger(event);
+            }
+        }
+        if self.show_hexdump {
+            self.hexdump.follow(self.selected_device);
+            self.hexdump.record(packet);
+        }
+        if connected {
+            if let Some(device) = self.devices.get(&key) {
+                self.hooks.device_event(HookEvent::DeviceConnected, device, Instant::now());
+            }
+        }
+        
+        let bytes = packet.bandwidth_bytes() as u64;
+        if bytes > 0 && !(packet.endpoint == 0 && exclude_control_endpoint) {
+            self.heatmap.record(self.graph_time(), key, bytes);
+        }
+        
+        self.enforce_device_limit();
+    }
+    
+    /// Read a new device's descriptors, or None if the filter rejects it
+    /// Rejections are remembered for a while so a busy blocked device doesn't
+    /// cost a sysfs read per packet.
+    fn probe_device(&mut self, key: DeviceKey) -> Option<UsbDevice> {
+        if self.filtered.get(&key).is_some_and(|since| since.elapsed() < FILTER_RECHECK_INTERVAL) {
+            return None;
+        }
+        
+        let mut device = UsbDevice::new(key.bus_id(), key.device_id());
+        match &self.origin {
+            Some(origin) => device.origin = Some(origin.clone()),
+            None => {
+                let _ = device.update_from_sysfs();
+            }
+        }
+        // Without sysfs (stdin, adb, replays) the ids come only from enumeration traffic
+        if let Some(identity) = self.descriptors.get(key) {
+            device.apply_sniffed(identity);
+        }
+        device.exclude_control_endpoint = self.exclude_control_endpoint;
+        device.set_periodic_window(self.periodic_window);
+        if self.filter.allows(&device) {
+            self.filtered.remove(&key);
+            Some(device)
+        } else {
+            if self.filtered.insert(key, Instant::now()).is_none() {
+                debug!("Device {} is excluded by the device filter", key);
+            }
+            None
+        }
+    }
+    
+    /// Start tracking a device seen for the first time, taking over the
+    /// entry it left behind if it re-enumerated
+    fn track_new_device(&mut self, mut device: UsbDevice) {
+        self.retire_ghost(&mut device);
+        self.restore_state(&mut device);
+        self.devices.insert(device.key(), device);
+    }
+    
+    /// Replace the entry a re-enumerated device left behind, keeping its
+    /// totals and the selection
+    fn retire_ghost(&mut self, device: &mut UsbDevice) {
+        let Some(ghost_key) = find_ghost(self.devices.values(), device, self.merge_window, self.pair_companion_links) else {
+            return;
+        };
+        
+        if let Some(ghost) = self.devices.get(&ghost_key) {
+            device.absorb(ghost);
+        }
+        let was_selected = self.selected_device == Some(ghost_key);
+        // Its totals live on in the new entry, not in the state file
+        self.forget_device(ghost_key);
+        if was_selected {
+            self.selected_device = Some(device.key());
+        }
+        info!("Device {} re-enumerated as {}; merged its previous entry", ghost_key, device.key());
+        if let Some(other) = device.other_link.as_ref().filter(|other| other.speed.is_superspeed() != device.speed.is_superspeed()) {
+            let change = if device.speed.is_superspeed() { "came back up at SuperSpeed" } else { "fell back to USB 2" };
+            self.notifications.push((Instant::now(), format!("Device {} {} as {}", other.key, change, device.key())));
+        }
+    }
+    
+    /// Pick up totals saved by a previous run for a device seen for the first time
+    fn restore_state(&mut self, device: &mut UsbDevice) {
+        if let Some(state) = self.state.as_mut() {
+            if state.restore(device) {
+                info!("Restored saved totals for device {}", device.key());
+            }
+        }
+    }
+    
+    /// How long ago a packet with this capture timestamp was seen, in
+    /// relative time mode; None shows the timestamp itself
+    fn capture_age(&self, timestamp_us: u64) -> Option<Duration> {
+        if self.time_format != TimeFormat::Relative {
+            return None;
+        }
+        let (latest_us, arrived) = self.capture_clock?;
+        Some(Duration::from_micros(latest_us.saturating_sub(timestamp_us)) + arrived.elapsed())
+    }
+    
+    /// An event marker's time: seconds on the graph clock, or how long ago
+    fn event_time_label(&self, at_secs: f64) -> String {
+        match self.time_format {
+            TimeFormat::Absolute => format!("{:.0}s", at_secs),
+            TimeFormat::Relative => format_ago(Duration::from_secs_f64((self.graph_time() - at_secs).max(0.0))),
+        }
+    }
+    
+    /// Log a trigger firing or its dump, and show it in the status line
+    fn report_trigger(&mut self, event: TriggerEvent) {
+        let message = match event {
+            TriggerEvent::Fired(reason) => format!("Trigger fired: {}", reason),
+            TriggerEvent::Saved(path, count) => format!("Trigger capture of {} packets written to {}", count, path.display()),
+            TriggerEvent::Failed(message) => {
+                warn!("{}", message);
+                self.notifications.push((Instant::now(), message));
+                return;
+            }
+        };
+        info!("{}", message);
+        self.notifications.push((Instant::now(), message));
+    }
+    
+    /// Write out a triggered capture still collecting its post-trigger packets
+    pub fn flush_trigger(&mut self) {
+        if let Some(event) = self.trigger.flush() {
+            self.report_trigger(event);
+        }
+    }
+    
+    /// Write cumulative totals to the state file, if one is configured
+    pub fn save_state(&mut self) {
+        if let Some(state) = self.state.as_mut() {
+            if let Err(e) = state.save(self.devices.values()) {
+                warn!("{}", e);
+            }
+        }
+    }
+    
+    /// Evict disconnected devices once more than `max_devices` are tracked
+    fn enforce_device_limit(&mut self) {
+        let excess = self.devices.len().saturating_sub(self.max_devices);
+        if excess == 0 {
+            return;
+        }
+        
+        for key in select_evictions(self.devices.values(), excess) {
+            self.remove_device(key);
+        }
+    }
+    
+    fn recalculate_totals(&mut self) {
+        self.total_bandwidth = self.devices.values()
+            .map(|device| device.bandwidth_stats.current_bps)
+            .sum();
+        if self.total_bandwidth > self.peak_bandwidth {
+            self.peak_bandwidth = self.total_bandwidth;
+        }
+    }
+    
+    pub fn update_device(&mut self, mut device: UsbDevice) {
+        if !self.filter.allows(&device) {
+            return;
+        }
+        let device_key = device.key();
+        device.exclude_control_endpoint = self.exclude_control_endpoint;
+        device.set_periodic_window(self.periodic_window);
+        
+        // A different device under a reused number is a disconnect and a connect
+        if self.devices.get(&device_key).is_some_and(|existing| existing.identity_changed(&device)) {
+            info!("Device {} now belongs to a different device; starting a new entry", device_key);
+            self.remove_device(device_key);
+        }
         
         // Update total bandwidth
         if let Some(existing_device) = self.devices.get(&device_key) {
             self.total_bandwidth -= existing_device.bandwidth_stats.current_bps;
+            device.carry_first_seen(existing_device);
         }
         
         self.total_bandwidth += device.bandwidth_stats.current_bps;
@@ -69,92 +703,677 @@ impl UsbTopApp {
             self.peak_bandwidth = self.total_bandwidth;
         }
         
+        if !self.devices.contains_key(&device_key) {
+            self.restore_state(&mut device);
+            self.events.record(self.graph_time(), device_key, DeviceEventKind::Connected);
+            self.hooks.device_event(HookEvent::DeviceConnected, &device, Instant::now());
+        }
         self.devices.insert(device_key, device);
     }
     
-    pub fn remove_device(&mut self, bus_id: u8, device_id: u8) {
-        let device_key = format!("{}:{}", bus_id, device_id);
-        if let Some(device) = self.devices.remove(&device_key) {
+    /// Drop a device, handing its totals back to the state file
+    pub fn remove_device(&mut self, key: DeviceKey) {
+        let Some(device) = self.forget_device(key) else {
+            return;
+        };
+        if let Some(state) = self.state.as_mut() {
+            state.retain(&device);
+        }
+    }
+    
+    /// Drop a device and everything kept for it, returning its entry
+    fn forget_device(&mut self, key: DeviceKey) -> Option<UsbDevice> {
+        let removed = self.devices.remove(&key);
+        if let Some(device) = &removed {
             self.total_bandwidth -= device.bandwidth_stats.current_bps;
+            // Already reported when it was marked disconnected
+            if !device.is_disconnected {
+                self.events.record(self.graph_time(), key, DeviceEventKind::Disconnected);
+                self.hooks.device_event(HookEvent::DeviceDisconnected, device, Instant::now());
+            }
+        }
+        self.device_history.remove(&key);
+        if !self.devices.values().any(|device| device.bus_id == key.bus_id()) {
+            self.bus_history.remove(&key.bus_id());
+        }
+        self.heatmap.remove(key);
+        self.descriptors.forget(key);
+        if self.selected_device == Some(key) {
+            self.selected_device = None;
         }
+        removed
     }
     
-    pub fn update_bandwidth_history(&mut self) {
-        let now = self.last_update.elapsed().as_secs_f64();
-        self.bandwidth_history.push((now, self.total_bandwidth));
-        
-        // Keep only last 60 seconds of data
-        if self.bandwidth_history.len() > 60 {
-            self.bandwidth_history.drain(0..self.bandwidth_history.len() - 60);
+    /// Flag a device that left sysfs; it stays listed until it's removed
+    /// after the grace period, or cleared in dim mode
+    fn mark_disconnected(&mut self, key: DeviceKey) {
+        let Some(device) = self.devices.get_mut(&key) else {
+            return;
+        };
+        device.mark_disconnected();
+        self.events.record(self.graph_time(), key, DeviceEventKind::Disconnected);
+        if let Some(device) = self.devices.get(&key) {
+            self.hooks.device_event(HookEvent::DeviceDisconnected, device, Instant::now());
+        }
+    }
+    
+    /// Check which devices are still plugged in, and drop disconnected ones
+    /// whose grace period is over unless they're kept for dimming
+    fn track_disconnects(&mut self) {
+        if self.detect_disconnects {
+            let gone: Vec<DeviceKey> = self.devices.iter()
+                .filter(|(_, device)| !device.is_disconnected && device.is_present() == Some(false))
+                .map(|(key, _)| *key)
+                .collect();
+            for key in gone {
+                self.mark_disconnected(key);
+            }
         }
         
-        self.last_update = Instant::now();
+        if self.inactive_devices == InactiveDevices::Remove {
+            let expired: Vec<DeviceKey> = self.devices.iter()
+                .filter(|(_, device)| device.should_remove())
+                .map(|(key, _)| *key)
+                .collect();
+            for key in expired {
+                self.remove_device(key);
+            }
+        }
     }
     
-    pub fn handle_input(&mut self) -> Result<bool> {
-        if event::poll(Duration::from_millis(50))? {
-            if let Event::Key(key) = event::read()? {
-                if key.kind == KeyEventKind::Press {
-                    match key.code {
-                        KeyCode::Char('q') | KeyCode::Esc => return Ok(true),
-                        KeyCode::Char('h') => self.show_help = !self.show_help,
-                        KeyCode::Up => self.select_previous_device(),
-                        KeyCode::Down => self.select_next_device(),
-                        _ => {}
-                    }
+    /// Drop every disconnected device kept in the list
+    pub fn clear_disconnected(&mut self) {
+        let disconnected: Vec<DeviceKey> = self.devices.iter()
+            .filter(|(_, device)| device.is_disconnected)
+            .map(|(key, _)| *key)
+            .collect();
+        let count = disconnected.len();
+        for key in disconnected {
+            self.remove_device(key);
+        }
+        self.recalculate_totals();
+        self.notifications.push((Instant::now(), format!("Cleared {} disconnected devices", count)));
+    }
+    
+    /// Stats for a device under the active transfer filter
+    /// Returns None when the device has no traffic of the selected type
+    pub fn get_filtered_stats<'a>(&self, device: &'a UsbDevice) -> Option<&'a BandwidthStats> {
+        match self.transfer_filter.transfer_type() {
+            Some(transfer_type) => device.get_transfer_stats(transfer_type),
+            None => Some(&device.bandwidth_stats),
+        }
+    }
+    
+    /// Total bandwidth across devices, restricted to the active transfer filter
+    pub fn get_filtered_bandwidth(&self) -> f64 {
+        match self.transfer_filter {
+            TransferFilter::All => self.total_bandwidth,
+            _ => self.devices.values()
+                .filter_map(|device| self.get_filtered_stats(device))
+                .map(|stats| stats.current_bps)
+                .sum(),
+        }
+    }
+    
+    /// Bus ids with at least one tracked device, in ascending order
+    pub fn get_bus_ids(&self) -> Vec<u8> {
+        let mut bus_ids: Vec<u8> = self.devices.values().map(|device| device.bus_id).collect();
+        bus_ids.sort();
+        bus_ids.dedup();
+        bus_ids
+    }
+    
+    /// Bandwidth in human-readable units at the configured precision
+    pub fn format_rate(&self, bps: f64) -> String {
+        format_bandwidth_with(bps, self.rate_precision)
+    }
+    
+    /// A device list bandwidth cell: the rate, or in relative mode its share
+    /// of what the device's speed can carry
+    fn format_bandwidth_cell(&self, device: &UsbDevice, bps: f64) -> String {
+        if !self.show_relative_bandwidth {
+            return self.format_rate(bps);
+        }
+        match device.share_of_capacity(bps) {
+            Some(percentage) => format!("{:.1}%", percentage),
+            None => "n/a".to_string(),
+        }
+    }
+    
+    /// Order of two device rows under the current sort key and direction
+    /// Ties fall back to bus:dev so equal rows don't swap between frames.
+    fn compare_rows(&self, (a, a_stats): (&UsbDevice, &BandwidthStats), (b, b_stats): (&UsbDevice, &BandwidthStats)) -> std::cmp::Ordering {
+        // Relative mode ranks by how hard each device works against its own limit
+        let rate = |device: &UsbDevice, bps: f64| {
+            if self.show_relative_bandwidth { device.share_of_capacity(bps).unwrap_or(0.0) } else { bps }
+        };
+        let ordering = match self.sort_by {
+            SortKey::Bandwidth => rate(a, a_stats.current_bps).total_cmp(&rate(b, b_stats.current_bps)),
+            SortKey::Rx => rate(a, a_stats.rx_bps).total_cmp(&rate(b, b_stats.rx_bps)),
+            SortKey::Tx => rate(a, a_stats.tx_bps).total_cmp(&rate(b, b_stats.tx_bps)),
+            SortKey::Device => a.key().cmp(&b.key()),
+            SortKey::Vendor => a.vendor.cmp(&b.vendor),
+            SortKey::Product => a.product.cmp(&b.product),
+            SortKey::Connected => a.connected_for().cmp(&b.connected_for()),
+        };
+        let ordering = match self.sort_order {
+            SortOrder::Asc => ordering,
+            SortOrder::Desc => ordering.reverse(),
+        };
+        ordering.then_with(|| a.key().cmp(&b.key()))
+    }
+    
+    /// Device rows in sort order, before any hysteresis
+    fn ranked_device_keys(&self) -> Vec<DeviceKey> {
+        let mut ranked: Vec<(&UsbDevice, &BandwidthStats)> = self.devices.values()
+            .filter_map(|device| self.get_filtered_stats(device).map(|stats| (device, stats)))
+            .collect();
+        ranked.sort_by(|a, b| self.compare_rows(*a, *b));
+        // Kept disconnected devices sink below the connected ones
+        if self.inactive_devices == InactiveDevices::Dim {
+            ranked.sort_by_key(|(device, _)| device.is_disconnected);
+        }
+        ranked.into_iter().map(|(device, _)| device.key()).collect()
+    }
+    
+    /// Total bandwidth of all devices on a bus
+    pub fn get_bus_bandwidth(&self, bus_id: u8) -> f64 {
+        self.devices.values()
+            .filter(|device| device.bus_id == bus_id)
+            .map(|device| device.bandwidth_stats.current_bps)
+            .sum()
+    }
+    
+    /// Find the local host controllers and the link speed of each bus
+    pub fn detect_controllers(&mut self) {
+        self.host_controllers = discover_controllers(&sysfs::root());
+        for controller in &self.host_controllers {
+            debug!("Host controller {} ({}): {:?}", controller.name, controller.kind.label(), controller.root_hubs);
+        }
+        self.typec_ports = discover_typec_ports(Path::new(SYSFS_TYPEC));
+    }
+    
+    /// What each occupied USB-C connector carries, e.g. ("port0", "USB3 + DP alt mode (bus 2)")
+    pub fn typec_summaries(&self) -> Vec<(String, String)> {
+        self.typec_ports.iter()
+            .filter_map(|port| {
+                let speed = port.usb_speed(self.devices.values());
+                let mut description = port.describe(speed.as_ref())?;
+                let mut buses: Vec<u8> = port.usb_ports.iter().map(|(bus, _)| *bus).collect();
+                buses.dedup();
+                if !buses.is_empty() {
+                    let buses: Vec<String> = buses.iter().map(|bus| bus.to_string()).collect();
+                    description.push_str(&format!(" (bus {})", buses.join(", ")));
+                }
+                Some((port.name.clone(), description))
+            })
+            .collect()
+    }
+    
+    /// Share of its root hub's link a bus is using, in percent
+    /// None when the bus's controller wasn't found or its speed is unknown
+    pub fn get_bus_utilization(&self, bus_id: u8) -> Option<f64> {
+        let capacity = controller_for_bus(&self.host_controllers, bus_id)?
+            .bus_speed(bus_id)?
+            .to_practical_bytes_per_second();
+        (capacity > 0.0).then(|| self.get_bus_bandwidth(bus_id) / capacity * 100.0)
+    }
+    
+    /// Summed bandwidth of all buses behind a shared host controller
+    pub fn get_controller_bandwidth(&self, controller: &ControllerConfig) -> f64 {
+        controller.buses.iter().map(|bus_id| self.get_bus_bandwidth(*bus_id)).sum()
+    }
+    
+    /// External hubs with devices behind them and their combined downstream bandwidth
+    pub fn hub_loads(&self) -> Vec<HubLoad> {
+        aggregate_by_hub(self.devices.values())
+    }
+    
+    /// Keys of the devices that pass the active transfer filter
+    fn visible_device_keys(&self) -> Vec<DeviceKey> {
+        let mut keys: Vec<DeviceKey> = self.devices.iter()
+            .filter(|(_, device)| self.get_filtered_stats(device).is_some())
+            .map(|(key, _)| *key)
+            .collect();
+        keys.sort();
+        keys
+    }
+    
+    /// Pull warnings logged while the TUI is active into the notification line
+    pub fn collect_notifications(&mut self) {
+        let now = Instant::now();
+        self.notifications.extend(logging::drain_notifications().into_iter().map(|message| (now, message)));
+        self.notifications.retain(|(logged, _)| logged.elapsed() < NOTIFICATION_TIMEOUT);
+    }
+    
+    /// Zero bandwidth totals, peaks and URB counters for every device
+    pub fn reset_stats(&mut self) {
+        for device in self.devices.values_mut() {
+            device.reset_stats();
+        }
+        self.heatmap.clear();
+        self.total_bandwidth = 0.0;
+        self.peak_bandwidth = 0.0;
+    }
+    
+    /// Switch to a new device filter
+    /// Tracked devices it rejects keep their entries and totals but stop
+    /// counting until a later filter lets them back in; devices rejected by
+    /// the old filter get another look on their next packet.
+    pub fn set_filter(&mut self, filter: DeviceFilter) {
+        self.filter = filter;
+        self.filtered.clear();
+    }
+    
+    /// Carry out a control socket command; the text is the status line detail
+    /// `quit` is left to the UI loop.
+    pub fn handle_control(&mut self, command: &ControlCommand) -> Result<String, String> {
+        match command {
+            ControlCommand::Snapshot { vendors } => {
+                let mut message = Message::snapshot(self.devices.values());
+                if *vendors {
+                    message = message.with_vendors(aggregate_by_vendor(self.devices.values()));
                 }
+                serde_json::to_string(&message)
+                    .map_err(|e| format!("failed to encode snapshot: {}", e))
+            }
+            ControlCommand::Reset => {
+                self.reset_stats();
+                Ok("statistics reset".to_string())
+            }
+            ControlCommand::Pause => {
+                self.paused = true;
+                Ok("paused".to_string())
+            }
+            ControlCommand::Resume => {
+                self.paused = false;
+                Ok("resumed".to_string())
+            }
+            ControlCommand::Filter(patterns) => {
+                let patterns: Vec<&str> = patterns.iter().map(String::as_str).collect();
+                let filter = self.filter.narrowed_to(&patterns)?;
+                self.set_filter(filter);
+                let recorded = self.devices.values().filter(|device| self.filter.allows(device)).count();
+                Ok(format!("{} of {} devices recorded", recorded, self.devices.len()))
             }
+            ControlCommand::Quit => Ok("quitting".to_string()),
         }
-        Ok(false)
     }
     
-    fn select_previous_device(&mut self) {
-        let device_keys: Vec<String> = self.devices.keys().cloned().collect();
-        if device_keys.is_empty() {
-            return;
+    /// Carry out a command palette line; the text goes to the notification line
+    /// `quit` is left to the key handler.
+    pub fn run_palette_command(&mut self, command: &PaletteCommand) -> Result<String, String> {
+        match command {
+            PaletteCommand::Sort(key, order) => {
+                self.sort_by = *key;
+                self.sort_order = *order;
+                let key = format!("{:?}", key).to_lowercase();
+                let direction = if *order == SortOrder::Asc { "ascending" } else { "descending" };
+                Ok(format!("Sorted by {}, {}", key, direction))
+            }
+            PaletteCommand::Columns(columns) => {
+                self.columns = columns.clone();
+                Ok(format!("Showing {} columns", columns.len()))
+            }
+            PaletteCommand::Control(command) => self.handle_control(command),
+        }
+    }
+    
+    /// Re-read descriptors for the selected device, or every device when none is selected
+    /// For devices whose vendor/product strings weren't readable when they were
+    /// first seen. A device the filter now rejects is dropped.
+    pub fn rescan_devices(&mut self) {
+        let keys: Vec<DeviceKey> = match self.selected_device.filter(|key| self.devices.contains_key(key)) {
+            Some(key) => vec![key],
+            None => self.devices.keys().copied().collect(),
+        };
+        if self.origin.is_none() {
+            self.detect_controllers();
         }
         
-        let current_index = self.selected_device
-            .as_ref()
-            .and_then(|selected| device_keys.iter().position(|k| k == selected))
-            .unwrap_or(0);
+        let mut failed = 0;
+        for key in &keys {
+            if self.devices.get(key).is_none_or(|device| device.origin.is_some()) {
+                continue;
+            }
+            if !self.refresh_descriptors(*key) {
+                failed += 1;
+            }
+            if !self.filter.permits(&self.devices[key]) {
+                self.remove_device(*key);
+                self.filtered.insert(*key, Instant::now());
+            }
+        }
         
-        let new_index = if current_index == 0 {
-            device_keys.len() - 1
+        let target = match keys.as_slice() {
+            [key] => format!("device {}", key),
+            keys => format!("{} devices", keys.len()),
+        };
+        let message = if failed > 0 {
+            format!("Refreshed descriptors for {} ({} failed)", target, failed)
         } else {
-            current_index - 1
+            format!("Refreshed descriptors for {}", target)
         };
-        
-        self.selected_device = Some(device_keys[new_index].clone());
+        self.notifications.push((Instant::now(), message));
     }
     
-    fn select_next_device(&mut self) {
-        let device_keys: Vec<String> = self.devices.keys().cloned().collect();
-        if device_keys.is_empty() {
+    /// Re-read one device's descriptors; false if that failed
+    /// A different device found behind the number is recorded as the old one
+    /// disconnecting and the new one connecting, with its own totals.
+    fn refresh_descriptors(&mut self, key: DeviceKey) -> bool {
+        let Some(device) = self.devices.get_mut(&key) else {
+            return true;
+        };
+        let previous = match device.refresh_descriptors() {
+            Ok(previous) => previous,
+            Err(e) => {
+                warn!("Failed to re-read descriptors for {}: {}", key, e);
+                return false;
+            }
+        };
+        if let Some(previous) = previous {
+            info!("Device {} now belongs to a different device; its totals start over", key);
+            self.device_history.remove(&key);
+            self.heatmap.remove(key);
+            let now = self.graph_time();
+            self.events.record(now, key, DeviceEventKind::Disconnected);
+            self.events.record(now, key, DeviceEventKind::Connected);
+            self.hooks.device_event(HookEvent::DeviceDisconnected, &previous, Instant::now());
+            if let Some(device) = self.devices.get(&key) {
+                self.hooks.device_event(HookEvent::DeviceConnected, device, Instant::now());
+            }
+            self.recalculate_totals();
+        }
+        true
+    }
+    
+    /// Seconds since startup; the x axis of the bandwidth graph and event markers
+    fn graph_time(&self) -> f64 {
+        self.started.elapsed().as_secs_f64()
+    }
+    
+    /// Follow the scheduled capture state published by the reader supervisor
+    pub fn set_capture_active(&mut self, active: bool) {
+        let idle = !active;
+        if idle == self.capture_idle {
             return;
         }
+        self.capture_idle = idle;
         
-        let current_index = self.selected_device
-            .as_ref()
-            .and_then(|selected| device_keys.iter().position(|k| k == selected))
-            .unwrap_or(0);
-        
-        let new_index = (current_index + 1) % device_keys.len();
-        self.selected_device = Some(device_keys[new_index].clone());
+        // Rates spanning the idle gap would be meaningless, so restart them either way
+        for device in self.devices.values_mut() {
+            device.reset_rates();
+        }
+        self.recalculate_totals();
+    }
+    
+    /// Bytes captured across all tracked devices
+    fn total_bytes(&self) -> u64 {
+        self.devices.values()
+            .map(|device| device.bandwidth_stats.total_rx_bytes.saturating_add(device.bandwidth_stats.total_tx_bytes))
+            .fold(0, u64::saturating_add)
+    }
+    
+    /// Log what was captured once the local date rolls over
+    pub fn check_daily_summary(&mut self) {
+        let today = Local::now().date_naive();
+        let total = self.total_bytes();
+        match self.summary_day {
+            Some((day, _)) if day == today => {}
+            Some((day, baseline)) => {
+                let message = format!(
+                    "Daily summary for {}: {:.1} MB captured across {} devices",
+                    day,
+                    total.saturating_sub(baseline) as f64 / 1_000_000.0,
+                    self.devices.len(),
+                );
+                info!("{}", message);
+                self.notifications.push((Instant::now(), message));
+                self.summary_day = Some((today, total));
+            }
+            None => self.summary_day = Some((today, total)),
+        }
+    }
+    
+    /// The packet source closed (end of a piped capture): take a last sample and freeze the graph
+    pub fn end_input(&mut self) {
+        self.update_bandwidth_history();
+        self.input_ended = true;
+        
+        let summary = CaptureSummary::from_app(self);
+        self.notifications.push((Instant::now(), format!(
+            "End of input: {} packets, {:.1} MB from {} devices (summary printed on exit)",
+            summary.total_packets(),
+            summary.total_bytes() as f64 / 1_000_000.0,
+            summary.devices.len(),
+        )));
+    }
+    
+    /// Times of the oldest and newest retained graph samples
+    fn history_range(&self) -> (f64, f64) {
+        let earliest = self.bandwidth_history.first().map_or(0.0, |(t, _)| *t);
+        let latest = self.bandwidth_history.last().map_or(0.0, |(t, _)| *t);
+        (earliest, latest)
+    }
+    
+    pub fn update_bandwidth_history(&mut self) {
+        if self.paused || self.input_ended {
+            return;
+        }
+        
+        if self.state.as_ref().is_some_and(|state| state.is_due(Instant::now())) {
+            self.save_state();
+        }
+        
+        // Rates only move when packets arrive, so quiet devices are brought
+        // down here before anything reads them
+        let refreshed = Instant::now();
+        for device in self.devices.values_mut() {
+            device.refresh_rates(refreshed);
+        }
+        self.recalculate_totals();
+        
+        // Seconds since startup, so samples stay spaced on the time axis
+        let now = self.graph_time();
+        let capacity = self.history_capacity;
+        push_sample(&mut self.bandwidth_history, (now, self.total_bandwidth), capacity);
+        
+        for (key, device) in &self.devices {
+            let stats = &device.bandwidth_stats;
+            push_sample(self.device_history.entry(*key).or_default(), (now, stats.rx_bps, stats.tx_bps), capacity);
+        }
+        
+        for bus_id in self.get_bus_ids() {
+            let bandwidth = self.get_bus_bandwidth(bus_id);
+            push_sample(self.bus_history.entry(bus_id).or_default(), (now, bandwidth), capacity);
+        }
+        self.heatmap.advance(now);
+        self.hooks.check_thresholds(self.devices.values(), Instant::now());
+        self.track_disconnects();
+        
+        if self.last_descriptor_check.elapsed() >= DESCRIPTOR_RECHECK_INTERVAL {
+            let keys: Vec<DeviceKey> = self.devices.iter()
+                .filter(|(_, device)| !device.is_disconnected)
+                .map(|(key, _)| *key)
+                .collect();
+            for key in keys {
+                self.refresh_descriptors(key);
+            }
+            // Partners and alt modes change without any USB traffic, e.g. a
+            // DisplayPort-only monitor
+            if !self.typec_ports.is_empty() {
+                self.typec_ports = discover_typec_ports(Path::new(SYSFS_TYPEC));
+            }
+            self.last_descriptor_check = Instant::now();
+        }
+        
+        self.last_update = Instant::now();
+    }
+    
+    /// Handle every pending event, waiting up to `timeout` for the first one
+    pub fn handle_input(&mut self, timeout: Duration) -> Result<InputOutcome> {
+        let mut outcome = InputOutcome::Idle;
+        let mut wait = timeout;
+        while event::poll(wait)? {
+            wait = Duration::ZERO;
+            match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => {
+                    let quit = if self.command_line.is_some() {
+                        self.handle_palette_key(&key)
+                    } else {
+                        self.handle_key(&key)
+                    };
+                    if quit {
+                        return Ok(InputOutcome::Quit);
+                    }
+                    outcome = InputOutcome::Handled;
+                }
+                Event::Resize(..) => outcome = InputOutcome::Handled,
+                _ => {}
+            }
+        }
+        Ok(outcome)
+    }
+    
+    /// Apply a key press; true when it asks to quit
+    fn handle_key(&mut self, key: &KeyEvent) -> bool {
+        match self.keybindings.action_for_press(KeyPress::from_event(key)) {
+            Some(Action::Quit) => return true,
+            Some(Action::Help) => self.show_help = !self.show_help,
+            Some(Action::Filter) => self.transfer_filter = self.transfer_filter.next(),
+            Some(Action::ResetStats) => self.reset_stats(),
+            Some(Action::Detail) => self.show_detail = !self.show_detail,
+            Some(Action::Pause) => self.paused = !self.paused,
+            Some(Action::GraphSource) => self.graph_source = self.graph_source.next(),
+            Some(Action::PeakHold) => self.show_peak_hold = !self.show_peak_hold,
+            Some(Action::LogScale) => self.graph_log_scale = !self.graph_log_scale,
+            Some(Action::GraphLayout) => {
+                self.graph_layout = self.graph_layout.next();
+                self.notifications.push((Instant::now(), format!("Graph layout: {}", self.graph_layout.label())));
+            }
+            Some(Action::VendorView) => self.show_vendors = !self.show_vendors,
+            Some(Action::ClassView) => self.show_classes = !self.show_classes,
+            Some(Action::BudgetView) => self.show_budget = !self.show_budget,
+            Some(Action::HeatmapView) => self.show_heatmap = !self.show_heatmap,
+            Some(Action::HexDump) => self.show_hexdump = !self.show_hexdump,
+            Some(Action::RelativeBandwidth) => self.show_relative_bandwidth = !self.show_relative_bandwidth,
+            Some(Action::Explain) => self.show_explain = !self.show_explain,
+            Some(Action::Rescan) => self.rescan_devices(),
+            Some(Action::PanBack) => {
+                let (earliest, latest) = self.history_range();
+                self.viewport.pan_back(earliest, latest);
+            }
+            Some(Action::PanForward) => self.viewport.pan_forward(self.history_range().1),
+            Some(Action::ZoomIn) => self.viewport.zoom_in(),
+            Some(Action::ZoomOut) => self.viewport.zoom_out(self.history_retention.as_secs_f64()),
+            Some(Action::NavUp) => {
+                self.follow_busiest = false;
+                self.select_previous_device();
+            }
+            Some(Action::NavDown) => {
+                self.follow_busiest = false;
+                self.select_next_device();
+            }
+            Some(Action::CommandPalette) => self.command_line = Some(String::new()),
+            Some(Action::ClearDisconnected) => self.clear_disconnected(),
+            Some(Action::ToggleTimeFormat) => {
+                self.time_format = self.time_format.toggle();
+                self.notifications.push((Instant::now(), format!("Time format: {}", self.time_format.label())));
+            }
+            Some(Action::FollowBusiest) => {
+                self.follow_busiest = !self.follow_busiest;
+                let message = if self.follow_busiest { "Following the top device" } else { "Stopped following the top device" };
+                self.notifications.push((Instant::now(), message.to_string()));
+            }
+            None => {}
+        }
+        false
+    }
+    
+    /// Edit the command palette line; true when the command run asks to quit
+    fn handle_palette_key(&mut self, key: &KeyEvent) -> bool {
+        let Some(input) = self.command_line.as_mut() else {
+            return false;
+        };
+        match key.code {
+            KeyCode::Esc => self.command_line = None,
+            // Backspace on an empty line closes the palette
+            KeyCode::Backspace if input.pop().is_none() => self.command_line = None,
+            KeyCode::Tab => {
+                if let Some(completed) = palette::complete(input) {
+                    *input = completed;
+                }
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => input.push(c),
+            KeyCode::Enter => {
+                let line = self.command_line.take().unwrap_or_default();
+                if line.trim().is_empty() {
+                    return false;
+                }
+                let result = PaletteCommand::parse(&line).and_then(|command| {
+                    if command == PaletteCommand::Control(ControlCommand::Quit) {
+                        return Ok(None);
+                    }
+                    self.run_palette_command(&command).map(Some)
+                });
+                let message = match result {
+                    Ok(None) => return true,
+                    Ok(Some(message)) => message,
+                    Err(e) => format!("Error: {}", e),
+                };
+                self.notifications.push((Instant::now(), message));
+            }
+            _ => {}
+        }
+        false
+    }
+    
+    fn select_previous_device(&mut self) {
+        let device_keys = self.visible_device_keys();
+        if device_keys.is_empty() {
+            return;
+        }
+        
+        let current_index = self.selected_device
+            .as_ref()
+            .and_then(|selected| device_keys.iter().position(|k| k == selected))
+            .unwrap_or(0);
+        
+        let new_index = if current_index == 0 {
+            device_keys.len() - 1
+        } else {
+            current_index - 1
+        };
+        
+        self.selected_device = Some(device_keys[new_index]);
+    }
+    
+    fn select_next_device(&mut self) {
+        let device_keys = self.visible_device_keys();
+        if device_keys.is_empty() {
+            return;
+        }
+        
+        let current_index = self.selected_device
+            .as_ref()
+            .and_then(|selected| device_keys.iter().position(|k| k == selected))
+            .unwrap_or(0);
+        
+        let new_index = (current_index + 1) % device_keys.len();
+        self.selected_device = Some(device_keys[new_index]);
     }
 }
 
-pub fn run_ui(mut app: UsbTopApp) -> Result<()> {
-    // Setup terminal
+pub(crate) fn setup_terminal() -> Result<Terminal<CrosstermBackend<io::Stdout>>> {
+    logging::set_tui_active(true);
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
     let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
-    
-    let result = run_app(&mut terminal, &mut app);
-    
-    // Restore terminal
+    Ok(Terminal::new(backend)?)
+}
+
+pub(crate) fn restore_terminal(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<()> {
     disable_raw_mode()?;
     execute!(
         terminal.backend_mut(),
@@ -162,186 +1381,1227 @@ pub fn run_ui(mut app: UsbTopApp) -> Result<()> {
         DisableMouseCapture
     )?;
     terminal.show_cursor()?;
+    logging::set_tui_active(false);
+    Ok(())
+}
+
+pub fn run_ui(mut app: UsbTopApp, mut packets: UnboundedReceiver<UsbPacket>, tasks: ReaderTasks) -> Result<()> {
+    let mut terminal = setup_terminal()?;
+    
+    let capture_state = tasks.capture_state();
+    let result = run_app(&mut terminal, &mut app, &mut packets, capture_state);
+    app.save_state();
+    app.flush_trigger();
+    
+    // Stop the readers while the TUI still owns the terminal so their last
+    // log lines land in the notification queue rather than on the screen
+    tasks.shutdown_blocking();
+    
+    restore_terminal(&mut terminal)?;
+    
+    if result.is_ok() && app.input_ended {
+        CaptureSummary::from_app(&app).print();
+    } else if result.is_ok() {
+        print_class_report(&aggregate_by_class(app.devices.values()));
+    }
     
     result
 }
 
-fn run_app(terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>, app: &mut UsbTopApp) -> Result<()> {
+fn run_app(
+    terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>,
+    app: &mut UsbTopApp,
+    packets: &mut UnboundedReceiver<UsbPacket>,
+    capture_state: Option<watch::Receiver<bool>>,
+) -> Result<()> {
+    let mut pacer = FramePacer::new(app.refresh_rate);
     loop {
-        terminal.draw(|f| draw_ui(f, app))?;
+        if let Some(state) = &capture_state {
+            app.set_capture_active(*state.borrow());
+            app.check_daily_summary();
+        }
         
-        if app.handle_input()? {
+        // Packets are never dropped, but under a flood they are handled in
+        // slices so the screen and the keyboard keep responding
+        let drain_started = Instant::now();
+        let mut backlog = false;
+        'drain: loop {
+            for _ in 0..PACKET_DRAIN_BATCH {
+                match packets.try_recv() {
+                    Ok(packet) => app.process_packet(&packet),
+                    Err(TryRecvError::Empty) => break 'drain,
+                    Err(TryRecvError::Disconnected) => {
+                        if !app.input_ended {
+                            app.end_input();
+                        }
+                        break 'drain;
+                    }
+                }
+            }
+            if drain_started.elapsed() >= PACKET_DRAIN_BUDGET {
+                backlog = true;
+                break;
+            }
+        }
+        app.collect_notifications();
+        
+        let mut quit = false;
+        while let Some(request) = app.control.as_mut().and_then(|control| control.try_recv().ok()) {
+            let reply = app.handle_control(&request.command);
+            quit |= request.command == ControlCommand::Quit;
+            let _ = request.reply.send(reply);
+            pacer.request_redraw();
+        }
+        if quit {
             break;
         }
         
-        // Update bandwidth history periodically
-        if app.last_update.elapsed() >= app.refresh_rate {
+        // Sample on its own schedule so the graph gets every point even
+        // when the screen is redrawn less often
+        if app.last_update.elapsed() >= app.sample_interval {
             app.update_bandwidth_history();
         }
+        
+        let now = Instant::now();
+        if pacer.is_due(now) {
+            terminal.draw(|f| draw_ui(f, app))?;
+            pacer.record_frame(now, now.elapsed());
+            app.slow_refresh = pacer.is_degraded();
+        }
+        
+        // Don't wait on the keyboard while packets are queued
+        let timeout = if backlog {
+            Duration::ZERO
+        } else {
+            let next_sample = app.sample_interval.saturating_sub(app.last_update.elapsed());
+            pacer.time_until_due(Instant::now())
+                .min(next_sample)
+                .clamp(Duration::from_millis(1), MIN_FRAME_INTERVAL)
+        };
+        match app.handle_input(timeout)? {
+            InputOutcome::Quit => break,
+            InputOutcome::Handled => pacer.request_redraw(),
+            InputOutcome::Idle => {}
+        }
+    }
+    Ok(())
+}
+
+fn draw_ui(f: &mut Frame, app: &mut UsbTopApp) {
+    profile_span!("draw");
+    
+    if app.show_help {
+        draw_help_overlay(f);
+        return;
+    }
+    
+    let size = f.size();
+    let hub_loads = app.hub_loads();
+    let typec = app.typec_summaries();
+    let bus_summary_height = (app.get_bus_ids().len() + app.controllers.len() + hub_loads.len() + typec.len()) as u16 + 2;
+    // The sparkline takes a header row of its own instead of the graph panel
+    let sparkline = app.graph_layout.uses_sparkline(size.height);
+    
+    // Create main layout
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(if sparkline { 5 } else { 3 }), // Header
+            Constraint::Length(if sparkline { 0 } else { 8 }), // Bandwidth graph
+            Constraint::Min(10),       // Device list
+            Constraint::Length(bus_summary_height), // Bus summary
+            Constraint::Length(6),     // Color reference
+            Constraint::Length(if app.notifications.is_empty() && app.command_line.is_none() { 0 } else { 1 }), // Notifications
+        ])
+        .split(size);
+    
+    draw_header(f, chunks[0], app, sparkline);
+    if !sparkline {
+        draw_bandwidth_graph(f, chunks[1], app);
+    }
+    if app.show_hexdump {
+        draw_hexdump(f, chunks[2], app);
+    } else if app.show_heatmap {
+        draw_heatmap(f, chunks[2], app);
+    } else if app.show_vendors {
+        draw_vendor_list(f, chunks[2], app);
+    } else if app.show_classes {
+        draw_class_list(f, chunks[2], app);
+    } else if app.show_budget {
+        draw_budget(f, chunks[2], app);
+    } else if app.show_detail {
+        let list_chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+            .split(chunks[2]);
+        draw_device_list(f, list_chunks[0], app);
+        draw_device_detail(f, list_chunks[1], app);
+    } else {
+        draw_device_list(f, chunks[2], app);
+    }
+    draw_bus_summary(f, chunks[3], app, &hub_loads, &typec);
+    draw_color_reference(f, chunks[4]);
+    match &app.command_line {
+        Some(input) => draw_command_line(f, chunks[5], input),
+        None => draw_notifications(f, chunks[5], app),
+    }
+    
+    if app.show_explain {
+        draw_explain_overlay(f, app);
+    }
+}
+
+fn draw_notifications(f: &mut Frame, area: Rect, app: &UsbTopApp) {
+    let Some((_, latest)) = app.notifications.last() else {
+        return;
+    };
+    
+    let mut spans = vec![Span::styled(latest.clone(), Style::default().fg(WARNING_COLOR))];
+    if app.notifications.len() > 1 {
+        spans.push(Span::raw(format!("  (+{} more)", app.notifications.len() - 1)));
+    }
+    f.render_widget(Paragraph::new(Line::from(spans)), area);
+}
+
+/// The command palette, in place of the notification line while it's open
+fn draw_command_line(f: &mut Frame, area: Rect, input: &str) {
+    let line = Line::from(vec![
+        Span::styled(":", Style::default().fg(ACCENT_COLOR).add_modifier(Modifier::BOLD)),
+        Span::raw(input.to_string()),
+        Span::styled("█", Style::default().fg(ACCENT_COLOR)),
+    ]);
+    f.render_widget(Paragraph::new(line), area);
+}
+
+fn draw_header(f: &mut Frame, area: Rect, app: &UsbTopApp, sparkline: bool) {
+    let error_rates = ErrorRates::from_devices(app.devices.values(), Instant::now());
+    let header_text = vec![
+        Line::from(vec![
+            Span::styled("ng-usbtop", Style::default().fg(ACCENT_COLOR).add_modifier(Modifier::BOLD)),
+            Span::raw(" - Next-Gen USB Traffic Monitor"),
+        ]),
+        Line::from(vec![
+            Span::raw("Total: "),
+            Span::styled(
+                app.format_rate(app.get_filtered_bandwidth()),
+                Style::default().fg(PRIMARY_COLOR).add_modifier(Modifier::BOLD)
+            ),
+            Span::raw(" | Peak: "),
+            Span::styled(
+                app.format_rate(app.peak_bandwidth),
+                Style::default().fg(SECONDARY_COLOR).add_modifier(Modifier::BOLD)
+            ),
+            Span::raw(" | Devices: "),
+            Span::styled(
+                app.devices.len().to_string(),
+                Style::default().fg(SUCCESS_COLOR).add_modifier(Modifier::BOLD)
+            ),
+            Span::raw(" | Errors: "),
+            Span::styled(
+                error_rates_label(&error_rates),
+                Style::default().fg(error_rate_color(error_rates.total_per_sec)).add_modifier(Modifier::BOLD)
+            ),
+            Span::raw(" | Filter: "),
+            Span::styled(
+                app.transfer_filter.label(),
+                Style::default().fg(WARNING_COLOR).add_modifier(Modifier::BOLD)
+            ),
+            Span::styled(
+                if app.paused { "  [PAUSED]" } else { "" },
+                Style::default().fg(ERROR_COLOR).add_modifier(Modifier::BOLD)
+            ),
+            Span::styled(
+                if app.follow_busiest { "  [FOLLOW]" } else { "" },
+                Style::default().fg(ACCENT_COLOR).add_modifier(Modifier::BOLD)
+            ),
+            Span::styled(
+                if app.input_ended { "  [END OF INPUT]" } else { "" },
+                Style::default().fg(WARNING_COLOR).add_modifier(Modifier::BOLD)
+            ),
+            Span::styled(
+                if app.slow_refresh { "  [SLOW REFRESH]" } else { "" },
+                Style::default().fg(WARNING_COLOR)
+            ),
+            Span::styled(
+                if app.capture_idle { "  [IDLE (SCHEDULED)]" } else { "" },
+                Style::default().fg(WARNING_COLOR).add_modifier(Modifier::BOLD)
+            ),
+        ]),
+    ];
+    
+    let title = match &app.origin {
+        Some(origin) => format!(" ng-usbtop ({}) ", origin),
+        None => " ng-usbtop ".to_string(),
+    };
+    let block = Block::default().borders(Borders::ALL).title(title);
+    if !sparkline {
+        f.render_widget(Paragraph::new(header_text).block(block), area);
+        return;
+    }
+    
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(inner);
+    f.render_widget(Paragraph::new(header_text), rows[0]);
+    
+    // Total bandwidth over the newest samples, one per column
+    let data = create_sparkline_data(&app.bandwidth_history, rows[1].width as usize);
+    let trend = Sparkline::default()
+        .data(&data)
+        .style(Style::default().fg(PRIMARY_COLOR));
+    f.render_widget(trend, rows[1]);
+}
+
+/// Errors per second at or above which the header turns red; a steady trickle
+/// usually points at a cable or power problem
+const ERROR_RATE_CRITICAL: f64 = 1.0;
+
+fn error_rate_color(per_sec: f64) -> Color {
+    if per_sec <= 0.0 {
+        SUCCESS_COLOR
+    } else if per_sec < ERROR_RATE_CRITICAL {
+        WARNING_COLOR
+    } else {
+        ERROR_COLOR
+    }
+}
+
+fn error_rates_label(rates: &ErrorRates) -> String {
+    match rates.worst {
+        Some((key, _)) => format!("{:.1}/s (worst {})", rates.total_per_sec, key),
+        None => format!("{:.1}/s", rates.total_per_sec),
+    }
+}
+
+/// Colors cycled through for the per-bus overlay
+const BUS_COLORS: [Color; 6] = [
+    PRIMARY_COLOR,
+    SECONDARY_COLOR,
+    ACCENT_COLOR,
+    WARNING_COLOR,
+    ERROR_COLOR,
+    USB_SUPER_SPEED_PLUS,
+];
+
+/// Samples inside `[start, end]`, converted to MB/s
+fn to_mbps(history: &[(f64, f64)], (start, end): (f64, f64)) -> Vec<(f64, f64)> {
+    history.iter()
+        .filter(|(t, _)| *t >= start && *t <= end)
+        .map(|(t, bps)| (*t, bps / 1_000_000.0))
+        .collect()
+}
+
+/// Y axis room above the peak-hold line
+const PEAK_HOLD_HEADROOM: f64 = 1.1;
+
+/// A line on the bandwidth graph: name, color and points in MB/s
+type GraphSeries = (String, Color, Vec<(f64, f64)>);
+
+/// A connect or disconnect marker: color, label and its vertical line
+type EventMarker = (Color, String, [(f64, f64); 2]);
+
+fn draw_bandwidth_graph(f: &mut Frame, area: Rect, app: &UsbTopApp) {
+    let (x_start, x_end) = app.viewport.window(app.history_range().1);
+    let window = (x_start, x_end);
+    
+    let mut series: Vec<GraphSeries> = Vec::new();
+    let title = match app.graph_source {
+        GraphSource::Total => {
+            series.push(("Total".to_string(), PRIMARY_COLOR, to_mbps(&app.bandwidth_history, window)));
+            " Bandwidth History: Total (MB/s) ".to_string()
+        }
+        GraphSource::SelectedDevice => match app.selected_device {
+            Some(key) => {
+                let history = app.device_history.get(&key).map(Vec::as_slice).unwrap_or(&[]);
+                let rx: Vec<(f64, f64)> = history.iter().map(|(t, rx, _)| (*t, *rx)).collect();
+                let tx: Vec<(f64, f64)> = history.iter().map(|(t, _, tx)| (*t, *tx)).collect();
+                series.push(("RX".to_string(), PRIMARY_COLOR, to_mbps(&rx, window)));
+                series.push(("TX".to_string(), SECONDARY_COLOR, to_mbps(&tx, window)));
+                format!(" Bandwidth History: {} RX/TX (MB/s) ", key)
+            }
+            None => " Bandwidth History: Selected device (MB/s) ".to_string(),
+        },
+        GraphSource::PerBus => {
+            let mut bus_ids: Vec<&u8> = app.bus_history.keys().collect();
+            bus_ids.sort();
+            for (i, bus_id) in bus_ids.into_iter().enumerate() {
+                series.push((
+                    format!("Bus {}", bus_id),
+                    BUS_COLORS[i % BUS_COLORS.len()],
+                    to_mbps(&app.bus_history[bus_id], window),
+                ));
+            }
+            " Bandwidth History: Per-bus (MB/s) ".to_string()
+        }
+    };
+    let title = if app.graph_log_scale { title.replace("(MB/s)", "(log)") } else { title };
+    let title = if app.viewport.is_live() {
+        format!("{}[LIVE {:.0}s] ", title, app.viewport.span_secs)
+    } else {
+        format!("{}[PAUSED at {:.0}s, {:.0}s span] ", title, x_end, app.viewport.span_secs)
+    };
+    
+    if series.iter().all(|(_, _, points)| points.is_empty()) {
+        let message = if app.graph_source == GraphSource::SelectedDevice && app.selected_device.is_none() {
+            "No device selected..."
+        } else {
+            "No bandwidth data yet..."
+        };
+        let empty_graph = Paragraph::new(message)
+            .block(Block::default().borders(Borders::ALL).title(title));
+        f.render_widget(empty_graph, area);
+        return;
+    }
+    
+    // Peak within the visible window, tracked apart from the all-time peak in the header
+    let visible_peak = window_peak(series.iter().flat_map(|(_, _, points)| points.iter())).unwrap_or(0.0);
+    let peak = Some(visible_peak).filter(|mbps| app.show_peak_hold && *mbps > 0.0);
+    
+    // The log axis spans whole decades from 1 KB/s; the linear one starts at
+    // zero, with headroom keeping the peak line clear of the top border
+    let (y_min, y_max) = if app.graph_log_scale {
+        for (_, _, points) in &mut series {
+            for point in points.iter_mut() {
+                point.1 = log_scale(point.1);
+            }
+        }
+        log_bounds(visible_peak)
+    } else {
+        (0.0, peak.map_or(visible_peak, |mbps| mbps * PEAK_HOLD_HEADROOM).max(1.0)) // Minimum scale
+    };
+    let peak_line = peak.map(|mbps| {
+        let label = format!("Peak {}", app.format_rate(mbps * 1_000_000.0));
+        let y = if app.graph_log_scale { log_scale(mbps) } else { mbps };
+        (label, dashed_line(y, window, area.width as usize * 2))
+    });
+    
+    // Vertical lines at connect/disconnect events within the visible span
+    let markers: Vec<EventMarker> = if app.show_event_markers {
+        app.events.in_span(x_start, x_end)
+            .map(|event| {
+                let color = match event.kind {
+                    DeviceEventKind::Connected => SUCCESS_COLOR,
+                    DeviceEventKind::Disconnected => ERROR_COLOR,
+                };
+                let label = format!("{} {} {}", event.kind.symbol(), event.key, app.event_time_label(event.at_secs));
+                (color, label, [(event.at_secs, y_min), (event.at_secs, y_max)])
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    
+    let mut datasets: Vec<Dataset> = series.iter()
+        .map(|(name, color, points)| {
+            Dataset::default()
+                .name(name.clone())
+                .marker(symbols::Marker::Braille)
+                .style(Style::default().fg(*color))
+                .data(points)
+        })
+        .collect();
+    if let Some((label, points)) = &peak_line {
+        datasets.push(
+            Dataset::default()
+                .name(label.clone())
+                .marker(symbols::Marker::Braille)
+                .graph_type(GraphType::Scatter)
+                .style(Style::default().fg(WARNING_COLOR))
+                .data(points)
+        );
+    }
+    datasets.extend(markers.iter().map(|(color, _, points)| {
+        Dataset::default()
+            .marker(symbols::Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(*color))
+            .data(points)
+    }));
+    
+    // Marker row beneath the chart labels each line with its device
+    let (chart_area, marker_area) = if markers.is_empty() {
+        (area, None)
+    } else {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(area);
+        (chunks[0], Some(chunks[1]))
+    };
+    
+    let chart = Chart::new(datasets)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .x_axis(
+            Axis::default()
+                .title("Time (s)")
+                .style(Style::default().fg(TEXT_COLOR))
+                .bounds([x_start, x_end])
+        )
+        .y_axis(if app.graph_log_scale {
+            let labels = log_axis_labels((y_min, y_max), chart_area.height.saturating_sub(2) as usize);
+            Axis::default()
+                .title("B/s")
+                .style(Style::default().fg(TEXT_COLOR))
+                .labels(labels.into_iter().map(Span::raw).collect())
+                .bounds([y_min, y_max])
+        } else {
+            Axis::default()
+                .title("MB/s")
+                .style(Style::default().fg(TEXT_COLOR))
+                .bounds([y_min, y_max])
+        });
+    
+    f.render_widget(chart, chart_area);
+    
+    if let Some(marker_area) = marker_area {
+        let spans: Vec<Span> = markers.iter()
+            .flat_map(|(color, label, _)| [Span::styled(label.clone(), Style::default().fg(*color)), Span::raw("  ")])
+            .collect();
+        f.render_widget(Paragraph::new(Line::from(spans)), marker_area);
+    }
+}
+
+/// Header of a device list column
+fn column_header(column: Column, relative: bool) -> &'static str {
+    match column {
+        Column::Device => "Device",
+        Column::Speed => "Speed",
+        Column::Vendor => "Vendor",
+        Column::Product => "Product",
+        Column::Rx if relative => "Load ↓",
+        Column::Rx => "Bandwidth ↓",
+        Column::Tx if relative => "Load ↑",
+        Column::Tx => "Bandwidth ↑",
+        Column::Status => "Status",
+        Column::Connected => "Connected",
+    }
+}
+
+fn column_width(column: Column) -> Constraint {
+    match column {
+        Column::Device => Constraint::Length(8),
+        Column::Speed => Constraint::Length(12),
+        Column::Vendor => Constraint::Length(15),
+        Column::Product => Constraint::Length(20),
+        Column::Rx | Column::Tx => Constraint::Length(12),
+        Column::Status => Constraint::Length(17),
+        Column::Connected => Constraint::Length(9),
+    }
+}
+
+fn draw_device_list(f: &mut Frame, area: Rect, app: &mut UsbTopApp) {
+    let mut header_cells: Vec<&str> = app.columns.iter()
+        .map(|column| column_header(*column, app.show_relative_bandwidth))
+        .collect();
+    if app.show_packet_counts {
+        header_cells.push("URBs");
+    }
+    let header = Row::new(header_cells)
+        .style(Style::default().fg(ACCENT_COLOR).add_modifier(Modifier::BOLD))
+        .height(1);
+    
+    let desired = app.ranked_device_keys();
+    
+    // Hysteresis is applied against the last rendered order to keep rows from jumping
+    let order = match app.sort_strategy {
+        SortStrategy::Immediate => desired,
+        SortStrategy::Stable => app.device_order.apply(desired),
+    };
+    if app.follow_busiest {
+        let top = order.iter()
+            .find(|key| app.devices.get(key).is_some_and(|device| app.get_filtered_stats(device).is_some()));
+        if let Some(key) = top {
+            app.selected_device = Some(*key);
+        }
+    }
+    
+    let app = &*app;
+    let devices: Vec<_> = order.iter()
+        .filter_map(|key| app.devices.get(key))
+        .filter_map(|device| app.get_filtered_stats(device).map(|stats| (device, stats)))
+        .collect();
+    
+    let rows: Vec<Row> = devices
+        .iter()
+        .enumerate()
+        .map(|(i, (device, stats))| {
+            let is_selected = app.selected_device == Some(device.key());
+            
+            let speed_color = Color::Rgb(
+                device.speed.color_code().0,
+                device.speed.color_code().1,
+                device.speed.color_code().2,
+            );
+            
+            let idle = if app.inactive_devices == InactiveDevices::Dim { device.idle_fraction(app.idle_fade) } else { 0.0 };
+            let row_style = device_row_style(is_selected, device.is_disconnected, idle);
+            
+            // Bandwidth cells follow the utilization scale, except where the row is highlighted
+            let max_bandwidth = device.speed.to_practical_bytes_per_second();
+            let utilization = if max_bandwidth > 0.0 { stats.current_bps / max_bandwidth } else { 0.0 };
+            let bandwidth_style = if device.is_disconnected || is_selected {
+                Style::default()
+            } else {
+                Style::default().fg(app.utilization_scale.color_for(utilization))
+            };
+            
+            let mut cells: Vec<Cell> = app.columns.iter()
+                .map(|column| match column {
+                    Column::Device => Cell::from(device.key().to_string()),
+                    Column::Speed => Cell::from(format!("{:.1} Mbps", device.speed.to_mbps())),
+                    Column::Vendor => Cell::from(device.vendor.clone().unwrap_or_else(|| "Unknown".to_string())),
+                    Column::Product => Cell::from(device.product.clone().unwrap_or_else(|| "Unknown".to_string())),
+                    Column::Rx => Cell::from(app.format_bandwidth_cell(device, stats.rx_bps)).style(bandwidth_style),
+                    Column::Tx => Cell::from(app.format_bandwidth_cell(device, stats.tx_bps)).style(bandwidth_style),
+                    Column::Status => status_cell(device, is_selected, device.is_without_driver(app.no_driver_after), app.inactive_devices),
+                    Column::Connected => connected_cell(device, is_selected),
+                })
+                .collect();
+            if app.show_packet_counts {
+                cells.push(Cell::from(format_urb_counts(&stats.urb_counts)));
+            }
+            
+            Row::new(cells)
+            .style(row_style)
+            .height(1)
+        })
+        .collect();
+    
+    let mut widths: Vec<Constraint> = app.columns.iter().map(|column| column_width(*column)).collect();
+    if app.show_packet_counts {
+        widths.push(Constraint::Length(30)); // URB counts
+    }
+    
+    let table = Table::new(rows, widths)
+        .header(header)
+        .block(Block::default().borders(Borders::ALL).title(" USB Devices "));
+    
+    f.render_widget(table, area);
+}
+
+/// Disconnected rows are dimmed and struck through rather than given a
+/// background, so a selected disconnected row keeps the selection highlight
+/// and still reads differently from a selected connected one
+/// `idle` (0.0 - 1.0) fades a connected row towards the disconnected color.
+fn device_row_style(is_selected: bool, is_disconnected: bool, idle: f64) -> Style {
+    let style = match (is_selected, is_disconnected) {
+        (true, _) => Style::default().bg(ACCENT_COLOR).fg(Color::Black),
+        (false, true) => Style::default().fg(DISCONNECTED_FG).add_modifier(Modifier::DIM),
+        (false, false) => Style::default().fg(blend(TEXT_COLOR, DISCONNECTED_FG, idle)),
+    };
+    if is_disconnected {
+        style.add_modifier(Modifier::CROSSED_OUT)
+    } else {
+        style
+    }
+}
+
+/// How long the device has been connected, highlighted while it is new so
+/// an unexpected plug-in stands out
+fn connected_cell(device: &UsbDevice, is_selected: bool) -> Cell<'static> {
+    let connected_for = device.connected_for();
+    let style = if connected_for < NEW_DEVICE_HIGHLIGHT && !is_selected && !device.is_disconnected {
+        Style::default().fg(WARNING_COLOR).add_modifier(Modifier::BOLD)
+    } else {
+        Style::default()
+    };
+    Cell::from(format_uptime(connected_for)).style(style)
+}
+
+/// Status with an icon; a disconnected device counts down its grace period
+/// while the indicator fades out, or just says so when it's kept
+fn status_cell(device: &UsbDevice, is_selected: bool, without_driver: bool, inactive_devices: InactiveDevices) -> Cell<'static> {
+    let Some(progress) = device.disconnect_progress().filter(|_| device.is_disconnected) else {
+        let (label, color) = if without_driver { ("◌ No driver", WARNING_COLOR) } else { ("● Connected", SUCCESS_COLOR) };
+        let style = if is_selected { Style::default() } else { Style::default().fg(color) };
+        return Cell::from(label).style(style);
+    };
+    if inactive_devices == InactiveDevices::Dim {
+        let style = Style::default().remove_modifier(Modifier::CROSSED_OUT);
+        let style = if is_selected { style } else { style.fg(DISCONNECTED_FG) };
+        return Cell::from("✖ Disconnected").style(style);
+    }
+    
+    let remaining = DISCONNECT_GRACE_PERIOD.as_secs_f64() * (1.0 - progress);
+    let mut style = Style::default().remove_modifier(Modifier::CROSSED_OUT | Modifier::DIM);
+    if !is_selected {
+        style = style.fg(blend(ERROR_COLOR, DISCONNECTED_FADED, progress));
+    }
+    Cell::from(format!("✖ Disconnected {:.0}s", remaining.ceil())).style(style)
+}
+
+/// Whether a driver has the device, for the detail view
+fn driver_summary(device: &UsbDevice, no_driver_after: Duration) -> String {
+    let flagged = device.is_without_driver(no_driver_after);
+    match (device.driver_bound, flagged) {
+        (Some(true), _) => "bound".to_string(),
+        (Some(false), true) => "none bound, device unused".to_string(),
+        (Some(false), false) => "none bound yet".to_string(),
+        (None, true) => "likely none (control traffic only)".to_string(),
+        (None, false) => "Unknown".to_string(),
+    }
+}
+
+/// Top talkers by vendor: devices of the same vendor summed into one row
+fn draw_vendor_list(f: &mut Frame, area: Rect, app: &UsbTopApp) {
+    let vendors = aggregate_by_vendor(app.devices.values());
+    let total: f64 = vendors.iter().map(|vendor| vendor.current_bps()).sum();
+    
+    let header = Row::new(vec!["Vendor ID", "Vendor", "Devices", "RX Bandwidth", "TX Bandwidth", "Share"])
+        .style(Style::default().fg(ACCENT_COLOR).add_modifier(Modifier::BOLD))
+        .height(1);
+    
+    let rows: Vec<Row> = vendors.iter()
+        .map(|vendor| {
+            let share = if total > 0.0 { vendor.current_bps() / total * 100.0 } else { 0.0 };
+            Row::new(vec![
+                vendor.vendor_id.map(|id| format!("{:04x}", id)).unwrap_or_else(|| "-".to_string()),
+                vendor.name.clone(),
+                vendor.devices.to_string(),
+                app.format_rate(vendor.rx_bps),
+                app.format_rate(vendor.tx_bps),
+                format!("{:.1}%", share),
+            ])
+            .style(Style::default().fg(TEXT_COLOR))
+        })
+        .collect();
+    
+    let widths = [
+        Constraint::Length(10),  // Vendor ID
+        Constraint::Length(30),  // Vendor
+        Constraint::Length(8),   // Devices
+        Constraint::Length(12),  // RX Bandwidth
+        Constraint::Length(12),  // TX Bandwidth
+        Constraint::Length(8),   // Share
+    ];
+    
+    let title = format!(" Top Talkers by Vendor ({}) ", app.format_rate(total));
+    let table = Table::new(rows, widths)
+        .header(header)
+        .block(Block::default().borders(Borders::ALL).title(title));
+    
+    f.render_widget(table, area);
+}
+
+/// Session totals by device class: what the bus was used for
+fn draw_class_list(f: &mut Frame, area: Rect, app: &UsbTopApp) {
+    let classes = aggregate_by_class(app.devices.values());
+    let total: u64 = classes.iter().map(|class| class.total_bytes()).sum();
+    
+    let header = Row::new(vec!["Class", "Devices", "RX", "TX", "Total", "Share"])
+        .style(Style::default().fg(ACCENT_COLOR).add_modifier(Modifier::BOLD))
+        .height(1);
+    
+    let rows: Vec<Row> = classes.iter()
+        .map(|class| {
+            let share = if total > 0 { class.total_bytes() as f64 / total as f64 * 100.0 } else { 0.0 };
+            Row::new(vec![
+                class.name.clone(),
+                class.devices.to_string(),
+                format_bytes(class.total_rx_bytes),
+                format_bytes(class.total_tx_bytes),
+                format_bytes(class.total_bytes()),
+                format!("{:.1}%", share),
+            ])
+            .style(Style::default().fg(TEXT_COLOR))
+        })
+        .collect();
+    
+    let widths = [
+        Constraint::Length(32),  // Class
+        Constraint::Length(8),   // Devices
+        Constraint::Length(10),  // RX
+        Constraint::Length(10),  // TX
+        Constraint::Length(10),  // Total
+        Constraint::Length(8),   // Share
+    ];
+    
+    let title = format!(" Data Moved by Device Class ({}) ", format_bytes(total));
+    let table = Table::new(rows, widths)
+        .header(header)
+        .block(Block::default().borders(Borders::ALL).title(title));
+    
+    f.render_widget(table, area);
+}
+
+/// What each bus's devices could demand at their best speed against what the
+/// bus delivers; a planning aid rather than a measure of current traffic
+fn draw_budget(f: &mut Frame, area: Rect, app: &UsbTopApp) {
+    let budget = aggregate_budget(
+        app.devices.values(),
+        UsbDevice::get_device_max_capability,
+        |bus_id| controller_for_bus(&app.host_controllers, bus_id).and_then(|controller| controller.bus_speed(bus_id)),
+    );
+    
+    let header = Row::new(vec!["Bus", "Devices", "Demand", "Capacity", "Subscribed", "Status"])
+        .style(Style::default().fg(ACCENT_COLOR).add_modifier(Modifier::BOLD))
+        .height(1);
+    
+    let rows: Vec<Row> = budget.iter()
+        .map(|bus| {
+            let (capacity, subscribed, status, color) = match bus.subscription() {
+                Some(share) if bus.is_oversubscribed() => (format_bandwidth(bus.capacity_bps), format!("{:.0}%", share * 100.0), "⚠ Over", ERROR_COLOR),
+                Some(share) => (format_bandwidth(bus.capacity_bps), format!("{:.0}%", share * 100.0), "✔ Under", SUCCESS_COLOR),
+                None => ("unknown".to_string(), "-".to_string(), "? Unknown bus speed", TEXT_COLOR),
+            };
+            Row::new(vec![
+                format!("Bus {:03}", bus.bus_id),
+                bus.devices.to_string(),
+                format_bandwidth(bus.demand_bps),
+                capacity,
+                subscribed,
+                status.to_string(),
+            ])
+            .style(Style::default().fg(color))
+        })
+        .collect();
+    
+    let widths = [
+        Constraint::Length(8),   // Bus
+        Constraint::Length(8),   // Devices
+        Constraint::Length(12),  // Demand
+        Constraint::Length(12),  // Capacity
+        Constraint::Length(11),  // Subscribed
+        Constraint::Length(20),  // Status
+    ];
+    
+    let oversubscribed = budget.iter().filter(|bus| bus.is_oversubscribed()).count();
+    let title = format!(" Bandwidth Budget: device capability vs bus capacity ({} oversubscribed) ", oversubscribed);
+    let table = Table::new(rows, widths)
+        .header(header)
+        .block(Block::default().borders(Borders::ALL).title(title));
+    
+    f.render_widget(table, area);
+}
+
+/// Live `hexdump -C` of the selected device's payloads, newest at the bottom
+fn draw_hexdump(f: &mut Frame, area: Rect, app: &UsbTopApp) {
+    let Some(key) = app.selected_device else {
+        let block = Block::default().borders(Borders::ALL).title(" Hex Dump ");
+        f.render_widget(Paragraph::new("No device selected (use ↑/↓)").block(block), area);
+        return;
+    };
+    let title = format!(
+        " Hex Dump: {} (up to {} B per packet){} ",
+        key,
+        app.hexdump.byte_limit(),
+        if app.paused { " [PAUSED]" } else { "" },
+    );
+    let block = Block::default().borders(Borders::ALL).title(title);
+    
+    // Walk back from the newest packet until the view is full
+    let height = area.height.saturating_sub(2) as usize;
+    let mut lines: Vec<Line> = Vec::new();
+    for entry in app.hexdump.entries().rev() {
+        if lines.len() >= height {
+            break;
+        }
+        let color = if entry.direction { PRIMARY_COLOR } else { SECONDARY_COLOR };
+        let mut packet_lines = vec![Line::from(Span::styled(entry.header(app.capture_age(entry.timestamp_us)), Style::default().fg(color).add_modifier(Modifier::BOLD)))];
+        packet_lines.extend(hexdump_rows(&entry.data).into_iter().map(Line::from));
+        lines.splice(0..0, packet_lines);
     }
-    Ok(())
+    if lines.is_empty() {
+        let message = "No payloads captured for this device yet (text captures show up to 32 bytes; binary needs capture_len)";
+        f.render_widget(Paragraph::new(message).block(block).wrap(Wrap { trim: true }), area);
+        return;
+    }
+    let skip = lines.len().saturating_sub(height);
+    f.render_widget(Paragraph::new(lines.split_off(skip)).block(block), area);
 }
 
-fn draw_ui(f: &mut Frame, app: &UsbTopApp) {
-    if app.show_help {
-        draw_help_overlay(f);
+/// Devices down the side, time buckets across (oldest left), each cell
+/// shaded by its bytes relative to the busiest bucket on screen
+fn draw_heatmap(f: &mut Frame, area: Rect, app: &UsbTopApp) {
+    const LABEL_WIDTH: usize = 26;
+    
+    let heatmap = &app.heatmap;
+    let columns = heatmap.buckets().min((area.width as usize).saturating_sub(LABEL_WIDTH + 2));
+    let span_secs = columns as f64 * heatmap.bucket_secs();
+    let max = heatmap.max_bucket();
+    let title = format!(
+        " Activity Heatmap ({:.0}s buckets, last {:.0}s, peak {} B/bucket) ",
+        heatmap.bucket_secs(),
+        span_secs,
+        format_count(max),
+    );
+    let block = Block::default().borders(Borders::ALL).title(title);
+    
+    let rows = heatmap.rows();
+    if rows.is_empty() {
+        f.render_widget(Paragraph::new("No device activity in the heatmap span yet...").block(block), area);
         return;
     }
     
-    let size = f.size();
-    
-    // Create main layout
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(3),     // Header
-            Constraint::Length(8),     // Bandwidth graph
-            Constraint::Min(10),       // Device list
-            Constraint::Length(6),     // Color reference
-        ])
-        .split(size);
+    let lines: Vec<Line> = rows.into_iter()
+        .map(|(key, buckets)| {
+            let name = app.devices.get(&key)
+                .and_then(|device| device.product.clone().or_else(|| device.vendor.clone()))
+                .unwrap_or_else(|| "Unknown".to_string());
+            let label: String = format!("{} {}", key, name).chars().take(LABEL_WIDTH - 1).collect();
+            
+            let mut spans = vec![Span::styled(format!("{:<width$}", label, width = LABEL_WIDTH), Style::default().fg(ACCENT_COLOR))];
+            spans.extend(buckets.iter().skip(buckets.len() - columns).map(|bytes| {
+                let level = intensity(*bytes, max);
+                Span::styled(
+                    intensity_glyph(level).to_string(),
+                    Style::default().fg(app.utilization_scale.color_for(level)),
+                )
+            }));
+            Line::from(spans)
+        })
+        .collect();
     
-    draw_header(f, chunks[0], app);
-    draw_bandwidth_graph(f, chunks[1], app);
-    draw_device_list(f, chunks[2], app);
-    draw_color_reference(f, chunks[3]);
+    f.render_widget(Paragraph::new(lines).block(block), area);
 }
 
-fn draw_header(f: &mut Frame, area: Rect, app: &UsbTopApp) {
-    let header_text = vec![
-        Line::from(vec![
-            Span::styled("ng-usbtop", Style::default().fg(ACCENT_COLOR).add_modifier(Modifier::BOLD)),
-            Span::raw(" - Next-Gen USB Traffic Monitor"),
-        ]),
-        Line::from(vec![
-            Span::raw("Total: "),
-            Span::styled(
-                format!("{:.1} MB/s", app.total_bandwidth / 1_000_000.0),
-                Style::default().fg(PRIMARY_COLOR).add_modifier(Modifier::BOLD)
-            ),
-            Span::raw(" | Peak: "),
-            Span::styled(
-                format!("{:.1} MB/s", app.peak_bandwidth / 1_000_000.0),
-                Style::default().fg(SECONDARY_COLOR).add_modifier(Modifier::BOLD)
+fn draw_device_detail(f: &mut Frame, area: Rect, app: &UsbTopApp) {
+    let block = Block::default().borders(Borders::ALL).title(" Device Detail ");
+    
+    let device = match app.selected_device.and_then(|key| app.devices.get(&key)) {
+        Some(device) => device,
+        None => {
+            let empty = Paragraph::new("No device selected (use ↑/↓)").block(block);
+            f.render_widget(empty, area);
+            return;
+        }
+    };
+    
+    let stats = &device.bandwidth_stats;
+    let ids = match (device.vendor_id, device.product_id) {
+        (Some(vendor_id), Some(product_id)) => format!("{:04x}:{:04x}", vendor_id, product_id),
+        _ => "Unknown".to_string(),
+    };
+    
+    let mut lines = vec![
+        detail_line("Device", device.key().to_string()),
+        detail_line("Origin", device.origin.clone().unwrap_or_else(|| "local".to_string())),
+        detail_line("ID", ids),
+        detail_line("Vendor", device.vendor.clone().unwrap_or_else(|| "Unknown".to_string())),
+        detail_line("Product", device.product.clone().unwrap_or_else(|| "Unknown".to_string())),
+        detail_line("Speed", format_speed(&device.speed)),
+    ];
+    if let Some(links) = device.link_summary() {
+        lines.push(detail_line("Links", links));
+    }
+    lines.extend([
+        detail_line("Connected", format!("for {}", format_uptime(device.connected_for()))),
+        detail_line("Driver", driver_summary(device, app.no_driver_after)),
+        detail_line("USB", device.usb_version.map(format_usb_version).unwrap_or_else(|| "Unknown".to_string())),
+        detail_line("RX", app.format_rate(stats.rx_bps)),
+        detail_line("TX", app.format_rate(stats.tx_bps)),
+        detail_line("Peak", app.format_rate(stats.peak_bps)),
+        detail_line(
+            "Control",
+            format!(
+                "{}{}",
+                app.format_rate(device.control_stats.current_bps),
+                if device.exclude_control_endpoint { " (excluded)" } else { "" },
             ),
-            Span::raw(" | Devices: "),
-            Span::styled(
-                app.devices.len().to_string(),
-                Style::default().fg(SUCCESS_COLOR).add_modifier(Modifier::BOLD)
+        ),
+        detail_line("Total", format!("↓ {} B  ↑ {} B", format_count(stats.total_rx_bytes), format_count(stats.total_tx_bytes))),
+        detail_line("URBs", format_urb_counts(&stats.urb_counts)),
+        efficiency_line(device),
+    ]);
+    if let Some(hub) = app.hub_loads().into_iter().find(|hub| hub.key == device.key()) {
+        lines.push(detail_line(
+            "Behind",
+            format!(
+                "{} devices, {} of {} upstream{}",
+                hub.downstream_devices,
+                app.format_rate(hub.downstream_bps),
+                format_bandwidth(hub.upstream_bps),
+                if hub.is_saturated() { " — hub-limited" } else { "" },
             ),
-        ]),
-    ];
+        ));
+    }
+    lines.extend(max_packet_lines(device));
+    lines.extend(urb_size_lines(device));
+    lines.extend(averaging_line(device));
+    lines.extend(endpoint_lines(device));
     
-    let header = Paragraph::new(header_text)
-        .block(Block::default().borders(Borders::ALL).title(" ng-usbtop "));
+    let endpoints = device.active_endpoints();
+    let shown = endpoints.len().min(MAX_ENDPOINT_GAUGES);
+    let gauge_rows = shown + usize::from(endpoints.len() > shown);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(gauge_rows as u16)])
+        .split(inner);
     
-    f.render_widget(header, area);
+    let detail = Paragraph::new(lines).wrap(Wrap { trim: true });
+    f.render_widget(detail, chunks[0]);
+    draw_endpoint_gauges(f, chunks[1], app, device, &endpoints);
 }
 
-fn draw_bandwidth_graph(f: &mut Frame, area: Rect, app: &UsbTopApp) {
-    if app.bandwidth_history.is_empty() {
-        let empty_graph = Paragraph::new("No bandwidth data yet...")
-            .block(Block::default().borders(Borders::ALL).title(" Bandwidth History "));
-        f.render_widget(empty_graph, area);
+/// One gauge per active endpoint, busiest first, filled to its share of the
+/// device's practical bandwidth so the endpoint carrying the load stands out;
+/// endpoints past `MAX_ENDPOINT_GAUGES` share a single summary row
+fn draw_endpoint_gauges(f: &mut Frame, area: Rect, app: &UsbTopApp, device: &UsbDevice, endpoints: &[(u8, f64)]) {
+    if endpoints.is_empty() || area.height == 0 {
         return;
     }
+    let max_bandwidth = device.speed.to_practical_bytes_per_second();
+    let (shown, rest) = endpoints.split_at(endpoints.len().min(MAX_ENDPOINT_GAUGES));
+    let labels: Vec<String> = shown.iter().map(|(address, _)| device.endpoint_label(*address)).collect();
+    let width = labels.iter().map(|label| label.chars().count()).max().unwrap_or(0) as u16 + 1;
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(vec![Constraint::Length(1); area.height as usize])
+        .split(area);
     
-    let max_bandwidth = app.bandwidth_history
-        .iter()
-        .map(|(_, bw)| *bw)
-        .fold(0.0, f64::max)
-        .max(1.0); // Minimum scale
-    
-    let data: Vec<(f64, f64)> = app.bandwidth_history.clone();
+    for ((row, label), (_, bps)) in rows.iter().zip(labels).zip(shown) {
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(width), Constraint::Min(0)])
+            .split(*row);
+        f.render_widget(Paragraph::new(Span::styled(label, Style::default().fg(ACCENT_COLOR))), columns[0]);
+        f.render_widget(create_bandwidth_gauge(*bps, max_bandwidth, columns[1].width, &app.utilization_scale), columns[1]);
+    }
+    if rest.is_empty() {
+        return;
+    }
+    if let Some(row) = rows.get(shown.len()) {
+        let rest_bps: f64 = rest.iter().map(|(_, bps)| bps).sum();
+        let summary = detail_line(&format!("+{} more", rest.len()), format!("{} combined", app.format_rate(rest_bps)));
+        f.render_widget(Paragraph::new(summary), *row);
+    }
+}
+
+/// Why polled traffic is averaged over a longer span, for devices that have
+/// some: a HID report every few milliseconds is steady, and a short window
+/// turns the reports falling in or out of it into jitter
+fn averaging_line(device: &UsbDevice) -> Option<Line<'static>> {
+    let stats = &device.bandwidth_stats;
+    let has_periodic_traffic = device.get_transfer_stats(TransferType::Interrupt).is_some()
+        || device.get_transfer_stats(TransferType::Isochronous).is_some();
+    if !has_periodic_traffic || stats.periodic_window == stats.history_window {
+        return None;
+    }
+    Some(detail_line(
+        "Averaging",
+        format!(
+            "interrupt/iso over {}s, bulk/control over {}s: polled reports are steady, so the longer window smooths jitter, not bursts",
+            stats.periodic_window.as_secs(),
+            stats.history_window.as_secs(),
+        ),
+    ))
+}
+
+/// Configured polling of each interrupt/isochronous endpoint next to the
+/// completion rate actually observed, e.g. to check a 1000 Hz mouse
+fn endpoint_lines(device: &UsbDevice) -> Vec<Line<'static>> {
+    let has_periodic_traffic = device.get_transfer_stats(TransferType::Interrupt).is_some()
+        || device.get_transfer_stats(TransferType::Isochronous).is_some();
+    if device.periodic_endpoints.is_empty() {
+        return if has_periodic_traffic {
+            vec![detail_line("Polling", "No endpoint descriptors in sysfs".to_string())]
+        } else {
+            Vec::new()
+        };
+    }
     
-    let datasets = vec![Dataset::default()
-        .marker(symbols::Marker::Braille)
-        .style(Style::default().fg(PRIMARY_COLOR))
-        .data(&data)];
+    let now = Instant::now();
+    let labels: Vec<String> = device.periodic_endpoints.iter()
+        .map(|endpoint| device.endpoint_label(endpoint.address))
+        .collect();
+    let width = labels.iter().map(String::len).max().unwrap_or(0) + 1;
+    device.periodic_endpoints.iter()
+        .zip(labels)
+        .map(|(endpoint, label)| {
+            let polling = match endpoint.polling_interval(&device.speed) {
+                Some(interval) => format!(
+                    "every {} ({:.0} Hz)",
+                    format_interval(interval),
+                    1.0 / interval.as_secs_f64(),
+                ),
+                None => format!("bInterval {}", endpoint.interval.map_or("?".to_string(), |i| i.to_string())),
+            };
+            let packet_size = endpoint.packet_size().map_or("?".to_string(), |size| size.to_string());
+            let observed = device.endpoint_rates.get(&endpoint.address)
+                .map_or(0.0, |rate| rate.rate_hz(now));
+            
+            detail_line(
+                &format!("{:<width$}", label, width = width),
+                format!("{}, {} B max, seen {:.0} Hz", polling, packet_size, observed),
+            )
+        })
+        .collect()
+}
+
+/// Largest URB seen on the device and on each endpoint, as a multiple of
+/// wMaxPacketSize where descriptors give it
+fn max_packet_lines(device: &UsbDevice) -> Vec<Line<'static>> {
+    let max_seen = device.bandwidth_stats.max_packet_seen;
+    if max_seen == 0 {
+        return Vec::new();
+    }
     
-    let chart = Chart::new(datasets)
-        .block(Block::default().borders(Borders::ALL).title(" Bandwidth History (MB/s) "))
-        .x_axis(
-            Axis::default()
-                .title("Time (s)")
-                .style(Style::default().fg(TEXT_COLOR))
-                .bounds([0.0, 60.0])
-        )
-        .y_axis(
-            Axis::default()
-                .title("MB/s")
-                .style(Style::default().fg(TEXT_COLOR))
-                .bounds([0.0, max_bandwidth / 1_000_000.0])
-        );
+    let endpoints = device.endpoint_packet_sizes();
+    let labels: Vec<String> = endpoints.iter()
+        .map(|(address, _, _)| format!(" {}", device.endpoint_label(*address)))
+        .collect();
+    let width = labels.iter().map(String::len).max().unwrap_or(0) + 1;
     
-    f.render_widget(chart, area);
+    let mut lines = vec![detail_line("Max URB", format!("{} B", format_count(max_seen)))];
+    lines.extend(endpoints.into_iter().zip(labels).map(|((_, seen, max_packet_size), label)| {
+        let packets = match max_packet_size {
+            Some(size) if size > 0 => format!(" = {:.1} × {} B packets", seen as f64 / size as f64, size),
+            _ => String::new(),
+        };
+        detail_line(&format!("{:<width$}", label, width = width), format!("max {} B{}", format_count(seen as u64), packets))
+    }));
+    lines
 }
 
-fn draw_device_list(f: &mut Frame, area: Rect, app: &UsbTopApp) {
-    let header = Row::new(vec!["Device", "Speed", "Vendor", "Product", "Bandwidth ↓", "Bandwidth ↑", "Status"])
-        .style(Style::default().fg(ACCENT_COLOR).add_modifier(Modifier::BOLD))
-        .height(1);
+/// Distribution of URB payload sizes as horizontal bars scaled to the
+/// fullest bucket, with each bucket's count and share
+fn urb_size_lines(device: &UsbDevice) -> Vec<Line<'static>> {
+    let histogram = &device.urb_sizes;
+    let total = histogram.total();
+    if total == 0 {
+        return Vec::new();
+    }
+    let fullest = histogram.counts.iter().copied().max().unwrap_or(0);
     
-    let mut devices: Vec<_> = app.devices.values().collect();
-    devices.sort_by(|a, b| b.bandwidth_stats.current_bps.partial_cmp(&a.bandwidth_stats.current_bps).unwrap_or(std::cmp::Ordering::Equal));
+    let mut lines = vec![detail_line("Sizes", format!("{} URBs with data", format_count(total)))];
+    lines.extend(URB_SIZE_LABELS.iter().zip(histogram.counts).map(|(label, count)| {
+        // Any non-empty bucket gets at least a sliver
+        let filled = if count == 0 { 0 } else { ((count as f64 / fullest as f64) * URB_SIZE_BAR_WIDTH as f64).ceil() as usize };
+        Line::from(vec![
+            Span::styled(format!(" {:<8}", label), Style::default().fg(ACCENT_COLOR)),
+            Span::styled(format!("{:<width$}", "█".repeat(filled), width = URB_SIZE_BAR_WIDTH), Style::default().fg(PRIMARY_COLOR)),
+            Span::styled(
+                format!(" {} ({:.0}%)", format_count(count), count as f64 / total as f64 * 100.0),
+                Style::default().fg(TEXT_COLOR),
+            ),
+        ])
+    }));
+    lines
+}
+
+/// Average URB size as a badge, highlighted when bulk transfers are too small
+fn efficiency_line(device: &UsbDevice) -> Line<'static> {
+    let efficiency = match device.transfer_efficiency() {
+        Some(efficiency) => efficiency,
+        None => return detail_line("URB size", "No completed URBs".to_string()),
+    };
     
-    let rows: Vec<Row> = devices
-        .iter()
-        .enumerate()
-        .map(|(i, device)| {
-            let device_key = format!("{}:{}", device.bus_id, device.device_id);
-            let is_selected = app.selected_device.as_ref() == Some(&device_key);
-            
-            let speed_color = Color::Rgb(
-                device.speed.color_code().0,
-                device.speed.color_code().1,
-                device.speed.color_code().2,
-            );
-            
-            let status_style = if device.is_disconnected {
-                Style::default().bg(Color::Gray).fg(Color::White)
-            } else if is_selected {
-                Style::default().bg(ACCENT_COLOR).fg(Color::Black)
-            } else {
-                Style::default().fg(TEXT_COLOR)
-            };
-            
-            Row::new(vec![
-                format!("{:03}:{:03}", device.bus_id, device.device_id),
-                format!("{:.1} Mbps", device.speed.to_mbps()),
-                device.vendor.clone().unwrap_or_else(|| "Unknown".to_string()),
-                device.product.clone().unwrap_or_else(|| "Unknown".to_string()),
-                format!("{:.1} KB/s", device.bandwidth_stats.rx_bps / 1000.0),
-                format!("{:.1} KB/s", device.bandwidth_stats.tx_bps / 1000.0),
-                if device.is_disconnected { "Disconnected" } else { "Connected" }.to_string(),
-            ])
-            .style(status_style)
-            .height(1)
+    let color = if efficiency.is_inefficient() { WARNING_COLOR } else { TEXT_COLOR };
+    Line::from(vec![
+        Span::styled(format!("{:<9}", "URB size"), Style::default().fg(ACCENT_COLOR)),
+        Span::styled(
+            format!("avg {:.0} B/URB — {}", efficiency.avg_bytes_per_urb, efficiency.note()),
+            Style::default().fg(color),
+        ),
+    ])
+}
+
+fn detail_line(label: &str, value: String) -> Line<'static> {
+    Line::from(vec![
+        Span::styled(format!("{:<9}", label), Style::default().fg(ACCENT_COLOR)),
+        Span::styled(value, Style::default().fg(TEXT_COLOR)),
+    ])
+}
+
+fn draw_bus_summary(f: &mut Frame, area: Rect, app: &UsbTopApp, hub_loads: &[HubLoad], typec: &[(String, String)]) {
+    let mut lines: Vec<Line> = app.get_bus_ids()
+        .into_iter()
+        .map(|bus_id| {
+            let mut spans = vec![
+                Span::styled(format!("Bus {:03}", bus_id), Style::default().fg(ACCENT_COLOR)),
+                Span::raw(format!("  {}", app.format_rate(app.get_bus_bandwidth(bus_id)))),
+            ];
+            // Measured against the bus's own root hub: the USB 2 half of an
+            // xHCI controller is a 480 Mb/s link however fast its USB 3 half is
+            let controller = controller_for_bus(&app.host_controllers, bus_id);
+            if let (Some(controller), Some(percentage)) = (controller, app.get_bus_utilization(bus_id)) {
+                let speed = controller.bus_speed(bus_id).unwrap_or(UsbSpeed::Unknown);
+                spans.push(Span::styled(
+                    format!(" / {} ({:.1}%)", format_speed(&speed), percentage),
+                    Style::default().fg(app.utilization_scale.color_for(percentage / 100.0)),
+                ));
+                let siblings = controller.sibling_buses(bus_id);
+                let pairing = if siblings.is_empty() {
+                    String::new()
+                } else {
+                    format!(", with bus {}", siblings.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(", "))
+                };
+                spans.push(Span::styled(
+                    format!("  {} {}{}", controller.kind.label(), controller.name, pairing),
+                    Style::default().fg(Color::Gray),
+                ));
+            }
+            Line::from(spans)
         })
         .collect();
     
-    let table = Table::new(rows, [
-        Constraint::Length(8),   // Device
-        Constraint::Length(12),  // Speed  
-        Constraint::Length(15),  // Vendor
-        Constraint::Length(20),  // Product
-        Constraint::Length(12),  // RX Bandwidth
-        Constraint::Length(12),  // TX Bandwidth
-        Constraint::Length(12),  // Status
-    ])
-        .header(header)
-        .block(Block::default().borders(Borders::ALL).title(" USB Devices "))
-        .widths(&[
-            Constraint::Length(8),
-            Constraint::Length(12),
-            Constraint::Length(15),
-            Constraint::Length(20),
-            Constraint::Length(12),
-            Constraint::Length(12),
-            Constraint::Length(12),
-        ]);
+    // Buses sharing a host controller can't all run at full speed at once
+    for controller in &app.controllers {
+        let total = app.get_controller_bandwidth(controller);
+        let percentage = controller.get_utilization_percentage(total);
+        let mut style = Style::default().fg(app.utilization_scale.color_for(percentage / 100.0));
+        if controller.is_near_limit(total) {
+            style = style.add_modifier(Modifier::BOLD);
+        }
+        let marker = if controller.is_near_limit(total) { "⚠ " } else { "" };
+        
+        lines.push(Line::from(vec![
+            Span::styled(format!("Controller {}", controller.name), Style::default().fg(PRIMARY_COLOR)),
+            Span::raw(format!(" {:?}", controller.buses)),
+            Span::styled(
+                format!(
+                    "  {}{} / {} ({:.1}%)",
+                    marker,
+                    app.format_rate(total),
+                    format_bandwidth(controller.limit_mbps * 1_000_000.0 / 8.0),
+                    percentage,
+                ),
+                style,
+            ),
+        ]));
+    }
     
-    f.render_widget(table, area);
+    // Devices behind a hub share its upstream link, so the hub can be the bottleneck
+    for hub in hub_loads {
+        let mut style = Style::default().fg(app.utilization_scale.color_for(hub.utilization()));
+        if hub.is_saturated() {
+            style = style.add_modifier(Modifier::BOLD);
+        }
+        let marker = if hub.is_saturated() { "⚠ " } else { "" };
+        
+        lines.push(Line::from(vec![
+            Span::styled(format!("Hub {} ({})", hub.port_path, hub.key), Style::default().fg(PRIMARY_COLOR)),
+            Span::raw(format!(" {} downstream", hub.downstream_devices)),
+            Span::styled(
+                format!(
+                    "  {}{} / {} upstream ({:.1}%)",
+                    marker,
+                    app.format_rate(hub.downstream_bps),
+                    format_bandwidth(hub.upstream_bps),
+                    hub.utilization() * 100.0,
+                ),
+                style,
+            ),
+        ]));
+    }
+    
+    // What each plugged-in USB-C connector carries besides USB
+    for (port, description) in typec {
+        lines.push(Line::from(vec![
+            Span::styled(format!("USB-C {}", port), Style::default().fg(PRIMARY_COLOR)),
+            Span::raw(format!(": {}", description)),
+        ]));
+    }
+    
+    let summary = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title(" Buses "));
+    
+    f.render_widget(summary, area);
 }
 
 fn draw_color_reference(f: &mut Frame, area: Rect) {
@@ -368,6 +2628,12 @@ fn draw_color_reference(f: &mut Frame, area: Rect) {
             Span::raw(" Navigate  "),
             Span::styled("h", Style::default().fg(ACCENT_COLOR).add_modifier(Modifier::BOLD)),
             Span::raw(" Help  "),
+            Span::styled("f", Style::default().fg(ACCENT_COLOR).add_modifier(Modifier::BOLD)),
+            Span::raw(" Transfer filter  "),
+            Span::styled("Enter", Style::default().fg(ACCENT_COLOR).add_modifier(Modifier::BOLD)),
+            Span::raw(" Detail  "),
+            Span::styled("G", Style::default().fg(ACCENT_COLOR).add_modifier(Modifier::BOLD)),
+            Span::raw(" Graph  "),
             Span::styled("q/Esc", Style::default().fg(ACCENT_COLOR).add_modifier(Modifier::BOLD)),
             Span::raw(" Quit"),
         ]),
@@ -394,6 +2660,95 @@ fn draw_help_overlay(f: &mut Frame) {
             Span::styled("  h", Style::default().fg(ACCENT_COLOR)),
             Span::raw("        Toggle this help"),
         ]),
+        Line::from(vec![
+            Span::styled("  f", Style::default().fg(ACCENT_COLOR)),
+            Span::raw("        Cycle transfer filter (All/Bulk/Control/Interrupt/Isochronous)"),
+        ]),
+        Line::from(vec![
+            Span::styled("  Enter", Style::default().fg(ACCENT_COLOR)),
+            Span::raw("    Toggle detail view for the selected device"),
+        ]),
+        Line::from(vec![
+            Span::styled("  z", Style::default().fg(ACCENT_COLOR)),
+            Span::raw("        Reset statistics"),
+        ]),
+        Line::from(vec![
+            Span::styled("  p", Style::default().fg(ACCENT_COLOR)),
+            Span::raw("        Pause/resume updates"),
+        ]),
+        Line::from(vec![
+            Span::styled("  G", Style::default().fg(ACCENT_COLOR)),
+            Span::raw("        Cycle graph source (Total/Selected device/Per-bus)"),
+        ]),
+        Line::from(vec![
+            Span::styled("  c", Style::default().fg(ACCENT_COLOR)),
+            Span::raw("        Cycle graph layout (Auto/Full graph/Header sparkline)"),
+        ]),
+        Line::from(vec![
+            Span::styled("  t", Style::default().fg(ACCENT_COLOR)),
+            Span::raw("        Toggle absolute/relative packet and event times"),
+        ]),
+        Line::from(vec![
+            Span::styled("  m", Style::default().fg(ACCENT_COLOR)),
+            Span::raw("        Toggle the graph's peak-hold line"),
+        ]),
+        Line::from(vec![
+            Span::styled("  L", Style::default().fg(ACCENT_COLOR)),
+            Span::raw("        Toggle a logarithmic graph scale"),
+        ]),
+        Line::from(vec![
+            Span::styled("  v", Style::default().fg(ACCENT_COLOR)),
+            Span::raw("        Toggle top talkers by vendor"),
+        ]),
+        Line::from(vec![
+            Span::styled("  u", Style::default().fg(ACCENT_COLOR)),
+            Span::raw("        Toggle data moved by device class"),
+        ]),
+        Line::from(vec![
+            Span::styled("  b", Style::default().fg(ACCENT_COLOR)),
+            Span::raw("        Toggle per-bus bandwidth budget (device capability vs bus capacity)"),
+        ]),
+        Line::from(vec![
+            Span::styled("  d", Style::default().fg(ACCENT_COLOR)),
+            Span::raw("        Toggle device activity heatmap"),
+        ]),
+        Line::from(vec![
+            Span::styled("  x", Style::default().fg(ACCENT_COLOR)),
+            Span::raw("        Toggle hex dump of the selected device's payloads"),
+        ]),
+        Line::from(vec![
+            Span::styled("  %", Style::default().fg(ACCENT_COLOR)),
+            Span::raw("        Toggle bandwidth as % of each device's own capability"),
+        ]),
+        Line::from(vec![
+            Span::styled("  i", Style::default().fg(ACCENT_COLOR)),
+            Span::raw("        Explain the selected device's bandwidth numbers"),
+        ]),
+        Line::from(vec![
+            Span::styled("  F5", Style::default().fg(ACCENT_COLOR)),
+            Span::raw("       Re-read descriptors for the selected device (all if none; also Ctrl-R)"),
+        ]),
+        Line::from(vec![
+            Span::styled("  ←/→", Style::default().fg(ACCENT_COLOR)),
+            Span::raw("      Pan the graph back/forward (→ at the end returns to live)"),
+        ]),
+        Line::from(vec![
+            Span::styled("  +/-", Style::default().fg(ACCENT_COLOR)),
+            Span::raw("      Zoom the graph's time span in/out"),
+        ]),
+        Line::from(vec![
+            Span::styled("  :", Style::default().fg(ACCENT_COLOR)),
+            Span::raw("        Command palette: sort, columns, filter, reset, pause, resume, quit (Tab completes)"),
+        ]),
+        Line::from(vec![
+            Span::styled("  C", Style::default().fg(ACCENT_COLOR)),
+            Span::raw("        Clear disconnected devices kept by inactive_devices = \"dim\""),
+        ]),
+        Line::from(vec![
+            Span::styled("  F", Style::default().fg(ACCENT_COLOR)),
+            Span::raw("        Follow the top device of the list (moving the selection stops)"),
+        ]),
+        Line::from("  (keys can be remapped in the [keybindings] config section)"),
         Line::from(vec![
             Span::styled("  q/Esc", Style::default().fg(ACCENT_COLOR)),
             Span::raw("    Quit application"),
@@ -439,6 +2794,77 @@ fn draw_help_overlay(f: &mut Frame) {
     f.render_widget(help, area);
 }
 
+/// Walk through how the selected device's speed turns into the bandwidth
+/// ceiling the utilization figures are measured against
+fn draw_explain_overlay(f: &mut Frame, app: &UsbTopApp) {
+    let area = centered_rect(70, 50, f.size());
+    let block = Block::default().borders(Borders::ALL).title(" Bandwidth Explained ");
+    
+    let device = app.selected_device.and_then(|key| app.devices.get(&key));
+    let lines = match device.map(|device| (device, device.explain_bandwidth())) {
+        None => vec![Line::from("No device selected (use ↑/↓)")],
+        Some((device, None)) => vec![Line::from(format!(
+            "{}: negotiated speed unknown, so there is no ceiling to compare against",
+            device.key(),
+        ))],
+        Some((device, Some(explanation))) => {
+            let bits_per_sec = explanation.theoretical_bps * 8.0;
+            vec![
+                Line::from(vec![Span::styled(
+                    format!("{} {}", device.key(), device.product.as_deref().unwrap_or("Unknown")),
+                    Style::default().fg(ACCENT_COLOR).add_modifier(Modifier::BOLD),
+                )]),
+                Line::from(""),
+                explain_line("1. Speed", format!("negotiated {}", format_speed(&explanation.speed))),
+                explain_line(
+                    "2. Raw",
+                    format!("{:.0} Mbit/s ÷ 8 bits = {}", bits_per_sec / 1_000_000.0, format_bandwidth(explanation.theoretical_bps)),
+                ),
+                explain_line(
+                    "3. Usable",
+                    format!(
+                        "{} × {:.0}% protocol efficiency ≈ {}",
+                        format_bandwidth(explanation.theoretical_bps),
+                        explanation.efficiency * 100.0,
+                        format_bandwidth(explanation.practical_bps),
+                    ),
+                ),
+                explain_line(
+                    "4. Now",
+                    format!(
+                        "{} = {:.1}% of usable ({:.1}% of raw)",
+                        format_bandwidth(explanation.current_bps),
+                        explanation.busy_percentage,
+                        explanation.theoretical_percentage,
+                    ),
+                ),
+                Line::from(""),
+                Line::from("Packet framing, bit stuffing, token and handshake packets and bus"),
+                Line::from("scheduling use part of the link, so no device reaches the raw rate."),
+                Line::from(format!(
+                    "Near {} this device is saturating its link; well below it, the",
+                    format_bandwidth(explanation.practical_bps),
+                )),
+                Line::from("device or its workload is the limit, not USB."),
+            ]
+        }
+    };
+    
+    let explain = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: true });
+    
+    f.render_widget(Clear, area);
+    f.render_widget(explain, area);
+}
+
+fn explain_line(label: &str, value: String) -> Line<'static> {
+    Line::from(vec![
+        Span::styled(format!("{:<11}", label), Style::default().fg(ACCENT_COLOR)),
+        Span::styled(value, Style::default().fg(TEXT_COLOR)),
+    ])
+}
+
 // Helper function to create centered rectangle
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::default()
@@ -458,4 +2884,338 @@ fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
             Constraint::Percentage((100 - percent_x) / 2),
         ])
         .split(popup_layout[1])[1]
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::usbmon::parser::parse_usbmon_text_line;
+    
+    /// A 0781:5581 stick on bus 1 with `rx_bytes` already counted
+    fn identified(device_id: u8, serial: Option<&str>, rx_bytes: u64) -> UsbDevice {
+        let mut device = UsbDevice::new(1, device_id);
+        device.vendor_id = Some(0x0781);
+        device.product_id = Some(0x5581);
+        device.serial = serial.map(str::to_string);
+        device.bandwidth_stats.total_rx_bytes = rx_bytes;
+        device
+    }
+    
+    /// A bulk IN completion from `key`, as a replayed capture delivers it
+    fn bulk_in(key: DeviceKey) -> UsbPacket {
+        parse_usbmon_text_line(&format!("ffff8800aa000300 400 C Bi:{}:{:03}:1 0 512 = 00", key.bus_id(), key.device_id())).unwrap()
+    }
+    
+    /// Replay the enumeration of a 0781:5581 stick at `key` while paused, so
+    /// its identity is known before its first data packet creates the entry
+    fn enumerate(app: &mut UsbTopApp, key: DeviceKey, serial: Option<&str>) {
+        let address = format!("{}:{:03}", key.bus_id(), key.device_id());
+        let mut lines = vec![
+            format!("ffff8800aa000100 100 S Ci:{}:0 s 80 06 0100 0000 0012 18 <", address),
+            format!("ffff8800aa000100 200 C Ci:{}:0 0 18 = 12010002 00000040 81078155 00010102 0301", address),
+        ];
+        if let Some(serial) = serial {
+            let mut descriptor = vec![2 + 2 * serial.len() as u8, 0x03];
+            descriptor.extend(serial.encode_utf16().flat_map(u16::to_le_bytes));
+            let words: Vec<String> = descriptor.chunks(4)
+                .map(|word| word.iter().map(|byte| format!("{:02x}", byte)).collect())
+                .collect();
+            lines.push(format!("ffff8800aa000200 300 S Ci:{}:0 s 80 06 0303 0409 00ff 255 <", address));
+            lines.push(format!("ffff8800aa000200 400 C Ci:{}:0 0 {} = {}", address, descriptor.len(), words.join(" ")));
+        }
+        
+        let paused = app.paused;
+        app.paused = true;
+        for line in lines {
+            app.process_packet(&parse_usbmon_text_line(&line).unwrap());
+        }
+        app.paused = paused;
+    }
+    
+    #[test]
+    fn test_equal_rows_keep_bus_dev_order() {
+        let keys = [DeviceKey(2, 7), DeviceKey(1, 9), DeviceKey(2, 3), DeviceKey(1, 4)];
+        for sort_by in [SortKey::Bandwidth, SortKey::Rx, SortKey::Tx, SortKey::Vendor, SortKey::Product] {
+            for sort_order in [SortOrder::Asc, SortOrder::Desc] {
+                // Insertion order varies so HashMap iteration can't decide it
+                for rotation in 0..keys.len() {
+                    let mut app = UsbTopApp::new(Duration::from_secs(1), Duration::from_secs(1));
+                    app.sort_by = sort_by;
+                    app.sort_order = sort_order;
+                    for key in keys.iter().cycle().skip(rotation).take(keys.len()) {
+                        app.update_device(UsbDevice::new(key.bus_id(), key.device_id()));
+                    }
+                    assert_eq!(
+                        app.ranked_device_keys(),
+                        [DeviceKey(1, 4), DeviceKey(1, 9), DeviceKey(2, 3), DeviceKey(2, 7)],
+                        "{:?} {:?}", sort_by, sort_order,
+                    );
+                }
+            }
+        }
+    }
+    
+    #[test]
+    fn test_transfer_filter_selects_per_type_stats() {
+        let mut app = UsbTopApp::new(Duration::from_secs(1), Duration::from_secs(1));
+        let mut storage = UsbDevice::new(1, 2);
+        for line in [
+            "ffff88003b4a3c00 3575914655 C Ci:1:002:0 0 18 = 12010002 00000040",
+            "ffff88003b4a3d00 3575915000 C Bi:1:002:1 0 512 = 00",
+        ] {
+            storage.record_packet(&parse_usbmon_text_line(line).unwrap());
+        }
+        let mut keyboard = UsbDevice::new(1, 3);
+        keyboard.record_packet(&parse_usbmon_text_line("ffff88003b4a3e00 3575916000 C Ii:1:003:1 0 8 = 00000400 00000000").unwrap());
+        app.update_device(storage);
+        app.update_device(keyboard);
+        
+        let rx_bytes = |app: &UsbTopApp, key: DeviceKey| {
+            app.get_filtered_stats(&app.devices[&key]).map(|stats| stats.total_rx_bytes)
+        };
+        let expected = [
+            (TransferFilter::All, Some(512 + 18), Some(8)),
+            (TransferFilter::Bulk, Some(512), None),
+            (TransferFilter::Control, Some(18), None),
+            (TransferFilter::Interrupt, None, Some(8)),
+            (TransferFilter::Isochronous, None, None),
+        ];
+        for (filter, storage_rx, keyboard_rx) in expected {
+            assert_eq!(app.transfer_filter, filter);
+            assert_eq!(rx_bytes(&app, DeviceKey(1, 2)), storage_rx, "{:?}", filter);
+            assert_eq!(rx_bytes(&app, DeviceKey(1, 3)), keyboard_rx, "{:?}", filter);
+            app.transfer_filter = app.transfer_filter.next();
+        }
+        assert_eq!(app.transfer_filter, TransferFilter::All);
+    }
+    
+    #[test]
+    fn test_bus_history_goes_with_the_last_device_on_the_bus() {
+        let mut app = UsbTopApp::new(Duration::from_secs(1), Duration::from_secs(1));
+        for key in [DeviceKey(1, 2), DeviceKey(1, 3), DeviceKey(2, 2)] {
+            app.update_device(UsbDevice::new(key.bus_id(), key.device_id()));
+            app.bus_history.insert(key.bus_id(), vec![(0.0, 0.0)]);
+        }
+        
+        app.remove_device(DeviceKey(1, 2));
+        app.remove_device(DeviceKey(2, 2));
+        assert!(app.bus_history.contains_key(&1));
+        assert!(!app.bus_history.contains_key(&2));
+    }
+    
+    #[test]
+    fn test_device_cap_evicts_oldest_disconnected_only() {
+        let mut app = UsbTopApp::new(Duration::from_secs(1), Duration::from_secs(1));
+        app.origin = Some("replay".to_string());
+        app.max_devices = 3;
+        let now = Instant::now();
+        for (device_id, idle_secs, disconnected) in [(1, 30, true), (2, 10, true), (3, 60, false)] {
+            let mut device = UsbDevice::new(1, device_id);
+            device.last_seen = now - Duration::from_secs(idle_secs);
+            if disconnected {
+                device.mark_disconnected();
+            }
+            app.update_device(device);
+        }
+        
+        // Over the cap: the longest-idle disconnected device goes, not the idle active one
+        app.process_packet(&bulk_in(DeviceKey(1, 4)));
+        assert_eq!(app.devices.len(), 3);
+        assert!(!app.devices.contains_key(&DeviceKey(1, 1)));
+        assert!(app.devices.contains_key(&DeviceKey(1, 2)));
+        assert!(app.devices.contains_key(&DeviceKey(1, 3)));
+        
+        // Only active devices left to choose from: the cap is exceeded rather than evicting them
+        app.process_packet(&bulk_in(DeviceKey(1, 5)));
+        app.process_packet(&bulk_in(DeviceKey(1, 6)));
+        assert!(!app.devices.contains_key(&DeviceKey(1, 2)));
+        assert_eq!(app.devices.len(), 4);
+        assert!(app.devices.contains_key(&DeviceKey(1, 3)));
+    }
+    
+    #[test]
+    fn test_first_seen_survives_updates_and_resets() {
+        let plugged_in = Instant::now() - Duration::from_secs(600);
+        let mut app = UsbTopApp::new(Duration::from_secs(1), Duration::from_secs(1));
+        let mut device = identified(5, Some("4C530001"), 0);
+        device.first_seen = plugged_in;
+        app.update_device(device);
+        
+        // Fresh descriptors for the same entry, then a reset under a new number
+        app.update_device(identified(5, Some("4C530001"), 0));
+        assert_eq!(app.devices[&DeviceKey(1, 5)].first_seen, plugged_in);
+        app.track_new_device(identified(6, Some("4C530001"), 0));
+        assert_eq!(app.devices[&DeviceKey(1, 6)].first_seen, plugged_in);
+        assert!(app.devices[&DeviceKey(1, 6)].connected_for() >= Duration::from_secs(600));
+        
+        // A different device behind the number, or the same one back after a disconnect, is a new connection
+        let mut other = identified(6, Some("4C530001"), 0);
+        other.product_id = Some(0x5583);
+        app.update_device(other);
+        assert!(app.devices[&DeviceKey(1, 6)].connected_for() < Duration::from_secs(1));
+        let mut device = identified(7, Some("4C530001"), 0);
+        device.first_seen = plugged_in;
+        app.update_device(device);
+        app.mark_disconnected(DeviceKey(1, 7));
+        app.update_device(identified(7, Some("4C530001"), 0));
+        assert!(app.devices[&DeviceKey(1, 7)].connected_for() < Duration::from_secs(1));
+    }
+    
+    #[test]
+    fn test_reenumerated_device_replaces_ghost() {
+        let replay = || {
+            let mut app = UsbTopApp::new(Duration::from_secs(1), Duration::from_secs(1));
+            app.origin = Some("replay".to_string());
+            app
+        };
+        
+        let mut app = replay();
+        let mut ghost = identified(5, Some("4C530001"), 0);
+        ghost.bandwidth_stats.update_rx(4096);
+        ghost.bandwidth_stats.urb_counts.callbacks = 8;
+        app.update_device(ghost);
+        app.selected_device = Some(DeviceKey(1, 5));
+        
+        // Same stick after a reset, now on another bus: the old entry goes, its totals and the selection stay
+        enumerate(&mut app, DeviceKey(2, 9), Some("4C530001"));
+        app.process_packet(&bulk_in(DeviceKey(2, 9)));
+        assert!(!app.devices.contains_key(&DeviceKey(1, 5)));
+        let survivor = &app.devices[&DeviceKey(2, 9)];
+        assert_eq!(survivor.bandwidth_stats.total_rx_bytes, 4096 + 512);
+        assert_eq!(survivor.bandwidth_stats.urb_counts.callbacks, 8 + 1);
+        assert_eq!(app.selected_device, Some(DeviceKey(2, 9)));
+        // A reset on the same kind of link isn't announced
+        assert!(app.notifications.is_empty());
+        
+        // Without a serial two identical devices are indistinguishable, so both stay
+        let mut app = replay();
+        app.update_device(identified(2, None, 0));
+        enumerate(&mut app, DeviceKey(1, 3), None);
+        app.process_packet(&bulk_in(DeviceKey(1, 3)));
+        assert_eq!(app.devices.len(), 2);
+        
+        // Outside the merge window the old entry is left to expire normally
+        let mut app = replay();
+        let mut stale = identified(5, Some("4C530001"), 0);
+        stale.last_seen = Instant::now() - Duration::from_secs(60);
+        app.update_device(stale);
+        enumerate(&mut app, DeviceKey(1, 6), Some("4C530001"));
+        app.process_packet(&bulk_in(DeviceKey(1, 6)));
+        assert_eq!(app.devices.len(), 2);
+    }
+    
+    #[test]
+    fn test_superspeed_fallback_pairs_across_buses() {
+        let link = |bus_id: u8, device_id: u8, speed: UsbSpeed| {
+            let mut device = UsbDevice::new(bus_id, device_id);
+            device.vendor_id = Some(0x0bda);
+            device.product_id = Some(0x8153);
+            device.port_path = Some(format!("{}-2.1", bus_id));
+            device.speed = speed;
+            device
+        };
+        let last_notification = |app: &UsbTopApp| app.notifications.last().map(|(_, message)| message.clone());
+        
+        // SuperSpeed fails and the adapter shows up on the USB 2 twin of its port
+        let mut app = UsbTopApp::new(Duration::from_secs(1), Duration::from_secs(1));
+        let mut superspeed = link(2, 3, UsbSpeed::SuperSpeed);
+        superspeed.bandwidth_stats.update_rx(2048);
+        app.update_device(superspeed);
+        app.track_new_device(link(1, 7, UsbSpeed::High));
+        assert!(!app.devices.contains_key(&DeviceKey(2, 3)));
+        let fallback = &app.devices[&DeviceKey(1, 7)];
+        assert_eq!(fallback.bandwidth_stats.total_rx_bytes, 2048);
+        assert_eq!(fallback.other_link, Some(crate::device::OtherLink { key: DeviceKey(2, 3), speed: UsbSpeed::SuperSpeed }));
+        assert!(fallback.link_summary().unwrap().ends_with("fell back to USB 2"));
+        assert_eq!(last_notification(&app).as_deref(), Some("Device 002:003 fell back to USB 2 as 001:007"));
+        
+        // And back on the SuperSpeed bus after a replug
+        app.track_new_device(link(2, 4, UsbSpeed::SuperSpeed));
+        assert_eq!(app.devices.len(), 1);
+        assert_eq!(app.devices[&DeviceKey(2, 4)].bandwidth_stats.total_rx_bytes, 2048);
+        assert_eq!(last_notification(&app).as_deref(), Some("Device 001:007 came back up at SuperSpeed as 002:004"));
+        
+        // Pairing turned off keeps both
+        let mut app = UsbTopApp::new(Duration::from_secs(1), Duration::from_secs(1));
+        app.pair_companion_links = false;
+        app.update_device(link(2, 3, UsbSpeed::SuperSpeed));
+        app.track_new_device(link(1, 7, UsbSpeed::High));
+        assert_eq!(app.devices.len(), 2);
+        assert!(app.notifications.is_empty());
+    }
+    
+    #[test]
+    fn test_removed_device_keeps_its_saved_totals() {
+        let path = std::env::temp_dir().join(format!("usbtop-ng-removed-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut store = SessionStore::open(&path).unwrap();
+        store.save([&identified(4, Some("4C530001"), 1_000)]).unwrap();
+        
+        // Restored, then gone again before the next save
+        let mut app = UsbTopApp::new(Duration::from_secs(1), Duration::from_secs(1));
+        app.state = Some(SessionStore::open(&path).unwrap());
+        app.update_device(identified(7, Some("4C530001"), 24));
+        assert_eq!(app.devices[&DeviceKey(1, 7)].bandwidth_stats.total_rx_bytes, 1_024);
+        app.remove_device(DeviceKey(1, 7));
+        app.save_state();
+        
+        let mut replugged = identified(9, Some("4C530001"), 0);
+        let restored = SessionStore::open(&path).unwrap().restore(&mut replugged);
+        std::fs::remove_file(&path).unwrap();
+        assert!(restored);
+        assert_eq!(replugged.bandwidth_stats.total_rx_bytes, 1_024);
+    }
+    
+    #[test]
+    fn test_filter_rejection_is_remembered_for_a_while() {
+        let mut config = Config::default();
+        config.filter.blocklist = vec!["0781:*".to_string()];
+        let mut app = UsbTopApp::new(Duration::from_secs(1), Duration::from_secs(1));
+        app.apply_config(&config);
+        app.origin = Some("replay".to_string());
+        let blocked = DeviceKey(1, 4);
+        
+        enumerate(&mut app, blocked, None);
+        app.process_packet(&bulk_in(blocked));
+        app.process_packet(&bulk_in(DeviceKey(1, 5)));
+        assert!(!app.devices.contains_key(&blocked));
+        assert!(app.filtered.contains_key(&blocked));
+        assert!(app.devices.contains_key(&DeviceKey(1, 5)));
+        
+        // Let through now, but the rejection still stands until it's rechecked
+        app.filter = DeviceFilter::default();
+        assert!(app.probe_device(blocked).is_none());
+        app.process_packet(&bulk_in(blocked));
+        assert!(!app.devices.contains_key(&blocked));
+        
+        app.filtered.insert(blocked, Instant::now() - FILTER_RECHECK_INTERVAL);
+        app.process_packet(&bulk_in(blocked));
+        assert!(app.devices.contains_key(&blocked));
+        assert!(!app.filtered.contains_key(&blocked));
+    }
+    
+    #[test]
+    fn test_allowlist_matches_ids_sniffed_from_enumeration() {
+        let mut config = Config::default();
+        config.filter.allowlist = vec!["0781:*".to_string()];
+        let mut app = UsbTopApp::new(Duration::from_secs(1), Duration::from_secs(1));
+        app.apply_config(&config);
+        // A replayed capture: no sysfs to read the ids from
+        app.origin = Some("replay".to_string());
+        
+        for line in [
+            "ffff8800aa000100 100 S Ci:1:005:0 s 80 06 0100 0000 0012 18 <",
+            "ffff8800aa000100 200 C Ci:1:005:0 0 18 = 12010002 00000040 81078155 00010102 0301",
+            "ffff8800aa000200 300 C Bi:1:005:1 0 512 = 00",
+            "ffff8800aa000300 400 C Bi:1:006:1 0 512 = 00",
+        ] {
+            app.process_packet(&parse_usbmon_text_line(line).unwrap());
+        }
+        
+        let device = &app.devices[&DeviceKey(1, 5)];
+        assert_eq!(device.vendor_id, Some(0x0781));
+        assert!(device.bandwidth_stats.total_rx_bytes > 0);
+        // Never enumerated in the capture, so its ids are unknown
+        assert!(!app.devices.contains_key(&DeviceKey(1, 6)));
+    }
+}
@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Device table ordering with hysteresis
+/// Keeps the last rendered order until some device's bandwidth rank differs from
+/// its displayed position by more than `rank_threshold` for `frames` consecutive frames
+#[derive(Debug, Clone)]
+pub struct StableOrder<K> {
+    order: Vec<K>,
+    pending: HashMap<K, u32>,
+    rank_threshold: usize,
+    frames: u32,
+}
+
+impl<K: Clone + Eq + Hash> StableOrder<K> {
+    pub fn new(rank_threshold: usize, frames: u32) -> Self {
+        Self {
+            order: Vec::new(),
+            pending: HashMap::new(),
+            rank_threshold,
+            frames,
+        }
+    }
+    
+    /// Take the desired (bandwidth-sorted) order and return the order to render
+    pub fn apply(&mut self, desired: Vec<K>) -> Vec<K> {
+        // Drop devices that went away, append newly seen ones at the bottom
+        self.order.retain(|key| desired.contains(key));
+        for key in &desired {
+            if !self.order.contains(key) {
+                self.order.push(key.clone());
+            }
+        }
+        self.pending.retain(|key, _| desired.contains(key));
+        
+        let mut reorder = false;
+        for (current_rank, key) in self.order.iter().enumerate() {
+            let desired_rank = desired.iter().position(|k| k == key).unwrap_or(current_rank);
+            let counter = self.pending.entry(key.clone()).or_insert(0);
+            
+            if desired_rank.abs_diff(current_rank) > self.rank_threshold {
+                *counter += 1;
+                if *counter >= self.frames {
+                    reorder = true;
+                }
+            } else {
+                *counter = 0;
+            }
+        }
+        
+        if reorder {
+            self.order = desired;
+            self.pending.clear();
+        }
+        
+        self.order.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn keys(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+    
+    #[test]
+    fn test_small_rank_changes_are_ignored() {
+        let mut order = StableOrder::new(1, 2);
+        assert_eq!(order.apply(keys(&["a", "b", "c"])), keys(&["a", "b", "c"]));
+        
+        // Swapping neighbours moves each device by one rank, which is within the threshold
+        for _ in 0..5 {
+            assert_eq!(order.apply(keys(&["b", "a", "c"])), keys(&["a", "b", "c"]));
+        }
+    }
+    
+    #[test]
+    fn test_large_rank_change_reorders_after_frames() {
+        let mut order = StableOrder::new(1, 2);
+        order.apply(keys(&["a", "b", "c"]));
+        
+        assert_eq!(order.apply(keys(&["c", "b", "a"])), keys(&["a", "b", "c"]));
+        assert_eq!(order.apply(keys(&["c", "b", "a"])), keys(&["c", "b", "a"]));
+    }
+    
+    #[test]
+    fn test_new_and_removed_devices() {
+        let mut order = StableOrder::new(1, 3);
+        order.apply(keys(&["a", "b"]));
+        
+        assert_eq!(order.apply(keys(&["c", "b"])), keys(&["b", "c"]));
+    }
+}
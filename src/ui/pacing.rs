@@ -0,0 +1,135 @@
+use std::time::{Duration, Instant};
+
+/// Shortest time between frames, used when input asks for one; also how
+/// long input waits when idle
+pub const MIN_FRAME_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Longest time between frames however slow drawing gets, unless the
+/// refresh interval is longer still
+pub const MAX_FRAME_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Share of the frame interval drawing may take before frames are spaced out
+const DRAW_BUDGET_RATIO: f64 = 0.25;
+
+/// Weight of the newest draw time in the running average
+const DRAW_TIME_SMOOTHING: f64 = 0.2;
+
+/// Decides when the next frame is drawn
+/// Frames come every refresh interval, and the interval follows the measured draw time so a slow terminal or a
+/// loaded machine gets fewer frames instead of a backlog: frames that would
+/// fall in between are simply skipped, and the next one shows the latest
+/// state. Input asks for a frame as soon as the minimum interval allows.
+#[derive(Debug, Clone)]
+pub struct FramePacer {
+    /// Regular spacing between frames while drawing keeps up
+    refresh: Duration,
+    /// Smoothed draw time in seconds
+    draw_secs: f64,
+    last_frame: Option<Instant>,
+    redraw_requested: bool,
+}
+
+impl FramePacer {
+    pub fn new(refresh: Duration) -> Self {
+        Self {
+            refresh: refresh.max(MIN_FRAME_INTERVAL),
+            draw_secs: 0.0,
+            last_frame: None,
+            redraw_requested: false,
+        }
+    }
+    
+    /// Current spacing between regular frames
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs_f64(self.draw_secs / DRAW_BUDGET_RATIO)
+            .clamp(self.refresh, MAX_FRAME_INTERVAL.max(self.refresh))
+    }
+    
+    /// Whether frames are being spaced out beyond the refresh interval
+    pub fn is_degraded(&self) -> bool {
+        self.interval() > self.refresh
+    }
+    
+    /// Draw at the next opportunity, e.g. after a key press
+    pub fn request_redraw(&mut self) {
+        self.redraw_requested = true;
+    }
+    
+    /// Time left until a frame is due; zero when it already is
+    pub fn time_until_due(&self, now: Instant) -> Duration {
+        let Some(last) = self.last_frame else {
+            return Duration::ZERO;
+        };
+        let interval = if self.redraw_requested { MIN_FRAME_INTERVAL } else { self.interval() };
+        (last + interval).saturating_duration_since(now)
+    }
+    
+    pub fn is_due(&self, now: Instant) -> bool {
+        self.time_until_due(now).is_zero()
+    }
+    
+    /// Account a frame that started at `started` and took `draw_time`
+    pub fn record_frame(&mut self, started: Instant, draw_time: Duration) {
+        let secs = draw_time.as_secs_f64();
+        self.draw_secs = if self.last_frame.is_none() {
+            secs
+        } else {
+            self.draw_secs + (secs - self.draw_secs) * DRAW_TIME_SMOOTHING
+        };
+        self.last_frame = Some(started);
+        self.redraw_requested = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_fast_draws_keep_minimum_interval() {
+        let mut pacer = FramePacer::new(Duration::from_millis(10));
+        let start = Instant::now();
+        assert!(pacer.is_due(start));
+        
+        pacer.record_frame(start, Duration::from_millis(2));
+        assert_eq!(pacer.interval(), MIN_FRAME_INTERVAL);
+        assert!(!pacer.is_degraded());
+        assert!(!pacer.is_due(start + Duration::from_millis(20)));
+        assert!(pacer.is_due(start + MIN_FRAME_INTERVAL));
+    }
+    
+    #[test]
+    fn test_slow_draws_space_frames_out() {
+        let mut pacer = FramePacer::new(MIN_FRAME_INTERVAL);
+        let start = Instant::now();
+        pacer.record_frame(start, Duration::from_millis(100));
+        assert_eq!(pacer.interval(), Duration::from_millis(400));
+        assert!(pacer.is_degraded());
+        assert_eq!(pacer.time_until_due(start + Duration::from_millis(100)), Duration::from_millis(300));
+        
+        // Input still gets a frame at the minimum interval
+        pacer.request_redraw();
+        assert!(pacer.is_due(start + MIN_FRAME_INTERVAL));
+        
+        // Pathologically slow draws are capped
+        pacer.record_frame(start, Duration::from_secs(10));
+        assert_eq!(pacer.interval(), MAX_FRAME_INTERVAL);
+        
+        // Recovery is gradual rather than one fast frame resetting the pace
+        pacer.record_frame(start, Duration::from_millis(1));
+        assert!(pacer.is_degraded());
+    }
+    
+    #[test]
+    fn test_long_refresh_still_answers_input() {
+        let mut pacer = FramePacer::new(Duration::from_secs(2));
+        let start = Instant::now();
+        pacer.record_frame(start, Duration::from_millis(5));
+        assert_eq!(pacer.interval(), Duration::from_secs(2));
+        assert!(!pacer.is_degraded());
+        assert!(!pacer.is_due(start + Duration::from_secs(1)));
+        
+        pacer.request_redraw();
+        assert!(pacer.is_due(start + MIN_FRAME_INTERVAL));
+    }
+}
@@ -0,0 +1,107 @@
+use crate::config::{Column, Config, SortKey, SortOrder};
+use crate::control::ControlCommand;
+
+/// Command names, for completion and the help overlay
+pub const COMMANDS: [&str; 7] = ["columns", "filter", "pause", "quit", "reset", "resume", "sort"];
+
+/// A command typed after `:`
+/// Values are parsed like the matching `[ui]` settings, so `:sort tx desc`
+/// accepts the same words as `sort_by = "tx"`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PaletteCommand {
+    Sort(SortKey, SortOrder),
+    Columns(Vec<Column>),
+    /// reset, pause, resume, filter and quit behave as on the control socket
+    Control(ControlCommand),
+}
+
+impl PaletteCommand {
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let mut words = line.split_whitespace();
+        let Some(name) = words.next() else {
+            return Err("empty command".to_string());
+        };
+        let args: Vec<&str> = words.collect();
+        
+        match name {
+            "sort" => {
+                let (key, order) = match args.as_slice() {
+                    [key] => (Config::parse_value::<SortKey>(key)?, None),
+                    [key, order] => (Config::parse_value::<SortKey>(key)?, Some(Config::parse_value::<SortOrder>(order)?)),
+                    _ => return Err("usage: sort <column> [asc|desc]".to_string()),
+                };
+                Ok(PaletteCommand::Sort(key, order.unwrap_or_else(|| key.default_order())))
+            }
+            "columns" => {
+                let columns = args.iter()
+                    .flat_map(|arg| arg.split(','))
+                    .filter(|name| !name.is_empty())
+                    .map(Config::parse_value::<Column>)
+                    .collect::<Result<Vec<Column>, String>>()?;
+                if columns.is_empty() {
+                    return Err("usage: columns <column>[,<column>...]".to_string());
+                }
+                Ok(PaletteCommand::Columns(columns))
+            }
+            // JSON is no use in the status line
+            "snapshot" => Err("snapshot is only available on the control socket".to_string()),
+            _ if COMMANDS.contains(&name) => ControlCommand::parse(line).map(PaletteCommand::Control),
+            _ => Err(format!("unknown command '{}'", name)),
+        }
+    }
+}
+
+/// Complete a partly typed command name
+/// A single match is completed with a trailing space; several are completed
+/// as far as they agree. None when there is nothing to add.
+pub fn complete(input: &str) -> Option<String> {
+    if input.is_empty() || input.contains(char::is_whitespace) {
+        return None;
+    }
+    let matches: Vec<&str> = COMMANDS.iter().copied().filter(|command| command.starts_with(input)).collect();
+    let completed = match matches.as_slice() {
+        [] => return None,
+        [command] => format!("{} ", command),
+        [first, rest @ ..] => {
+            let shared = rest.iter().fold(first.len(), |shared, command| {
+                first.bytes().zip(command.bytes()).take(shared).take_while(|(a, b)| a == b).count()
+            });
+            first[..shared].to_string()
+        }
+    };
+    (completed != input).then_some(completed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parse_palette_commands() {
+        assert_eq!(PaletteCommand::parse("sort tx desc"), Ok(PaletteCommand::Sort(SortKey::Tx, SortOrder::Desc)));
+        assert_eq!(PaletteCommand::parse("sort vendor"), Ok(PaletteCommand::Sort(SortKey::Vendor, SortOrder::Asc)));
+        assert_eq!(
+            PaletteCommand::parse("columns device,tx, rx"),
+            Ok(PaletteCommand::Columns(vec![Column::Device, Column::Tx, Column::Rx])),
+        );
+        assert_eq!(PaletteCommand::parse("reset"), Ok(PaletteCommand::Control(ControlCommand::Reset)));
+        assert_eq!(
+            PaletteCommand::parse("filter 046d:*"),
+            Ok(PaletteCommand::Control(ControlCommand::Filter(vec!["046d:*".to_string()]))),
+        );
+        assert!(PaletteCommand::parse("sort speed").is_err());
+        assert!(PaletteCommand::parse("columns").is_err());
+        assert!(PaletteCommand::parse("snapshot").is_err());
+        assert_eq!(PaletteCommand::parse("theme light"), Err("unknown command 'theme'".to_string()));
+    }
+    
+    #[test]
+    fn test_complete_command_names() {
+        assert_eq!(complete("so"), Some("sort ".to_string()));
+        assert_eq!(complete("re"), Some("res".to_string()));
+        assert_eq!(complete("res"), None);
+        assert_eq!(complete("resu"), Some("resume ".to_string()));
+        assert_eq!(complete("x"), None);
+        assert_eq!(complete("sort t"), None);
+    }
+}
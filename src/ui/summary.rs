@@ -0,0 +1,126 @@
+use std::time::Duration;
+
+use super::widgets::{format_bytes, format_count};
+use super::UsbTopApp;
+use crate::device::classes::{aggregate_by_class, ClassTotals};
+use crate::logging::decorated;
+
+/// Totals for one device in a capture summary
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceTotals {
+    pub label: String,
+    pub packets: u64,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+}
+
+/// Results printed when a piped capture has been read to the end
+#[derive(Debug, Clone)]
+pub struct CaptureSummary {
+    pub duration: Duration,
+    pub devices: Vec<DeviceTotals>,
+    pub classes: Vec<ClassTotals>,
+}
+
+impl CaptureSummary {
+    /// Per-device totals, busiest device first
+    pub fn from_app(app: &UsbTopApp) -> Self {
+        let mut devices: Vec<DeviceTotals> = app.devices.values()
+            .map(|device| {
+                let stats = &device.bandwidth_stats;
+                let counts = &stats.urb_counts;
+                let name = device.product.clone().unwrap_or_else(|| "Unknown device".to_string());
+                DeviceTotals {
+                    label: format!("{} {}", device.key(), name),
+                    packets: counts.submissions + counts.callbacks + counts.errors,
+                    rx_bytes: stats.total_rx_bytes,
+                    tx_bytes: stats.total_tx_bytes,
+                }
+            })
+            .collect();
+        devices.sort_by(|a, b| {
+            (b.rx_bytes + b.tx_bytes).cmp(&(a.rx_bytes + a.tx_bytes))
+                .then_with(|| a.label.cmp(&b.label))
+        });
+        
+        Self {
+            duration: app.started.elapsed(),
+            devices,
+            classes: aggregate_by_class(app.devices.values()),
+        }
+    }
+    
+    pub fn total_packets(&self) -> u64 {
+        self.devices.iter().map(|device| device.packets).sum()
+    }
+    
+    pub fn total_bytes(&self) -> u64 {
+        self.devices.iter().map(|device| device.rx_bytes + device.tx_bytes).sum()
+    }
+    
+    pub fn print(&self) {
+        println!("{}", decorated("📊", "Capture summary"));
+        println!("   Duration:    {:.1}s", self.duration.as_secs_f64());
+        println!("   Devices:     {}", self.devices.len());
+        println!("   Packets:     {}", format_count(self.total_packets()));
+        println!("   Transferred: {:.1} MB ({} bytes)", self.total_bytes() as f64 / 1_000_000.0, format_count(self.total_bytes()));
+        for device in &self.devices {
+            println!(
+                "   {:<32} {:>10} packets  RX {:>9.1} MB  TX {:>9.1} MB",
+                device.label,
+                format_count(device.packets),
+                device.rx_bytes as f64 / 1_000_000.0,
+                device.tx_bytes as f64 / 1_000_000.0,
+            );
+        }
+        print_class_report(&self.classes);
+    }
+}
+
+/// What the session's traffic was for, e.g. "Mass Storage 4.2 GB"; classes
+/// that moved nothing are left out, and nothing is printed for an idle session
+pub fn print_class_report(classes: &[ClassTotals]) {
+    let moved: Vec<&ClassTotals> = classes.iter().filter(|class| class.total_bytes() > 0).collect();
+    if moved.is_empty() {
+        return;
+    }
+    println!("{}", decorated("📦", "Data moved by device class"));
+    for class in moved {
+        println!(
+            "   {:<32} {:>10}  ({} device{})",
+            class.name,
+            format_bytes(class.total_bytes()),
+            class.devices,
+            if class.devices == 1 { "" } else { "s" },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::UsbDevice;
+    use crate::usbmon::parser::UrbType;
+    
+    #[test]
+    fn test_summary_totals_busiest_first() {
+        let mut app = UsbTopApp::new(Duration::from_secs(1), Duration::from_secs(1));
+        
+        let mut keyboard = UsbDevice::new(1, 3);
+        keyboard.bandwidth_stats.total_rx_bytes = 64;
+        keyboard.bandwidth_stats.urb_counts.record(&UrbType::Submission);
+        keyboard.bandwidth_stats.urb_counts.record(&UrbType::Callback);
+        app.update_device(keyboard);
+        
+        let mut disk = UsbDevice::new(2, 2);
+        disk.bandwidth_stats.total_rx_bytes = 4_000_000;
+        disk.bandwidth_stats.total_tx_bytes = 1_000_000;
+        disk.bandwidth_stats.urb_counts.record(&UrbType::Callback);
+        app.update_device(disk);
+        
+        let summary = CaptureSummary::from_app(&app);
+        assert_eq!(summary.total_packets(), 3);
+        assert_eq!(summary.total_bytes(), 5_000_064);
+        assert!(summary.devices[0].label.starts_with("002:002"));
+    }
+}
@@ -0,0 +1,199 @@
+use std::time::Duration;
+
+/// Shortest time span the graph can be zoomed into
+pub const MIN_GRAPH_SPAN_SECS: f64 = 10.0;
+
+/// Fraction of the visible span moved by one pan step
+const PAN_STEP: f64 = 0.25;
+
+/// Lowest rate the log-scale graph resolves, in MB/s (1 KB/s); lower rates,
+/// zero included, sit on the bottom edge
+pub const LOG_FLOOR_MBPS: f64 = 0.001;
+
+/// Samples needed to cover `retention` at one sample per `sample_interval`
+pub fn history_capacity(retention: Duration, sample_interval: Duration) -> usize {
+    let sample_secs = sample_interval.as_secs_f64().max(0.001);
+    (retention.as_secs_f64() / sample_secs).ceil().max(1.0) as usize
+}
+
+/// The slice of retained history shown by the bandwidth graph
+/// While live the window follows the newest sample; panning back pins its end
+/// to a fixed time until it is panned forward to the tail again.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GraphViewport {
+    pub span_secs: f64,
+    pinned_end: Option<f64>,
+}
+
+impl GraphViewport {
+    pub fn new(span_secs: f64) -> Self {
+        Self {
+            span_secs,
+            pinned_end: None,
+        }
+    }
+    
+    pub fn is_live(&self) -> bool {
+        self.pinned_end.is_none()
+    }
+    
+    /// Visible `(start, end)` given the newest sample time
+    pub fn window(&self, latest: f64) -> (f64, f64) {
+        let end = self.pinned_end.unwrap_or(latest);
+        let start = (end - self.span_secs).max(0.0);
+        (start, end.max(start + 1.0))
+    }
+    
+    /// Move back in time, never past the oldest retained sample
+    pub fn pan_back(&mut self, earliest: f64, latest: f64) {
+        let (_, end) = self.window(latest);
+        let oldest_end = (earliest + self.span_secs).min(latest);
+        self.pinned_end = Some((end - self.span_secs * PAN_STEP).max(oldest_end));
+    }
+    
+    /// Move forward in time, going live again on reaching the tail
+    pub fn pan_forward(&mut self, latest: f64) {
+        if let Some(end) = self.pinned_end {
+            let end = end + self.span_secs * PAN_STEP;
+            self.pinned_end = if end >= latest { None } else { Some(end) };
+        }
+    }
+    
+    /// Halve the span, keeping the window's end in place
+    pub fn zoom_in(&mut self) {
+        self.span_secs = (self.span_secs / 2.0).max(MIN_GRAPH_SPAN_SECS);
+    }
+    
+    /// Double the span, up to `max_span_secs` (the retention)
+    pub fn zoom_out(&mut self, max_span_secs: f64) {
+        self.span_secs = (self.span_secs * 2.0).min(max_span_secs.max(MIN_GRAPH_SPAN_SECS));
+    }
+}
+
+/// Largest value among the visible `(time, value)` points
+/// Unlike the all-time peak this falls again once a burst scrolls out of view.
+pub fn window_peak<'a>(points: impl IntoIterator<Item = &'a (f64, f64)>) -> Option<f64> {
+    points.into_iter().map(|(_, value)| *value).fold(None, |peak, value| {
+        Some(peak.map_or(value, |peak: f64| peak.max(value)))
+    })
+}
+
+/// Points for a dashed horizontal line at `y` across `(start, end)`
+/// `dots` is the horizontal resolution; every other run of `DASH_DOTS` is left out.
+pub fn dashed_line(y: f64, (start, end): (f64, f64), dots: usize) -> Vec<(f64, f64)> {
+    const DASH_DOTS: usize = 3;
+    let step = (end - start) / dots.max(1) as f64;
+    (0..=dots)
+        .filter(|dot| (dot / DASH_DOTS).is_multiple_of(2))
+        .map(|dot| (start + dot as f64 * step, y))
+        .collect()
+}
+
+/// Position of `mbps` on the log-scale axis: decades above 1 MB/s
+pub fn log_scale(mbps: f64) -> f64 {
+    mbps.max(LOG_FLOOR_MBPS).log10()
+}
+
+/// Log axis bounds covering `peak_mbps`, on whole decades so evenly spread
+/// labels land on powers of ten
+pub fn log_bounds(peak_mbps: f64) -> (f64, f64) {
+    let bottom = log_scale(LOG_FLOOR_MBPS);
+    (bottom, log_scale(peak_mbps).ceil().max(bottom + 1.0))
+}
+
+/// Rate labels for each decade between `bounds`, e.g. "1K", "10K", "1M"
+/// With more decades than `max_labels` only the ends are labelled.
+pub fn log_axis_labels((bottom, top): (f64, f64), max_labels: usize) -> Vec<String> {
+    let decades: Vec<i32> = (bottom.round() as i32..=top.round() as i32).collect();
+    let shown: Vec<i32> = if decades.len() > max_labels.max(2) {
+        vec![decades[0], decades[decades.len() - 1]]
+    } else {
+        decades
+    };
+    shown.into_iter()
+        .map(|decade| {
+            // Decades are of MB/s; count them in bytes to pick a unit
+            let exponent = decade + 6;
+            let (unit, scale) = match exponent {
+                i32::MIN..=2 => ("", 0),
+                3..=5 => ("K", 3),
+                6..=8 => ("M", 6),
+                _ => ("G", 9),
+            };
+            format!("{}{}", 10u64.pow(exponent.max(0) as u32 - scale), unit)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_history_capacity() {
+        assert_eq!(history_capacity(Duration::from_secs(3600), Duration::from_secs(1)), 3600);
+        assert_eq!(history_capacity(Duration::from_secs(60), Duration::from_millis(250)), 240);
+    }
+    
+    #[test]
+    fn test_pan_detaches_and_returns_to_live() {
+        let mut viewport = GraphViewport::new(60.0);
+        assert_eq!(viewport.window(600.0), (540.0, 600.0));
+        
+        viewport.pan_back(0.0, 600.0);
+        assert!(!viewport.is_live());
+        assert_eq!(viewport.window(600.0), (525.0, 585.0));
+        // A pinned window stays put while new samples arrive
+        assert_eq!(viewport.window(700.0), (525.0, 585.0));
+        
+        viewport.pan_forward(600.0);
+        assert!(viewport.is_live());
+        assert_eq!(viewport.window(700.0), (640.0, 700.0));
+    }
+    
+    #[test]
+    fn test_pan_and_zoom_stay_within_retention() {
+        let mut viewport = GraphViewport::new(60.0);
+        for _ in 0..100 {
+            viewport.pan_back(300.0, 600.0);
+        }
+        assert_eq!(viewport.window(600.0), (300.0, 360.0));
+        
+        viewport.zoom_out(240.0);
+        viewport.zoom_out(240.0);
+        viewport.zoom_out(240.0);
+        assert_eq!(viewport.span_secs, 240.0);
+        
+        for _ in 0..10 {
+            viewport.zoom_in();
+        }
+        assert_eq!(viewport.span_secs, MIN_GRAPH_SPAN_SECS);
+    }
+    
+    #[test]
+    fn test_window_peak_and_dashes() {
+        let rx = [(1.0, 2.0), (2.0, 7.5)];
+        let tx = [(1.0, 3.0), (2.0, 0.5)];
+        assert_eq!(window_peak(rx.iter().chain(tx.iter())), Some(7.5));
+        assert_eq!(window_peak(&[]), None);
+        
+        let dashes = dashed_line(7.5, (0.0, 12.0), 12);
+        let xs: Vec<f64> = dashes.iter().map(|(x, _)| *x).collect();
+        assert_eq!(xs, vec![0.0, 1.0, 2.0, 6.0, 7.0, 8.0, 12.0]);
+        assert!(dashes.iter().all(|(_, y)| *y == 7.5));
+    }
+    
+    #[test]
+    fn test_log_scale_axis() {
+        assert_eq!(log_scale(0.0), -3.0);
+        assert_eq!(log_scale(-5.0), -3.0);
+        assert_eq!(log_scale(100.0), 2.0);
+        
+        let bounds = log_bounds(500.0);
+        assert_eq!(bounds, (-3.0, 3.0));
+        assert_eq!(log_axis_labels(bounds, 10), vec!["1K", "10K", "100K", "1M", "10M", "100M", "1G"]);
+        assert_eq!(log_axis_labels(bounds, 4), vec!["1K", "1G"]);
+        // Idle history still gets a decade to draw in
+        assert_eq!(log_bounds(0.0), (-3.0, -2.0));
+    }
+}
@@ -6,17 +6,16 @@ use ratatui::{
     Frame,
 };
 
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
 use super::colors::*;
+pub use crate::device::{format_bandwidth, format_bandwidth_with};
+use crate::stats::UrbCounts;
 
-pub fn create_bandwidth_gauge(current: f64, max: f64, width: u16) -> Gauge<'static> {
+pub fn create_bandwidth_gauge(current: f64, max: f64, width: u16, scale: &UtilizationScale) -> Gauge<'static> {
     let ratio = if max > 0.0 { (current / max).min(1.0) } else { 0.0 };
-    
-    let color = match ratio {
-        r if r < 0.25 => BANDWIDTH_LOW,
-        r if r < 0.5 => BANDWIDTH_MEDIUM,
-        r if r < 0.75 => BANDWIDTH_HIGH,
-        _ => BANDWIDTH_CRITICAL,
-    };
+    let color = scale.color_for(ratio);
     
     Gauge::default()
         .ratio(ratio)
@@ -24,28 +23,139 @@ pub fn create_bandwidth_gauge(current: f64, max: f64, width: u16) -> Gauge<'stat
         .label(format!("{:.1} MB/s", current / 1_000_000.0))
 }
 
-pub fn format_bandwidth(bytes_per_sec: f64) -> String {
-    if bytes_per_sec >= 1_000_000_000.0 {
-        format!("{:.1} GB/s", bytes_per_sec / 1_000_000_000.0)
-    } else if bytes_per_sec >= 1_000_000.0 {
-        format!("{:.1} MB/s", bytes_per_sec / 1_000_000.0)
-    } else if bytes_per_sec >= 1_000.0 {
-        format!("{:.1} KB/s", bytes_per_sec / 1_000.0)
+/// Separator `format_count` groups digits with, as a char; 0 for none
+static THOUSANDS_SEPARATOR: AtomicU32 = AtomicU32::new(',' as u32);
+
+/// Resolve `ui.thousands_separator`: one character, "" for none, or "locale"
+/// for the convention of LC_ALL / LC_NUMERIC / LANG. Anything else keeps
+/// the comma, with a warning
+pub fn thousands_separator_from_config(value: &str) -> (Option<char>, Vec<String>) {
+    if value == "locale" {
+        let locale = ["LC_ALL", "LC_NUMERIC", "LANG"].iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .unwrap_or_default();
+        return (Some(locale_thousands_separator(&locale)), Vec::new());
+    }
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (None, _) => (None, Vec::new()),
+        (Some(separator), None) => (Some(separator), Vec::new()),
+        _ => (Some(','), vec![format!(
+            "Ignoring thousands_separator '{}': expected one character, \"\" or \"locale\"", value,
+        )]),
+    }
+}
+
+/// Digit grouping for a locale name such as "de_DE.UTF-8"; a comma for C,
+/// POSIX and anything not known to group differently
+fn locale_thousands_separator(locale: &str) -> char {
+    let name = locale.split(['.', '@']).next().unwrap_or_default();
+    if name == "de_CH" || name == "it_CH" {
+        return '\'';
+    }
+    match name.split('_').next().unwrap_or_default() {
+        "de" | "es" | "it" | "nl" | "pt" | "da" | "id" | "tr" | "el" | "ro" | "hr" | "sl" | "sr" | "vi" => '.',
+        "fr" | "ru" | "pl" | "cs" | "sk" | "sv" | "fi" | "nb" | "nn" | "no" | "uk" | "hu" | "bg" | "et" | "lv" | "lt" => ' ',
+        _ => ',',
+    }
+}
+
+/// Set the separator `format_count` uses; None writes digits ungrouped
+pub fn set_thousands_separator(separator: Option<char>) {
+    THOUSANDS_SEPARATOR.store(separator.map_or(0, u32::from), Ordering::Relaxed);
+}
+
+/// Format a count with thousands separators, e.g. 12034 -> "12,034"
+pub fn format_count(count: u64) -> String {
+    let separator = char::from_u32(THOUSANDS_SEPARATOR.load(Ordering::Relaxed)).filter(|&c| c != '\0');
+    format_count_with(count, separator)
+}
+
+fn format_count_with(count: u64, separator: Option<char>) -> String {
+    let digits = count.to_string();
+    let Some(separator) = separator else {
+        return digits;
+    };
+    let mut formatted = String::with_capacity(digits.len() + digits.len() / 3 * separator.len_utf8());
+    
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            formatted.push(separator);
+        }
+        formatted.push(c);
+    }
+    
+    formatted
+}
+
+/// Format a byte total, e.g. "4.2 GB" or "512 B"
+pub fn format_bytes(bytes: u64) -> String {
+    let value = bytes as f64;
+    if bytes >= 1_000_000_000 {
+        format!("{:.1} GB", value / 1_000_000_000.0)
+    } else if bytes >= 1_000_000 {
+        format!("{:.1} MB", value / 1_000_000.0)
+    } else if bytes >= 1_000 {
+        format!("{:.1} KB", value / 1_000.0)
     } else {
-        format!("{:.0} B/s", bytes_per_sec)
+        format!("{} B", bytes)
     }
 }
 
+pub fn format_urb_counts(counts: &UrbCounts) -> String {
+    format!(
+        "S: {} C: {} E: {}",
+        format_count(counts.submissions),
+        format_count(counts.callbacks),
+        format_count(counts.errors),
+    )
+}
+
+/// Format a polling interval, e.g. "125 µs" or "8 ms"
+pub fn format_interval(interval: Duration) -> String {
+    let micros = interval.as_micros();
+    if micros < 1000 {
+        format!("{} µs", micros)
+    } else if micros.is_multiple_of(1000) {
+        format!("{} ms", micros / 1000)
+    } else {
+        format!("{:.3} ms", micros as f64 / 1000.0)
+    }
+}
+
+/// Compact connection time for the device list, e.g. "42s", "12m05s",
+/// "3h04m" or "2d03h"
+pub fn format_uptime(uptime: Duration) -> String {
+    let secs = uptime.as_secs();
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m{:02}s", secs / 60, secs % 60),
+        3600..=86_399 => format!("{}h{:02}m", secs / 3600, secs % 3600 / 60),
+        _ => format!("{}d{:02}h", secs / 86_400, secs % 86_400 / 3600),
+    }
+}
+
+/// How long ago something happened, e.g. "2.3s ago" or "12m05s ago"
+pub fn format_ago(age: Duration) -> String {
+    if age.as_secs() < 60 {
+        format!("{:.1}s ago", age.as_secs_f64())
+    } else {
+        format!("{} ago", format_uptime(age))
+    }
+}
+
+/// The newest `max_points` samples scaled to 0-64 against the largest of them
 pub fn create_sparkline_data(history: &[(f64, f64)], max_points: usize) -> Vec<u64> {
     if history.is_empty() {
         return vec![0; max_points];
     }
     
+    let history = &history[history.len().saturating_sub(max_points)..];
     let max_value = history.iter().map(|(_, v)| *v).fold(0.0, f64::max).max(1.0);
     
     history
         .iter()
-        .take(max_points)
         .map(|(_, v)| ((v / max_value) * 64.0) as u64)
         .collect()
 }
@@ -58,4 +168,38 @@ pub fn create_device_status_indicator(is_connected: bool, is_active: bool) -> Sp
     } else {
         Span::styled("●", Style::default().fg(WARNING_COLOR))
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_count_grouping_boundaries() {
+        let comma = Some(',');
+        assert_eq!(format_count_with(0, comma), "0");
+        assert_eq!(format_count_with(999, comma), "999");
+        assert_eq!(format_count_with(1_000, comma), "1,000");
+        assert_eq!(format_count_with(999_999, comma), "999,999");
+        assert_eq!(format_count_with(1_000_000, comma), "1,000,000");
+        assert_eq!(format_count_with(4_234_567_890, comma), "4,234,567,890");
+        assert_eq!(format_count_with(u64::MAX, comma), "18,446,744,073,709,551,615");
+        assert_eq!(format_count_with(4_234_567_890, Some('.')), "4.234.567.890");
+        assert_eq!(format_count_with(4_234_567_890, None), "4234567890");
+    }
+    
+    #[test]
+    fn test_thousands_separator_config() {
+        assert_eq!(thousands_separator_from_config(","), (Some(','), Vec::new()));
+        assert_eq!(thousands_separator_from_config(""), (None, Vec::new()));
+        assert_eq!(thousands_separator_from_config("'").0, Some('\''));
+        assert_eq!(thousands_separator_from_config(", ").1.len(), 1);
+        
+        assert_eq!(locale_thousands_separator("de_DE.UTF-8"), '.');
+        assert_eq!(locale_thousands_separator("de_CH.UTF-8"), '\'');
+        assert_eq!(locale_thousands_separator("fr_FR@euro"), ' ');
+        assert_eq!(locale_thousands_separator("en_US.UTF-8"), ',');
+        assert_eq!(locale_thousands_separator("C"), ',');
+        assert_eq!(locale_thousands_separator(""), ',');
+    }
+}
@@ -0,0 +1,162 @@
+use crate::config::FilterConfig;
+use crate::device::DeviceKey;
+
+/// Bus, device and endpoint allowlist checked on raw capture records
+/// usbmon itself can't filter, so the reader looks at just the address of
+/// each record (bytes 10-13 of a binary header, the address field of a text
+/// line) and drops anything not listed before it is parsed or its payload
+/// copied. Each non-empty list must match; empty lists admit everything.
+/// Readers are only opened for the buses a filter can match.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CaptureFilter {
+    buses: Vec<u8>,
+    devices: Vec<DeviceKey>,
+    /// Endpoint numbers, matched in either direction
+    endpoints: Vec<u8>,
+}
+
+impl CaptureFilter {
+    /// Build from the `[filter]` section, with warnings for entries that are skipped
+    pub fn from_config(config: &FilterConfig) -> (Self, Vec<String>) {
+        let mut warnings = Vec::new();
+        let devices = config.devices.iter()
+            .filter_map(|value| match value.trim().parse::<DeviceKey>() {
+                Ok(key) => Some(key),
+                Err(e) => {
+                    warnings.push(format!("Ignoring devices entry '{}': {}", value, e));
+                    None
+                }
+            })
+            .collect();
+        let endpoints = config.endpoints.iter()
+            .copied()
+            .filter(|&endpoint| {
+                let valid = endpoint < 16;
+                if !valid {
+                    warnings.push(format!("Ignoring endpoints entry {}: endpoint numbers run 0-15", endpoint));
+                }
+                valid
+            })
+            .collect();
+        
+        let filter = Self {
+            buses: config.buses.clone(),
+            devices,
+            endpoints,
+        };
+        (filter, warnings)
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.buses.is_empty() && self.devices.is_empty() && self.endpoints.is_empty()
+    }
+    
+    pub fn admits_bus(&self, bus_id: u8) -> bool {
+        (self.buses.is_empty() || self.buses.contains(&bus_id))
+            && (self.devices.is_empty() || self.devices.iter().any(|key| key.bus_id() == bus_id))
+    }
+    
+    /// Whether traffic to `endpoint` (an address; the direction bit is ignored) passes
+    pub fn admits(&self, bus_id: u8, device_id: u8, endpoint: u8) -> bool {
+        self.admits_bus(bus_id)
+            && (self.devices.is_empty() || self.devices.contains(&DeviceKey(bus_id, device_id)))
+            && (self.endpoints.is_empty() || self.endpoints.contains(&(endpoint & 0x0f)))
+    }
+    
+    /// Check the address bytes of a binary header without parsing the rest
+    pub fn admits_binary_header(&self, header: &[u8]) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+        let Some(&[endpoint, device_id, bus_low, bus_high]) = header.get(10..14) else {
+            return true;
+        };
+        bus_high == 0 && self.admits(bus_low, device_id, endpoint)
+    }
+    
+    /// Check the address field of a text line ("Bi:1:002:1") without parsing
+    /// the rest; a line whose address can't be read is left to the parser
+    pub fn admits_text_line(&self, line: &str) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+        let Some(address) = line.split_whitespace().nth(3) else {
+            return true;
+        };
+        let mut fields = address.split(':').skip(1).map(|field| field.parse::<u8>().ok());
+        match (fields.next().flatten(), fields.next().flatten(), fields.next().flatten()) {
+            (Some(bus_id), Some(device_id), Some(endpoint)) => self.admits(bus_id, device_id, endpoint),
+            _ => true,
+        }
+    }
+    
+    /// The buses among `buses` a reader is worth opening for
+    /// Bus 0, usbmon's node for all buses, is only kept when the filter
+    /// doesn't narrow the buses or none of the ones it names has its own node.
+    pub fn capture_buses(&self, buses: &[u8]) -> Vec<u8> {
+        let named: Vec<u8> = buses.iter().copied().filter(|&bus_id| bus_id != 0 && self.admits_bus(bus_id)).collect();
+        let narrowed = !self.buses.is_empty() || !self.devices.is_empty();
+        if narrowed && !named.is_empty() {
+            named
+        } else if buses.contains(&0) {
+            // Reading the all-buses node alongside the individual buses would
+            // count every packet twice
+            vec![0]
+        } else {
+            buses.to_vec()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn filter(buses: &[u8], devices: &[&str], endpoints: &[u8]) -> CaptureFilter {
+        let config = FilterConfig {
+            buses: buses.to_vec(),
+            devices: devices.iter().map(|device| device.to_string()).collect(),
+            endpoints: endpoints.to_vec(),
+            ..FilterConfig::default()
+        };
+        let (filter, warnings) = CaptureFilter::from_config(&config);
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        filter
+    }
+    
+    #[test]
+    fn test_matches_address_fields_only() {
+        let device = filter(&[], &["2:4"], &[1]);
+        assert!(device.admits_text_line("ffff880000000001 100 S Bo:2:004:1 -115 4 = 01020304"));
+        assert!(device.admits_text_line("ffff880000000002 200 C Bi:2:004:1 0 4 = 01020304"));
+        assert!(!device.admits_text_line("ffff880000000003 300 C Bi:2:004:2 0 4 = 01020304"));
+        assert!(!device.admits_text_line("ffff880000000004 400 C Bi:2:005:1 0 4 = 01020304"));
+        assert!(!device.admits_text_line("ffff880000000005 500 C Bi:3:004:1 0 4 = 01020304"));
+        // Not an address: the parser gets to reject it
+        assert!(device.admits_text_line("garbage"));
+        
+        let mut header = [0u8; 64];
+        header[10] = 0x81;
+        header[11] = 4;
+        header[12] = 2;
+        assert!(device.admits_binary_header(&header));
+        header[10] = 0x02;
+        assert!(!device.admits_binary_header(&header));
+        assert!(CaptureFilter::default().admits_binary_header(&header));
+    }
+    
+    #[test]
+    fn test_capture_buses_follow_the_filter() {
+        let available = [0, 1, 2, 3];
+        assert_eq!(CaptureFilter::default().capture_buses(&available), vec![0]);
+        assert_eq!(CaptureFilter::default().capture_buses(&[1, 2]), vec![1, 2]);
+        assert_eq!(filter(&[2, 3], &[], &[]).capture_buses(&available), vec![2, 3]);
+        assert_eq!(filter(&[], &["3:7"], &[]).capture_buses(&available), vec![3]);
+        // Only the all-buses node can see bus 5
+        assert_eq!(filter(&[5], &[], &[]).capture_buses(&available), vec![0]);
+        assert_eq!(filter(&[], &[], &[1]).capture_buses(&available), vec![0]);
+        
+        let config = FilterConfig { devices: vec!["usb".to_string()], endpoints: vec![16], ..FilterConfig::default() };
+        assert_eq!(CaptureFilter::from_config(&config).1.len(), 2);
+    }
+}
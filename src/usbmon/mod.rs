@@ -1,11 +1,19 @@
 use std::fs;
-use std::path::Path;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use anyhow::{Result, anyhow};
 use log::{info, warn, debug};
 
+use crate::config::paths::SystemPaths;
+use crate::logging::decorated;
+
+pub mod capture_filter;
 pub mod reader;
 pub mod parser;
+pub mod permissions;
+pub mod ring;
+pub mod verify;
 
 #[derive(Debug, Clone)]
 pub struct UsbmonStatus {
@@ -15,133 +23,196 @@ pub struct UsbmonStatus {
     pub available_buses: Vec<u8>,
 }
 
-pub fn check_usbmon_status() -> Result<UsbmonStatus> {
+pub const DEFAULT_DEBUGFS_PATH: &str = "/sys/kernel/debug";
+
+/// Environment variable overriding the usbmon directory when `--usbmon-path` isn't given
+pub const USBMON_PATH_ENV: &str = "USBTOP_USBMON_PATH";
+
+/// The usbmon directory inside a debugfs mount
+pub fn usbmon_dir(debugfs_path: &Path) -> PathBuf {
+    debugfs_path.join("usb").join("usbmon")
+}
+
+/// Probe usbmon support at the configured debugfs and usbmon locations
+/// Missing or unreadable `/proc` and `/sys` entries (common in containers) are
+/// reported as unavailable rather than failing, so the caller decides what to do
+pub fn check_usbmon_status(paths: &SystemPaths) -> UsbmonStatus {
     debug!("Checking usbmon kernel module status");
     
-    let module_loaded = is_usbmon_module_loaded()?;
-    let debugfs_mounted = is_debugfs_mounted()?;
-    let usbmon_available = debugfs_mounted && check_usbmon_debugfs_exists()?;
+    let module_loaded = is_usbmon_module_loaded();
+    let usbmon_available = check_usbmon_debugfs_exists(&paths.usbmon);
+    // A bind-mounted usbmon directory works even when /proc/mounts doesn't show debugfs
+    let debugfs_mounted = usbmon_available || is_debugfs_mounted(&paths.debugfs);
     let available_buses = if usbmon_available {
-        get_available_buses()?
+        get_available_buses(&paths.usbmon)
     } else {
         Vec::new()
     };
 
-    Ok(UsbmonStatus {
+    UsbmonStatus {
         module_loaded,
         debugfs_mounted,
         usbmon_available,
         available_buses,
-    })
+    }
+}
+
+/// Best-effort guess that we're inside a container, where usbmon has to be
+/// loaded on the host and its directory mounted in
+pub fn is_running_in_container() -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        Path::new("/.dockerenv").exists()
+            || Path::new("/run/.containerenv").exists()
+            || std::env::var_os("container").is_some()
+    }
+    
+    #[cfg(not(target_os = "linux"))]
+    {
+        false
+    }
+}
+
+/// Running kernel release, e.g. "6.8.0-45-generic"; None off Linux
+pub fn kernel_version() -> Option<String> {
+    #[cfg(target_os = "linux")]
+    {
+        fs::read_to_string("/proc/sys/kernel/osrelease")
+            .ok()
+            .map(|release| release.trim().to_string())
+    }
+    
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
 }
 
-fn is_usbmon_module_loaded() -> Result<bool> {
+fn is_usbmon_module_loaded() -> bool {
     #[cfg(target_os = "linux")]
     {
-        let modules = fs::read_to_string("/proc/modules")?;
-        Ok(modules.lines().any(|line| line.starts_with("usbmon ")))
+        match fs::read_to_string("/proc/modules") {
+            Ok(modules) => modules.lines().any(|line| line.starts_with("usbmon ")),
+            Err(e) => {
+                debug!("Cannot read /proc/modules: {}", e);
+                false
+            }
+        }
     }
     
     #[cfg(any(target_os = "freebsd", target_os = "openbsd", target_os = "netbsd"))]
     {
         // BSD systems may have USB monitoring built-in or use different mechanisms
-        let output = Command::new("kldstat")
-            .output()
-            .map_err(|e| anyhow!("Failed to run kldstat: {}", e))?;
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        Ok(stdout.contains("usb") || stdout.contains("ugen"))
+        match Command::new("kldstat").output() {
+            Ok(output) => {
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                stdout.contains("usb") || stdout.contains("ugen")
+            }
+            Err(e) => {
+                debug!("Failed to run kldstat: {}", e);
+                false
+            }
+        }
     }
     
     #[cfg(target_os = "macos")]
     {
         // macOS doesn't have usbmon, but we can still detect USB via system_profiler
         warn!("macOS does not support usbmon kernel module");
-        Ok(false)
+        false
     }
 }
 
-fn is_debugfs_mounted() -> Result<bool> {
+fn is_debugfs_mounted(debugfs_path: &Path) -> bool {
     #[cfg(target_os = "linux")]
     {
-        let mounts = fs::read_to_string("/proc/mounts")?;
-        Ok(mounts.lines().any(|line| {
-            line.contains("debugfs") && line.contains("/sys/kernel/debug")
-        }))
+        match fs::read_to_string("/proc/mounts") {
+            Ok(mounts) => mounts.lines().any(|line| {
+                let mut fields = line.split_whitespace().skip(1);
+                fields.next().map(Path::new) == Some(debugfs_path) && fields.next() == Some("debugfs")
+            }),
+            Err(e) => {
+                debug!("Cannot read /proc/mounts: {}", e);
+                false
+            }
+        }
     }
     
     #[cfg(not(target_os = "linux"))]
     {
         // Non-Linux systems use different paths
-        Ok(true)
+        let _ = debugfs_path;
+        true
     }
 }
 
-fn check_usbmon_debugfs_exists() -> Result<bool> {
+fn check_usbmon_debugfs_exists(usbmon_path: &Path) -> bool {
     #[cfg(target_os = "linux")]
     {
-        Ok(Path::new("/sys/kernel/debug/usb/usbmon").exists())
+        usbmon_path.is_dir()
     }
     
     #[cfg(any(target_os = "freebsd", target_os = "openbsd", target_os = "netbsd"))]
     {
         // BSD systems may use /dev/ugen* or similar
-        Ok(Path::new("/dev").exists())
+        let _ = usbmon_path;
+        Path::new("/dev").exists()
     }
     
     #[cfg(target_os = "macos")]
     {
-        Ok(false)
+        let _ = usbmon_path;
+        false
     }
 }
 
-fn get_available_buses() -> Result<Vec<u8>> {
+fn get_available_buses(usbmon_path: &Path) -> Vec<u8> {
     #[cfg(target_os = "linux")]
     {
         let mut buses = Vec::new();
         
-        if let Ok(entries) = fs::read_dir("/sys/kernel/debug/usb/usbmon") {
+        if let Ok(entries) = fs::read_dir(usbmon_path) {
             for entry in entries {
                 if let Ok(entry) = entry {
                     let filename = entry.file_name();
                     let filename_str = filename.to_string_lossy();
                     
                     // Look for files like "0u", "1u", "2u", etc.
-                    if filename_str.ends_with('u') && filename_str.len() >= 2 {
-                        if let Ok(bus_num) = filename_str[0..filename_str.len()-1].parse::<u8>() {
-                            buses.push(bus_num);
-                        }
+                    if let Some(bus_num) = filename_str.strip_suffix('u')
+                        .and_then(|bus| bus.parse::<u8>().ok())
+                    {
+                        buses.push(bus_num);
                     }
                 }
             }
         }
         
         buses.sort();
-        Ok(buses)
+        buses
     }
     
     #[cfg(not(target_os = "linux"))]
     {
         // For non-Linux systems, we'll implement bus discovery differently
-        Ok(vec![0])
+        let _ = usbmon_path;
+        vec![0]
     }
 }
 
 pub fn prompt_user_to_load_module() -> Result<bool> {
-    use std::io::{self, Write};
-    
-    println!("❌ usbmon kernel module is not loaded!");
-    println!();
-    println!("usbtop-ng requires the usbmon kernel module to monitor USB traffic.");
-    println!("This module is safe and provides read-only access to USB bus activity.");
-    println!();
-    println!("To load the module, run:");
-    println!("  sudo modprobe usbmon");
-    println!();
-    println!("You may also need to mount debugfs if not already mounted:");
-    println!("  sudo mount -t debugfs none /sys/kernel/debug");
-    println!();
-    print!("Would you like usbtop-ng to attempt loading the module? (y/N): ");
-    io::stdout().flush()?;
+    eprintln!("{}", decorated("❌", "usbmon kernel module is not loaded!"));
+    eprintln!();
+    eprintln!("usbtop-ng requires the usbmon kernel module to monitor USB traffic.");
+    eprintln!("This module is safe and provides read-only access to USB bus activity.");
+    eprintln!();
+    eprintln!("To load the module, run:");
+    eprintln!("  sudo modprobe usbmon");
+    eprintln!();
+    eprintln!("You may also need to mount debugfs if not already mounted:");
+    eprintln!("  sudo mount -t debugfs none /sys/kernel/debug");
+    eprintln!();
+    eprint!("Would you like usbtop-ng to attempt loading the module? (y/N): ");
+    io::stderr().flush()?;
     
     let mut input = String::new();
     io::stdin().read_line(&mut input)?;
@@ -150,7 +221,7 @@ pub fn prompt_user_to_load_module() -> Result<bool> {
     Ok(response == "y" || response == "yes")
 }
 
-pub fn attempt_load_usbmon() -> Result<()> {
+pub fn attempt_load_usbmon(debugfs_path: &Path) -> Result<()> {
     info!("Attempting to load usbmon kernel module");
     
     #[cfg(target_os = "linux")]
@@ -167,10 +238,11 @@ pub fn attempt_load_usbmon() -> Result<()> {
         }
         
         // Try to mount debugfs if needed
-        if !is_debugfs_mounted()? {
-            info!("Attempting to mount debugfs");
+        if !is_debugfs_mounted(debugfs_path) {
+            info!("Attempting to mount debugfs at {}", debugfs_path.display());
             let output = Command::new("sudo")
-                .args(&["mount", "-t", "debugfs", "none", "/sys/kernel/debug"])
+                .args(["mount", "-t", "debugfs", "none"])
+                .arg(debugfs_path)
                 .output()
                 .map_err(|e| anyhow!("Failed to mount debugfs: {}", e))?;
             
@@ -185,36 +257,89 @@ pub fn attempt_load_usbmon() -> Result<()> {
     
     #[cfg(not(target_os = "linux"))]
     {
+        let _ = debugfs_path;
         Err(anyhow!("Automatic module loading not supported on this platform"))
     }
 }
 
-pub fn print_platform_instructions() {
+/// usbmon can't be loaded from inside a container; it has to come from the host
+pub fn print_container_hint(out: &mut dyn Write) -> io::Result<()> {
+    writeln!(out, "{}", decorated("📦", "Running inside a container:"))?;
+    writeln!(out, "1. Load usbmon on the host (modprobe doesn't work in the container):")?;
+    writeln!(out, "   sudo modprobe usbmon")?;
+    writeln!(out, "2. Mount the host's debugfs (or just its usbmon directory) into the container:")?;
+    writeln!(out, "   docker run --privileged -v /sys/kernel/debug:/sys/kernel/debug ...")?;
+    writeln!(out, "3. If usbmon is mounted elsewhere, point usbtop-ng at it:")?;
+    writeln!(out, "   usbtop-ng --usbmon-path /mnt/usbmon   (or {}=/mnt/usbmon)", USBMON_PATH_ENV)?;
+    Ok(())
+}
+
+/// Written to stdout for `--setup` and to stderr when startup fails
+pub fn print_platform_instructions(out: &mut dyn Write) -> io::Result<()> {
     #[cfg(target_os = "linux")]
     {
-        println!("📋 Linux Setup Instructions:");
-        println!("1. Load the usbmon kernel module:");
-        println!("   sudo modprobe usbmon");
-        println!("2. Ensure debugfs is mounted:");
-        println!("   sudo mount -t debugfs none /sys/kernel/debug");
-        println!("3. Run usbtop-ng as root or add your user to the appropriate group");
+        writeln!(out, "{}", decorated("📋", "Linux Setup Instructions:"))?;
+        writeln!(out, "1. Load the usbmon kernel module:")?;
+        writeln!(out, "   sudo modprobe usbmon")?;
+        writeln!(out, "2. Ensure debugfs is mounted:")?;
+        writeln!(out, "   sudo mount -t debugfs none /sys/kernel/debug")?;
+        writeln!(out, "3. Run usbtop-ng as root or add your user to the appropriate group")?;
+        if is_running_in_container() {
+            writeln!(out)?;
+            print_container_hint(out)?;
+        }
     }
     
     #[cfg(any(target_os = "freebsd", target_os = "openbsd", target_os = "netbsd"))]
     {
-        println!("📋 BSD Setup Instructions:");
-        println!("1. Ensure USB support is enabled in kernel");
-        println!("2. Check available USB devices with: usbconfig");
-        println!("3. Run usbtop-ng with appropriate permissions");
+        writeln!(out, "{}", decorated("📋", "BSD Setup Instructions:"))?;
+        writeln!(out, "1. Ensure USB support is enabled in kernel")?;
+        writeln!(out, "2. Check available USB devices with: usbconfig")?;
+        writeln!(out, "3. Run usbtop-ng with appropriate permissions")?;
     }
     
     #[cfg(target_os = "macos")]
     {
-        println!("📋 macOS Setup Instructions:");
-        println!("⚠️  Note: macOS does not have usbmon equivalent");
-        println!("Consider using alternative tools like:");
-        println!("- USB Prober (part of Additional Tools for Xcode)");
-        println!("- system_profiler SPUSBDataType");
-        println!("- ioreg -p IOUSB");
+        writeln!(out, "{}", decorated("📋", "macOS Setup Instructions:"))?;
+        writeln!(out, "{}", decorated("⚠️", "Note: macOS does not have usbmon equivalent"))?;
+        writeln!(out, "Consider using alternative tools like:")?;
+        writeln!(out, "- USB Prober (part of Additional Tools for Xcode)")?;
+        writeln!(out, "- system_profiler SPUSBDataType")?;
+        writeln!(out, "- ioreg -p IOUSB")?;
     }
-}
\ No newline at end of file
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_missing_usbmon_dir_is_unavailable() {
+        let root = std::env::temp_dir().join(format!("usbtop-ng-usbmon-{}", std::process::id()));
+        let missing = root.join("missing");
+        let status = check_usbmon_status(&SystemPaths {
+            debugfs: missing.clone(),
+            usbmon: missing,
+            sysfs: root.clone(),
+        });
+        assert!(!status.usbmon_available);
+        assert!(status.available_buses.is_empty());
+        
+        fs::create_dir_all(&root).unwrap();
+        for name in ["0u", "1s", "1t", "1u", "3u"] {
+            fs::write(root.join(name), "").unwrap();
+        }
+        let status = check_usbmon_status(&SystemPaths {
+            debugfs: root.clone(),
+            usbmon: root.clone(),
+            sysfs: root.clone(),
+        });
+        assert!(status.usbmon_available);
+        assert!(status.debugfs_mounted);
+        assert_eq!(status.available_buses, vec![0, 1, 3]);
+        
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -9,6 +9,59 @@ pub enum UrbType {
     Error,        // 'E' - Error
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TransferType {
+    Isochronous,  // 'Z' / 0
+    Interrupt,    // 'I' / 1
+    Control,      // 'C' / 2
+    Bulk,         // 'B' / 3
+}
+
+impl TransferType {
+    pub fn from_text_char(c: char) -> Option<Self> {
+        match c {
+            'Z' => Some(TransferType::Isochronous),
+            'I' => Some(TransferType::Interrupt),
+            'C' => Some(TransferType::Control),
+            'B' => Some(TransferType::Bulk),
+            _ => None,
+        }
+    }
+    
+    pub fn text_char(&self) -> char {
+        match self {
+            TransferType::Isochronous => 'Z',
+            TransferType::Interrupt => 'I',
+            TransferType::Control => 'C',
+            TransferType::Bulk => 'B',
+        }
+    }
+    
+    pub fn from_binary(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(TransferType::Isochronous),
+            1 => Some(TransferType::Interrupt),
+            2 => Some(TransferType::Control),
+            3 => Some(TransferType::Bulk),
+            _ => None,
+        }
+    }
+    
+    pub fn name(&self) -> &'static str {
+        match self {
+            TransferType::Isochronous => "Isochronous",
+            TransferType::Interrupt => "Interrupt",
+            TransferType::Control => "Control",
+            TransferType::Bulk => "Bulk",
+        }
+    }
+    
+    /// Interrupt and isochronous endpoints are polled on a fixed schedule
+    pub fn is_periodic(&self) -> bool {
+        matches!(self, TransferType::Interrupt | TransferType::Isochronous)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum UsbSpeed {
     Low,         // 1.5 Mbps
@@ -20,17 +73,32 @@ pub enum UsbSpeed {
 }
 
 impl UsbSpeed {
+    /// Parse a speed as sysfs reports it
+    /// The `speed` attribute gives the link rate in Mbps, where 20000 is
+    /// Gen 2x2 and counted as SuperSpeedPlus. Other files and some kernels use
+    /// names instead ("high-speed", "SuperSpeedPlus", "super"), matched
+    /// regardless of case, dashes and spaces.
     pub fn from_speed_str(speed: &str) -> Self {
-        match speed {
-            "1.5" => UsbSpeed::Low,
-            "12" => UsbSpeed::Full,
-            "480" => UsbSpeed::High,
-            "5000" => UsbSpeed::SuperSpeed,
-            "10000" | "20000" => UsbSpeed::SuperSpeedPlus,
+        let name: String = speed.trim()
+            .chars()
+            .filter(|c| !matches!(c, '-' | '_' | ' '))
+            .collect::<String>()
+            .to_ascii_lowercase();
+        match name.as_str() {
+            "1.5" | "low" | "lowspeed" => UsbSpeed::Low,
+            "12" | "full" | "fullspeed" => UsbSpeed::Full,
+            "480" | "high" | "highspeed" => UsbSpeed::High,
+            "5000" | "super" | "superspeed" => UsbSpeed::SuperSpeed,
+            "10000" | "20000" | "superplus" | "superspeedplus" => UsbSpeed::SuperSpeedPlus,
             _ => UsbSpeed::Unknown,
         }
     }
     
+    /// Whether this is a USB 3 link rather than one of the USB 2 speeds
+    pub fn is_superspeed(&self) -> bool {
+        matches!(self, UsbSpeed::SuperSpeed | UsbSpeed::SuperSpeedPlus)
+    }
+    
     pub fn to_mbps(&self) -> f64 {
         match self {
             UsbSpeed::Low => 1.5,
@@ -81,24 +149,95 @@ impl UsbSpeed {
     }
 }
 
+/// Default upper bound on a plausible URB length
+/// Real transfers stay well below this; larger claims come from a misparsed header
+pub const DEFAULT_MAX_DATA_LENGTH: u32 = 16 * 1024 * 1024;
+
+/// bEndpointAddress bit 7, set for IN (device to host) endpoints
+/// Both capture formats describe direction with it: the binary header keeps
+/// the whole address, and the text format's `i`/`o` letter prints this bit.
+pub const ENDPOINT_DIR_IN: u8 = 0x80;
+
+/// Callback statuses of URBs that were cancelled rather than failed
+/// (-ENOENT, -ECONNRESET, -ESHUTDOWN); drivers do this routinely
+const CANCELLED_STATUSES: [i32; 3] = [-2, -104, -108];
+
+/// Split an endpoint address into its number and direction (true = IN)
+pub fn split_endpoint_address(address: u8) -> (u8, bool) {
+    (address & !ENDPOINT_DIR_IN, address & ENDPOINT_DIR_IN != 0)
+}
+
 #[derive(Debug, Clone)]
 pub struct UsbPacket {
     pub timestamp: DateTime<Utc>,
+    /// Kernel capture time in microseconds; see `TimestampUnwrapper` for text captures
+    pub timestamp_us: u64,
     pub urb_tag: String,
     pub urb_type: UrbType,
+    pub transfer_type: TransferType,
     pub bus_id: u8,
     pub device_id: u8,
+    /// Endpoint number without the direction bit
     pub endpoint: u8,
-    pub direction: bool, // true = IN (device->host), false = OUT (host->device)
+    /// Endpoint direction: true = IN (device->host), false = OUT (host->device)
+    /// This is the pipe's direction, the same in both formats; for control
+    /// transfers the data stage can differ, see `data_direction`.
+    pub direction: bool,
+    /// Bytes the URB actually moved; what bandwidth is accounted from
     pub data_length: u32,
+    /// Bytes of that payload usbmon captured, which may be fewer: the text
+    /// format prints at most 32 and the binary format stops at its snap length
+    pub captured_length: u32,
     pub status: i32,
+    /// Polling interval of an interrupt or isochronous URB, in frames at low
+    /// and full speed and microframes above; None for other transfer types
+    pub interval: Option<u32>,
     pub setup_packet: Option<Vec<u8>>,
     pub data: Option<Vec<u8>>,
 }
 
 impl UsbPacket {
+    /// Endpoint address with the direction bit, e.g. 0x81 for EP1 IN
+    pub fn endpoint_address(&self) -> u8 {
+        self.endpoint | if self.direction { ENDPOINT_DIR_IN } else { 0 }
+    }
+    
+    /// Whether this event carries bytes that crossed the bus
+    /// Each URB is counted once, on the side that moves its data: OUT data
+    /// at submission, IN data at the callback. An IN submission only states
+    /// the buffer size; the callback's length is what the device actually
+    /// returned, which a short packet or a zero-length packet can cut short.
     pub fn is_data_packet(&self) -> bool {
-        self.data_length > 0 && matches!(self.urb_type, UrbType::Submission | UrbType::Callback)
+        self.data_length > 0 && match self.urb_type {
+            UrbType::Submission => !self.data_direction(),
+            UrbType::Callback => self.data_direction(),
+            UrbType::Error => false,
+        }
+    }
+    
+    /// Whether the URB failed: an error event, or a completion with an error
+    /// status other than a routine cancellation (e.g. -71 EPROTO, -32 EPIPE)
+    pub fn is_failure(&self) -> bool {
+        match self.urb_type {
+            UrbType::Error => true,
+            UrbType::Callback => self.status != 0 && !CANCELLED_STATUSES.contains(&self.status),
+            UrbType::Submission => false,
+        }
+    }
+    
+    /// Whether the claimed length is small enough to be a real transfer
+    pub fn has_plausible_length(&self, max_data_length: u32) -> bool {
+        self.data_length <= max_data_length
+    }
+    
+    /// Direction of the data stage: true = IN (device->host)
+    /// For control transfers the setup packet's bmRequestType bit 7 decides;
+    /// the endpoint direction bit only applies to the other transfer types.
+    pub fn data_direction(&self) -> bool {
+        match (&self.transfer_type, self.setup_packet.as_deref()) {
+            (TransferType::Control, Some([request_type, ..])) => request_type & 0x80 != 0,
+            _ => self.direction,
+        }
     }
     
     pub fn bandwidth_bytes(&self) -> u32 {
@@ -110,13 +249,44 @@ impl UsbPacket {
     }
 }
 
+/// Text-format timestamps are printed from a 32-bit microsecond counter and
+/// wrap roughly every 71 minutes
+pub const TEXT_TIMESTAMP_WRAP: u64 = 1 << 32;
+
+/// Turns wrapping text-format timestamps into a monotonic microsecond counter
+/// Keep one per reader: a large backward jump is taken as a wrap and adds
+/// `TEXT_TIMESTAMP_WRAP` to every later timestamp. Small backward steps (events
+/// logged slightly out of order) are passed through unchanged.
+#[derive(Debug, Clone, Default)]
+pub struct TimestampUnwrapper {
+    last: Option<u64>,
+    offset: u64,
+}
+
+impl TimestampUnwrapper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    pub fn unwrap(&mut self, raw_us: u64) -> u64 {
+        if let Some(last) = self.last {
+            if raw_us < last && last - raw_us > TEXT_TIMESTAMP_WRAP / 2 {
+                self.offset += TEXT_TIMESTAMP_WRAP;
+            }
+        }
+        self.last = Some(raw_us);
+        raw_us + self.offset
+    }
+}
+
 pub fn parse_usbmon_text_line(line: &str) -> Result<UsbPacket> {
     // usbmon text format:
     // URB_TAG TIMESTAMP EVENT_TYPE ADDR:EP:D S URB_STATUS LENGTH DATA...
     // Example: ffff88007c861a00 2389264913 S Bo:1:001:0 -115 31 = 55534243 ...
     
+    // A zero-length callback ends at the length column, with no data tag
     let parts: Vec<&str> = line.split_whitespace().collect();
-    if parts.len() < 7 {
+    if parts.len() < 6 {
         return Err(anyhow!("Invalid usbmon text line format: too few fields"));
     }
     
@@ -141,51 +311,225 @@ pub fn parse_usbmon_text_line(line: &str) -> Result<UsbPacket> {
         return Err(anyhow!("Invalid address format: {}", parts[3]));
     }
     
-    let transfer_type = &addr_parts[0][0..1]; // B=Bulk, C=Control, I=Interrupt, Z=Isochronous
-    let direction_char = &addr_parts[0][1..2]; // i=IN, o=OUT
-    let direction = direction_char == "i";
+    // Walk chars rather than slicing bytes so corrupted multibyte input can't panic
+    let mut type_chars = addr_parts[0].chars();
+    let transfer_char = type_chars.next() // B=Bulk, C=Control, I=Interrupt, Z=Isochronous
+        .ok_or_else(|| anyhow!("Missing transfer type in address: {}", parts[3]))?;
+    let transfer_type = TransferType::from_text_char(transfer_char)
+        .ok_or_else(|| anyhow!("Invalid transfer type: {}", transfer_char))?;
+    let direction = match type_chars.next() { // i=IN, o=OUT
+        Some('i') => true,
+        Some('o') => false,
+        Some(c) => return Err(anyhow!("Invalid direction: {}", c)),
+        None => return Err(anyhow!("Missing direction in address: {}", parts[3])),
+    };
     
     let bus_id: u8 = addr_parts[1].parse()
         .map_err(|_| anyhow!("Invalid bus ID: {}", addr_parts[1]))?;
     let device_id: u8 = addr_parts[2].parse()
         .map_err(|_| anyhow!("Invalid device ID: {}", addr_parts[2]))?;
-    let endpoint: u8 = addr_parts[3].parse()
-        .map_err(|_| anyhow!("Invalid endpoint: {}", addr_parts[3]))?;
+    // The number only; binary headers can't express one that collides with the direction bit
+    let endpoint: u8 = addr_parts[3].parse().ok()
+        .filter(|endpoint| endpoint & ENDPOINT_DIR_IN == 0)
+        .ok_or_else(|| anyhow!("Invalid endpoint: {}", addr_parts[3]))?;
     
-    // Parse status
-    let status: i32 = parts[4].parse()
-        .map_err(|_| anyhow!("Invalid status: {}", parts[4]))?;
+    // A control submission carries "s" and the five setup words in place of the status
+    let (status, interval, setup_packet, rest) = if parts[4] == "s" {
+        if parts.len() < 11 {
+            return Err(anyhow!("Invalid usbmon text line format: truncated setup packet"));
+        }
+        (-EINPROGRESS, None, Some(parse_text_setup(&parts[5..10])?), &parts[10..])
+    } else {
+        // Interrupt and isochronous URBs report "status:interval", isochronous
+        // ones followed by the start frame and, on completion, the error count
+        let mut fields = parts[4].split(':');
+        let status: i32 = fields.next().and_then(|field| field.parse().ok())
+            .ok_or_else(|| anyhow!("Invalid status: {}", parts[4]))?;
+        let interval: Option<u32> = fields.next()
+            .map(|field| field.parse().map_err(|_| anyhow!("Invalid interval: {}", parts[4])))
+            .transpose()?;
+        let mut rest = &parts[5..];
+        // Then the number of isochronous frame descriptors and the first few of them
+        if transfer_type == TransferType::Isochronous && interval.is_some() {
+            let count: usize = rest[0].parse()
+                .map_err(|_| anyhow!("Invalid isochronous descriptor count: {}", rest[0]))?;
+            rest = rest.get(1 + count.min(TEXT_ISO_DESCRIPTORS)..)
+                .filter(|rest| !rest.is_empty())
+                .ok_or_else(|| anyhow!("Invalid usbmon text line format: truncated isochronous descriptors"))?;
+        }
+        (status, interval, None, rest)
+    };
     
-    // Parse data length
-    let data_length: u32 = parts[5].parse()
-        .map_err(|_| anyhow!("Invalid data length: {}", parts[5]))?;
+    // The length column is what the URB moved, even when fewer data words follow
+    let data_length: u32 = rest[0].parse()
+        .map_err(|_| anyhow!("Invalid data length: {}", rest[0]))?;
     
-    // Parse data if present (parts[6] should be '=' if data follows)
-    let data = if parts.len() > 7 && parts[6] == "=" {
-        Some(parse_hex_data(&parts[7..]).unwrap_or_default())
+    // Parse data if present (the data tag should be '=' if data follows)
+    let data = if rest.len() > 2 && rest[1] == "=" {
+        Some(parse_hex_data(&rest[2..]).unwrap_or_default())
     } else {
         None
     };
+    let captured_length = data.as_ref()
+        .map_or(0, |data| (data.len() as u32).min(data_length));
     
     Ok(UsbPacket {
         timestamp,
+        timestamp_us,
         urb_tag,
         urb_type,
+        transfer_type,
         bus_id,
         device_id,
         endpoint,
         direction,
         data_length,
+        captured_length,
         status,
-        setup_packet: None, // TODO: Parse setup packets for control transfers
+        interval,
+        setup_packet,
         data,
     })
 }
 
-pub fn parse_usbmon_binary_packet(buffer: &[u8]) -> Result<UsbPacket> {
-    if buffer.len() < 64 {
+/// Write `packet` back out as a usbmon text line
+/// `parse_usbmon_text_line` reads the result back unchanged. Unlike the
+/// kernel's text interface the whole captured payload is written rather than
+/// the first 32 bytes, so a dump can be replayed with `--stdin` in full.
+pub fn format_usbmon_text_line(packet: &UsbPacket) -> String {
+    let event = match packet.urb_type {
+        UrbType::Submission => 'S',
+        UrbType::Callback => 'C',
+        UrbType::Error => 'E',
+    };
+    let mut line = format!(
+        "{} {} {} {}{}:{}:{:03}:{} ",
+        packet.urb_tag,
+        packet.timestamp_us,
+        event,
+        packet.transfer_type.text_char(),
+        if packet.direction { 'i' } else { 'o' },
+        packet.bus_id,
+        packet.device_id,
+        packet.endpoint,
+    );
+    
+    match packet.setup_packet.as_deref() {
+        Some(&[request_type, request, v0, v1, i0, i1, l0, l1, ..]) if packet.urb_type == UrbType::Submission => {
+            line.push_str(&format!(
+                "s {:02x} {:02x} {:04x} {:04x} {:04x}",
+                request_type,
+                request,
+                u16::from_le_bytes([v0, v1]),
+                u16::from_le_bytes([i0, i1]),
+                u16::from_le_bytes([l0, l1]),
+            ));
+        }
+        _ => match packet.interval {
+            // No start frame or descriptors are kept, so an isochronous URB lists none
+            Some(interval) if packet.transfer_type == TransferType::Isochronous => {
+                line.push_str(&format!("{}:{} 0", packet.status, interval));
+            }
+            Some(interval) => line.push_str(&format!("{}:{}", packet.status, interval)),
+            None => line.push_str(&packet.status.to_string()),
+        },
+    }
+    line.push_str(&format!(" {}", packet.data_length));
+    
+    match packet.data.as_deref().filter(|data| !data.is_empty()) {
+        Some(data) => {
+            line.push_str(" =");
+            for word in data.chunks(4) {
+                line.push(' ');
+                for byte in word {
+                    line.push_str(&format!("{:02x}", byte));
+                }
+            }
+        }
+        None if packet.urb_type == UrbType::Submission && packet.data_direction() => line.push_str(" <"),
+        None => {}
+    }
+    line
+}
+
+/// Status usbmon reports for a URB still in flight (-EINPROGRESS)
+const EINPROGRESS: i32 = 115;
+
+/// Length of a control setup packet
+const SETUP_PACKET_LEN: usize = 8;
+
+/// Isochronous frame descriptors the text format prints at most, whatever the count
+const TEXT_ISO_DESCRIPTORS: usize = 5;
+
+/// Rebuild the 8 setup bytes from the text words bmRequestType, bRequest,
+/// wValue, wIndex and wLength (e.g. "80 06 0100 0000 0012"); the 16-bit
+/// fields go back into wire (little-endian) order
+fn parse_text_setup(words: &[&str]) -> Result<Vec<u8>> {
+    let byte = |word: &str| u8::from_str_radix(word, 16)
+        .map_err(|_| anyhow!("Invalid setup byte: {}", word));
+    let word = |word: &str| u16::from_str_radix(word, 16)
+        .map_err(|_| anyhow!("Invalid setup word: {}", word));
+    
+    let mut setup = Vec::with_capacity(SETUP_PACKET_LEN);
+    setup.push(byte(words[0])?);
+    setup.push(byte(words[1])?);
+    for value in &words[2..5] {
+        setup.extend_from_slice(&word(value)?.to_le_bytes());
+    }
+    Ok(setup)
+}
+
+/// Size of a usbmon binary event header
+pub const BINARY_HEADER_LEN: usize = 64;
+
+/// Payload bytes following a binary header (`len_cap`), without validating the rest
+pub fn binary_captured_length(header: &[u8]) -> u32 {
+    match header.get(36..40) {
+        Some(bytes) => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        None => 0,
+    }
+}
+
+/// Largest errno the kernel reports as a negative URB status
+const MAX_ERRNO: i32 = 4095;
+
+/// Reject a buffer that can't be a real binary header, without building a packet
+/// Besides the URB and transfer type bytes, the bus number, status and
+/// length fields are checked, so a buffer that has lost alignment with the
+/// stream (data bytes read as a header) is an error rather than a bogus packet.
+pub fn validate_binary_header(buffer: &[u8]) -> Result<()> {
+    if buffer.len() < BINARY_HEADER_LEN {
         return Err(anyhow!("Binary packet too short: {} bytes", buffer.len()));
     }
+    if !matches!(buffer[8], b'S' | b'C' | b'E') {
+        return Err(anyhow!("Invalid URB type: {}", buffer[8] as char));
+    }
+    if TransferType::from_binary(buffer[9]).is_none() {
+        return Err(anyhow!("Invalid transfer type: {}", buffer[9]));
+    }
+    let bus_number = u16::from_le_bytes([buffer[12], buffer[13]]);
+    if u8::try_from(bus_number).is_err() {
+        return Err(anyhow!("Implausible bus number: {}", bus_number));
+    }
+    
+    // Status is 0 or a negative errno
+    let status = i32::from_le_bytes([buffer[28], buffer[29], buffer[30], buffer[31]]);
+    if !(-MAX_ERRNO..=0).contains(&status) {
+        return Err(anyhow!("Implausible URB status: {}", status));
+    }
+    
+    // usbmon never captures more than the URB carries
+    let data_length = u32::from_le_bytes([buffer[32], buffer[33], buffer[34], buffer[35]]);
+    let captured_length = binary_captured_length(buffer);
+    if captured_length > data_length {
+        return Err(anyhow!("Captured length {} exceeds URB length {}", captured_length, data_length));
+    }
+    Ok(())
+}
+
+/// Parse a binary header, rejecting anything `validate_binary_header` does
+pub fn parse_usbmon_binary_packet(buffer: &[u8]) -> Result<UsbPacket> {
+    validate_binary_header(buffer)?;
     
     // usbmon binary format (64 bytes):
     // Offset 0: urb_id (8 bytes)
@@ -201,7 +545,9 @@ pub fn parse_usbmon_binary_packet(buffer: &[u8]) -> Result<UsbPacket> {
     // Offset 28: status (4 bytes, little endian, signed)
     // Offset 32: length (4 bytes, little endian)
     // Offset 36: len_cap (4 bytes, little endian)
-    // Rest: setup packet or data
+    // Offset 40: setup packet, or isochronous error count and descriptor count (8 bytes)
+    // Offset 48: interval (4 bytes, little endian)
+    // Offset 52: start_frame, xfer_flags, ndesc (12 bytes)
     
     let urb_id = u64::from_le_bytes([
         buffer[0], buffer[1], buffer[2], buffer[3],
@@ -209,18 +555,18 @@ pub fn parse_usbmon_binary_packet(buffer: &[u8]) -> Result<UsbPacket> {
     ]);
     let urb_tag = format!("{:016x}", urb_id);
     
-    let urb_type = match buffer[8] as char {
-        'S' => UrbType::Submission,
-        'C' => UrbType::Callback,
-        'E' => UrbType::Error,
-        _ => return Err(anyhow!("Invalid URB type: {}", buffer[8] as char)),
+    let urb_type = match buffer[8] {
+        b'S' => UrbType::Submission,
+        b'C' => UrbType::Callback,
+        _ => UrbType::Error,
     };
     
-    let transfer_type = buffer[9];
-    let endpoint = buffer[10] & 0x7F; // Lower 7 bits
-    let direction = (buffer[10] & 0x80) != 0; // MSB indicates direction
+    let transfer_type = TransferType::from_binary(buffer[9])
+        .ok_or_else(|| anyhow!("Invalid transfer type: {}", buffer[9]))?;
+    let (endpoint, direction) = split_endpoint_address(buffer[10]);
     let device_id = buffer[11];
-    let bus_id = u16::from_le_bytes([buffer[12], buffer[13]]) as u8;
+    // The high byte of the bus number was checked to be zero
+    let bus_id = buffer[12];
     
     let ts_sec = u64::from_le_bytes([
         buffer[16], buffer[17], buffer[18], buffer[19],
@@ -228,25 +574,38 @@ pub fn parse_usbmon_binary_packet(buffer: &[u8]) -> Result<UsbPacket> {
     ]);
     let ts_usec = u32::from_le_bytes([buffer[24], buffer[25], buffer[26], buffer[27]]);
     
-    let timestamp = DateTime::from_timestamp(ts_sec as i64, (ts_usec * 1000) as u32)
+    let timestamp = ts_usec.checked_mul(1000)
+        .and_then(|ts_nsec| DateTime::from_timestamp(ts_sec as i64, ts_nsec))
         .unwrap_or_else(|| Utc::now());
     
     let status = i32::from_le_bytes([buffer[28], buffer[29], buffer[30], buffer[31]]);
     let data_length = u32::from_le_bytes([buffer[32], buffer[33], buffer[34], buffer[35]]);
+    let captured_length = u32::from_le_bytes([buffer[36], buffer[37], buffer[38], buffer[39]]);
+    let interval = transfer_type.is_periodic()
+        .then(|| u32::from_le_bytes([buffer[48], buffer[49], buffer[50], buffer[51]]));
     
-    // TODO: Parse setup packet and data from remaining bytes
+    // flag_setup is 0 when the header's last bytes hold a valid setup packet
+    let setup_packet = if buffer[14] == 0 && transfer_type == TransferType::Control {
+        Some(buffer[40..40 + SETUP_PACKET_LEN].to_vec())
+    } else {
+        None
+    };
     
     Ok(UsbPacket {
         timestamp,
+        timestamp_us: ts_sec.saturating_mul(1_000_000).saturating_add(ts_usec as u64),
         urb_tag,
         urb_type,
+        transfer_type,
         bus_id,
         device_id,
         endpoint,
         direction,
         data_length,
+        captured_length,
         status,
-        setup_packet: None,
+        interval,
+        setup_packet,
         data: None,
     })
 }
@@ -255,7 +614,7 @@ fn parse_hex_data(hex_parts: &[&str]) -> Result<Vec<u8>> {
     let mut data = Vec::new();
     for part in hex_parts {
         // Each part might be multiple hex bytes like "55534243"
-        if part.len() % 2 != 0 {
+        if part.len() % 2 != 0 || !part.is_ascii() {
             continue; // Skip malformed hex
         }
         
@@ -279,6 +638,7 @@ mod tests {
         
         assert_eq!(packet.urb_tag, "ffff88007c861a00");
         assert_eq!(packet.urb_type, UrbType::Submission);
+        assert_eq!(packet.transfer_type, TransferType::Bulk);
         assert_eq!(packet.bus_id, 1);
         assert_eq!(packet.device_id, 1);
         assert_eq!(packet.endpoint, 0);
@@ -288,6 +648,295 @@ mod tests {
         assert!(packet.data.is_some());
     }
     
+    #[test]
+    fn test_text_line_round_trip() {
+        let lines = [
+            "ffff88007c861a00 2389264913 S Bo:1:001:0 -115 31 = 55534243 1f000000 00000000 00000600 00000000 00000000 00000000 000000",
+            "ffff8800aa000100 100 S Ci:1:005:0 s 80 06 0100 0000 0012 18 <",
+            "ffff8800aa000100 200 C Ci:1:005:0 0 18 = 12010002 00000040 81078155 00010102 0301",
+            "ffff88007c861a00 2389265000 C Bi:2:004:1 -71 0",
+            "ffff8800369b7cc0 1843616085 S Ii:3:002:1 -115:10 4 <",
+            "ffff8800369b7cc0 1843647971 C Ii:3:002:1 0:10 4 = 00fe0100",
+        ];
+        for line in lines {
+            let packet = parse_usbmon_text_line(line).unwrap();
+            assert_eq!(format_usbmon_text_line(&packet), line);
+        }
+    }
+    
+    #[test]
+    fn test_periodic_status_words_carry_the_interval() {
+        // A low-speed mouse polled every 10 frames, and a root hub's status endpoint
+        let submission = parse_usbmon_text_line("ffff8800369b7cc0 1843616085 S Ii:3:002:1 -115:10 4 <").unwrap();
+        assert_eq!((submission.status, submission.interval), (-115, Some(10)));
+        assert_eq!(submission.bandwidth_bytes(), 0);
+        let report = parse_usbmon_text_line("ffff8800369b7cc0 1843647971 C Ii:3:002:1 0:10 4 = 00fe0100").unwrap();
+        assert_eq!((report.transfer_type, report.status, report.interval), (TransferType::Interrupt, 0, Some(10)));
+        assert_eq!(report.data, Some(vec![0x00, 0xfe, 0x01, 0x00]));
+        assert_eq!(report.bandwidth_bytes(), 4);
+        let hub = parse_usbmon_text_line("ffff880036c3a600 1843612345 S Ii:1:001:1 -115:2048 2 <").unwrap();
+        assert_eq!(hub.interval, Some(2048));
+        
+        // Audio streaming: "status:interval:start_frame[:error_count]", then the
+        // descriptor count and at most five "status:offset:length" descriptors
+        let lines = [
+            "ffff88003e0b1800 2231049155 S Zi:1:004:3 -115:1:2632 8 -18:0:192 -18:192:192 -18:384:192 -18:576:192 -18:768:192 1536 <",
+            "ffff88003e0b1800 2231057155 C Zi:1:004:3 0:1:2632:0 8 0:0:192 0:192:192 0:384:192 0:576:192 0:768:192 1536 = 00000000 01000100 feff0000 02000200",
+            "ffff88003e0b1c00 2231049210 S Zo:1:005:1 -115:1:4120 10 -18:0:176 -18:176:176 -18:352:176 -18:528:176 -18:704:176 1760 = 00000000 00000000 00000000 00000000",
+            "ffff88003e0b1c00 2231059210 C Zo:1:005:1 0:1:4120:0 10 0:0:176 0:176:176 0:352:176 0:528:176 0:704:176 1760 >",
+        ];
+        let packets: Vec<UsbPacket> = lines.iter().map(|line| parse_usbmon_text_line(line).unwrap()).collect();
+        for packet in &packets {
+            assert_eq!(packet.transfer_type, TransferType::Isochronous);
+            assert_eq!(packet.interval, Some(1));
+        }
+        assert_eq!((packets[0].status, packets[0].data_length), (-115, 1536));
+        assert_eq!((packets[1].status, packets[1].bandwidth_bytes()), (0, 1536));
+        assert_eq!(packets[1].captured_length, 16);
+        assert_eq!(packets[2].bandwidth_bytes(), 1760);
+        assert_eq!(packets[3].bandwidth_bytes(), 0);
+        
+        // Written back without start frame or descriptors, and read again the same
+        let reread = parse_usbmon_text_line(&format_usbmon_text_line(&packets[1])).unwrap();
+        assert_eq!(
+            (reread.status, reread.interval, reread.data_length, reread.data),
+            (packets[1].status, packets[1].interval, packets[1].data_length, packets[1].data.clone()),
+        );
+        
+        // A descriptor list cut short isn't mistaken for the length
+        assert!(parse_usbmon_text_line("ffff88003e0b1800 2231057155 C Zi:1:004:3 0:1:2632:0 8 0:0:192 0:192:192").is_err());
+        assert!(parse_usbmon_text_line("ffff8800369b7cc0 1843647971 C Ii:3:002:1 0:x 4 = 00fe0100").is_err());
+    }
+    
+    #[test]
+    fn test_truncated_text_capture_counts_full_length() {
+        // The text format prints only the first 32 bytes of a 512-byte transfer
+        let line = "ffff88003b4a3c00 3575914655 C Bi:1:002:1 0 512 = 00010203 04050607 08090a0b 0c0d0e0f 10111213 14151617 18191a1b 1c1d1e1f";
+        let packet = parse_usbmon_text_line(line).unwrap();
+        assert_eq!(packet.data_length, 512);
+        assert_eq!(packet.captured_length, 32);
+        assert_eq!(packet.bandwidth_bytes(), 512);
+        assert_eq!(packet.data.as_ref().map(Vec::len), Some(32));
+        
+        // A short final word is still captured data
+        let packet = parse_usbmon_text_line("ffff88003b4a3c00 3575914700 C Bi:1:002:1 0 64 = 01020304 0506").unwrap();
+        assert_eq!(packet.captured_length, 6);
+        assert_eq!(packet.bandwidth_bytes(), 64);
+        
+        // Data not captured at all ('<' on an IN submission)
+        let packet = parse_usbmon_text_line("ffff88003b4a3c00 3575914555 S Bi:1:002:1 -115 512 <").unwrap();
+        assert_eq!(packet.captured_length, 0);
+        assert!(packet.data.is_none());
+        assert_eq!(packet.bandwidth_bytes(), 0);
+        
+        let packet = parse_usbmon_text_line("ffff88003b4a3c00 3575914800 C Bi:1:002:1 0 4 = 01020304").unwrap();
+        assert_eq!(packet.captured_length, packet.data_length);
+    }
+    
+    #[test]
+    fn test_in_transfers_count_completed_bytes() {
+        // A 16 KiB bulk read that the device ends with a 100-byte short packet
+        let submit = parse_usbmon_text_line("ffff88003b4a3c00 3575914555 S Bi:1:002:1 -115 16384 <").unwrap();
+        let complete = parse_usbmon_text_line("ffff88003b4a3c00 3575914655 C Bi:1:002:1 0 100 = 00010203").unwrap();
+        assert_eq!(submit.bandwidth_bytes() + complete.bandwidth_bytes(), 100);
+        
+        // A zero-length packet ends the transfer without data
+        let zlp = parse_usbmon_text_line("ffff88003b4a3d00 3575914700 C Bi:1:002:1 0 0").unwrap();
+        assert!(!zlp.is_data_packet());
+        assert_eq!(zlp.bandwidth_bytes(), 0);
+        
+        // OUT data is counted at submission; the callback only confirms it
+        let submit = parse_usbmon_text_line("ffff88003b4a3e00 3575915000 S Bo:1:002:2 -115 31 = 55534243").unwrap();
+        let complete = parse_usbmon_text_line("ffff88003b4a3e00 3575915100 C Bo:1:002:2 0 31 >").unwrap();
+        assert_eq!((submit.bandwidth_bytes(), complete.bandwidth_bytes()), (31, 0));
+        
+        // Control reads follow bmRequestType: a short GET_DESCRIPTOR counts the 8 bytes returned
+        let submit = parse_usbmon_text_line("ffff88003b4a3c00 3575916000 S Ci:1:002:0 s 80 06 0100 0000 0040 64 <").unwrap();
+        let complete = parse_usbmon_text_line("ffff88003b4a3c00 3575916100 C Ci:1:002:0 0 8 = 12010002 00000040").unwrap();
+        assert_eq!(submit.bandwidth_bytes() + complete.bandwidth_bytes(), 8);
+    }
+    
+    #[test]
+    fn test_failed_urbs_exclude_cancellations() {
+        let parse = |line: &str| parse_usbmon_text_line(line).unwrap();
+        let submit = parse("ffff88003b4a3c00 3575914555 S Bi:1:002:1 -115 512 <");
+        assert!(!submit.is_failure());
+        assert!(!parse("ffff88003b4a3c00 3575914655 C Bi:1:002:1 0 512 = 00").is_failure());
+        assert!(parse("ffff88003b4a3c00 3575914655 C Bi:1:002:1 -71 0").is_failure());
+        assert!(parse("ffff88003b4a3c00 3575914655 C Bi:1:002:1 -110 0").is_failure());
+        assert!(!parse("ffff88003b4a3c00 3575914655 C Bi:1:002:1 -104 0").is_failure());
+        assert!(!parse("ffff88003b4a3c00 3575914655 C Bi:1:002:1 -2 0").is_failure());
+        assert!(UsbPacket { urb_type: UrbType::Error, ..submit }.is_failure());
+    }
+    
+    #[test]
+    fn test_control_setup_packet_sets_data_direction() {
+        // GET_DESCRIPTOR (device): bmRequestType 0x80, device-to-host data stage
+        let line = "ffff88003b4a3c00 3575914555 S Ci:1:002:0 s 80 06 0100 0000 0012 18 <";
+        let packet = parse_usbmon_text_line(line).unwrap();
+        assert_eq!(packet.setup_packet, Some(vec![0x80, 0x06, 0x00, 0x01, 0x00, 0x00, 0x12, 0x00]));
+        assert_eq!(packet.status, -EINPROGRESS);
+        assert_eq!(packet.data_length, 18);
+        assert!(packet.data_direction());
+        
+        // SET_CONFIGURATION: bmRequestType 0x00, host-to-device
+        let line = "ffff88003b4a3c00 3575914555 S Co:1:002:0 s 00 09 0001 0000 0000 0";
+        let packet = parse_usbmon_text_line(line).unwrap();
+        assert!(!packet.data_direction());
+        
+        // bmRequestType wins over a contradicting direction bit
+        let mut packet = packet;
+        packet.direction = true;
+        assert!(!packet.data_direction());
+        
+        // Without a setup packet (callbacks) the URB direction is used
+        let line = "ffff88003b4a3c00 3575916000 C Ci:1:002:0 0 18 = 12010002 00000040";
+        assert!(parse_usbmon_text_line(line).unwrap().data_direction());
+        
+        let mut buffer = [0u8; 64];
+        buffer[8] = b'S';
+        buffer[9] = 2; // control
+        buffer[10] = 0x80;
+        buffer[12] = 1;
+        buffer[40..48].copy_from_slice(&[0x00, 0x09, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        let packet = parse_usbmon_binary_packet(&buffer).unwrap();
+        assert_eq!(packet.setup_packet.as_deref().map(|setup| setup[1]), Some(0x09));
+        assert!(!packet.data_direction());
+        
+        buffer[14] = b'-'; // no setup packet
+        assert!(parse_usbmon_binary_packet(&buffer).unwrap().data_direction());
+    }
+    
+    // Regression inputs found by the fuzz targets in fuzz/
+    
+    #[test]
+    fn test_multibyte_address_field_returns_error() {
+        let line = "ffff88007c861a00 2389264913 S \u{e9}o:1:001:0 -115 31";
+        assert!(parse_usbmon_text_line(line).is_err());
+    }
+    
+    #[test]
+    fn test_empty_address_type_returns_error() {
+        let line = "ffff88007c861a00 2389264913 S :1:001:0 -115 31";
+        assert!(parse_usbmon_text_line(line).is_err());
+    }
+    
+    #[test]
+    fn test_single_char_address_type_returns_error() {
+        let line = "ffff88007c861a00 2389264913 S B:1:001:0 -115 31";
+        assert!(parse_usbmon_text_line(line).is_err());
+    }
+    
+    #[test]
+    fn test_multibyte_hex_data_does_not_panic() {
+        let line = "ffff88007c861a00 2389264913 C Bi:1:001:1 0 4 = a\u{e9}b 0102";
+        let packet = parse_usbmon_text_line(line).unwrap();
+        assert_eq!(packet.data, Some(vec![0x01, 0x02]));
+    }
+    
+    #[test]
+    fn test_text_and_binary_agree_on_endpoint_fields() {
+        let binary = |urb_type: u8, transfer_type: u8, address: u8, device_id: u8, bus_id: u16, interval: u32| {
+            let mut buffer = [0u8; 64];
+            buffer[8] = urb_type;
+            buffer[9] = transfer_type;
+            buffer[10] = address;
+            buffer[11] = device_id;
+            buffer[12..14].copy_from_slice(&bus_id.to_le_bytes());
+            buffer[14] = b'-';
+            buffer[48..52].copy_from_slice(&interval.to_le_bytes());
+            parse_usbmon_binary_packet(&buffer).unwrap()
+        };
+        let cases = [
+            ("ffff88003b4a3c00 3575914555 S Bi:2:005:1 -115 512 <", binary(b'S', 3, 0x81, 5, 2, 0)),
+            ("ffff88003b4a3c00 3575914555 C Bo:2:005:2 0 31 >", binary(b'C', 3, 0x02, 5, 2, 0)),
+            ("ffff88003b4a3c00 3575914555 C Ii:1:003:15 0:8 8 = 00000400 00000000", binary(b'C', 1, 0x8f, 3, 1, 8)),
+            ("ffff88003b4a3c00 3575914555 C Ci:1:002:0 0 18 = 12010002 00000040 81078155 00010102 0301", binary(b'C', 2, 0x80, 2, 1, 0)),
+            (
+                "ffff88003b4a3c00 3575914555 C Zo:3:127:3 0:1:1024:0 1 0:0:192 192 >",
+                binary(b'C', 0, 0x03, 127, 3, 1),
+            ),
+        ];
+        
+        for (line, binary) in &cases {
+            let text = parse_usbmon_text_line(line).unwrap();
+            assert_eq!(
+                (text.direction, text.endpoint, text.bus_id, text.device_id, text.interval),
+                (binary.direction, binary.endpoint, binary.bus_id, binary.device_id, binary.interval),
+                "{}", line,
+            );
+            assert_eq!(text.endpoint_address(), binary.endpoint_address());
+            assert_eq!(split_endpoint_address(binary.endpoint_address()), (binary.endpoint, binary.direction));
+        }
+        
+        // A number only the binary direction bit could carry is malformed text
+        assert!(parse_usbmon_text_line("ffff88003b4a3c00 3575914555 C Bi:1:002:129 0 0").is_err());
+    }
+    
+    #[test]
+    fn test_binary_timestamp_overflow_does_not_panic() {
+        let mut buffer = [0u8; 64];
+        buffer[8] = b'C';
+        buffer[9] = 3;
+        buffer[24..28].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(parse_usbmon_binary_packet(&buffer).is_ok());
+    }
+    
+    #[test]
+    fn test_binary_oversized_length_is_implausible() {
+        let mut buffer = [0u8; 64];
+        buffer[8] = b'C';
+        buffer[9] = 3;
+        buffer[32..36].copy_from_slice(&0xFFFF_FFF0u32.to_le_bytes());
+        let packet = parse_usbmon_binary_packet(&buffer).unwrap();
+        assert!(!packet.has_plausible_length(DEFAULT_MAX_DATA_LENGTH));
+        
+        // A captured length larger than the URB itself means a corrupt header
+        buffer[32..36].copy_from_slice(&8u32.to_le_bytes());
+        buffer[36..40].copy_from_slice(&64u32.to_le_bytes());
+        assert!(parse_usbmon_binary_packet(&buffer).is_err());
+    }
+    
+    #[test]
+    fn test_binary_misaligned_header_is_rejected() {
+        let mut buffer = [0u8; 64];
+        buffer[8] = b'S';
+        buffer[9] = 3;
+        buffer[12] = 2;
+        buffer[28..32].copy_from_slice(&(-115i32).to_le_bytes());
+        assert!(parse_usbmon_binary_packet(&buffer).is_ok());
+        
+        // The same header read a few bytes off its boundary
+        let mut shifted = [0xAAu8; 64];
+        shifted[5..].copy_from_slice(&buffer[..59]);
+        assert!(parse_usbmon_binary_packet(&shifted).is_err());
+        
+        let mut bad = buffer;
+        bad[13] = 1; // bus 258
+        assert!(parse_usbmon_binary_packet(&bad).is_err());
+        
+        let mut bad = buffer;
+        bad[28..32].copy_from_slice(&77i32.to_le_bytes());
+        assert!(parse_usbmon_binary_packet(&bad).is_err());
+    }
+    
+    #[test]
+    fn test_text_timestamp_wrap_stays_monotonic() {
+        let mut clock = TimestampUnwrapper::new();
+        let near_wrap = TEXT_TIMESTAMP_WRAP - 1_000;
+        
+        assert_eq!(clock.unwrap(near_wrap - 500), near_wrap - 500);
+        assert_eq!(clock.unwrap(near_wrap), near_wrap);
+        // Slightly out-of-order event is not a wrap
+        assert_eq!(clock.unwrap(near_wrap - 10), near_wrap - 10);
+        // Counter wrapped: 2000us after near_wrap
+        assert_eq!(clock.unwrap(1_000), TEXT_TIMESTAMP_WRAP + 1_000);
+        assert_eq!(clock.unwrap(5_000), TEXT_TIMESTAMP_WRAP + 5_000);
+        // Second wrap keeps accumulating
+        assert_eq!(clock.unwrap(near_wrap), 2 * TEXT_TIMESTAMP_WRAP - 1_000);
+        assert_eq!(clock.unwrap(0), 2 * TEXT_TIMESTAMP_WRAP);
+    }
+    
     #[test]
     fn test_usb_speed_color_codes() {
         assert_eq!(UsbSpeed::SuperSpeed.color_code(), (0, 255, 0));
@@ -296,6 +945,29 @@ mod tests {
         assert_eq!(UsbSpeed::SuperSpeed.to_mbps(), 5000.0);
     }
     
+    #[test]
+    fn test_speed_from_numbers_and_names() {
+        for (text, speed) in [
+            ("1.5", UsbSpeed::Low),
+            ("12", UsbSpeed::Full),
+            ("480\n", UsbSpeed::High),
+            ("5000", UsbSpeed::SuperSpeed),
+            ("10000", UsbSpeed::SuperSpeedPlus),
+            ("20000", UsbSpeed::SuperSpeedPlus),
+            ("low", UsbSpeed::Low),
+            ("full-speed", UsbSpeed::Full),
+            ("High", UsbSpeed::High),
+            ("SuperSpeed", UsbSpeed::SuperSpeed),
+            ("super", UsbSpeed::SuperSpeed),
+            ("super-speed-plus", UsbSpeed::SuperSpeedPlus),
+            ("SuperSpeedPlus", UsbSpeed::SuperSpeedPlus),
+            ("UNKNOWN", UsbSpeed::Unknown),
+            ("40000", UsbSpeed::Unknown),
+        ] {
+            assert_eq!(UsbSpeed::from_speed_str(text), speed, "{:?}", text);
+        }
+    }
+    
     #[test]
     fn test_bandwidth_calculations() {
         // Test theoretical bandwidth
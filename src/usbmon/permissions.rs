@@ -0,0 +1,425 @@
+use anyhow::{Result, anyhow};
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::config::paths::SystemPaths;
+use crate::logging::decorated;
+
+/// Group given read access to the usbmon nodes by `--fix`
+pub const USBMON_GROUP: &str = "usbmon";
+
+/// Rule installed by `--fix`
+pub const UDEV_RULE_PATH: &str = "/etc/udev/rules.d/70-usbtop-ng-usbmon.rules";
+
+/// Loads usbmon at boot, so the rule has a module load to react to
+pub const MODULES_LOAD_PATH: &str = "/etc/modules-load.d/usbtop-ng-usbmon.conf";
+
+/// Whether the current user can open one usbmon path
+#[derive(Debug, Clone, PartialEq)]
+pub enum Access {
+    Readable,
+    Denied,
+    Missing,
+    Failed(String),
+}
+
+impl Access {
+    fn of<T>(result: &io::Result<T>) -> Self {
+        match result {
+            Ok(_) => Access::Readable,
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => Access::Denied,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Access::Missing,
+            Err(e) => Access::Failed(e.to_string()),
+        }
+    }
+    
+    fn label(&self) -> String {
+        match self {
+            Access::Readable => decorated("✅", "readable"),
+            Access::Denied => decorated("❌", "permission denied"),
+            Access::Missing => decorated("❌", "missing"),
+            Access::Failed(e) => decorated("❌", &e.to_string()),
+        }
+    }
+}
+
+/// What stands between the current user and the usbmon nodes
+#[derive(Debug, Clone, PartialEq)]
+pub struct PermissionReport {
+    /// Login name of the user to grant access to (the one behind sudo, if any)
+    pub user: String,
+    pub is_root: bool,
+    /// The usbmon directory itself, then each node in it
+    pub directory: Access,
+    pub nodes: Vec<(PathBuf, Access)>,
+    /// gid and members of the usbmon group, if it exists
+    pub group: Option<(u32, Vec<String>)>,
+    /// Groups the running process has; a fresh membership needs a new login
+    pub process_gids: Vec<u32>,
+    pub rule_installed: bool,
+}
+
+impl PermissionReport {
+    /// Probe access by opening each node the way the reader would
+    pub fn check(paths: &SystemPaths) -> Self {
+        let status = fs::read_to_string("/proc/self/status").unwrap_or_default();
+        let (uid, process_gids) = parse_status_ids(&status);
+        let user = std::env::var("SUDO_USER")
+            .or_else(|_| std::env::var("USER"))
+            .unwrap_or_else(|_| "unknown".to_string());
+        
+        let listing = fs::read_dir(&paths.usbmon);
+        let directory = Access::of(&listing);
+        let mut nodes: Vec<(PathBuf, Access)> = listing
+            .map(|entries| {
+                entries.filter_map(|entry| entry.ok())
+                    .map(|entry| entry.path())
+                    .filter(|path| path.file_name().and_then(|name| name.to_str()).is_some_and(is_capture_node))
+                    .map(|path| {
+                        let access = Access::of(&fs::File::open(&path));
+                        (path, access)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        nodes.sort_by(|a, b| a.0.cmp(&b.0));
+        
+        let group = Command::new("getent").args(["group", USBMON_GROUP]).output().ok()
+            .filter(|output| output.status.success())
+            .and_then(|output| parse_group_entry(String::from_utf8_lossy(&output.stdout).trim()));
+        
+        Self {
+            user,
+            is_root: uid == Some(0),
+            directory,
+            nodes,
+            group,
+            process_gids,
+            rule_installed: Path::new(UDEV_RULE_PATH).exists(),
+        }
+    }
+    
+    /// Whether every node can be read right now
+    pub fn is_ok(&self) -> bool {
+        self.directory == Access::Readable
+            && !self.nodes.is_empty()
+            && self.nodes.iter().all(|(_, access)| *access == Access::Readable)
+    }
+    
+    fn in_group(&self) -> bool {
+        self.group.as_ref().is_some_and(|(_, members)| members.contains(&self.user))
+    }
+    
+    pub fn print(&self, paths: &SystemPaths) {
+        println!("{}", decorated("🔐", &format!("usbmon access for {}{}", self.user, if self.is_root { " (running as root)" } else { "" })));
+        println!("  {}: {}", paths.usbmon.display(), self.directory.label());
+        for (path, access) in &self.nodes {
+            println!("  {}: {}", path.display(), access.label());
+        }
+        if self.directory == Access::Readable && self.nodes.is_empty() {
+            println!("  No capture nodes; is the usbmon module loaded? (sudo modprobe usbmon)");
+        }
+        println!();
+        
+        match &self.group {
+            None => println!("  The '{}' group doesn't exist", USBMON_GROUP),
+            Some((gid, _)) if self.in_group() && !self.process_gids.contains(gid) => {
+                println!("  {} is in the '{}' group, but this login predates it; log out and back in", self.user, USBMON_GROUP);
+            }
+            Some(_) if self.in_group() => println!("  {} is in the '{}' group", self.user, USBMON_GROUP),
+            Some(_) => println!("  {} is not in the '{}' group", self.user, USBMON_GROUP),
+        }
+        if self.rule_installed {
+            println!("  udev rule installed at {}", UDEV_RULE_PATH);
+        } else {
+            println!("  No udev rule grants the '{}' group access ({})", USBMON_GROUP, UDEV_RULE_PATH);
+        }
+        println!();
+        
+        if self.is_ok() {
+            println!("{}", decorated("✅", "usbtop-ng can capture as this user"));
+        } else if self.plan(paths).is_ok_and(|steps| steps.is_empty()) {
+            println!("Access is still missing; try loading usbmon again (sudo modprobe -r usbmon && sudo modprobe usbmon) to reapply the rule");
+        } else {
+            println!("Run with --check-permissions --fix to grant the '{}' group read access (uses sudo)", USBMON_GROUP);
+        }
+    }
+    
+    /// Steps `--fix` would take, in order; empty when there's nothing to set up.
+    /// Fails when the usbmon paths can't go into the rule's root shell command.
+    pub fn plan(&self, paths: &SystemPaths) -> Result<Vec<FixStep>> {
+        if !self.rule_installed {
+            if let Some(path) = [&paths.debugfs, &paths.usbmon].into_iter().find(|path| !is_plain_path(path)) {
+                return Err(anyhow!(
+                    "--fix won't write a udev rule for {}: only absolute paths of letters, digits, '/', '.', '_' and '-' are supported",
+                    path.display(),
+                ));
+            }
+        }
+        let mut steps = Vec::new();
+        if self.group.is_none() {
+            steps.push(FixStep::Run(vec!["groupadd".into(), "--system".into(), USBMON_GROUP.into()]));
+        }
+        if !self.in_group() && self.user != "root" {
+            steps.push(FixStep::Run(vec!["usermod".into(), "-aG".into(), USBMON_GROUP.into(), self.user.clone()]));
+        }
+        if !self.rule_installed {
+            steps.push(FixStep::Write(PathBuf::from(MODULES_LOAD_PATH), "usbmon\n".to_string()));
+            steps.push(FixStep::Write(PathBuf::from(UDEV_RULE_PATH), udev_rule(paths)));
+            steps.push(FixStep::Run(vec!["udevadm".into(), "control".into(), "--reload-rules".into()]));
+            // Reload the module so the rule runs for the nodes that exist now;
+            // this fails when usbmon is built in or in use, and the rule then
+            // applies from the next load instead
+            steps.push(FixStep::Attempt(vec!["modprobe".into(), "-r".into(), "usbmon".into()]));
+            steps.push(FixStep::Run(vec!["modprobe".into(), "usbmon".into()]));
+        }
+        Ok(steps)
+    }
+}
+
+/// One privileged action of `--fix`, run through sudo
+#[derive(Debug, Clone, PartialEq)]
+pub enum FixStep {
+    Run(Vec<String>),
+    /// Like `Run`, but a failure is reported and the remaining steps still run
+    Attempt(Vec<String>),
+    /// Create or replace a file with these contents
+    Write(PathBuf, String),
+}
+
+impl FixStep {
+    pub fn describe(&self) -> String {
+        match self {
+            FixStep::Run(argv) => format!("sudo {}", argv.join(" ")),
+            FixStep::Attempt(argv) => format!("sudo {} || true", argv.join(" ")),
+            FixStep::Write(path, contents) => format!("sudo tee {} <<'EOF'\n{}EOF", path.display(), contents),
+        }
+    }
+    
+    fn is_rule(&self) -> bool {
+        matches!(self, FixStep::Write(path, _) if path == Path::new(UDEV_RULE_PATH))
+    }
+    
+    fn run(&self) -> Result<()> {
+        let status = match self {
+            FixStep::Run(argv) | FixStep::Attempt(argv) => Command::new("sudo").args(argv).status()?,
+            FixStep::Write(path, contents) => {
+                let mut child = Command::new("sudo")
+                    .arg("tee")
+                    .arg(path)
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::null())
+                    .spawn()?;
+                child.stdin.take().ok_or_else(|| anyhow!("tee has no stdin"))?.write_all(contents.as_bytes())?;
+                child.wait()?
+            }
+        };
+        if let (FixStep::Attempt(argv), false) = (self, status.success()) {
+            println!("'sudo {}' failed ({}); carrying on", argv.join(" "), status);
+            return Ok(());
+        }
+        if !status.success() {
+            return Err(anyhow!("'{}' failed ({})", self.describe().lines().next().unwrap_or_default(), status));
+        }
+        Ok(())
+    }
+}
+
+/// Report access, and with `fix` set up group access after showing each step
+/// The steps run once confirmed at the terminal, or straight away with `yes`;
+/// without a terminal to ask on, `yes` is required. Returns false when access
+/// is missing and nothing was done about it.
+pub fn run(paths: &SystemPaths, fix: bool, yes: bool) -> Result<bool> {
+    if !cfg!(target_os = "linux") {
+        println!("--check-permissions only applies to Linux usbmon");
+        return Ok(false);
+    }
+    
+    let report = PermissionReport::check(paths);
+    report.print(paths);
+    if !fix {
+        return Ok(report.is_ok());
+    }
+    
+    let steps = report.plan(paths)?;
+    if steps.is_empty() {
+        return Ok(report.is_ok());
+    }
+    println!();
+    println!("--fix will run:");
+    for step in &steps {
+        println!("  {}", step.describe().replace('\n', "\n  "));
+    }
+    if let Some(exposure) = steps.iter().any(FixStep::is_rule).then(|| debugfs_exposure(paths)).flatten() {
+        println!();
+        println!("{}", decorated("⚠️", &exposure));
+    }
+    if !yes {
+        if !io::stdin().is_terminal() {
+            println!("Nothing changed: there is no terminal to confirm on; rerun with --yes to apply these steps");
+            return Ok(false);
+        }
+        print!("Continue? (y/N): ");
+        io::stdout().flush()?;
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+        if !matches!(input.trim().to_lowercase().as_str(), "y" | "yes") {
+            println!("Nothing changed");
+            return Ok(false);
+        }
+    }
+    for step in &steps {
+        step.run()?;
+    }
+    
+    println!();
+    println!("{}", decorated("✅", &format!("Done. Log out and back in so the '{}' group applies, then run usbtop-ng without sudo", USBMON_GROUP)));
+    Ok(true)
+}
+
+/// debugfs directories above the usbmon directory, outermost first
+fn debugfs_parents(paths: &SystemPaths) -> Vec<String> {
+    let mut parents: Vec<&Path> = paths.usbmon.ancestors()
+        .skip(1)
+        .take_while(|dir| dir.starts_with(&paths.debugfs))
+        .collect();
+    parents.reverse();
+    parents.iter().map(|dir| dir.display().to_string()).collect()
+}
+
+/// What the rule exposes beyond usbmon, shown before `--fix` asks to go ahead
+fn debugfs_exposure(paths: &SystemPaths) -> Option<String> {
+    let parents = debugfs_parents(paths);
+    if parents.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "The rule lets the '{}' group pass through {} (search only, no listing) to reach usbmon. \
+         Members can then also open any other debugfs file that is readable by everyone, \
+         not just the usbmon nodes; only add users you would trust with that.",
+        USBMON_GROUP, parents.join(" and "),
+    ))
+}
+
+/// The `/dev/usbmonN` character devices get the group through udev directly.
+/// The debugfs nodes are created root-only with debugfs usually mode 0700, so
+/// on every load of the module the rule gives the group search (not read)
+/// permission on the directories on the way down, and read access to the
+/// usbmon directory and its nodes.
+fn udev_rule(paths: &SystemPaths) -> String {
+    let parents = debugfs_parents(paths);
+    let usbmon = paths.usbmon.display();
+    
+    let mut script = String::new();
+    if !parents.is_empty() {
+        script.push_str(&format!("chgrp {group} {dirs} && chmod g+x {dirs} && ", group = USBMON_GROUP, dirs = parents.join(" ")));
+    }
+    script.push_str(&format!("chgrp -R {} {usbmon} && chmod g+rx {usbmon} && chmod g+r {usbmon}/*", USBMON_GROUP, usbmon = usbmon));
+    format!(
+        "# Installed by usbtop-ng --check-permissions --fix\n\
+         # Lets the '{group}' group read usbmon captures without root\n\
+         SUBSYSTEM==\"usbmon\", GROUP=\"{group}\", MODE=\"0640\"\n\
+         # Search permission on the debugfs directories above usbmon also reaches\n\
+         # other debugfs files that are readable by everyone\n\
+         ACTION==\"add\", SUBSYSTEM==\"module\", KERNEL==\"usbmon\", RUN+=\"/bin/sh -c '{script}'\"\n",
+        group = USBMON_GROUP, script = script,
+    )
+}
+
+/// Whether a path can go into the rule as is: udev's RUN value and the quoted
+/// `sh -c` script both break on spaces, quotes and `;`, and a leading `-`
+/// would read as an option to chgrp
+fn is_plain_path(path: &Path) -> bool {
+    path.is_absolute() && path.to_str().is_some_and(|path| {
+        path.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | '.' | '_' | '-'))
+    })
+}
+
+/// Capture nodes are `<bus>t` and `<bus>u`; `<bus>s` only holds statistics
+fn is_capture_node(name: &str) -> bool {
+    name.strip_suffix(['t', 'u']).is_some_and(|bus| bus.parse::<u8>().is_ok())
+}
+
+/// Real uid and supplementary gids from `/proc/self/status`
+fn parse_status_ids(status: &str) -> (Option<u32>, Vec<u32>) {
+    let field = |name: &str| status.lines().find_map(|line| line.strip_prefix(name)).unwrap_or_default();
+    let uid = field("Uid:").split_whitespace().next().and_then(|uid| uid.parse().ok());
+    let gids = field("Groups:").split_whitespace().filter_map(|gid| gid.parse().ok()).collect();
+    (uid, gids)
+}
+
+/// gid and members of a `getent group` line, e.g. "usbmon:x:973:alice,bob"
+fn parse_group_entry(line: &str) -> Option<(u32, Vec<String>)> {
+    let mut fields = line.split(':');
+    let gid = fields.nth(2)?.parse().ok()?;
+    let members = fields.next()?.split(',').filter(|name| !name.is_empty()).map(str::to_string).collect();
+    Some((gid, members))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn report(group: Option<(u32, Vec<String>)>, rule_installed: bool) -> PermissionReport {
+        PermissionReport {
+            user: "alice".to_string(),
+            is_root: false,
+            directory: Access::Denied,
+            nodes: Vec::new(),
+            group,
+            process_gids: vec![100],
+            rule_installed,
+        }
+    }
+    
+    #[test]
+    fn test_parse_ids() {
+        let status = "Name:\tusbtop-ng\nUid:\t1000\t1000\t1000\t1000\nGid:\t1000\t1000\t1000\t1000\nGroups:\t4 27 973 \n";
+        assert_eq!(parse_status_ids(status), (Some(1000), vec![4, 27, 973]));
+        assert_eq!(parse_group_entry("usbmon:x:973:alice,bob"), Some((973, vec!["alice".to_string(), "bob".to_string()])));
+        assert_eq!(parse_group_entry("usbmon:x:973:"), Some((973, Vec::new())));
+        assert!(is_capture_node("1u") && is_capture_node("0t"));
+        assert!(!is_capture_node("1s") && !is_capture_node("u"));
+    }
+    
+    #[test]
+    fn test_fix_plan_skips_what_is_in_place() {
+        let paths = SystemPaths {
+            debugfs: PathBuf::from("/sys/kernel/debug"),
+            usbmon: PathBuf::from("/sys/kernel/debug/usb/usbmon"),
+            sysfs: PathBuf::from("/sys/bus/usb/devices"),
+        };
+        let steps = report(None, false).plan(&paths).unwrap();
+        assert_eq!(steps[0].describe(), "sudo groupadd --system usbmon");
+        assert_eq!(steps[1].describe(), "sudo usermod -aG usbmon alice");
+        let FixStep::Write(path, rule) = &steps[3] else {
+            panic!("expected the udev rule");
+        };
+        assert_eq!(path, Path::new(UDEV_RULE_PATH));
+        assert!(rule.contains("chgrp usbmon /sys/kernel/debug /sys/kernel/debug/usb && chmod g+x /sys/kernel/debug /sys/kernel/debug/usb"));
+        assert!(rule.contains("chmod g+r /sys/kernel/debug/usb/usbmon/*"));
+        assert!(rule.contains("SUBSYSTEM==\"usbmon\", GROUP=\"usbmon\", MODE=\"0640\"\n"));
+        assert!(steps[3].is_rule() && !steps[2].is_rule());
+        assert_eq!(steps[5].describe(), "sudo modprobe -r usbmon || true");
+        assert_eq!(steps[6].describe(), "sudo modprobe usbmon");
+        assert!(debugfs_exposure(&paths).unwrap().contains("/sys/kernel/debug and /sys/kernel/debug/usb"));
+        
+        assert!(report(Some((973, vec!["alice".to_string()])), true).plan(&paths).unwrap().is_empty());
+    }
+    
+    #[test]
+    fn test_fix_plan_refuses_paths_unsafe_in_the_rule() {
+        for usbmon in ["/sys/kernel/debug/usb/usb mon", "/sys/kernel/debug/usb/'usbmon", "/sys/kernel/debug/usb;reboot", "usbmon"] {
+            let paths = SystemPaths {
+                debugfs: PathBuf::from("/sys/kernel/debug"),
+                usbmon: PathBuf::from(usbmon),
+                sysfs: PathBuf::from("/sys/bus/usb/devices"),
+            };
+            assert!(report(None, false).plan(&paths).is_err(), "{usbmon}");
+            // Nothing goes into a rule that's already there
+            assert!(report(None, true).plan(&paths).is_ok());
+        }
+    }
+}
@@ -2,95 +2,310 @@ use std::fs::File;
 use std::io::{Read, BufReader, BufRead};
 use std::path::Path;
 use anyhow::{Result, anyhow};
-use log::{debug, warn, error};
+use log::{debug, info, warn, error};
 use tokio::fs::File as TokioFile;
-use tokio::io::{AsyncReadExt, AsyncBufReadExt, BufReader as TokioBufReader};
+use tokio::io::{AsyncBufRead, AsyncRead, AsyncReadExt, AsyncBufReadExt, BufReader as TokioBufReader};
+use tokio::sync::watch;
 
-use super::parser::{UsbPacket, parse_usbmon_text_line, parse_usbmon_binary_packet};
+use crate::device::assembler::DESCRIPTOR_CAPTURE_LEN;
+use crate::logging::{suppressed_suffix, LogThrottle, LOG_THROTTLE_INTERVAL};
+use crate::profiling::profile_span;
+
+use super::capture_filter::CaptureFilter;
+use super::parser::{TransferType, UrbType, UsbPacket, TimestampUnwrapper, BINARY_HEADER_LEN, DEFAULT_MAX_DATA_LENGTH, binary_captured_length, parse_usbmon_text_line, parse_usbmon_binary_packet, validate_binary_header};
+
+/// Path shown for a capture piped into stdin
+pub const STDIN_PATH: &str = "-";
+
+/// Bytes requested per read of a binary stream; reads may return fewer
+const BINARY_READ_CHUNK_LEN: usize = 64 * 1024;
+
+/// `O_NONBLOCK` from asm-generic/fcntl.h
+#[cfg(target_os = "linux")]
+const O_NONBLOCK: i32 = 0o4000;
+
+/// Whether a capture source can run out, decided when the reader is built
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceKind {
+    /// A usbmon node: end of data only means the bus is quiet, so keep polling
+    Live,
+    /// A file or pipe: end of data is the end of the capture
+    Finite,
+}
 
 #[derive(Debug, Clone)]
 pub struct UsbmonReader {
     pub bus_id: u8,
     pub use_binary: bool,
     pub path: String,
+    pub max_data_length: u32,
+    /// Payload bytes kept per binary packet; the rest of the captured data is
+    /// skipped without being stored. 0 keeps headers only
+    pub capture_len: u32,
+    /// What end of stream means for this source
+    pub source_kind: SourceKind,
+    /// Grow the kernel ring when it is small for the bus speed, instead of
+    /// only warning; only `/dev/usbmonN` has a ring
+    pub grow_ring: bool,
+    /// Records outside it are dropped on their address, before parsing
+    pub filter: CaptureFilter,
 }
 
 impl UsbmonReader {
-    pub fn new(bus_id: u8, use_binary: bool) -> Self {
-        let path = Self::get_usbmon_path(bus_id, use_binary);
+    /// Reader for `bus_id`'s node inside the usbmon directory `usbmon_dir`
+    pub fn new(bus_id: u8, use_binary: bool, usbmon_dir: &Path) -> Self {
+        let (path, use_binary) = match Self::resolve_usbmon_node(usbmon_dir, bus_id, use_binary) {
+            Some((path, use_binary)) => {
+                info!("Resolved usbmon node for bus {}: {}", bus_id, path);
+                (path, use_binary)
+            }
+            None => (Self::get_usbmon_path(usbmon_dir, bus_id, use_binary), use_binary),
+        };
+        
         Self {
             bus_id,
             use_binary,
             path,
+            max_data_length: DEFAULT_MAX_DATA_LENGTH,
+            capture_len: 0,
+            source_kind: SourceKind::Live,
+            grow_ring: false,
+            filter: CaptureFilter::default(),
+        }
+    }
+    
+    /// Reader for a capture piped into stdin; text or binary is detected from the data
+    pub fn stdin() -> Self {
+        Self {
+            bus_id: 0,
+            use_binary: false,
+            path: STDIN_PATH.to_string(),
+            max_data_length: DEFAULT_MAX_DATA_LENGTH,
+            capture_len: 0,
+            source_kind: SourceKind::Finite,
+            grow_ring: false,
+            filter: CaptureFilter::default(),
+        }
+    }
+    
+    /// Reader for a capture streamed from another process, labelled `source` in logs
+    /// Like stdin the format is detected from the data and the stream ends at EOF.
+    pub fn piped(source: &str) -> Self {
+        Self {
+            path: source.to_string(),
+            ..Self::stdin()
         }
     }
     
-    fn get_usbmon_path(bus_id: u8, use_binary: bool) -> String {
+    pub fn with_max_data_length(mut self, max_data_length: u32) -> Self {
+        self.max_data_length = max_data_length;
+        self
+    }
+    
+    pub fn with_capture_len(mut self, capture_len: u32) -> Self {
+        self.capture_len = capture_len;
+        self
+    }
+    
+    pub fn with_ring_growth(mut self, grow_ring: bool) -> Self {
+        self.grow_ring = grow_ring;
+        self
+    }
+    
+    pub fn with_filter(mut self, filter: CaptureFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+    
+    /// Drop packets whose claimed length can't be real so a corrupt header
+    /// doesn't inflate totals and rates
+    fn is_plausible(&self, packet: &UsbPacket, throttle: &mut LogThrottle) -> bool {
+        if packet.has_plausible_length(self.max_data_length) {
+            return true;
+        }
+        self.log_implausible(packet, throttle);
+        false
+    }
+    
+    fn log_implausible(&self, packet: &UsbPacket, throttle: &mut LogThrottle) {
+        if let Some(suppressed) = throttle.allow(&format!("length:{}", packet.device_id)) {
+            warn!(
+                "Rejected packet on bus {} device {}: claimed length {} exceeds limit {}{}",
+                packet.bus_id, packet.device_id, packet.data_length, self.max_data_length,
+                suppressed_suffix(suppressed),
+            );
+        }
+    }
+    
+    fn get_usbmon_path(usbmon_dir: &Path, bus_id: u8, use_binary: bool) -> String {
         #[cfg(target_os = "linux")]
         {
             let suffix = if use_binary { "u" } else { "t" };
-            format!("/sys/kernel/debug/usb/usbmon/{}{}",  bus_id, suffix)
+            usbmon_dir.join(format!("{}{}", bus_id, suffix)).to_string_lossy().into_owned()
         }
         
         #[cfg(any(target_os = "freebsd", target_os = "openbsd", target_os = "netbsd"))]
         {
             // BSD systems might use different paths
+            let _ = usbmon_dir;
             format!("/dev/ugen{}.{}", bus_id, if use_binary { "1" } else { "0" })
         }
         
         #[cfg(target_os = "macos")]
         {
             // macOS doesn't have usbmon, return a placeholder
+            let _ = usbmon_dir;
             format!("/dev/null")
         }
     }
     
+    /// Find the node that actually exists for `bus_id` instead of assuming its name
+    /// Returns the path and whether it should be read in binary mode
+    #[cfg(target_os = "linux")]
+    fn resolve_usbmon_node(usbmon_dir: &Path, bus_id: u8, use_binary: bool) -> Option<(String, bool)> {
+        let names: Vec<String> = std::fs::read_dir(usbmon_dir)
+            .ok()?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect();
+        
+        select_usbmon_node(&names, bus_id, use_binary)
+            .map(|(name, use_binary)| (usbmon_dir.join(name).to_string_lossy().into_owned(), use_binary))
+    }
+    
+    #[cfg(not(target_os = "linux"))]
+    fn resolve_usbmon_node(_usbmon_dir: &Path, _bus_id: u8, _use_binary: bool) -> Option<(String, bool)> {
+        None
+    }
+    
+    fn source_name(&self) -> &str {
+        if self.path == STDIN_PATH { "stdin" } else { &self.path }
+    }
+    
     pub fn is_available(&self) -> bool {
         Path::new(&self.path).exists()
     }
     
-    pub async fn read_packets<F>(&self, mut callback: F) -> Result<()> 
+    /// Capture until the callback fails, the source errors or `shutdown` flips to true
+    pub async fn read_packets<F>(&self, callback: F, shutdown: watch::Receiver<bool>) -> Result<()>
     where
         F: FnMut(UsbPacket) -> Result<()>,
     {
+        if self.path == STDIN_PATH {
+            return self.read_stdin(callback, shutdown).await;
+        }
+        
         if !self.is_available() {
             return Err(anyhow!("usbmon interface not available: {}", self.path));
         }
         
         debug!("Starting packet capture from {}", self.path);
         
+        let file = open_live_node(&self.path).await
+            .map_err(|e| anyhow!("Failed to open {}: {}", self.path, e))?;
+        
+        // The ring can only be resized before the first read maps it
+        #[cfg(target_os = "linux")]
+        if self.use_binary {
+            use std::os::unix::io::AsRawFd;
+            super::ring::check_ring_size(file.as_raw_fd(), self.bus_id, &crate::device::sysfs::root(), self.grow_ring);
+        }
+        
         if self.use_binary {
-            self.read_binary_packets(callback).await
+            self.read_binary_stream(file, callback, shutdown).await
         } else {
-            self.read_text_packets(callback).await
+            self.read_text_stream(file, callback, shutdown).await
         }
     }
     
-    async fn read_binary_packets<F>(&self, mut callback: F) -> Result<()>
+    async fn read_stdin<F>(&self, callback: F, shutdown: watch::Receiver<bool>) -> Result<()>
     where
         F: FnMut(UsbPacket) -> Result<()>,
     {
-        let mut file = TokioFile::open(&self.path).await
-            .map_err(|e| anyhow!("Failed to open {}: {}", self.path, e))?;
+        debug!("Starting packet capture from stdin");
+        self.read_piped(TokioBufReader::new(tokio::io::stdin()), callback, shutdown).await
+    }
+    
+    /// Capture from a pipe whose format isn't known up front
+    pub async fn read_piped<R, F>(&self, mut source: R, callback: F, mut shutdown: watch::Receiver<bool>) -> Result<()>
+    where
+        R: AsyncBufRead + Unpin,
+        F: FnMut(UsbPacket) -> Result<()>,
+    {
+        // fill_buf only peeks, so the bytes used for detection are still parsed below
+        let use_binary = tokio::select! {
+            _ = shutdown.changed() => return Ok(()),
+            prefix = source.fill_buf() => match prefix {
+                Ok([]) => {
+                    info!("No capture data on {}", self.source_name());
+                    return Ok(());
+                }
+                Ok(prefix) => looks_binary(prefix),
+                Err(e) => return Err(anyhow!("Failed to read {}: {}", self.source_name(), e)),
+            },
+        };
+        info!("Reading {} usbmon capture from {}", if use_binary { "binary" } else { "text" }, self.source_name());
         
-        let mut buffer = vec![0u8; 64]; // usbmon binary packets are 64 bytes
+        if use_binary {
+            self.read_binary_stream(source, callback, shutdown).await
+        } else {
+            self.read_text_stream(source, callback, shutdown).await
+        }
+    }
+    
+    async fn read_binary_stream<R, F>(&self, mut source: R, mut callback: F, mut shutdown: watch::Receiver<bool>) -> Result<()>
+    where
+        R: AsyncRead + Unpin,
+        F: FnMut(UsbPacket) -> Result<()>,
+    {
+        let mut framer = BinaryFramer::new(self.capture_len, self.max_data_length, self.filter.clone());
+        let mut chunk = vec![0u8; BINARY_READ_CHUNK_LEN];
+        let mut throttle = LogThrottle::new(LOG_THROTTLE_INTERVAL);
         
-        loop {
-            match file.read_exact(&mut buffer).await {
-                Ok(_) => {
-                    match parse_usbmon_binary_packet(&buffer) {
-                        Ok(packet) => {
-                            if let Err(e) = callback(packet) {
-                                error!("Packet callback error: {}", e);
-                                break;
-                            }
+        'capture: while !*shutdown.borrow() {
+            while let Some(frame) = framer.next_frame() {
+                match frame {
+                    BinaryFrame::Packet(packet) => {
+                        if let Err(e) = callback(packet) {
+                            error!("Packet callback error: {}", e);
+                            break 'capture;
                         }
-                        Err(e) => {
-                            warn!("Failed to parse binary packet: {}", e);
-                            continue;
+                    }
+                    BinaryFrame::Implausible(packet) => self.log_implausible(&packet, &mut throttle),
+                    BinaryFrame::Invalid(e) => {
+                        if let Some(suppressed) = throttle.allow("parse") {
+                            warn!("Failed to parse binary packet on bus {}: {}{}", self.bus_id, e, suppressed_suffix(suppressed));
                         }
                     }
+                    BinaryFrame::Resynced(skipped) => {
+                        debug!("Resynchronized {} after skipping {} bytes", self.path, skipped);
+                    }
+                }
+            }
+            
+            // A read can block indefinitely on an idle bus, so race it against shutdown
+            let read = tokio::select! {
+                _ = shutdown.changed() => break,
+                read = source.read(&mut chunk) => read,
+            };
+            
+            match read {
+                Ok(0) if self.source_kind == SourceKind::Finite => {
+                    if framer.has_partial_packet() {
+                        debug!("End of capture on {} inside a packet", self.path);
+                    } else {
+                        debug!("End of capture on {}", self.path);
+                    }
+                    break;
+                }
+                Ok(0) => {
+                    // A live node has nothing yet; poll again
+                    tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
                 }
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                    tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+                }
+                Ok(read) => framer.push(&chunk[..read]),
                 Err(e) => {
                     error!("Failed to read from {}: {}", self.path, e);
                     break;
@@ -98,37 +313,62 @@ impl UsbmonReader {
             }
         }
         
+        debug!("Stopped packet capture from {}", self.path);
         Ok(())
     }
     
-    async fn read_text_packets<F>(&self, mut callback: F) -> Result<()>
+    async fn read_text_stream<R, F>(&self, source: R, mut callback: F, mut shutdown: watch::Receiver<bool>) -> Result<()>
     where
+        R: AsyncRead + Unpin,
         F: FnMut(UsbPacket) -> Result<()>,
     {
-        let file = TokioFile::open(&self.path).await
-            .map_err(|e| anyhow!("Failed to open {}: {}", self.path, e))?;
-        
-        let mut reader = TokioBufReader::new(file);
+        let mut reader = TokioBufReader::new(source);
         let mut line = String::new();
+        let mut clock = TimestampUnwrapper::new();
+        let mut throttle = LogThrottle::new(LOG_THROTTLE_INTERVAL);
+        // Set when a non-blocking read stopped inside a line, whose start is kept
+        let mut partial = false;
         
-        loop {
-            line.clear();
-            match reader.read_line(&mut line).await {
+        while !*shutdown.borrow() {
+            if !partial {
+                line.clear();
+            }
+            partial = false;
+            let read = tokio::select! {
+                _ = shutdown.changed() => break,
+                read = reader.read_line(&mut line) => read,
+            };
+            
+            match read {
+                Ok(0) if self.source_kind == SourceKind::Finite => {
+                    debug!("End of capture on {}", self.path);
+                    break;
+                }
                 Ok(0) => {
-                    // EOF reached, continue monitoring
+                    // A live node has nothing yet; poll again
+                    tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+                    continue;
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                    partial = true;
                     tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
                     continue;
                 }
+                Ok(_) if !self.filter.admits_text_line(&line) => continue,
                 Ok(_) => {
-                    match parse_usbmon_text_line(&line.trim()) {
-                        Ok(packet) => {
+                    match parse_text_timed(line.trim()) {
+                        Ok(packet) if !self.is_plausible(&packet, &mut throttle) => continue,
+                        Ok(mut packet) => {
+                            packet.timestamp_us = clock.unwrap(packet.timestamp_us);
                             if let Err(e) = callback(packet) {
                                 error!("Packet callback error: {}", e);
                                 break;
                             }
                         }
                         Err(e) => {
-                            debug!("Failed to parse text line '{}': {}", line.trim(), e);
+                            if let Some(suppressed) = throttle.allow("parse") {
+                                debug!("Failed to parse text line '{}': {}{}", line.trim(), e, suppressed_suffix(suppressed));
+                            }
                             continue;
                         }
                     }
@@ -140,6 +380,515 @@ impl UsbmonReader {
             }
         }
         
+        debug!("Stopped packet capture from {}", self.path);
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// What the framer found next in a buffered binary stream
+#[derive(Debug)]
+enum BinaryFrame {
+    Packet(UsbPacket),
+    /// Header parsed but claims an impossible length; the record is dropped
+    /// along with whatever payload was captured for it
+    Implausible(UsbPacket),
+    /// Header failed validation, meaning alignment with the stream was lost;
+    /// bytes are dropped one at a time until a valid header lines up again
+    Invalid(anyhow::Error),
+    /// Alignment recovered after dropping this many bytes
+    Resynced(usize),
+}
+
+/// Splits a binary usbmon stream into packets however the reads are chunked
+/// A header is only parsed once all of it is buffered and a packet is only
+/// emitted once the payload bytes it keeps have arrived, so a short read at
+/// any point just waits for more data. Captured bytes past `capture_len` are
+/// dropped as they arrive rather than buffered.
+struct BinaryFramer {
+    buffer: Vec<u8>,
+    /// Start of the unconsumed bytes in `buffer`
+    start: usize,
+    capture_len: u32,
+    max_data_length: u32,
+    filter: CaptureFilter,
+    /// Parsed header waiting for its kept payload, with the bytes to skip after it
+    awaiting: Option<(UsbPacket, usize, u64)>,
+    /// Captured payload bytes past `capture_len` still to discard
+    skip: u64,
+    /// Bytes dropped so far while hunting for a valid header
+    resyncing: Option<usize>,
+}
+
+impl BinaryFramer {
+    fn new(capture_len: u32, max_data_length: u32, filter: CaptureFilter) -> Self {
+        Self {
+            buffer: Vec::with_capacity(BINARY_READ_CHUNK_LEN),
+            start: 0,
+            capture_len,
+            max_data_length,
+            filter,
+            awaiting: None,
+            skip: 0,
+            resyncing: None,
+        }
+    }
+    
+    fn push(&mut self, bytes: &[u8]) {
+        self.buffer.drain(..self.start);
+        self.start = 0;
+        self.buffer.extend_from_slice(bytes);
+    }
+    
+    fn available(&self) -> &[u8] {
+        &self.buffer[self.start..]
+    }
+    
+    fn consume(&mut self, len: usize) {
+        self.start += len;
+    }
+    
+    /// Whether a packet was cut off part way, e.g. by the end of a capture file
+    fn has_partial_packet(&self) -> bool {
+        !self.available().is_empty() || self.awaiting.is_some() || self.skip > 0
+    }
+    
+    fn next_frame(&mut self) -> Option<BinaryFrame> {
+        loop {
+            if self.skip > 0 {
+                let skipped = (self.available().len() as u64).min(self.skip);
+                self.consume(skipped as usize);
+                self.skip -= skipped;
+                if self.skip > 0 {
+                    return None;
+                }
+            }
+            
+            if let Some((_, kept, _)) = &self.awaiting {
+                if self.available().len() < *kept {
+                    return None;
+                }
+                let (mut packet, kept, skip) = self.awaiting.take()?;
+                if kept > 0 {
+                    packet.data = Some(self.available()[..kept].to_vec());
+                    self.consume(kept);
+                }
+                self.skip = skip;
+                return Some(BinaryFrame::Packet(packet));
+            }
+            
+            let header = self.available().get(..BINARY_HEADER_LEN)?;
+            let captured = binary_captured_length(header);
+            // A filtered-out record is skipped whole without being parsed; it
+            // still has to be a valid header, or a misaligned buffer could
+            // send the skip through the middle of real records
+            if !self.filter.admits_binary_header(header) && validate_binary_header(header).is_ok() {
+                if let Some(skipped) = self.resyncing.take() {
+                    return Some(BinaryFrame::Resynced(skipped));
+                }
+                self.consume(BINARY_HEADER_LEN);
+                self.skip = u64::from(captured);
+                continue;
+            }
+            let parsed = parse_binary_timed(header);
+            match parsed {
+                Ok(packet) => {
+                    // Report the recovery first; the header is parsed again on the next call
+                    if let Some(skipped) = self.resyncing.take() {
+                        return Some(BinaryFrame::Resynced(skipped));
+                    }
+                    self.consume(BINARY_HEADER_LEN);
+                    if !packet.has_plausible_length(self.max_data_length) {
+                        self.skip = u64::from(captured);
+                        return Some(BinaryFrame::Implausible(packet));
+                    }
+                    // Endpoint 0 replies are kept regardless, so enumeration
+                    // can be decoded without sysfs
+                    let capture_len = if packet.transfer_type == TransferType::Control && packet.endpoint == 0 && packet.urb_type == UrbType::Callback {
+                        self.capture_len.max(DESCRIPTOR_CAPTURE_LEN)
+                    } else {
+                        self.capture_len
+                    };
+                    let kept = captured.min(capture_len);
+                    self.awaiting = Some((packet, kept as usize, u64::from(captured - kept)));
+                }
+                Err(e) => {
+                    self.consume(1);
+                    match self.resyncing.as_mut() {
+                        Some(skipped) => *skipped += 1,
+                        None => {
+                            self.resyncing = Some(1);
+                            return Some(BinaryFrame::Invalid(e));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Parsing is timed on its own, outside the awaits and the packet callback
+fn parse_binary_timed(buffer: &[u8]) -> Result<UsbPacket> {
+    profile_span!("parse");
+    parse_usbmon_binary_packet(buffer)
+}
+
+fn parse_text_timed(line: &str) -> Result<UsbPacket> {
+    profile_span!("parse");
+    parse_usbmon_text_line(line)
+}
+
+/// Whether a capture starts with a binary header rather than a text line
+/// Text captures are printable ASCII, while byte 9 of a binary header is the
+/// transfer type (0-3), so any control byte in the prefix means binary
+pub fn looks_binary(prefix: &[u8]) -> bool {
+    prefix.iter().any(|&byte| !(byte.is_ascii_graphic() || byte.is_ascii_whitespace()))
+}
+
+/// Open a usbmon node for non-blocking reads
+/// A blocking read waits for the next event, and on an idle bus it can't be
+/// cancelled: it keeps running on a blocking-pool thread that runtime
+/// shutdown then waits for. Non-blocking reads return `WouldBlock` instead,
+/// which the stream loops poll on like a quiet node.
+async fn open_live_node(path: &str) -> std::io::Result<TokioFile> {
+    let mut options = tokio::fs::OpenOptions::new();
+    options.read(true);
+    #[cfg(target_os = "linux")]
+    options.custom_flags(O_NONBLOCK);
+    options.open(path).await
+}
+
+/// Pick the usbmon node for `bus_id` from a directory listing
+/// Node names are `<bus><suffix>`; the preferred format is tried first and the
+/// other format is used as a fallback so a differently-named node still works
+fn select_usbmon_node(names: &[String], bus_id: u8, use_binary: bool) -> Option<(String, bool)> {
+    let preferences: [(char, bool); 2] = if use_binary {
+        [('u', true), ('t', false)]
+    } else {
+        [('t', false), ('u', true)]
+    };
+    
+    preferences.iter().find_map(|(suffix, binary)| {
+        names.iter()
+            .find(|name| {
+                name.strip_suffix(*suffix)
+                    .and_then(|bus| bus.parse::<u8>().ok())
+                    == Some(bus_id)
+            })
+            .map(|name| (name.clone(), *binary))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn names(list: &[&str]) -> Vec<String> {
+        list.iter().map(|name| name.to_string()).collect()
+    }
+    
+    #[test]
+    fn test_select_preferred_node() {
+        let listing = names(&["0s", "0u", "1s", "1t", "1u", "2s", "2t"]);
+        assert_eq!(select_usbmon_node(&listing, 1, true), Some(("1u".to_string(), true)));
+        assert_eq!(select_usbmon_node(&listing, 1, false), Some(("1t".to_string(), false)));
+    }
+    
+    #[test]
+    fn test_select_fallback_node() {
+        let listing = names(&["0s", "0u", "2s", "2t", "12u"]);
+        assert_eq!(select_usbmon_node(&listing, 2, true), Some(("2t".to_string(), false)));
+        assert_eq!(select_usbmon_node(&listing, 12, false), Some(("12u".to_string(), true)));
+        assert_eq!(select_usbmon_node(&listing, 1, true), None);
+    }
+    
+    #[tokio::test]
+    async fn test_reader_stops_on_shutdown_while_blocked() {
+        // The writer half is kept open but never written, so every read blocks
+        let (_writer, source) = tokio::io::duplex(64);
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let reader = UsbmonReader {
+            bus_id: 1,
+            use_binary: true,
+            path: "replay".to_string(),
+            max_data_length: DEFAULT_MAX_DATA_LENGTH,
+            capture_len: 0,
+            source_kind: SourceKind::Live,
+            grow_ring: false,
+            filter: CaptureFilter::default(),
+        };
+        
+        let task = tokio::spawn(async move {
+            reader.read_binary_stream(source, |_| Ok(()), shutdown_rx).await
+        });
+        
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(!task.is_finished());
+        
+        shutdown_tx.send(true).unwrap();
+        let result = tokio::time::timeout(std::time::Duration::from_secs(1), task).await
+            .expect("reader did not stop after shutdown");
+        assert!(result.unwrap().is_ok());
+    }
+    
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_idle_live_node_does_not_hold_up_runtime_shutdown() {
+        use std::time::Duration;
+        
+        extern "C" {
+            fn mkfifo(path: *const std::os::raw::c_char, mode: u32) -> i32;
+        }
+        
+        // A FIFO with a writer that never writes blocks readers like an idle usbmon node
+        let path = std::env::temp_dir().join(format!("usbtop-ng-idle-{}", std::process::id()));
+        let c_path = std::ffi::CString::new(path.to_string_lossy().into_owned()).unwrap();
+        // SAFETY: the path is a valid NUL-terminated string that outlives the call
+        assert_eq!(unsafe { mkfifo(c_path.as_ptr(), 0o600) }, 0);
+        let writer = std::fs::OpenOptions::new().read(true).write(true).open(&path).unwrap();
+        
+        let reader = UsbmonReader {
+            path: path.to_string_lossy().into_owned(),
+            source_kind: SourceKind::Live,
+            ..UsbmonReader::stdin()
+        };
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let (shutdown_tx, shutdown_rx) = watch::channel(false);
+            let task = tokio::spawn(async move { reader.read_packets(|_| Ok(()), shutdown_rx).await });
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            shutdown_tx.send(true).unwrap();
+            let result = tokio::time::timeout(Duration::from_secs(1), task).await
+                .expect("reader did not stop after shutdown");
+            assert!(result.unwrap().is_ok());
+        });
+        
+        // Dropping the runtime waits for blocking-pool reads; one stuck in read(2) never returns
+        let (dropped_tx, dropped_rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            drop(runtime);
+            let _ = dropped_tx.send(());
+        });
+        let dropped = dropped_rx.recv_timeout(Duration::from_secs(2));
+        drop(writer);
+        std::fs::remove_file(&path).unwrap();
+        assert!(dropped.is_ok(), "runtime shutdown waited on a blocked read");
+    }
+    
+    #[test]
+    fn test_detects_capture_format() {
+        let text = b"ffff88003b4a3c00 3575914555 S Bi:1:002:1 -115 512 <\n";
+        assert!(!looks_binary(text));
+        
+        let mut binary = [0u8; 64];
+        binary[..8].copy_from_slice(&0xffff_8800_3b4a_3c00u64.to_le_bytes());
+        binary[8] = b'S';
+        binary[9] = 3;
+        assert!(looks_binary(&binary));
+    }
+    
+    fn binary_header(device_id: u8) -> Vec<u8> {
+        let mut header = vec![0u8; BINARY_HEADER_LEN];
+        header[..8].copy_from_slice(&0xffff_8800_3b4a_3c00u64.to_le_bytes());
+        header[8] = b'C';
+        header[9] = 3;
+        header[10] = 0x81;
+        header[11] = device_id;
+        header[12] = 1;
+        header
+    }
+    
+    #[tokio::test]
+    async fn test_binary_stream_resyncs_after_misalignment() {
+        // Stray data bytes in front of the second header push every later read off its boundary
+        let mut capture = binary_header(2);
+        capture.extend_from_slice(&[0x55, 0x53, 0x42, 0x43, 0x01, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x80]);
+        capture.extend(binary_header(3));
+        capture.extend(binary_header(4));
+        
+        let (_shutdown_tx, shutdown_rx) = watch::channel(false);
+        let mut reader = UsbmonReader::stdin();
+        reader.path = "replay".to_string();
+        
+        let mut devices = Vec::new();
+        reader.read_binary_stream(capture.as_slice(), |packet| {
+            devices.push(packet.device_id);
+            Ok(())
+        }, shutdown_rx).await.unwrap();
+        
+        assert_eq!(devices, vec![2, 3, 4]);
+    }
+    
+    #[tokio::test]
+    async fn test_binary_payload_skipped_or_kept() {
+        let mut capture = Vec::new();
+        for (device_id, payload) in [(2u8, &b"USBC0123456789"[..]), (3, &b""[..]), (4, &b"abcdef"[..])] {
+            let mut header = binary_header(device_id);
+            header[32..36].copy_from_slice(&(payload.len() as u32).to_le_bytes());
+            header[36..40].copy_from_slice(&(payload.len() as u32).to_le_bytes());
+            capture.extend(header);
+            capture.extend_from_slice(payload);
+        }
+        
+        for (capture_len, expected) in [
+            (0, vec![(2, None), (3, None), (4, None)]),
+            (4, vec![(2, Some(b"USBC".to_vec())), (3, None), (4, Some(b"abcd".to_vec()))]),
+        ] {
+            let (_shutdown_tx, shutdown_rx) = watch::channel(false);
+            let mut reader = UsbmonReader::stdin().with_capture_len(capture_len);
+            reader.path = "replay".to_string();
+            
+            let mut packets = Vec::new();
+            reader.read_binary_stream(capture.as_slice(), |packet| {
+                packets.push((packet.device_id, packet.data));
+                Ok(())
+            }, shutdown_rx).await.unwrap();
+            
+            assert_eq!(packets, expected);
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_filtered_records_skipped_with_their_payload() {
+        let mut capture = Vec::new();
+        for (device_id, payload) in [(2u8, &b"USBC0123456789"[..]), (3, &b"wxyz"[..]), (2, &b"abcdef"[..])] {
+            let mut header = binary_header(device_id);
+            header[32..36].copy_from_slice(&(payload.len() as u32).to_le_bytes());
+            header[36..40].copy_from_slice(&(payload.len() as u32).to_le_bytes());
+            capture.extend(header);
+            capture.extend_from_slice(payload);
+        }
+        let config = crate::config::FilterConfig { devices: vec!["1:3".to_string()], ..Default::default() };
+        
+        let (_shutdown_tx, shutdown_rx) = watch::channel(false);
+        let mut reader = UsbmonReader::stdin().with_capture_len(4).with_filter(CaptureFilter::from_config(&config).0);
+        reader.path = "replay".to_string();
+        
+        let mut packets = Vec::new();
+        reader.read_binary_stream(capture.as_slice(), |packet| {
+            packets.push((packet.device_id, packet.data));
+            Ok(())
+        }, shutdown_rx).await.unwrap();
+        
+        assert_eq!(packets, vec![(3, Some(b"wxyz".to_vec()))]);
+    }
+    
+    #[tokio::test]
+    async fn test_implausible_record_skipped_with_its_payload() {
+        // The oversized record's payload happens to look like a header of its own
+        let mut capture = Vec::new();
+        for (device_id, payload) in [(2u8, binary_header(9)), (3, b"wxyz".to_vec())] {
+            let mut header = binary_header(device_id);
+            header[32..36].copy_from_slice(&(payload.len() as u32).to_le_bytes());
+            header[36..40].copy_from_slice(&(payload.len() as u32).to_le_bytes());
+            capture.extend(header);
+            capture.extend(payload);
+        }
+        
+        let (_shutdown_tx, shutdown_rx) = watch::channel(false);
+        let mut reader = UsbmonReader::stdin().with_capture_len(4).with_max_data_length(16);
+        reader.path = "replay".to_string();
+        
+        let mut packets = Vec::new();
+        reader.read_binary_stream(capture.as_slice(), |packet| {
+            packets.push((packet.device_id, packet.data));
+            Ok(())
+        }, shutdown_rx).await.unwrap();
+        
+        assert_eq!(packets, vec![(3, Some(b"wxyz".to_vec()))]);
+    }
+    
+    /// Hands out at most `chunk` bytes per read, like short reads from the kernel
+    struct ShortReads {
+        data: Vec<u8>,
+        position: usize,
+        chunk: usize,
+    }
+    
+    impl AsyncRead for ShortReads {
+        fn poll_read(
+            self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+            buf: &mut tokio::io::ReadBuf<'_>,
+        ) -> std::task::Poll<std::io::Result<()>> {
+            let this = self.get_mut();
+            let end = (this.position + this.chunk.min(buf.remaining())).min(this.data.len());
+            buf.put_slice(&this.data[this.position..end]);
+            this.position = end;
+            std::task::Poll::Ready(Ok(()))
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_binary_stream_survives_short_reads() {
+        let mut capture = Vec::new();
+        for (device_id, payload) in [(2u8, &b"USBC0123456789"[..]), (3, &b""[..]), (4, &b"abcdef"[..]), (5, &b"xy"[..])] {
+            let mut header = binary_header(device_id);
+            header[32..36].copy_from_slice(&(payload.len() as u32).to_le_bytes());
+            header[36..40].copy_from_slice(&(payload.len() as u32).to_le_bytes());
+            capture.extend(header);
+            capture.extend_from_slice(payload);
+        }
+        let expected = vec![(2, Some(b"USBC".to_vec())), (3, None), (4, Some(b"abcd".to_vec())), (5, Some(b"xy".to_vec()))];
+        
+        for chunk in [1, 3, 7, 63, 64, 65, 100, capture.len()] {
+            let (_shutdown_tx, shutdown_rx) = watch::channel(false);
+            let mut reader = UsbmonReader::stdin().with_capture_len(4);
+            reader.path = "replay".to_string();
+            let source = ShortReads { data: capture.clone(), position: 0, chunk };
+            
+            let mut packets = Vec::new();
+            reader.read_binary_stream(source, |packet| {
+                packets.push((packet.device_id, packet.data));
+                Ok(())
+            }, shutdown_rx).await.unwrap();
+            
+            assert_eq!(packets, expected, "chunk size {}", chunk);
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_piped_text_capture_ends_at_eof() {
+        let capture: &[u8] = b"ffff88003b4a3c00 3575914555 S Bi:1:002:1 -115 512 <\n\
+                               ffff88003b4a3c00 3575914655 C Bi:1:002:1 0 512 = 00010203\n";
+        let (_shutdown_tx, shutdown_rx) = watch::channel(false);
+        let mut reader = UsbmonReader::stdin();
+        reader.path = "replay".to_string();
+        
+        let mut packets = Vec::new();
+        let result = tokio::time::timeout(
+            std::time::Duration::from_secs(1),
+            reader.read_text_stream(capture, |packet| {
+                packets.push(packet);
+                Ok(())
+            }, shutdown_rx),
+        ).await.expect("reader kept waiting after end of input");
+        
+        assert!(result.is_ok());
+        assert_eq!(packets.len(), 2);
+    }
+    
+    #[tokio::test]
+    async fn test_finite_capture_file_ends_at_eof() {
+        let dir = std::env::temp_dir().join(format!("usbtop-ng-replay-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("1t"), "ffff88003b4a3c00 3575914555 S Bi:1:002:1 -115 512 <\n\
+                                        ffff88003b4a3c00 3575914655 C Bi:1:002:1 0 512 = 00010203\n").unwrap();
+        let (_shutdown_tx, shutdown_rx) = watch::channel(false);
+        // Resolved like a usbmon node, but a recorded capture runs out
+        let mut reader = UsbmonReader::new(1, false, &dir);
+        reader.source_kind = SourceKind::Finite;
+        
+        let mut packets = Vec::new();
+        let result = tokio::time::timeout(
+            std::time::Duration::from_secs(1),
+            reader.read_packets(|packet| {
+                packets.push(packet);
+                Ok(())
+            }, shutdown_rx),
+        ).await.expect("reader kept polling a finished file");
+        std::fs::remove_dir_all(&dir).unwrap();
+        
+        assert!(result.is_ok());
+        assert_eq!(packets.len(), 2);
+    }
+}
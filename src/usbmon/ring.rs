@@ -0,0 +1,175 @@
+use std::fs;
+use std::path::Path;
+
+/// Ring the kernel gives each open binary node (`BUFF_DFL` in mon_bin.c)
+pub const DEFAULT_RING_SIZE: u32 = 300 * 1024;
+
+/// Largest ring the kernel accepts (`BUFF_MAX` in mon_bin.c)
+pub const MAX_RING_SIZE: u32 = 1200 * 1024;
+
+/// Ring that keeps up with a busy bus at `speed_mbps`
+/// The default copes with High Speed; SuperSpeed bursts overflow it, and
+/// even the largest ring only buys time if the reader stalls.
+pub fn recommended_ring_size(speed_mbps: u32) -> u32 {
+    if speed_mbps > 480 { MAX_RING_SIZE } else { DEFAULT_RING_SIZE }
+}
+
+/// Negotiated speed of `bus_id`'s root hub in Mb/s, from `usbN/speed` under
+/// the sysfs devices directory; bus 0 (all buses) takes the fastest one
+pub fn bus_speed_mbps(sysfs_root: &Path, bus_id: u8) -> Option<u32> {
+    let read_speed = |name: &str| -> Option<u32> {
+        let speed: f64 = fs::read_to_string(sysfs_root.join(name).join("speed")).ok()?.trim().parse().ok()?;
+        Some(speed as u32)
+    };
+    
+    if bus_id != 0 {
+        return read_speed(&format!("usb{}", bus_id));
+    }
+    fs::read_dir(sysfs_root).ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| name.strip_prefix("usb").is_some_and(|bus| bus.parse::<u8>().is_ok()))
+        .filter_map(|name| read_speed(&name))
+        .max()
+}
+
+#[cfg(target_os = "linux")]
+mod sys {
+    use std::io;
+    use std::os::raw::{c_int, c_ulong};
+    use std::os::unix::io::RawFd;
+    
+    /// `_IO(MON_IOC_MAGIC, 4)`: resize the ring, only before it is mapped
+    const MON_IOCT_RING_SIZE: c_ulong = 0x9204;
+    /// `_IO(MON_IOC_MAGIC, 5)`: the ring size is the return value
+    const MON_IOCQ_RING_SIZE: c_ulong = 0x9205;
+    
+    extern "C" {
+        fn ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
+    }
+    
+    pub fn ring_size(fd: RawFd) -> io::Result<u32> {
+        // SAFETY: the request takes no argument and only reads driver state
+        let size = unsafe { ioctl(fd, MON_IOCQ_RING_SIZE) };
+        if size < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(size as u32)
+    }
+    
+    pub fn set_ring_size(fd: RawFd, size: u32) -> io::Result<()> {
+        // SAFETY: the size is passed by value, no memory is shared
+        if unsafe { ioctl(fd, MON_IOCT_RING_SIZE, size as c_ulong) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+/// What `check_ring_size` found for one reader
+#[derive(Debug, Clone, PartialEq)]
+pub enum RingStatus {
+    /// The bus speed is unknown, so there is nothing to compare against
+    UnknownSpeed,
+    /// The descriptor has no ring: only the `/dev/usbmonN` character device
+    /// takes the ring ioctls, not the debugfs nodes, files or pipes
+    Unsupported,
+    Sufficient,
+    Small { size: u32, recommended: u32 },
+    Grown { from: u32, to: u32 },
+}
+
+/// Warn when the ring behind `fd` is small for `bus_id`'s speed, or grow it
+/// when `grow` is set
+/// Every open of the character device gets a ring of its own, so this has to
+/// run on the reader's descriptor before the first read.
+#[cfg(target_os = "linux")]
+pub fn check_ring_size(fd: std::os::unix::io::RawFd, bus_id: u8, sysfs_root: &Path, grow: bool) -> RingStatus {
+    use log::{debug, info, warn};
+    
+    let Some(speed) = bus_speed_mbps(sysfs_root, bus_id) else {
+        debug!("Unknown speed for bus {}; not checking its usbmon ring", bus_id);
+        return RingStatus::UnknownSpeed;
+    };
+    let current = match sys::ring_size(fd) {
+        Ok(size) => size,
+        Err(e) => {
+            if grow {
+                warn!(
+                    "--grow-ring needs the /dev/usbmon{} character device; the node read for bus {} has no ring to resize ({})",
+                    bus_id, bus_id, e,
+                );
+            } else {
+                debug!("No usbmon ring to check for bus {}: {}", bus_id, e);
+            }
+            return RingStatus::Unsupported;
+        }
+    };
+    let recommended = recommended_ring_size(speed);
+    if current >= recommended {
+        return RingStatus::Sufficient;
+    }
+    
+    if grow {
+        match sys::set_ring_size(fd, recommended) {
+            Ok(()) => {
+                info!("Grew the usbmon ring for bus {} from {} to {} KiB", bus_id, current / 1024, recommended / 1024);
+                return RingStatus::Grown { from: current, to: recommended };
+            }
+            Err(e) => warn!("Failed to grow the usbmon ring for bus {}: {}", bus_id, e),
+        }
+    }
+    warn!(
+        "usbmon ring for bus {} is {} KiB, which a busy {} Mb/s bus can overflow and drop packets; \
+         run with --grow-ring to raise it to {} KiB",
+        bus_id, current / 1024, speed, recommended / 1024,
+    );
+    RingStatus::Small { size: current, recommended }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_recommended_ring_size_by_speed() {
+        assert_eq!(recommended_ring_size(12), DEFAULT_RING_SIZE);
+        assert_eq!(recommended_ring_size(480), DEFAULT_RING_SIZE);
+        assert_eq!(recommended_ring_size(5000), MAX_RING_SIZE);
+        assert_eq!(recommended_ring_size(20000), MAX_RING_SIZE);
+    }
+    
+    #[test]
+    fn test_bus_speed_from_root_hubs() {
+        let root = std::env::temp_dir().join(format!("usbtop-ng-ring-{}", std::process::id()));
+        for (name, speed) in [("usb1", "480"), ("usb2", "5000"), ("usb3", "1.5"), ("1-1", "10000")] {
+            fs::create_dir_all(root.join(name)).unwrap();
+            fs::write(root.join(name).join("speed"), format!("{}\n", speed)).unwrap();
+        }
+        
+        let speeds = (bus_speed_mbps(&root, 1), bus_speed_mbps(&root, 3), bus_speed_mbps(&root, 0), bus_speed_mbps(&root, 9));
+        fs::remove_dir_all(&root).unwrap();
+        // Bus 0 looks only at root hubs, not the faster device behind one
+        assert_eq!(speeds, (Some(480), Some(1), Some(5000), None));
+    }
+    
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_node_without_ring_ioctls_is_unsupported() {
+        use std::os::unix::io::AsRawFd;
+        
+        let root = std::env::temp_dir().join(format!("usbtop-ng-ring-fd-{}", std::process::id()));
+        fs::create_dir_all(root.join("usb2")).unwrap();
+        fs::write(root.join("usb2").join("speed"), "5000\n").unwrap();
+        // A regular file stands in for a debugfs node: the ioctls fail with ENOTTY
+        let node = fs::File::open(root.join("usb2").join("speed")).unwrap();
+        
+        let statuses = (
+            check_ring_size(node.as_raw_fd(), 2, &root, true),
+            check_ring_size(node.as_raw_fd(), 2, &root, false),
+            check_ring_size(node.as_raw_fd(), 9, &root, true),
+        );
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(statuses, (RingStatus::Unsupported, RingStatus::Unsupported, RingStatus::UnknownSpeed));
+    }
+}
@@ -0,0 +1,251 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+use crate::logging::decorated;
+use super::parser::{binary_captured_length, format_usbmon_text_line, parse_usbmon_binary_packet, parse_usbmon_text_line, UsbPacket, BINARY_HEADER_LEN};
+use super::reader::looks_binary;
+
+/// Mismatches printed in full; the rest are only counted
+pub const MAX_REPORTED_MISMATCHES: usize = 10;
+
+/// A field that didn't survive being written out and read back
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mismatch {
+    /// Where the packet is in the capture, e.g. "line 12 (byte 830)"
+    pub location: String,
+    pub field: &'static str,
+    pub original: String,
+    pub reread: String,
+}
+
+/// Outcome of round-tripping every packet of a capture through the text writer
+#[derive(Debug, Clone, Default)]
+pub struct VerifyReport {
+    pub binary: bool,
+    /// Packets the capture yielded
+    pub packets: usize,
+    /// Packets whose written line parsed again
+    pub reread: usize,
+    /// Input that isn't a packet at all, with where it was
+    pub unreadable: Vec<(String, String)>,
+    /// The first `MAX_REPORTED_MISMATCHES` mismatches
+    pub mismatches: Vec<Mismatch>,
+    pub mismatch_count: usize,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.mismatch_count == 0 && self.unreadable.is_empty() && self.reread == self.packets
+    }
+    
+    fn record(&mut self, mismatch: Mismatch) {
+        self.mismatch_count += 1;
+        if self.mismatches.len() < MAX_REPORTED_MISMATCHES {
+            self.mismatches.push(mismatch);
+        }
+    }
+    
+    /// Write `packet` as a usbmon text line, parse it back and compare
+    fn check(&mut self, location: String, packet: &UsbPacket) {
+        self.packets += 1;
+        let line = format_usbmon_text_line(packet);
+        match parse_usbmon_text_line(&line) {
+            Ok(reread) => {
+                self.reread += 1;
+                for (field, original, reread) in compare_packets(packet, &reread) {
+                    self.record(Mismatch { location: location.clone(), field, original, reread });
+                }
+            }
+            Err(e) => self.record(Mismatch {
+                location,
+                field: "line",
+                original: line,
+                reread: e.to_string(),
+            }),
+        }
+    }
+    
+    pub fn print(&self) {
+        println!("Capture format: {}", if self.binary { "binary" } else { "text" });
+        println!("Packets read:   {}", self.packets);
+        println!("Packets reread: {}", self.reread);
+        for (location, error) in &self.unreadable {
+            println!("{}", decorated("❌", &format!("{}: unreadable: {}", location, error)));
+        }
+        for mismatch in &self.mismatches {
+            println!("{}", decorated("❌", &format!(
+                "{}: {} was {} but reads back as {}",
+                mismatch.location,
+                mismatch.field,
+                mismatch.original,
+                mismatch.reread,
+            )));
+        }
+        if self.mismatch_count > self.mismatches.len() {
+            println!("   ... and {} more mismatches", self.mismatch_count - self.mismatches.len());
+        }
+        if self.is_ok() {
+            println!("{}", decorated("✅", "Every packet reads back unchanged"));
+        }
+    }
+}
+
+/// Fields of `original` that `reread` doesn't reproduce, as (field, original, reread)
+/// The captured length only counts when payload was kept: a binary capture
+/// read without payloads has nothing to write for it.
+fn compare_packets(original: &UsbPacket, reread: &UsbPacket) -> Vec<(&'static str, String, String)> {
+    let mut fields = vec![
+        ("urb_tag", format!("{:?}", original.urb_tag), format!("{:?}", reread.urb_tag)),
+        ("timestamp_us", original.timestamp_us.to_string(), reread.timestamp_us.to_string()),
+        ("urb_type", format!("{:?}", original.urb_type), format!("{:?}", reread.urb_type)),
+        ("transfer_type", format!("{:?}", original.transfer_type), format!("{:?}", reread.transfer_type)),
+        ("bus_id", original.bus_id.to_string(), reread.bus_id.to_string()),
+        ("device_id", original.device_id.to_string(), reread.device_id.to_string()),
+        ("endpoint", original.endpoint.to_string(), reread.endpoint.to_string()),
+        ("direction", original.direction.to_string(), reread.direction.to_string()),
+        ("status", original.status.to_string(), reread.status.to_string()),
+        ("interval", format!("{:?}", original.interval), format!("{:?}", reread.interval)),
+        ("data_length", original.data_length.to_string(), reread.data_length.to_string()),
+        ("setup_packet", format!("{:02x?}", original.setup_packet), format!("{:02x?}", reread.setup_packet)),
+        ("data", format!("{:02x?}", original.data), format!("{:02x?}", reread.data)),
+    ];
+    if original.data.is_some() {
+        fields.push(("captured_length", original.captured_length.to_string(), reread.captured_length.to_string()));
+    }
+    fields.retain(|(_, original, reread)| original != reread);
+    fields
+}
+
+/// Round-trip every packet of a capture held in memory
+/// Text captures skip blank lines and report unparsable ones; a binary
+/// capture stops at the first header that doesn't parse, since the packets
+/// after it can't be found without guessing.
+pub fn verify_capture(capture: &[u8]) -> VerifyReport {
+    let mut report = VerifyReport {
+        binary: looks_binary(&capture[..capture.len().min(BINARY_HEADER_LEN)]),
+        ..VerifyReport::default()
+    };
+    
+    if report.binary {
+        let mut offset = 0;
+        while offset < capture.len() {
+            let location = format!("byte {}", offset);
+            let Some(header) = capture.get(offset..offset + BINARY_HEADER_LEN) else {
+                report.unreadable.push((location, format!("{} trailing bytes are not a whole header", capture.len() - offset)));
+                break;
+            };
+            let mut packet = match parse_usbmon_binary_packet(header) {
+                Ok(packet) => packet,
+                Err(e) => {
+                    report.unreadable.push((location, e.to_string()));
+                    break;
+                }
+            };
+            let start = offset + BINARY_HEADER_LEN;
+            let end = start + binary_captured_length(header) as usize;
+            let Some(payload) = capture.get(start..end) else {
+                report.unreadable.push((location, "payload cut short by the end of the capture".to_string()));
+                break;
+            };
+            if !payload.is_empty() {
+                packet.data = Some(payload.to_vec());
+            }
+            report.check(location, &packet);
+            offset = end;
+        }
+    } else {
+        let text = String::from_utf8_lossy(capture);
+        let mut offset = 0;
+        for (number, line) in text.split_inclusive('\n').enumerate() {
+            let location = format!("line {} (byte {})", number + 1, offset);
+            offset += line.len();
+            if line.trim().is_empty() {
+                continue;
+            }
+            match parse_usbmon_text_line(line) {
+                Ok(packet) => report.check(location, &packet),
+                Err(e) => report.unreadable.push((location, e.to_string())),
+            }
+        }
+    }
+    report
+}
+
+/// `--verify-capture`: round-trip a capture file and print what didn't
+/// survive; true when everything did
+pub fn run(path: &Path) -> Result<bool> {
+    let capture = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let report = verify_capture(&capture);
+    report.print();
+    Ok(report.is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const TEXT_CAPTURE: &str = "\
+ffff88007c861a00 2389264913 S Bo:1:001:2 -115 31 = 55534243 1f000000 00000000 00000600 00000000 00000000 00000000 000000
+ffff88007c861a00 2389265013 C Bo:1:001:2 0 31 >
+ffff8800aa000100 2389265100 S Ci:1:005:0 s 80 06 0100 0000 0012 18 <
+ffff8800aa000100 2389265200 C Ci:1:005:0 0 18 = 12010002 00000040 81078155 00010102 0301
+ffff8800aa000200 2389265300 C Ii:1:003:1 -71 0
+";
+    
+    #[test]
+    fn test_text_capture_round_trips() {
+        let report = verify_capture(TEXT_CAPTURE.as_bytes());
+        assert!(!report.binary);
+        assert_eq!((report.packets, report.reread), (5, 5));
+        assert!(report.is_ok(), "{:?}", report.mismatches);
+    }
+    
+    #[test]
+    fn test_binary_capture_round_trips() {
+        let mut capture = Vec::new();
+        for (urb_type, status, payload) in [(b'S', -115i32, &[][..]), (b'C', 0, &[0xde, 0xad, 0xbe, 0xef][..])] {
+            let mut header = vec![0u8; BINARY_HEADER_LEN];
+            header[..8].copy_from_slice(&0xffff_8800_1234_5600u64.to_le_bytes());
+            header[8] = urb_type;
+            header[9] = 3; // bulk
+            header[10] = 0x81;
+            header[11] = 4;
+            header[12..14].copy_from_slice(&2u16.to_le_bytes());
+            header[14] = b'-';
+            header[16..24].copy_from_slice(&1_700_000_000u64.to_le_bytes());
+            header[28..32].copy_from_slice(&status.to_le_bytes());
+            header[32..36].copy_from_slice(&512u32.to_le_bytes());
+            header[36..40].copy_from_slice(&(payload.len() as u32).to_le_bytes());
+            capture.extend_from_slice(&header);
+            capture.extend_from_slice(payload);
+        }
+        
+        let report = verify_capture(&capture);
+        assert!(report.binary);
+        assert_eq!(report.packets, 2);
+        assert!(report.is_ok(), "{:?} {:?}", report.unreadable, report.mismatches);
+        
+        // A capture cut off inside a payload says where
+        let report = verify_capture(&capture[..capture.len() - 1]);
+        assert_eq!(report.unreadable, [("byte 64".to_string(), "payload cut short by the end of the capture".to_string())]);
+    }
+    
+    #[test]
+    fn test_asymmetry_is_reported() {
+        // A setup packet on a submission that already failed: the writer
+        // has nowhere to put the status next to the setup words
+        let mut packet = parse_usbmon_text_line("ffff8800aa000100 100 S Ci:1:005:0 s 80 06 0100 0000 0012 18 <").unwrap();
+        packet.status = -19;
+        let mut report = VerifyReport::default();
+        report.check("line 1 (byte 0)".to_string(), &packet);
+        
+        assert!(!report.is_ok());
+        assert_eq!(report.mismatches, [Mismatch {
+            location: "line 1 (byte 0)".to_string(),
+            field: "status",
+            original: "-19".to_string(),
+            reread: "-115".to_string(),
+        }]);
+    }
+}
@@ -0,0 +1,329 @@
+use anyhow::{Result, anyhow};
+use log::{debug, info, warn};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::sync::watch;
+
+use crate::metrics::MetricsExporter;
+use crate::monitor::ReaderTasks;
+use crate::snapshot::Message;
+use crate::ui::UsbTopApp;
+use crate::usbmon::parser::UsbPacket;
+
+/// Largest request head accepted before the connection is dropped
+const MAX_REQUEST_LEN: usize = 8 * 1024;
+
+/// Single-page dashboard; kept inline so serving it needs no build step or files
+pub const DASHBOARD_HTML: &str = r##"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>usbtop-ng</title>
+<style>
+body { background: #282c34; color: #fff; font: 14px monospace; margin: 1em; }
+h1 { color: #32cd32; font-size: 1.2em; }
+#total { color: #00bfff; font-weight: bold; }
+canvas { background: #1e2127; width: 100%; height: 160px; }
+table { border-collapse: collapse; width: 100%; margin-top: 1em; }
+th { color: #32cd32; text-align: left; }
+td, th { padding: 2px 8px; }
+td.num { text-align: right; }
+</style>
+</head>
+<body>
+<h1>usbtop-ng <span id="total">-</span> <span id="status">connecting...</span></h1>
+<canvas id="chart" width="800" height="160"></canvas>
+<table>
+<thead><tr><th>Device</th><th>Vendor</th><th>Product</th><th>RX</th><th>TX</th><th>Total</th><th>Errors</th></tr></thead>
+<tbody id="devices"></tbody>
+</table>
+<script>
+const samples = [];
+const HISTORY_POINTS = 120;
+function rate(bps) {
+  if (bps >= 1e9) return (bps / 1e9).toFixed(1) + " GB/s";
+  if (bps >= 1e6) return (bps / 1e6).toFixed(1) + " MB/s";
+  if (bps >= 1e3) return (bps / 1e3).toFixed(1) + " KB/s";
+  return bps.toFixed(0) + " B/s";
+}
+function pad(n) { return String(n).padStart(3, "0"); }
+function cell(text, cls) {
+  const td = document.createElement("td");
+  td.textContent = text;
+  if (cls) td.className = cls;
+  return td;
+}
+function draw() {
+  const canvas = document.getElementById("chart");
+  const ctx = canvas.getContext("2d");
+  ctx.clearRect(0, 0, canvas.width, canvas.height);
+  const max = Math.max(1e6, ...samples);
+  ctx.strokeStyle = "#00bfff";
+  ctx.beginPath();
+  samples.forEach((bps, i) => {
+    const x = i * canvas.width / (HISTORY_POINTS - 1);
+    const y = canvas.height - bps / max * (canvas.height - 10);
+    if (i === 0) ctx.moveTo(x, y); else ctx.lineTo(x, y);
+  });
+  ctx.stroke();
+  ctx.fillStyle = "#fff";
+  ctx.fillText(rate(max), 4, 12);
+}
+function update(snapshot) {
+  const devices = snapshot.devices.slice().sort((a, b) => (b.rx_bps + b.tx_bps) - (a.rx_bps + a.tx_bps));
+  const total = devices.reduce((sum, d) => sum + d.rx_bps + d.tx_bps, 0);
+  samples.push(total);
+  if (samples.length > HISTORY_POINTS) samples.shift();
+  document.getElementById("total").textContent = rate(total);
+  const body = document.getElementById("devices");
+  body.replaceChildren(...devices.map(d => {
+    const row = document.createElement("tr");
+    row.append(
+      cell(pad(d.bus_id) + ":" + pad(d.device_id)),
+      cell(d.vendor || "Unknown"),
+      cell(d.product || "Unknown"),
+      cell(rate(d.rx_bps), "num"),
+      cell(rate(d.tx_bps), "num"),
+      cell(((d.total_rx_bytes + d.total_tx_bytes) / 1e6).toFixed(1) + " MB", "num"),
+      cell(String(d.errors), "num"));
+    return row;
+  }));
+  draw();
+}
+const source = new EventSource("/stream");
+source.onopen = () => document.getElementById("status").textContent = "";
+source.onerror = () => document.getElementById("status").textContent = "(disconnected, retrying)";
+source.onmessage = event => update(JSON.parse(event.data));
+</script>
+</body>
+</html>
+"##;
+
+/// Capture headlessly and serve the dashboard, an SSE stream and metrics on `addr`
+/// `/` is the dashboard, `/stream` pushes a `Message::Snapshot` every refresh
+/// and `/metrics` is the Prometheus text rendering, updated on the exporter's
+/// own interval. Runs until the packet source closes or the process is
+/// interrupted.
+pub async fn serve(
+    addr: &str,
+    mut app: UsbTopApp,
+    mut packets: UnboundedReceiver<UsbPacket>,
+    tasks: ReaderTasks,
+    mut exporter: MetricsExporter,
+) -> Result<()> {
+    let listener = TcpListener::bind(addr).await
+        .map_err(|e| anyhow!("Failed to listen on {}: {}", addr, e))?;
+    info!("Serving the web dashboard on http://{}/", listener.local_addr()?);
+    
+    // Separate channels so a metrics update doesn't resend the snapshot to streams
+    let (snapshot_tx, snapshot_rx) = watch::channel(String::new());
+    let (metrics_tx, metrics_rx) = watch::channel(String::new());
+    let mut sample = tokio::time::interval(app.sample_interval);
+    let mut refresh = tokio::time::interval(app.refresh_rate);
+    let mut export = tokio::time::interval(exporter.interval);
+    
+    loop {
+        tokio::select! {
+            packet = packets.recv() => match packet {
+                Some(packet) => app.process_packet(&packet),
+                None => {
+                    info!("Packet source closed; stopping the web server");
+                    break;
+                }
+            },
+            _ = sample.tick() => app.update_bandwidth_history(),
+            _ = refresh.tick() => {
+                snapshot_tx.send_replace(snapshot_json(&app));
+            }
+            _ = export.tick() => {
+                metrics_tx.send_replace(exporter.render(app.devices.values(), Instant::now()));
+            }
+            accepted = listener.accept() => match accepted {
+                Ok((stream, peer)) => {
+                    debug!("Web client connected from {}", peer);
+                    tokio::spawn(handle_connection(stream, snapshot_rx.clone(), metrics_rx.clone(), app.refresh_rate));
+                }
+                Err(e) => warn!("Failed to accept web client: {}", e),
+            },
+        }
+    }
+    
+    tasks.shutdown().await;
+    app.save_state();
+    app.flush_trigger();
+    Ok(())
+}
+
+fn snapshot_json(app: &UsbTopApp) -> String {
+    serde_json::to_string(&Message::snapshot(app.devices.values())).unwrap_or_default()
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    mut snapshots: watch::Receiver<String>,
+    metrics: watch::Receiver<String>,
+    refresh_rate: Duration,
+) {
+    let path = match read_request_path(&mut stream).await {
+        Some(path) => path,
+        None => {
+            let _ = stream.write_all(&response("400 Bad Request", "text/plain", "Bad request\n")).await;
+            return;
+        }
+    };
+    
+    let result = match path.as_str() {
+        "/" | "/index.html" => stream.write_all(&response("200 OK", "text/html; charset=utf-8", DASHBOARD_HTML)).await,
+        "/metrics" => {
+            let metrics = metrics.borrow().clone();
+            stream.write_all(&response("200 OK", "text/plain; version=0.0.4", &metrics)).await
+        }
+        "/stream" => stream_snapshots(&mut stream, &mut snapshots, refresh_rate).await,
+        _ => stream.write_all(&response("404 Not Found", "text/plain", "Not found\n")).await,
+    };
+    if let Err(e) = result {
+        debug!("Web client went away: {}", e);
+    }
+}
+
+/// Push each new snapshot as a Server-Sent Event until the client disconnects
+async fn stream_snapshots(
+    stream: &mut TcpStream,
+    snapshots: &mut watch::Receiver<String>,
+    refresh_rate: Duration,
+) -> std::io::Result<()> {
+    let head = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\n\r\nretry: {}\n\n",
+        refresh_rate.as_millis().max(1000),
+    );
+    stream.write_all(head.as_bytes()).await?;
+    
+    loop {
+        let json = snapshots.borrow_and_update().clone();
+        if !json.is_empty() {
+            stream.write_all(sse_event(&json).as_bytes()).await?;
+        }
+        if snapshots.changed().await.is_err() {
+            return Ok(());
+        }
+    }
+}
+
+/// One SSE `data:` event; the JSON is a single line so it needs no splitting
+fn sse_event(data: &str) -> String {
+    format!("data: {}\n\n", data)
+}
+
+/// Read the request head and return the path of a GET request
+async fn read_request_path(stream: &mut TcpStream) -> Option<String> {
+    let mut head = Vec::new();
+    let mut chunk = [0u8; 1024];
+    while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+        if head.len() > MAX_REQUEST_LEN {
+            return None;
+        }
+        let read = stream.read(&mut chunk).await.ok()?;
+        if read == 0 {
+            return None;
+        }
+        head.extend_from_slice(&chunk[..read]);
+    }
+    parse_request_path(&head)
+}
+
+/// Path of a `GET <path> HTTP/1.x` request line, without any query string
+fn parse_request_path(head: &[u8]) -> Option<String> {
+    let line = head.split(|byte| *byte == b'\n').next()?;
+    let line = std::str::from_utf8(line).ok()?.trim_end();
+    let mut parts = line.split(' ');
+    if parts.next()? != "GET" {
+        return None;
+    }
+    let target = parts.next()?;
+    parts.next().filter(|version| version.starts_with("HTTP/1."))?;
+    Some(target.split('?').next().unwrap_or(target).to_string())
+}
+
+fn response(status: &str, content_type: &str, body: &str) -> Vec<u8> {
+    format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body,
+    )
+    .into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parse_request_path() {
+        assert_eq!(parse_request_path(b"GET /stream HTTP/1.1\r\nHost: x\r\n\r\n"), Some("/stream".to_string()));
+        assert_eq!(parse_request_path(b"GET /metrics?x=1 HTTP/1.0\r\n\r\n"), Some("/metrics".to_string()));
+        assert_eq!(parse_request_path(b"POST / HTTP/1.1\r\n\r\n"), None);
+        assert_eq!(parse_request_path(b"GET /\r\n\r\n"), None);
+    }
+    
+    #[test]
+    fn test_snapshot_event_is_single_data_line() {
+        let mut app = UsbTopApp::new(Duration::from_secs(1), Duration::from_secs(1));
+        app.update_device(crate::device::UsbDevice::new(1, 4));
+        
+        let event = sse_event(&snapshot_json(&app));
+        assert!(event.starts_with("data: {\"type\":\"snapshot\""));
+        assert_eq!(event.matches('\n').count(), 2);
+    }
+    
+    #[tokio::test]
+    async fn test_serves_the_dashboard_page() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (_snapshot_tx, snapshots) = watch::channel(String::new());
+        let (_metrics_tx, metrics) = watch::channel(String::new());
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            handle_connection(stream, snapshots, metrics, Duration::from_secs(1)).await;
+        });
+        
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        client.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").await.unwrap();
+        let mut reply = String::new();
+        client.read_to_string(&mut reply).await.unwrap();
+        
+        assert!(reply.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(reply.contains(&format!("Content-Length: {}\r\n", DASHBOARD_HTML.len())));
+        // The whole page, colors included, made it into the literal
+        assert!(reply.contains("ctx.strokeStyle = \"#00bfff\";"));
+        assert!(reply.ends_with("</html>\n"));
+    }
+    
+    #[tokio::test]
+    async fn test_serves_counters_only_metrics() {
+        let mut device = crate::device::UsbDevice::new(1, 4);
+        device.bandwidth_stats.total_rx_bytes = 4096;
+        let mut exporter = MetricsExporter::new(Duration::from_secs(15), true);
+        let (_metrics_tx, metrics) = watch::channel(exporter.render([&device], Instant::now()));
+        let (_snapshot_tx, snapshots) = watch::channel(String::new());
+        
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            handle_connection(stream, snapshots, metrics, Duration::from_secs(1)).await;
+        });
+        
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        client.write_all(b"GET /metrics HTTP/1.1\r\n\r\n").await.unwrap();
+        let mut reply = String::new();
+        client.read_to_string(&mut reply).await.unwrap();
+        
+        assert!(reply.starts_with("HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\n"));
+        assert!(reply.contains("usb_device_rx_bytes_total{bus=\"1\",device=\"4\"} 4096\n"));
+        assert!(!reply.contains("usb_device_rx_bytes_per_second"));
+    }
+}